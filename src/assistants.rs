@@ -0,0 +1,934 @@
+//! Assistants API: assistants, the threads they converse on, and their runs.
+//!
+//! The API works on runs in the background: `Client::wait_until_terminal` polls one until
+//! it is done, or needs the caller, and `Client::stream_run` streams its events instead.
+use std::collections::HashMap;
+
+use futures::Stream;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    api::{FunctionSpec, Problems, ToolCall},
+    poll::PollConfig,
+    Client, Error, Result,
+};
+
+/// Header opting in to version 2 of the Assistants API, which is still in beta
+const BETA_HEADER: (&str, &str) = ("OpenAI-Beta", "assistants=v2");
+
+/// Most files the code interpreter of an assistant, or thread, can be given
+pub const MAX_CODE_INTERPRETER_FILES: usize = 20;
+
+/// A tool of an assistant
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AssistantTool {
+    /// Runs Python code in a sandbox, on the files of `CodeInterpreterResources`
+    CodeInterpreter,
+    /// Searches the vector stores of `FileSearchResources`
+    FileSearch,
+    /// A function of the caller: runs calling it stop as `RunStatus::RequiresAction` until
+    /// its output is submitted with `Client::submit_tool_outputs`
+    Function { function: FunctionSpec },
+}
+
+impl From<FunctionSpec> for AssistantTool {
+    fn from(function: FunctionSpec) -> Self {
+        AssistantTool::Function { function }
+    }
+}
+
+/// The files and vector stores the tools of an assistant, or thread, work on
+///
+/// # Example
+/// ```
+/// # use openai_api::assistants::ToolResources;
+/// let resources = ToolResources::default()
+///     .with_code_interpreter_files(vec!["file-abc".into()])
+///     .with_vector_stores(vec!["vs_123".into()]);
+/// let json = serde_json::to_value(&resources).unwrap();
+/// assert_eq!(json["file_search"]["vector_store_ids"][0], "vs_123");
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct ToolResources {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code_interpreter: Option<CodeInterpreterResources>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_search: Option<FileSearchResources>,
+}
+
+impl ToolResources {
+    /// Gives the code interpreter these files, uploaded with the `assistants` purpose
+    #[must_use]
+    pub fn with_code_interpreter_files(mut self, file_ids: Vec<String>) -> Self {
+        self.code_interpreter = Some(CodeInterpreterResources { file_ids });
+        self
+    }
+
+    /// Has file search look in these vector stores
+    #[must_use]
+    pub fn with_vector_stores(mut self, vector_store_ids: Vec<String>) -> Self {
+        self.file_search = Some(FileSearchResources { vector_store_ids });
+        self
+    }
+
+    /// The problems of these resources, given the tools they are for
+    fn problems(&self, problems: &mut Problems, tools: Option<&[AssistantTool]>) {
+        let has = |tool| tools.is_none_or(|tools| tools.contains(&tool));
+        if let Some(code_interpreter) = &self.code_interpreter {
+            let files = code_interpreter.file_ids.len();
+            problems.check(files <= MAX_CODE_INTERPRETER_FILES, || {
+                format!(
+                    "the code interpreter takes at most {} files, got {}",
+                    MAX_CODE_INTERPRETER_FILES, files
+                )
+            });
+            problems.check(has(AssistantTool::CodeInterpreter), || {
+                "code_interpreter resources need the CodeInterpreter tool".into()
+            });
+        }
+        if let Some(file_search) = &self.file_search {
+            let stores = file_search.vector_store_ids.len();
+            problems.check(stores <= 1, || {
+                format!("file search takes at most 1 vector store, got {}", stores)
+            });
+            problems.check(has(AssistantTool::FileSearch), || {
+                "file_search resources need the FileSearch tool".into()
+            });
+        }
+    }
+}
+
+/// The resources of the code interpreter tool
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct CodeInterpreterResources {
+    /// Ids of files uploaded with the `assistants` purpose
+    #[serde(default)]
+    pub file_ids: Vec<String>,
+}
+
+/// The resources of the file search tool
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct FileSearchResources {
+    /// Ids of vector stores, see `vector_stores`
+    #[serde(default)]
+    pub vector_store_ids: Vec<String>,
+}
+
+/// Arguments of an assistant's creation. Checked when built: tool resources need their
+/// tool, and must stay within the API's limits.
+#[derive(Serialize, Deserialize, Debug, Builder, Clone)]
+#[builder(pattern = "immutable", build_fn(validate = "Self::check"))]
+pub struct AssistantArgs {
+    /// The id of the model the assistant uses
+    #[builder(setter(into))]
+    model: String,
+    #[builder(setter(into, strip_option), default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    /// The system instructions of the assistant
+    #[builder(setter(into, strip_option), default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    instructions: Option<String>,
+    #[builder(setter(into), default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<AssistantTool>,
+    #[builder(setter(strip_option), default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tool_resources: Option<ToolResources>,
+    /// Up to 16 key-value pairs, for the caller's own use
+    #[builder(setter(into), default)]
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    metadata: HashMap<String, String>,
+}
+
+impl AssistantArgs {
+    /// Build a `AssistantArgs` from the defaults
+    #[must_use]
+    pub fn builder() -> AssistantArgsBuilder {
+        AssistantArgsBuilder::default()
+    }
+}
+
+impl AssistantArgsBuilder {
+    fn check(&self) -> std::result::Result<(), String> {
+        let mut problems = Problems::default();
+        if let Some(Some(resources)) = &self.tool_resources {
+            let tools = self.tools.as_deref().unwrap_or_default();
+            resources.problems(&mut problems, Some(tools));
+        }
+        problems.into_message()
+    }
+}
+
+/// Arguments of a thread's creation
+#[derive(Serialize, Deserialize, Debug, Builder, Clone, Default)]
+#[builder(pattern = "immutable", build_fn(validate = "Self::check"))]
+pub struct ThreadArgs {
+    /// Resources for the tools of the assistants running on the thread, on top of their own
+    #[builder(setter(strip_option), default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tool_resources: Option<ToolResources>,
+    /// Up to 16 key-value pairs, for the caller's own use
+    #[builder(setter(into), default)]
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    metadata: HashMap<String, String>,
+}
+
+impl ThreadArgs {
+    /// Build a `ThreadArgs` from the defaults
+    #[must_use]
+    pub fn builder() -> ThreadArgsBuilder {
+        ThreadArgsBuilder::default()
+    }
+}
+
+impl ThreadArgsBuilder {
+    fn check(&self) -> std::result::Result<(), String> {
+        let mut problems = Problems::default();
+        if let Some(Some(resources)) = &self.tool_resources {
+            resources.problems(&mut problems, None);
+        }
+        problems.into_message()
+    }
+}
+
+impl TryFrom<AssistantArgsBuilder> for AssistantArgs {
+    type Error = AssistantArgsBuilderError;
+
+    fn try_from(builder: AssistantArgsBuilder) -> std::result::Result<Self, Self::Error> {
+        builder.build()
+    }
+}
+
+impl From<AssistantArgsBuilderError> for Error {
+    fn from(err: AssistantArgsBuilderError) -> Self {
+        Error::BadArguments(err.to_string())
+    }
+}
+
+impl TryFrom<ThreadArgsBuilder> for ThreadArgs {
+    type Error = ThreadArgsBuilderError;
+
+    fn try_from(builder: ThreadArgsBuilder) -> std::result::Result<Self, Self::Error> {
+        builder.build()
+    }
+}
+
+impl From<ThreadArgsBuilderError> for Error {
+    fn from(err: ThreadArgsBuilderError) -> Self {
+        Error::BadArguments(err.to_string())
+    }
+}
+
+/// An assistant
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct Assistant {
+    pub id: String,
+    /// Unix timestamp, in seconds
+    #[serde(default, deserialize_with = "crate::api::lenient")]
+    pub created_at: u64,
+    pub model: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub instructions: Option<String>,
+    #[serde(default, deserialize_with = "crate::api::lenient")]
+    pub tools: Vec<AssistantTool>,
+    #[serde(default, deserialize_with = "crate::api::lenient")]
+    pub tool_resources: ToolResources,
+    #[serde(default, deserialize_with = "crate::api::lenient")]
+    pub metadata: HashMap<String, String>,
+}
+
+/// A thread: the conversation assistants run on
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct Thread {
+    pub id: String,
+    /// Unix timestamp, in seconds
+    #[serde(default, deserialize_with = "crate::api::lenient")]
+    pub created_at: u64,
+    #[serde(default, deserialize_with = "crate::api::lenient")]
+    pub tool_resources: ToolResources,
+    #[serde(default, deserialize_with = "crate::api::lenient")]
+    pub metadata: HashMap<String, String>,
+}
+
+/// Where a run is in its lifecycle
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum RunStatus {
+    Queued,
+    InProgress,
+    /// The run waits for the outputs of the tool calls in `Run::required_action`
+    RequiresAction,
+    Cancelling,
+    Cancelled,
+    Failed,
+    Completed,
+    /// The run ended early, see `Run::incomplete_details`
+    Incomplete,
+    Expired,
+    /// A status this version of the library doesn't know about
+    #[serde(other)]
+    Other,
+}
+
+impl RunStatus {
+    /// Whether the run is over, successfully or not
+    #[must_use]
+    pub fn is_terminal(self) -> bool {
+        matches!(
+            self,
+            RunStatus::Cancelled
+                | RunStatus::Failed
+                | RunStatus::Completed
+                | RunStatus::Incomplete
+                | RunStatus::Expired
+        )
+    }
+
+    /// Whether the run won't progress any further without the caller: it is over, or
+    /// requires action
+    #[must_use]
+    pub fn is_waiting_on_caller(self) -> bool {
+        self.is_terminal() || self == RunStatus::RequiresAction
+    }
+}
+
+/// A run of an assistant on a thread
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct Run {
+    pub id: String,
+    pub thread_id: String,
+    pub assistant_id: String,
+    pub status: RunStatus,
+    /// Unix timestamp, in seconds
+    #[serde(default, deserialize_with = "crate::api::lenient")]
+    pub created_at: u64,
+    #[serde(default, deserialize_with = "crate::api::lenient")]
+    pub model: String,
+    /// What the run needs to continue, when it `RequiresAction`
+    #[serde(default)]
+    pub required_action: Option<RequiredAction>,
+    /// Why the run failed, if it did
+    #[serde(default)]
+    pub last_error: Option<RunError>,
+    /// Why the run is incomplete, if it is
+    #[serde(default)]
+    pub incomplete_details: Option<serde_json::Value>,
+}
+
+impl Run {
+    /// The calls of the caller's functions the run waits for the outputs of, if any
+    #[must_use]
+    pub fn tool_calls(&self) -> &[ToolCall] {
+        self.required_action
+            .as_ref()
+            .and_then(|action| action.submit_tool_outputs.as_ref())
+            .map_or(&[], |submit| &submit.tool_calls)
+    }
+}
+
+/// What a run needs from the caller to continue
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct RequiredAction {
+    /// The tool calls to submit the outputs of
+    #[serde(default)]
+    pub submit_tool_outputs: Option<SubmitToolOutputs>,
+}
+
+/// The tool calls a run waits for the outputs of
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SubmitToolOutputs {
+    #[serde(default)]
+    pub tool_calls: Vec<ToolCall>,
+}
+
+/// The output of a tool call, answering `Run::tool_calls`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ToolOutput {
+    pub tool_call_id: String,
+    pub output: String,
+}
+
+impl ToolOutput {
+    #[must_use]
+    pub fn new(tool_call_id: impl Into<String>, output: impl Into<String>) -> Self {
+        Self {
+            tool_call_id: tool_call_id.into(),
+            output: output.into(),
+        }
+    }
+}
+
+/// Arguments of a run's creation: the assistant to run, and what to override of it for
+/// this run
+///
+/// # Example
+/// ```
+/// # use openai_api::assistants::RunArgs;
+/// let args = RunArgs::builder()
+///     .assistant_id("asst_abc")
+///     .additional_instructions("Answer in French.")
+///     .build()
+///     .unwrap();
+/// let json = serde_json::to_value(&args).unwrap();
+/// assert_eq!(json["assistant_id"], "asst_abc");
+/// assert!(json.get("model").is_none());
+/// ```
+#[derive(Serialize, Deserialize, Debug, Builder, Clone)]
+#[builder(pattern = "immutable")]
+pub struct RunArgs {
+    #[builder(setter(into))]
+    assistant_id: String,
+    /// The model to run, instead of the assistant's
+    #[builder(setter(into, strip_option), default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    model: Option<String>,
+    /// Instructions replacing the assistant's
+    #[builder(setter(into, strip_option), default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    instructions: Option<String>,
+    /// Instructions appended to the assistant's
+    #[builder(setter(into, strip_option), default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    additional_instructions: Option<String>,
+    /// Tools replacing the assistant's
+    #[builder(setter(into, strip_option), default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tools: Option<Vec<AssistantTool>>,
+    /// Up to 16 key-value pairs, for the caller's own use
+    #[builder(setter(into), default)]
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    metadata: HashMap<String, String>,
+}
+
+impl RunArgs {
+    /// Build a `RunArgs` from the defaults
+    #[must_use]
+    pub fn builder() -> RunArgsBuilder {
+        RunArgsBuilder::default()
+    }
+}
+
+impl TryFrom<RunArgsBuilder> for RunArgs {
+    type Error = RunArgsBuilderError;
+
+    fn try_from(builder: RunArgsBuilder) -> std::result::Result<Self, Self::Error> {
+        builder.build()
+    }
+}
+
+impl From<RunArgsBuilderError> for Error {
+    fn from(err: RunArgsBuilderError) -> Self {
+        Error::BadArguments(err.to_string())
+    }
+}
+
+/// An event of a streamed run, told apart by the `object` the API sends
+///
+/// # Example
+/// ```
+/// # use openai_api::assistants::RunEvent;
+/// let event: RunEvent = serde_json::from_str(
+///     r#"{"id": "msg_1", "object": "thread.message.delta", "delta": {"content":
+///     [{"index": 0, "type": "text", "text": {"value": "Hello"}}]}}"#,
+/// )
+/// .unwrap();
+/// assert_eq!(event.text_delta().as_deref(), Some("Hello"));
+/// ```
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "object")]
+pub enum RunEvent {
+    /// The run changed status, e.g. it was created, requires action, or is completed
+    #[serde(rename = "thread.run")]
+    Run(Run),
+    /// A step of the run was created or changed
+    #[serde(rename = "thread.run.step")]
+    Step(serde_json::Value),
+    /// Part of a step, as it is generated
+    #[serde(rename = "thread.run.step.delta")]
+    StepDelta(serde_json::Value),
+    /// A message was created or changed
+    #[serde(rename = "thread.message")]
+    Message(serde_json::Value),
+    /// Part of a message, as it is generated
+    #[serde(rename = "thread.message.delta")]
+    MessageDelta(serde_json::Value),
+    /// An event this version of the library doesn't know about
+    #[serde(other)]
+    Other,
+}
+
+impl RunEvent {
+    /// The text a `MessageDelta` adds to its message, if it adds any
+    #[must_use]
+    pub fn text_delta(&self) -> Option<String> {
+        let RunEvent::MessageDelta(delta) = self else {
+            return None;
+        };
+        let text: String = delta
+            .pointer("/delta/content")?
+            .as_array()?
+            .iter()
+            .filter_map(|part| part.pointer("/text/value")?.as_str())
+            .collect();
+        Some(text).filter(|text| !text.is_empty())
+    }
+}
+
+/// The error a run failed with
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct RunError {
+    /// E.g. `"rate_limit_exceeded"`
+    #[serde(default, deserialize_with = "crate::api::lenient")]
+    pub code: String,
+    #[serde(default, deserialize_with = "crate::api::lenient")]
+    pub message: String,
+}
+
+impl Client {
+    /// Creates an assistant
+    ///
+    /// # Errors
+    ///  - `Error::BadArguments` if the arguments can't be built
+    ///  - `Error::DryRun` in dry-run mode
+    ///  - `Error::Api` if the api returns an error
+    pub async fn create_assistant<A>(&self, args: A) -> Result<Assistant>
+    where
+        A: TryInto<AssistantArgs>,
+        Error: From<<A as TryInto<AssistantArgs>>::Error>,
+    {
+        let args: AssistantArgs = args.try_into()?;
+        self.dry_run("assistants", &args, || Ok(()))?;
+        self.assistants_post("assistants", &args).await
+    }
+
+    /// Creates a thread, e.g. `ThreadArgs::default()` for an empty one
+    ///
+    /// # Errors
+    ///  - `Error::BadArguments` if the arguments can't be built
+    ///  - `Error::DryRun` in dry-run mode
+    ///  - `Error::Api` if the api returns an error
+    pub async fn create_thread<A>(&self, args: A) -> Result<Thread>
+    where
+        A: TryInto<ThreadArgs>,
+        Error: From<<A as TryInto<ThreadArgs>>::Error>,
+    {
+        let args: ThreadArgs = args.try_into()?;
+        self.dry_run("threads", &args, || Ok(()))?;
+        self.assistants_post("threads", &args).await
+    }
+
+    /// Starts a run of an assistant on a thread
+    ///
+    /// # Errors
+    ///  - `Error::BadArguments` if the arguments can't be built
+    ///  - `Error::DryRun` in dry-run mode
+    ///  - `Error::Api` if the api returns an error
+    pub async fn create_run<A>(&self, thread_id: &str, args: A) -> Result<Run>
+    where
+        A: TryInto<RunArgs>,
+        Error: From<<A as TryInto<RunArgs>>::Error>,
+    {
+        let args: RunArgs = args.try_into()?;
+        let endpoint = format!("threads/{}/runs", thread_id);
+        self.dry_run(&endpoint, &args, || Ok(()))?;
+        self.assistants_post(&endpoint, &args).await
+    }
+
+    /// Starts a run, and polls it until it is over or requires action, see
+    /// `wait_until_terminal`
+    ///
+    /// # Errors
+    ///  - The errors of `create_run`
+    ///  - `Error::PollTimeout` if the run is still going when `config` runs out of time
+    pub async fn run_and_poll<A>(
+        &self,
+        thread_id: &str,
+        args: A,
+        config: &PollConfig,
+    ) -> Result<Run>
+    where
+        A: TryInto<RunArgs>,
+        Error: From<<A as TryInto<RunArgs>>::Error>,
+    {
+        let run = self.create_run(thread_id, args).await?;
+        self.wait_until_terminal(run, config).await
+    }
+
+    /// Starts a run, streaming its events as it goes
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use futures::StreamExt;
+    /// # use openai_api::assistants::{RunArgs, RunEvent};
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), openai_api::Error> {
+    /// let client = openai_api::Client::new("sk-...")?;
+    /// let args = RunArgs::builder().assistant_id("asst_abc");
+    /// let mut events = Box::pin(client.stream_run("thread_abc", args).await?);
+    /// while let Some(event) = events.next().await {
+    ///     match event? {
+    ///         RunEvent::Run(run) if !run.tool_calls().is_empty() => break,
+    ///         event => print!("{}", event.text_delta().unwrap_or_default()),
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///  - The errors of `create_run`, before the stream starts
+    ///  - `Error::Api` in the stream, if the api reports an error mid-stream
+    pub async fn stream_run<A>(
+        &self,
+        thread_id: &str,
+        args: A,
+    ) -> Result<impl Stream<Item = Result<RunEvent>>>
+    where
+        A: TryInto<RunArgs>,
+        Error: From<<A as TryInto<RunArgs>>::Error>,
+    {
+        let args: RunArgs = args.try_into()?;
+        let endpoint = format!("threads/{}/runs", thread_id);
+        self.dry_run(&endpoint, &args, || Ok(()))?;
+        self.assistants_stream(&endpoint, &args).await
+    }
+
+    /// Submits the outputs of the tool calls a run requires, see `Run::tool_calls`, for it
+    /// to continue
+    ///
+    /// # Errors
+    /// - `Error::DryRun` in dry-run mode
+    /// - `Error::Api` if the api returns an error
+    pub async fn submit_tool_outputs(
+        &self,
+        thread_id: &str,
+        run_id: &str,
+        tool_outputs: Vec<ToolOutput>,
+    ) -> Result<Run> {
+        let endpoint = format!("threads/{}/runs/{}/submit_tool_outputs", thread_id, run_id);
+        let body = serde_json::json!({ "tool_outputs": tool_outputs });
+        self.dry_run(&endpoint, &body, || Ok(()))?;
+        self.assistants_post(&endpoint, &body).await
+    }
+
+    /// Submits the outputs of the tool calls a run requires, streaming the events of the
+    /// run as it continues
+    ///
+    /// # Errors
+    ///  - The errors of `submit_tool_outputs`, before the stream starts
+    ///  - `Error::Api` in the stream, if the api reports an error mid-stream
+    pub async fn submit_tool_outputs_stream(
+        &self,
+        thread_id: &str,
+        run_id: &str,
+        tool_outputs: Vec<ToolOutput>,
+    ) -> Result<impl Stream<Item = Result<RunEvent>>> {
+        let endpoint = format!("threads/{}/runs/{}/submit_tool_outputs", thread_id, run_id);
+        let body = serde_json::json!({ "tool_outputs": tool_outputs });
+        self.dry_run(&endpoint, &body, || Ok(()))?;
+        self.assistants_stream(&endpoint, &body).await
+    }
+
+    /// Fetches the current state of a run
+    ///
+    /// # Errors
+    /// - `Error::Api` if the api returns an error
+    pub async fn retrieve_run(&self, thread_id: &str, run_id: &str) -> Result<Run> {
+        self.assistants_get(&format!("threads/{}/runs/{}", thread_id, run_id))
+            .await
+    }
+
+    /// Polls `run` until it is over, or requires action, see
+    /// `RunStatus::is_waiting_on_caller`. Its status is checked first, so an already
+    /// finished run is returned without any request.
+    ///
+    /// # Errors
+    /// - `Error::PollTimeout` if the run is still going when `config` runs out of time
+    /// - `Error::Api` if the api returns an error
+    pub async fn wait_until_terminal(&self, run: Run, config: &PollConfig) -> Result<Run> {
+        let (thread_id, run_id) = (run.thread_id.clone(), run.id.clone());
+        crate::poll::until(
+            config,
+            run,
+            || self.retrieve_run(&thread_id, &run_id),
+            |run| run.status.is_waiting_on_caller(),
+            |_| {},
+        )
+        .await
+    }
+
+    /// Private helper for posts on the Assistants API
+    pub(crate) async fn assistants_post<B, T>(&self, endpoint: &str, body: &B) -> Result<T>
+    where
+        B: Serialize,
+        T: DeserializeOwned,
+    {
+        let url = self.url(endpoint)?;
+        let body = self.body(body)?;
+        let (name, value) = BETA_HEADER;
+        self.send(self.inner.client.post(url).header(name, value).json(&body))
+            .await
+    }
+
+    /// Private helper for posts on the Assistants API whose response streams back as
+    /// server-sent events
+    async fn assistants_stream<B, T>(
+        &self,
+        endpoint: &str,
+        body: &B,
+    ) -> Result<impl Stream<Item = Result<T>>>
+    where
+        B: Serialize,
+        T: DeserializeOwned,
+    {
+        let url = self.url(endpoint)?;
+        let mut body = self.body(body)?;
+        body["stream"] = true.into();
+        let (name, value) = BETA_HEADER;
+        self.send_stream(self.inner.client.post(url).header(name, value).json(&body))
+            .await
+    }
+
+    /// Private helper for gets on the Assistants API
+    pub(crate) async fn assistants_get<T: DeserializeOwned>(&self, endpoint: &str) -> Result<T> {
+        self.assistants_get_with_query(endpoint, &()).await
+    }
+
+    /// Private helper for gets with query parameters on the Assistants API, e.g.
+    /// `api::ListParams` on list endpoints
+    pub(crate) async fn assistants_get_with_query<T, Q>(
+        &self,
+        endpoint: &str,
+        query: &Q,
+    ) -> Result<T>
+    where
+        T: DeserializeOwned,
+        Q: Serialize + ?Sized,
+    {
+        let url = self.url(endpoint)?;
+        let (name, value) = BETA_HEADER;
+        self.send(self.inner.client.get(url).header(name, value).query(query))
+            .await
+    }
+
+    /// Private helper for deletes on the Assistants API
+    pub(crate) async fn assistants_delete<T: DeserializeOwned>(&self, endpoint: &str) -> Result<T> {
+        let url = self.url(endpoint)?;
+        let (name, value) = BETA_HEADER;
+        self.send(self.inner.client.delete(url).header(name, value))
+            .await
+    }
+}
+
+#[cfg(test)]
+mod unit {
+    use std::time::Duration;
+
+    use mockito::Matcher;
+    use serde_json::json;
+
+    use futures::TryStreamExt;
+
+    use super::{
+        AssistantArgs, AssistantTool, Run, RunArgs, RunEvent, RunStatus, ThreadArgs, ToolOutput,
+        ToolResources,
+    };
+    use crate::{api::FunctionSpec, poll::PollConfig, unit::mocked_client};
+
+    fn run(status: &str) -> String {
+        format!(
+            r#"{{"id": "run_1", "object": "thread.run", "created_at": 1699063290,
+            "thread_id": "thread_1", "assistant_id": "asst_1", "status": "{}",
+            "model": "gpt-4o"}}"#,
+            status
+        )
+    }
+
+    #[tokio::test]
+    async fn waits_for_runs() -> crate::Result<()> {
+        let queued: Run = serde_json::from_str(&run("queued"))?;
+        let mock = mockito::mock("GET", "/threads/thread_1/runs/run_1")
+            .match_header("openai-beta", "assistants=v2")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(run("completed"))
+            .expect(1)
+            .create();
+        let config = PollConfig {
+            initial_interval: Duration::from_millis(1),
+            ..PollConfig::default()
+        };
+        let done = mocked_client().wait_until_terminal(queued, &config).await?;
+        assert_eq!(done.status, RunStatus::Completed);
+        mock.assert();
+
+        let unknown: Run = serde_json::from_str(&run("paused"))?;
+        assert_eq!(unknown.status, RunStatus::Other);
+        assert!(!RunStatus::RequiresAction.is_terminal());
+        assert!(RunStatus::RequiresAction.is_waiting_on_caller());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn runs_with_tool_outputs() -> crate::Result<()> {
+        let requires_action = r#"{"id": "run_2", "object": "thread.run", "thread_id": "thread_2",
+            "assistant_id": "asst_1", "status": "requires_action", "required_action": {"type":
+            "submit_tool_outputs", "submit_tool_outputs": {"tool_calls": [{"id": "call_1",
+            "type": "function", "function": {"name": "get_time", "arguments": "{}"}}]}}}"#;
+        let create = mockito::mock("POST", "/threads/thread_2/runs")
+            .match_header("openai-beta", "assistants=v2")
+            .match_body(Matcher::Json(json!({
+                "assistant_id": "asst_1",
+                "tools": [{"type": "function", "function": {
+                    "name": "get_time", "parameters": {"type": "object"}
+                }}],
+            })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(requires_action)
+            .expect(1)
+            .create();
+        let submit = mockito::mock("POST", "/threads/thread_2/runs/run_2/submit_tool_outputs")
+            .match_header("openai-beta", "assistants=v2")
+            .match_body(Matcher::Json(json!({
+                "tool_outputs": [{"tool_call_id": "call_1", "output": "12:00"}],
+            })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(run("queued"))
+            .expect(1)
+            .create();
+
+        let client = mocked_client();
+        let args = RunArgs::builder()
+            .assistant_id("asst_1")
+            .tools(vec![FunctionSpec::new(
+                "get_time",
+                json!({"type": "object"}),
+            )
+            .into()]);
+        let config = PollConfig::default();
+        let run = client.run_and_poll("thread_2", args, &config).await?;
+        assert_eq!(run.status, RunStatus::RequiresAction);
+        let calls = run.tool_calls();
+        assert_eq!(calls[0].function.name, "get_time");
+        let outputs = vec![ToolOutput::new(&calls[0].id, "12:00")];
+        let run = client
+            .submit_tool_outputs(&run.thread_id, &run.id, outputs)
+            .await?;
+        assert_eq!(run.status, RunStatus::Queued);
+        assert!(run.tool_calls().is_empty());
+        create.assert();
+        submit.assert();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn streams_runs() -> crate::Result<()> {
+        let events = [
+            ("thread.run.created", run("queued")),
+            (
+                "thread.message.delta",
+                r#"{"id": "msg_1", "object": "thread.message.delta", "delta": {"content":
+                [{"index": 0, "type": "text", "text": {"value": "Hel"}}]}}"#
+                    .into(),
+            ),
+            (
+                "thread.message.delta",
+                r#"{"id": "msg_1", "object": "thread.message.delta", "delta": {"content":
+                [{"index": 0, "type": "text", "text": {"value": "lo!"}}]}}"#
+                    .into(),
+            ),
+            (
+                "thread.created",
+                r#"{"object": "thread", "id": "t"}"#.into(),
+            ),
+            ("thread.run.completed", run("completed")),
+            ("done", "[DONE]".into()),
+        ];
+        let body: String = events
+            .iter()
+            .map(|(event, data)| format!("event: {}\ndata: {}\n\n", event, data.replace('\n', "")))
+            .collect();
+        let mock = mockito::mock("POST", "/threads/thread_3/runs")
+            .match_header("openai-beta", "assistants=v2")
+            .match_body(Matcher::Json(
+                json!({"assistant_id": "asst_1", "stream": true}),
+            ))
+            .with_status(200)
+            .with_header("content-type", "text/event-stream")
+            .with_body(body)
+            .expect(1)
+            .create();
+        let args = RunArgs::builder().assistant_id("asst_1");
+        let events: Vec<RunEvent> = mocked_client()
+            .stream_run("thread_3", args)
+            .await?
+            .try_collect()
+            .await?;
+        assert_eq!(events.len(), 5);
+        let text: String = events.iter().filter_map(RunEvent::text_delta).collect();
+        assert_eq!(text, "Hello!");
+        assert_eq!(events[3], RunEvent::Other);
+        match &events[4] {
+            RunEvent::Run(run) => assert_eq!(run.status, RunStatus::Completed),
+            other => panic!("expected a run, got {:?}", other),
+        }
+        mock.assert();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn creates_assistants_with_tool_resources() -> crate::Result<()> {
+        let mock = mockito::mock("POST", "/assistants")
+            .match_header("openai-beta", "assistants=v2")
+            .match_body(Matcher::Json(json!({
+                "model": "gpt-4o",
+                "tools": [{"type": "code_interpreter"}, {"type": "file_search"}],
+                "tool_resources": {
+                    "code_interpreter": {"file_ids": ["file-1"]},
+                    "file_search": {"vector_store_ids": ["vs_1"]},
+                },
+            })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"{"id": "asst_1", "object": "assistant", "created_at": 1, "model": "gpt-4o",
+                "name": null, "instructions": null, "tools": [{"type": "code_interpreter"},
+                {"type": "file_search"}], "tool_resources": {"code_interpreter": {"file_ids":
+                ["file-1"]}, "file_search": {"vector_store_ids": ["vs_1"]}}, "metadata": {}}"#,
+            )
+            .expect(1)
+            .create();
+        let resources = ToolResources::default()
+            .with_code_interpreter_files(vec!["file-1".into()])
+            .with_vector_stores(vec!["vs_1".into()]);
+        let args = AssistantArgs::builder()
+            .model("gpt-4o")
+            .tools(vec![
+                AssistantTool::CodeInterpreter,
+                AssistantTool::FileSearch,
+            ])
+            .tool_resources(resources.clone());
+        let assistant = mocked_client().create_assistant(args).await?;
+        assert_eq!(assistant.tool_resources, resources);
+        mock.assert();
+
+        let missing_tool = AssistantArgs::builder()
+            .model("gpt-4o")
+            .tool_resources(resources.clone())
+            .build();
+        assert!(missing_tool.is_err());
+        let two_stores = ThreadArgs::builder()
+            .tool_resources(
+                ToolResources::default().with_vector_stores(vec!["a".into(), "b".into()]),
+            )
+            .build();
+        assert!(two_stores.is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,623 @@
+//! Audio: transcription of speech to text, and speech from text.
+use serde::{Deserialize, Serialize};
+
+use crate::{api::Problems, json, multipart::Form, Client, Error, Result};
+
+/// The format of a transcription, see `Transcription`
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TranscriptionFormat {
+    /// The text, as JSON
+    #[default]
+    #[serde(rename = "json")]
+    Json,
+    /// The text as it is
+    #[serde(rename = "text")]
+    Text,
+    /// SubRip subtitles
+    #[serde(rename = "srt")]
+    Srt,
+    /// The text with its language and duration, as JSON
+    #[serde(rename = "verbose_json")]
+    VerboseJson,
+    /// WebVTT subtitles
+    #[serde(rename = "vtt")]
+    Vtt,
+}
+
+impl TranscriptionFormat {
+    /// The API's name of the format, e.g. `"verbose_json"`
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TranscriptionFormat::Json => "json",
+            TranscriptionFormat::Text => "text",
+            TranscriptionFormat::Srt => "srt",
+            TranscriptionFormat::VerboseJson => "verbose_json",
+            TranscriptionFormat::Vtt => "vtt",
+        }
+    }
+}
+
+/// The detail of the timestamps of a `verbose_json` transcription
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TimestampGranularity {
+    /// Timestamps of each segment, given by default
+    Segment,
+    /// Timestamps of each word, at the cost of some latency
+    Word,
+}
+
+impl TimestampGranularity {
+    /// The API's name of the granularity
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TimestampGranularity::Segment => "segment",
+            TimestampGranularity::Word => "word",
+        }
+    }
+}
+
+/// Arguments of a transcription request.
+///
+/// # Example
+/// ```no_run
+/// # use openai_api::audio::{TranscriptionArgs, TranscriptionFormat};
+/// let args = TranscriptionArgs::builder()
+///     .file(std::fs::read("interview.mp3").unwrap())
+///     .file_name("interview.mp3")
+///     .response_format(TranscriptionFormat::Srt)
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Serialize, Deserialize, Debug, Builder, Clone)]
+#[builder(pattern = "immutable")]
+pub struct TranscriptionArgs {
+    /// The audio file: flac, mp3, mp4, mpeg, mpga, m4a, ogg, wav or webm. Not part of the
+    /// args when they are (de)serialized.
+    #[builder(setter(into))]
+    #[serde(skip)]
+    file: Vec<u8>,
+    /// Name of the file, whose extension tells the API its format, e.g. `"speech.mp3"`
+    #[builder(setter(into))]
+    file_name: String,
+    /// The id of the model to use for this request
+    #[builder(setter(into), default = "\"whisper-1\".into()")]
+    model: String,
+    /// The language of the audio, as an ISO-639-1 code such as `"en"`. Improves accuracy
+    /// and latency.
+    #[builder(setter(into, strip_option), default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    language: Option<String>,
+    /// Text guiding the style of the transcription, or continuing a previous segment
+    #[builder(setter(into, strip_option), default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    prompt: Option<String>,
+    /// JSON by default
+    #[builder(default)]
+    #[serde(default)]
+    response_format: TranscriptionFormat,
+    /// Sampling temperature, between 0 and 1
+    #[builder(setter(strip_option), default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    temperature: Option<f64>,
+    /// The timestamps to give, which requires the `verbose_json` format
+    #[builder(setter(into), default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    timestamp_granularities: Vec<TimestampGranularity>,
+}
+
+impl TranscriptionArgs {
+    /// Build a `TranscriptionArgs` from the defaults
+    #[must_use]
+    pub fn builder() -> TranscriptionArgsBuilder {
+        TranscriptionArgsBuilder::default()
+    }
+
+    /// Checks the arguments locally, without calling the API
+    ///
+    /// # Errors
+    /// - `Error::BadArguments` listing every problem found
+    pub fn validate(&self) -> Result<()> {
+        let mut problems = Problems::default();
+        problems.check(!self.file.is_empty(), || "the audio file is empty".into());
+        if let Some(temperature) = self.temperature {
+            problems.range("temperature", temperature, 0.0, 1.0);
+        }
+        problems.check(
+            self.timestamp_granularities.is_empty()
+                || self.response_format == TranscriptionFormat::VerboseJson,
+            || "timestamp_granularities require the verbose_json response_format".into(),
+        );
+        problems.into_result()
+    }
+
+    fn form(&self) -> Form {
+        let temperature = self.temperature.map(|t| t.to_string());
+        let form = self
+            .timestamp_granularities
+            .iter()
+            .fold(Form::new(), |form, granularity| {
+                form.text("timestamp_granularities[]", granularity.as_str())
+            });
+        form.text("model", &self.model)
+            .text("response_format", self.response_format.as_str())
+            .text_opt("language", self.language.as_deref())
+            .text_opt("prompt", self.prompt.as_deref())
+            .text_opt("temperature", temperature.as_deref())
+            .file("file", &self.file_name, &self.file)
+    }
+}
+
+impl TryFrom<TranscriptionArgsBuilder> for TranscriptionArgs {
+    type Error = TranscriptionArgsBuilderError;
+
+    fn try_from(builder: TranscriptionArgsBuilder) -> std::result::Result<Self, Self::Error> {
+        builder.build()
+    }
+}
+
+impl From<TranscriptionArgsBuilderError> for Error {
+    fn from(err: TranscriptionArgsBuilderError) -> Self {
+        Error::BadArguments(err.to_string())
+    }
+}
+
+/// A transcription, in the format that was requested
+#[derive(Debug, Clone, PartialEq)]
+pub enum Transcription {
+    Json(TranscriptionText),
+    Text(String),
+    /// SubRip subtitles, as the API wrote them
+    Srt(String),
+    VerboseJson(VerboseTranscription),
+    /// WebVTT subtitles, as the API wrote them
+    Vtt(String),
+}
+
+impl Transcription {
+    /// The transcribed text, or the subtitles
+    #[must_use]
+    pub fn text(&self) -> &str {
+        match self {
+            Transcription::Json(json) => &json.text,
+            Transcription::VerboseJson(json) => &json.text,
+            Transcription::Text(text) | Transcription::Srt(text) | Transcription::Vtt(text) => text,
+        }
+    }
+}
+
+impl std::fmt::Display for Transcription {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.text())
+    }
+}
+
+/// A `json` transcription
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct TranscriptionText {
+    pub text: String,
+}
+
+/// A `verbose_json` transcription
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct VerboseTranscription {
+    pub text: String,
+    /// The language detected, or given, e.g. `"english"`
+    #[serde(default, deserialize_with = "crate::api::lenient")]
+    pub language: String,
+    /// Duration of the audio, in seconds
+    #[serde(default, deserialize_with = "crate::api::lenient")]
+    pub duration: f64,
+    /// The segments of the text, unless only word timestamps were requested
+    #[serde(default, deserialize_with = "crate::api::lenient")]
+    pub segments: Vec<Segment>,
+    /// The words of the text, if word timestamps were requested
+    #[serde(default, deserialize_with = "crate::api::lenient")]
+    pub words: Vec<Word>,
+}
+
+/// A segment of a `verbose_json` transcription. Times are in seconds from the start of the
+/// audio.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct Segment {
+    pub id: u32,
+    pub start: f64,
+    pub end: f64,
+    pub text: String,
+    /// Average log probability of the segment's tokens; below -1 the segment is dubious
+    #[serde(default)]
+    pub avg_logprob: Option<f64>,
+    /// Probability that the segment holds no speech
+    #[serde(default)]
+    pub no_speech_prob: Option<f64>,
+    /// Above 2.4 the segment is likely a repetition the model got stuck on
+    #[serde(default)]
+    pub compression_ratio: Option<f64>,
+    #[serde(default)]
+    pub temperature: Option<f64>,
+    #[serde(default)]
+    pub tokens: Vec<u32>,
+}
+
+impl Segment {
+    /// Whether the segment's span covers the time `at`, in seconds
+    #[must_use]
+    pub fn contains(&self, at: f64) -> bool {
+        self.start <= at && at < self.end
+    }
+}
+
+/// A word of a `verbose_json` transcription, with its times in seconds
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct Word {
+    pub word: String,
+    pub start: f64,
+    pub end: f64,
+}
+
+impl Word {
+    /// Whether the word is being spoken at the time `at`, in seconds
+    #[must_use]
+    pub fn contains(&self, at: f64) -> bool {
+        self.start <= at && at < self.end
+    }
+}
+
+impl VerboseTranscription {
+    /// The word spoken at the time `at`, in seconds, e.g. to highlight it
+    #[must_use]
+    pub fn word_at(&self, at: f64) -> Option<&Word> {
+        self.words.iter().find(|word| word.contains(at))
+    }
+}
+
+impl Client {
+    /// Transcribes an audio file into text, or subtitles, in its language
+    ///
+    /// Accepts anything convertible to `TranscriptionArgs`, including an unbuilt
+    /// `TranscriptionArgsBuilder`.
+    ///
+    /// # Errors
+    ///  - `Error::BadArguments` if the arguments can't be built, or are invalid in dry-run
+    ///    mode
+    ///  - `Error::DryRun` in dry-run mode, if the arguments are valid
+    ///  - `Error::Api` if the api returns an error
+    pub async fn transcribe<A>(&self, args: A) -> Result<Transcription>
+    where
+        A: TryInto<TranscriptionArgs>,
+        Error: From<<A as TryInto<TranscriptionArgs>>::Error>,
+    {
+        let args: TranscriptionArgs = args.try_into()?;
+        self.dry_run("audio/transcriptions", &args, || args.validate())?;
+        let request = self.inner.client.post(self.url("audio/transcriptions")?);
+        let body = self.send_raw(args.form().attach(request)).await?;
+        let text = || String::from_utf8_lossy(&body).into_owned();
+        Ok(match args.response_format {
+            TranscriptionFormat::Json => Transcription::Json(json::from_bytes(body)?),
+            TranscriptionFormat::VerboseJson => Transcription::VerboseJson(json::from_bytes(body)?),
+            TranscriptionFormat::Text => Transcription::Text(text()),
+            TranscriptionFormat::Srt => Transcription::Srt(text()),
+            TranscriptionFormat::Vtt => Transcription::Vtt(text()),
+        })
+    }
+}
+
+/// The voice of generated speech
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Voice {
+    #[default]
+    Alloy,
+    Ash,
+    Coral,
+    Echo,
+    Fable,
+    Onyx,
+    Nova,
+    Sage,
+    Shimmer,
+}
+
+/// The audio format of generated speech
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SpeechFormat {
+    #[default]
+    Mp3,
+    /// For streaming over the internet, with little latency
+    Opus,
+    Aac,
+    Flac,
+    Wav,
+    /// Raw 24kHz samples, signed 16-bit little-endian, without header
+    Pcm,
+}
+
+impl SpeechFormat {
+    /// The file extension of the format, e.g. `"mp3"`
+    #[must_use]
+    pub fn extension(self) -> &'static str {
+        match self {
+            SpeechFormat::Mp3 => "mp3",
+            SpeechFormat::Opus => "opus",
+            SpeechFormat::Aac => "aac",
+            SpeechFormat::Flac => "flac",
+            SpeechFormat::Wav => "wav",
+            SpeechFormat::Pcm => "pcm",
+        }
+    }
+}
+
+/// The longest text, in characters, that can be spoken at once
+pub const MAX_SPEECH_CHARS: usize = 4096;
+
+/// Arguments of a speech request. Checked when built.
+///
+/// # Example
+/// ```
+/// # use openai_api::audio::{SpeechArgs, SpeechFormat, Voice};
+/// let args = SpeechArgs::builder()
+///     .input("Hello there.")
+///     .voice(Voice::Nova)
+///     .response_format(SpeechFormat::Opus)
+///     .speed(1.25)
+///     .build()
+///     .unwrap();
+/// assert!(SpeechArgs::builder().input("Hi").speed(5.0).build().is_err());
+/// ```
+#[derive(Serialize, Deserialize, Debug, Builder, Clone)]
+#[builder(pattern = "immutable", build_fn(validate = "Self::check"))]
+pub struct SpeechArgs {
+    /// The id of the model to use for this request
+    #[builder(setter(into), default = "\"tts-1\".into()")]
+    model: String,
+    /// The text to speak, at most `MAX_SPEECH_CHARS` long
+    #[builder(setter(into))]
+    input: String,
+    #[builder(default)]
+    #[serde(default)]
+    voice: Voice,
+    /// Mp3 by default
+    #[builder(default)]
+    #[serde(default)]
+    response_format: SpeechFormat,
+    /// Between 0.25 and 4, 1 by default
+    #[builder(setter(strip_option), default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    speed: Option<f64>,
+}
+
+impl SpeechArgs {
+    /// Build a `SpeechArgs` from the defaults
+    #[must_use]
+    pub fn builder() -> SpeechArgsBuilder {
+        SpeechArgsBuilder::default()
+    }
+
+    /// Checks the arguments locally, without calling the API. Built args are already
+    /// checked, deserialized ones aren't.
+    ///
+    /// # Errors
+    /// - `Error::BadArguments` listing every problem found
+    pub fn validate(&self) -> Result<()> {
+        speech_problems(&self.input, self.speed).into_result()
+    }
+}
+
+impl SpeechArgsBuilder {
+    fn check(&self) -> std::result::Result<(), String> {
+        speech_problems(self.input.as_deref().unwrap_or("-"), self.speed.flatten()).into_message()
+    }
+}
+
+/// The problems of some speech arguments
+fn speech_problems(input: &str, speed: Option<f64>) -> Problems {
+    let mut problems = Problems::default();
+    let chars = input.chars().count();
+    problems.check(chars > 0, || "the input is empty".into());
+    problems.check(chars <= MAX_SPEECH_CHARS, || {
+        format!(
+            "the input is at most {} characters long, got {}",
+            MAX_SPEECH_CHARS, chars
+        )
+    });
+    if let Some(speed) = speed {
+        problems.range("speed", speed, 0.25, 4.0);
+    }
+    problems
+}
+
+impl TryFrom<SpeechArgsBuilder> for SpeechArgs {
+    type Error = SpeechArgsBuilderError;
+
+    fn try_from(builder: SpeechArgsBuilder) -> std::result::Result<Self, Self::Error> {
+        builder.build()
+    }
+}
+
+impl From<SpeechArgsBuilderError> for Error {
+    fn from(err: SpeechArgsBuilderError) -> Self {
+        Error::BadArguments(err.to_string())
+    }
+}
+
+impl Client {
+    /// Speaks a text, returning the audio in the format requested
+    ///
+    /// # Errors
+    ///  - `Error::BadArguments` if the arguments can't be built, or are invalid in dry-run
+    ///    mode
+    ///  - `Error::DryRun` in dry-run mode, if the arguments are valid
+    ///  - `Error::Api` if the api returns an error
+    pub async fn speech<A>(&self, args: A) -> Result<bytes::Bytes>
+    where
+        A: TryInto<SpeechArgs>,
+        Error: From<<A as TryInto<SpeechArgs>>::Error>,
+    {
+        let args: SpeechArgs = args.try_into()?;
+        self.dry_run("audio/speech", &args, || args.validate())?;
+        let body = self.body(&args)?;
+        let request = self
+            .inner
+            .client
+            .post(self.url("audio/speech")?)
+            .json(&body);
+        self.send_raw(request).await
+    }
+}
+
+#[cfg(test)]
+mod unit {
+    use mockito::Matcher;
+
+    use super::{
+        SpeechArgs, SpeechFormat, TimestampGranularity, Transcription, TranscriptionArgs,
+        TranscriptionFormat, Voice,
+    };
+    use crate::{unit::mocked_client, Error};
+
+    const SRT: &str = "1\n00:00:00,000 --> 00:00:01,500\nHello there.\n";
+
+    #[tokio::test]
+    async fn transcribes_to_subtitles() -> crate::Result<()> {
+        let mock = mockito::mock("POST", "/audio/transcriptions")
+            .match_header(
+                "content-type",
+                Matcher::Regex("^multipart/form-data; boundary=".into()),
+            )
+            .match_body(Matcher::AllOf(vec![
+                Matcher::Regex("name=\"response_format\"\r\n\r\nsrt\r\n".into()),
+                Matcher::Regex("name=\"language\"\r\n\r\nen\r\n".into()),
+                Matcher::Regex("filename=\"hello.mp3\"".into()),
+            ]))
+            .with_status(200)
+            .with_header("content-type", "text/plain")
+            .with_body(SRT)
+            .expect(1)
+            .create();
+        let args = TranscriptionArgs::builder()
+            .file(b"ID3".to_vec())
+            .file_name("hello.mp3")
+            .language("en")
+            .response_format(TranscriptionFormat::Srt);
+        let transcription = mocked_client().transcribe(args).await?;
+        assert_eq!(transcription, Transcription::Srt(SRT.into()));
+        mock.assert();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn transcribes_to_verbose_json() -> crate::Result<()> {
+        let _m = mockito::mock("POST", "/audio/transcriptions")
+            .match_body(Matcher::Regex("verbose_json".into()))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"task": "transcribe", "language": "english", "duration": 1.5, "text": "Hello there."}"#)
+            .create();
+        let args = TranscriptionArgs::builder()
+            .file(b"ID3".to_vec())
+            .file_name("hello.mp3")
+            .response_format(TranscriptionFormat::VerboseJson);
+        match mocked_client().transcribe(args).await? {
+            Transcription::VerboseJson(verbose) => {
+                assert_eq!(verbose.text, "Hello there.");
+                assert_eq!(verbose.language, "english");
+                assert_eq!(verbose.duration, 1.5);
+            }
+            other => panic!("unexpected transcription {:?}", other),
+        }
+
+        let no_file = mocked_client()
+            .with_dry_run(true)
+            .transcribe(TranscriptionArgs::builder().file(vec![]).file_name("a.mp3"))
+            .await;
+        assert!(matches!(no_file, Err(Error::BadArguments(_))));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn transcribes_with_timestamps() -> crate::Result<()> {
+        let _m = mockito::mock("POST", "/audio/transcriptions")
+            .match_body(Matcher::AllOf(vec![
+                Matcher::Regex("name=\"timestamp_granularities\\[\\]\"\r\n\r\nword\r\n".into()),
+                Matcher::Regex("name=\"timestamp_granularities\\[\\]\"\r\n\r\nsegment\r\n".into()),
+            ]))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"{"text": "Hello there.", "language": "english", "duration": 1.5,
+                "segments": [{"id": 0, "seek": 0, "start": 0.0, "end": 1.5, "text": "Hello there.",
+                    "tokens": [50364, 2425], "temperature": 0.0, "avg_logprob": -0.25,
+                    "compression_ratio": 0.8, "no_speech_prob": 0.01}],
+                "words": [{"word": "Hello", "start": 0.0, "end": 0.6},
+                    {"word": "there", "start": 0.7, "end": 1.4}]}"#,
+            )
+            .create();
+        let args = TranscriptionArgs::builder()
+            .file(b"ID3".to_vec())
+            .file_name("hello.mp3")
+            .response_format(TranscriptionFormat::VerboseJson)
+            .timestamp_granularities(vec![
+                TimestampGranularity::Word,
+                TimestampGranularity::Segment,
+            ]);
+        let verbose = match mocked_client().transcribe(args).await? {
+            Transcription::VerboseJson(verbose) => verbose,
+            other => panic!("unexpected transcription {:?}", other),
+        };
+        assert_eq!(verbose.segments[0].avg_logprob, Some(-0.25));
+        assert!(verbose.segments[0].contains(1.0));
+        assert_eq!(verbose.words.len(), 2);
+        assert_eq!(verbose.word_at(1.0).map(|w| w.word.as_str()), Some("there"));
+        assert_eq!(verbose.word_at(0.65), None);
+
+        let not_verbose = mocked_client()
+            .with_dry_run(true)
+            .transcribe(
+                TranscriptionArgs::builder()
+                    .file(b"ID3".to_vec())
+                    .file_name("a.mp3")
+                    .timestamp_granularities(vec![TimestampGranularity::Word]),
+            )
+            .await;
+        assert!(matches!(not_verbose, Err(Error::BadArguments(_))));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn speaks() -> crate::Result<()> {
+        let mock = mockito::mock("POST", "/audio/speech")
+            .match_body(Matcher::Json(serde_json::json!({
+                "model": "tts-1",
+                "input": "Hello there.",
+                "voice": "shimmer",
+                "response_format": "flac",
+                "speed": 0.5,
+            })))
+            .with_status(200)
+            .with_header("content-type", "audio/flac")
+            .with_body(b"fLaC")
+            .expect(1)
+            .create();
+        let args = SpeechArgs::builder()
+            .input("Hello there.")
+            .voice(Voice::Shimmer)
+            .response_format(SpeechFormat::Flac)
+            .speed(0.5);
+        let audio = mocked_client().speech(args).await?;
+        assert_eq!(&audio[..], b"fLaC");
+        mock.assert();
+
+        let too_long = SpeechArgs::builder()
+            .input("a".repeat(super::MAX_SPEECH_CHARS + 1))
+            .build();
+        assert!(too_long.is_err());
+        let too_slow = mocked_client()
+            .speech(SpeechArgs::builder().input("Hi").speed(0.1))
+            .await;
+        assert!(matches!(too_slow, Err(Error::BadArguments(_))));
+        Ok(())
+    }
+}
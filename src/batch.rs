@@ -0,0 +1,720 @@
+//! The Batch API, which runs many requests asynchronously at a discount: building its
+//! input files, creating and polling batches, and parsing their output files.
+use std::collections::{HashMap, HashSet};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    api::{ApiError, ChatAnswer, ChatArgs, ErrorWrapper, ListParams, Page, Problems},
+    embeddings::{Embeddings, EmbeddingsArgs},
+    files::{FilePurpose, FileUploadArgs},
+    poll::PollConfig,
+    Client, Error, Result,
+};
+
+/// Most requests a batch input file may hold
+pub const MAX_REQUESTS_PER_FILE: usize = 50_000;
+
+/// The endpoints batches can run requests against
+pub const ENDPOINTS: [&str; 4] = [
+    "/v1/chat/completions",
+    "/v1/embeddings",
+    "/v1/completions",
+    "/v1/responses",
+];
+
+/// A line of a batch input file: one request, and the id its result will be reported under
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BatchRequest {
+    /// Caller-chosen id, unique within the file
+    pub custom_id: String,
+    /// HTTP method of the request, always `"POST"`
+    pub method: String,
+    /// Endpoint of the request, e.g. `"/v1/chat/completions"`
+    pub url: String,
+    /// Arguments of the request
+    pub body: Value,
+}
+
+/// Builds the JSONL input file of a batch, in the format `/v1/batches` expects.
+///
+/// All the requests of a batch must go to the same endpoint, and have distinct ids.
+///
+/// # Example
+/// ```
+/// # use openai_api::{api::{ChatArgs, ChatRole}, batch::BatchRequestFile};
+/// # fn main() -> Result<(), openai_api::Error> {
+/// let mut file = BatchRequestFile::new();
+/// for (i, question) in ["What is Rust?", "What is Go?"].iter().enumerate() {
+///     let args = ChatArgs::from(vec![(ChatRole::User, question.to_string())]);
+///     file.push_chat(format!("question-{}", i), &args)?;
+/// }
+/// let jsonl = file.to_jsonl()?;
+/// assert_eq!(jsonl.lines().count(), 2);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct BatchRequestFile {
+    requests: Vec<BatchRequest>,
+    ids: HashSet<String>,
+}
+
+impl BatchRequestFile {
+    /// Creates an empty file
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a chat completion request
+    ///
+    /// # Errors
+    /// - `Error::BadArguments` if the id is already used, the file holds requests to
+    ///   another endpoint, or it is full
+    /// - `Error::Json` if the arguments can't be serialized
+    pub fn push_chat(&mut self, custom_id: impl Into<String>, args: &ChatArgs) -> Result<()> {
+        self.push(custom_id.into(), "/v1/chat/completions", args)
+    }
+
+    /// Adds an embeddings request
+    ///
+    /// # Errors
+    /// - `Error::BadArguments` if the id is already used, the file holds requests to
+    ///   another endpoint, or it is full
+    /// - `Error::Json` if the arguments can't be serialized
+    pub fn push_embeddings(
+        &mut self,
+        custom_id: impl Into<String>,
+        args: &EmbeddingsArgs,
+    ) -> Result<()> {
+        self.push(custom_id.into(), "/v1/embeddings", args)
+    }
+
+    fn push<B: Serialize>(&mut self, custom_id: String, url: &str, body: &B) -> Result<()> {
+        if let Some(first) = self.requests.first() {
+            if first.url != url {
+                return Err(Error::BadArguments(format!(
+                    "a batch can't mix requests to {} and {}",
+                    first.url, url
+                )));
+            }
+        }
+        if self.requests.len() >= MAX_REQUESTS_PER_FILE {
+            return Err(Error::BadArguments(format!(
+                "a batch holds at most {} requests",
+                MAX_REQUESTS_PER_FILE
+            )));
+        }
+        if self.ids.contains(&custom_id) {
+            return Err(Error::BadArguments(format!(
+                "duplicate custom_id {:?}",
+                custom_id
+            )));
+        }
+        let body = serde_json::to_value(body)?;
+        self.ids.insert(custom_id.clone());
+        self.requests.push(BatchRequest {
+            custom_id,
+            method: "POST".into(),
+            url: url.into(),
+            body,
+        });
+        Ok(())
+    }
+
+    /// The requests added so far, in order
+    #[must_use]
+    pub fn requests(&self) -> &[BatchRequest] {
+        &self.requests
+    }
+
+    /// Number of requests in the file
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.requests.len()
+    }
+
+    /// Whether the file holds no requests
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.requests.is_empty()
+    }
+
+    /// Serializes the file, one request per line
+    ///
+    /// # Errors
+    /// - `Error::Json` if a request can't be serialized
+    pub fn to_jsonl(&self) -> Result<String> {
+        let mut jsonl = String::new();
+        for request in &self.requests {
+            jsonl.push_str(&serde_json::to_string(request)?);
+            jsonl.push('\n');
+        }
+        Ok(jsonl)
+    }
+
+    /// The endpoint of the requests, once one is added
+    #[must_use]
+    pub fn endpoint(&self) -> Option<&str> {
+        self.requests.first().map(|r| r.url.as_str())
+    }
+}
+
+/// Arguments of a batch's creation
+///
+/// # Example
+/// ```
+/// # use openai_api::batch::BatchArgs;
+/// let args = BatchArgs::builder()
+///     .input_file_id("file-abc")
+///     .endpoint("/v1/chat/completions")
+///     .build()
+///     .unwrap();
+/// assert!(args.validate().is_ok());
+/// ```
+#[derive(Serialize, Deserialize, Debug, Builder, Clone)]
+#[builder(pattern = "immutable")]
+pub struct BatchArgs {
+    /// Id of a JSONL file uploaded with the `batch` purpose
+    #[builder(setter(into))]
+    input_file_id: String,
+    /// The endpoint of all the requests of the file, one of `ENDPOINTS`
+    #[builder(setter(into))]
+    endpoint: String,
+    /// Time the batch has to run, only `"24h"` for now
+    #[builder(setter(into), default = "\"24h\".into()")]
+    completion_window: String,
+    /// Up to 16 key-value pairs, for the caller's own use
+    #[builder(setter(into), default)]
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    metadata: HashMap<String, String>,
+}
+
+impl BatchArgs {
+    /// Build a `BatchArgs` from the defaults
+    #[must_use]
+    pub fn builder() -> BatchArgsBuilder {
+        BatchArgsBuilder::default()
+    }
+
+    /// Checks the arguments locally, without calling the API
+    ///
+    /// # Errors
+    /// - `Error::BadArguments` listing every problem found
+    pub fn validate(&self) -> Result<()> {
+        let mut problems = Problems::default();
+        problems.check(ENDPOINTS.contains(&self.endpoint.as_str()), || {
+            format!(
+                "endpoint must be one of {}, got {:?}",
+                ENDPOINTS.join(", "),
+                self.endpoint
+            )
+        });
+        problems.check(self.completion_window == "24h", || {
+            format!(
+                "completion_window must be \"24h\", got {:?}",
+                self.completion_window
+            )
+        });
+        problems.check(self.metadata.len() <= 16, || {
+            format!(
+                "metadata holds at most 16 pairs, got {}",
+                self.metadata.len()
+            )
+        });
+        problems.into_result()
+    }
+}
+
+impl TryFrom<BatchArgsBuilder> for BatchArgs {
+    type Error = BatchArgsBuilderError;
+
+    fn try_from(builder: BatchArgsBuilder) -> std::result::Result<Self, Self::Error> {
+        builder.build()
+    }
+}
+
+impl From<BatchArgsBuilderError> for Error {
+    fn from(err: BatchArgsBuilderError) -> Self {
+        Error::BadArguments(err.to_string())
+    }
+}
+
+/// Where a batch is in its lifecycle
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchStatus {
+    /// The input file is being checked
+    Validating,
+    /// The input file failed validation, see `Batch::errors`
+    Failed,
+    InProgress,
+    /// The results are being written to the output and error files
+    Finalizing,
+    Completed,
+    /// The batch didn't finish within its completion window. The requests that ran have
+    /// their results in the output file.
+    Expired,
+    Cancelling,
+    Cancelled,
+    /// A status this version of the library doesn't know about
+    #[serde(other)]
+    Other,
+}
+
+impl BatchStatus {
+    /// Whether the batch is over, successfully or not
+    #[must_use]
+    pub fn is_terminal(self) -> bool {
+        matches!(
+            self,
+            BatchStatus::Failed
+                | BatchStatus::Completed
+                | BatchStatus::Expired
+                | BatchStatus::Cancelled
+        )
+    }
+}
+
+/// How many requests of a batch ran
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BatchRequestCounts {
+    pub total: u64,
+    pub completed: u64,
+    pub failed: u64,
+}
+
+/// A problem of a batch's input file
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct BatchError {
+    #[serde(default)]
+    pub code: Option<String>,
+    #[serde(default)]
+    pub message: String,
+    /// The parameter at fault, if any
+    #[serde(default)]
+    pub param: Option<String>,
+    /// The line of the input file at fault, if any, counting from 1
+    #[serde(default)]
+    pub line: Option<u64>,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+struct BatchErrors {
+    #[serde(default)]
+    data: Vec<BatchError>,
+}
+
+fn batch_errors<'de, D>(deserializer: D) -> std::result::Result<Vec<BatchError>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    crate::api::lenient::<D, BatchErrors>(deserializer).map(|errors| errors.data)
+}
+
+/// A batch
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct Batch {
+    pub id: String,
+    pub endpoint: String,
+    pub status: BatchStatus,
+    pub input_file_id: String,
+    #[serde(default)]
+    pub completion_window: String,
+    /// The file holding the results of the successful requests, once there are some
+    #[serde(default)]
+    pub output_file_id: Option<String>,
+    /// The file holding the results of the failed requests, once there are some
+    #[serde(default)]
+    pub error_file_id: Option<String>,
+    /// Why the input file failed validation, if it did
+    #[serde(default, deserialize_with = "batch_errors")]
+    pub errors: Vec<BatchError>,
+    #[serde(default, deserialize_with = "crate::api::lenient")]
+    pub request_counts: BatchRequestCounts,
+    /// Unix timestamp, in seconds
+    #[serde(default, deserialize_with = "crate::api::lenient")]
+    pub created_at: u64,
+    #[serde(default, deserialize_with = "crate::api::lenient")]
+    pub metadata: HashMap<String, String>,
+}
+
+impl Client {
+    /// Creates a batch from an uploaded input file
+    ///
+    /// # Errors
+    ///  - `Error::BadArguments` if the arguments can't be built, or are invalid
+    ///  - `Error::DryRun` in dry-run mode, if the arguments are valid
+    ///  - `Error::Api` if the api returns an error
+    pub async fn create_batch<A>(&self, args: A) -> Result<Batch>
+    where
+        A: TryInto<BatchArgs>,
+        Error: From<<A as TryInto<BatchArgs>>::Error>,
+    {
+        let args: BatchArgs = args.try_into()?;
+        args.validate()?;
+        self.dry_run("batches", &args, || Ok(()))?;
+        self.post("batches", args).await
+    }
+
+    /// Uploads `file` as `file_name`, which must end in `.jsonl`, and creates a batch
+    /// running its requests
+    ///
+    /// # Errors
+    ///  - `Error::BadArguments` if the file is empty, or the file name is invalid
+    ///  - `Error::DryRun` in dry-run mode
+    ///  - `Error::Api` if the api returns an error
+    pub async fn submit_batch(&self, file: &BatchRequestFile, file_name: &str) -> Result<Batch> {
+        let endpoint = file
+            .endpoint()
+            .ok_or_else(|| Error::BadArguments("a batch needs at least one request".into()))?;
+        let upload = FileUploadArgs::builder()
+            .file(file.to_jsonl()?.into_bytes())
+            .file_name(file_name)
+            .purpose(FilePurpose::Batch);
+        let input = self.upload_file(upload).await?;
+        self.create_batch(
+            BatchArgs::builder()
+                .input_file_id(input.id)
+                .endpoint(endpoint),
+        )
+        .await
+    }
+
+    /// Fetches the current state of a batch
+    ///
+    /// # Errors
+    /// - `Error::Api` if the api returns an error
+    pub async fn retrieve_batch(&self, batch_id: &str) -> Result<Batch> {
+        self.get(&format!("batches/{}", batch_id)).await
+    }
+
+    /// Cancels a batch. It is `Cancelling` for up to 10 minutes, then `Cancelled` with the
+    /// results of the requests that ran in its output file.
+    ///
+    /// # Errors
+    /// - `Error::Api` if the api returns an error
+    pub async fn cancel_batch(&self, batch_id: &str) -> Result<Batch> {
+        self.post(
+            &format!("batches/{}/cancel", batch_id),
+            serde_json::json!({}),
+        )
+        .await
+    }
+
+    /// Lists the batches of the organization, most recent first
+    ///
+    /// # Errors
+    /// - `Error::Api` if the api returns an error
+    pub async fn list_batches(&self, page: &ListParams) -> Result<Page<Batch>> {
+        self.get_with_query("batches", page).await
+    }
+
+    /// Polls `batch` until it is over, calling `progress` with each state seen before, e.g.
+    /// to report `batch.request_counts`. Check the status of the result: failed batches
+    /// are returned, not errors.
+    ///
+    /// Batches take up to their completion window, so `config` usually needs a longer
+    /// timeout and interval than the default ones.
+    ///
+    /// # Errors
+    /// - `Error::PollTimeout` if the batch is still running when `config` runs out of time
+    /// - `Error::Api` if the api returns an error
+    pub async fn wait_for_batch(
+        &self,
+        batch: Batch,
+        config: &PollConfig,
+        progress: impl FnMut(&Batch),
+    ) -> Result<Batch> {
+        let id = batch.id.clone();
+        crate::poll::until(
+            config,
+            batch,
+            || self.retrieve_batch(&id),
+            |batch| batch.status.is_terminal(),
+            progress,
+        )
+        .await
+    }
+
+    /// Downloads the output and error files of `batch`, those it has, as one JSONL text
+    /// to parse with `parse_output`
+    ///
+    /// # Errors
+    /// - `Error::Api` if the api returns an error
+    /// - `Error::ResponseTooLarge` if a file is larger than `Client::with_max_response_size`
+    /// - `Error::BadArguments` if a file isn't UTF-8
+    pub async fn batch_results(&self, batch: &Batch) -> Result<String> {
+        let mut jsonl = String::new();
+        for file_id in batch.output_file_id.iter().chain(&batch.error_file_id) {
+            let content = self.file_content(file_id).await?;
+            let text = std::str::from_utf8(&content).map_err(|e| {
+                Error::BadArguments(format!("batch file {} isn't UTF-8: {}", file_id, e))
+            })?;
+            jsonl.push_str(text);
+            if !jsonl.ends_with('\n') {
+                jsonl.push('\n');
+            }
+        }
+        Ok(jsonl)
+    }
+}
+
+/// A line of a batch output or error file, see `parse_output` to get typed results
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct BatchOutputLine {
+    /// Id of the line, set by the API
+    #[serde(default)]
+    pub id: Option<String>,
+    /// The id of the request, as in the input file
+    pub custom_id: String,
+    /// The response to the request, if it ran
+    #[serde(default)]
+    pub response: Option<BatchResponse>,
+    /// Why the request didn't run, if it didn't
+    #[serde(default)]
+    pub error: Option<ApiError>,
+}
+
+/// The response to a request of a batch
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct BatchResponse {
+    pub status_code: u16,
+    #[serde(default)]
+    pub request_id: Option<String>,
+    /// The body the endpoint answered, or its error
+    #[serde(default)]
+    pub body: Value,
+}
+
+impl BatchOutputLine {
+    fn into_result<T: DeserializeOwned>(
+        self,
+    ) -> Result<(String, std::result::Result<T, ApiError>)> {
+        let result = match (self.error, self.response) {
+            (Some(error), _) => Err(error),
+            (None, Some(response)) if response.status_code == 200 => {
+                Ok(serde_json::from_value(response.body)?)
+            }
+            (None, Some(response)) => {
+                let mut error = match serde_json::from_value::<ErrorWrapper>(response.body.clone())
+                {
+                    Ok(wrapper) => wrapper.error,
+                    Err(_) => ApiError {
+                        message: response.body.to_string(),
+                        ..ApiError::default()
+                    },
+                };
+                error.status = reqwest::StatusCode::from_u16(response.status_code).ok();
+                error.request_id = response.request_id;
+                Err(error)
+            }
+            (None, None) => {
+                return Err(Error::BadArguments(format!(
+                    "batch result {:?} has neither a response nor an error",
+                    self.custom_id
+                )))
+            }
+        };
+        Ok((self.custom_id, result))
+    }
+}
+
+/// Parses a batch output or error file, yielding each request's `custom_id` with its
+/// response, or the error the API returned for it. Results come in the file's order, which
+/// isn't the input's.
+///
+/// Each item is an `Err` of its own if its line isn't a batch result, or its response
+/// doesn't deserialize to `T`, so that one bad line doesn't lose the others.
+///
+/// # Example
+/// ```
+/// # use openai_api::{api::ChatAnswer, batch::parse_output};
+/// let jsonl = r#"{"id": "batch_req_1", "custom_id": "a", "response": null, "error": {"code": "batch_expired", "message": "This request could not be executed before the completion window expired."}}"#;
+/// for result in parse_output::<ChatAnswer>(jsonl) {
+///     let (custom_id, answer) = result.unwrap();
+///     assert_eq!(custom_id, "a");
+///     assert_eq!(answer.unwrap_err().code.as_deref(), Some("batch_expired"));
+/// }
+/// ```
+pub fn parse_output<T: DeserializeOwned>(
+    jsonl: &str,
+) -> impl Iterator<Item = Result<(String, std::result::Result<T, ApiError>)>> + '_ {
+    jsonl
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| serde_json::from_str::<BatchOutputLine>(line)?.into_result())
+}
+
+/// Parses the output or error file of a batch of chat completions, see `parse_output`
+pub fn parse_chat_output(
+    jsonl: &str,
+) -> impl Iterator<Item = Result<(String, std::result::Result<ChatAnswer, ApiError>)>> + '_ {
+    parse_output(jsonl)
+}
+
+/// Parses the output or error file of a batch of embeddings, see `parse_output`
+pub fn parse_embeddings_output(
+    jsonl: &str,
+) -> impl Iterator<Item = Result<(String, std::result::Result<Embeddings, ApiError>)>> + '_ {
+    parse_output(jsonl)
+}
+
+#[cfg(test)]
+mod unit {
+    use std::time::Duration;
+
+    use mockito::Matcher;
+    use serde_json::{json, Value};
+
+    use super::{
+        parse_chat_output, parse_embeddings_output, BatchArgs, BatchRequestFile, BatchStatus,
+    };
+    use crate::{
+        api::{ChatArgs, ChatRole},
+        embeddings::EmbeddingsArgs,
+        poll::PollConfig,
+        unit::mocked_client,
+        Error,
+    };
+
+    fn batch(status: &str, completed: u64, files: &str) -> String {
+        format!(
+            r#"{{"id": "batch_1", "object": "batch", "endpoint": "/v1/chat/completions",
+            "errors": null, "input_file_id": "file-in", "completion_window": "24h",
+            "status": "{}", "created_at": 1711471533, "request_counts": {{"total": 2,
+            "completed": {}, "failed": 0}}, "metadata": null{}}}"#,
+            status, completed, files
+        )
+    }
+
+    #[tokio::test]
+    async fn runs_batches() -> crate::Result<()> {
+        let create = mockito::mock("POST", "/batches")
+            .match_body(Matcher::Json(json!({
+                "input_file_id": "file-in",
+                "endpoint": "/v1/chat/completions",
+                "completion_window": "24h",
+            })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(batch("validating", 0, ""))
+            .expect(1)
+            .create();
+        let _done = mockito::mock("GET", "/batches/batch_1")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(batch(
+                "completed",
+                2,
+                r#", "output_file_id": "file-out", "error_file_id": "file-err""#,
+            ))
+            .create();
+        let _output = mockito::mock("GET", "/files/file-out/content")
+            .with_status(200)
+            .with_body(
+                r#"{"custom_id": "a", "response": {"status_code": 200, "body": {"choices": [{"index": 0, "message": {"role": "assistant", "content": "Hi!"}, "finish_reason": "stop"}]}}}"#,
+            )
+            .create();
+        let _errors = mockito::mock("GET", "/files/file-err/content")
+            .with_status(200)
+            .with_body(r#"{"custom_id": "b", "response": null, "error": {"code": "batch_expired", "message": "Expired"}}"#)
+            .create();
+
+        let client = mocked_client();
+        let args = BatchArgs::builder()
+            .input_file_id("file-in")
+            .endpoint("/v1/chat/completions");
+        let started = client.create_batch(args).await?;
+        assert_eq!(started.status, BatchStatus::Validating);
+        assert!(started.errors.is_empty());
+        create.assert();
+
+        let config = PollConfig {
+            initial_interval: Duration::from_millis(1),
+            ..PollConfig::default()
+        };
+        let mut seen = vec![];
+        let done = client
+            .wait_for_batch(started, &config, |batch| seen.push(batch.status))
+            .await?;
+        assert_eq!(seen, [BatchStatus::Validating]);
+        assert_eq!(done.request_counts.completed, 2);
+
+        let jsonl = client.batch_results(&done).await?;
+        let ids: Vec<_> = parse_chat_output(&jsonl)
+            .map(|result| result.map(|(id, answer)| (id, answer.is_ok())))
+            .collect::<crate::Result<_>>()?;
+        assert_eq!(ids, [("a".to_string(), true), ("b".to_string(), false)]);
+
+        let wrong = BatchArgs::builder()
+            .input_file_id("file-in")
+            .endpoint("/v1/images/generations")
+            .completion_window("1h");
+        match client.create_batch(wrong).await {
+            Err(Error::BadArguments(message)) => {
+                assert!(message.contains("endpoint"));
+                assert!(message.contains("completion_window"));
+            }
+            other => panic!("expected BadArguments, got {:?}", other),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn batch_envelope() -> crate::Result<()> {
+        let mut file = BatchRequestFile::new();
+        let args = ChatArgs::from(vec![(ChatRole::User, "Hello!".to_string())]);
+        file.push_chat("a", &args)?;
+        file.push_chat("b", &args)?;
+        assert!(matches!(
+            file.push_chat("a", &args),
+            Err(Error::BadArguments(_))
+        ));
+        assert!(matches!(
+            file.push_embeddings("c", &EmbeddingsArgs::from("Hello!")),
+            Err(Error::BadArguments(_))
+        ));
+        assert_eq!(file.len(), 2);
+
+        let jsonl = file.to_jsonl()?;
+        let first: Value = serde_json::from_str(jsonl.lines().next().unwrap())?;
+        assert_eq!(first["custom_id"], "a");
+        assert_eq!(first["method"], "POST");
+        assert_eq!(first["url"], "/v1/chat/completions");
+        assert_eq!(first["body"]["messages"][0]["content"], "Hello!");
+        Ok(())
+    }
+
+    #[test]
+    fn parses_output() -> crate::Result<()> {
+        let jsonl = r#"
+{"id": "batch_req_1", "custom_id": "b", "response": {"status_code": 200, "request_id": "req_1", "body": {"id": "chatcmpl-1", "object": "chat.completion", "created": 1711652795, "choices": [{"index": 0, "message": {"role": "assistant", "content": "Hi!"}, "finish_reason": "stop"}]}}, "error": null}
+{"id": "batch_req_2", "custom_id": "a", "response": {"status_code": 400, "request_id": "req_2", "body": {"error": {"message": "Invalid model", "type": "invalid_request_error", "code": "model_not_found"}}}, "error": null}
+{"id": "batch_req_3", "custom_id": "c", "response": null, "error": {"code": "batch_expired", "message": "Expired"}}
+{"not": "a result"}
+"#;
+        let results: Vec<_> = parse_chat_output(jsonl).collect();
+        assert_eq!(results.len(), 4);
+        let (id, answer) = results[0].as_ref().unwrap();
+        assert_eq!(id, "b");
+        assert_eq!(answer.as_ref().unwrap().choices[0].message.content, "Hi!");
+        let (id, error) = results[1].as_ref().unwrap();
+        assert_eq!(id, "a");
+        let error = error.as_ref().unwrap_err();
+        assert_eq!(error.code.as_deref(), Some("model_not_found"));
+        assert_eq!(error.kind.as_deref(), Some("invalid_request_error"));
+        assert_eq!(error.status, Some(reqwest::StatusCode::BAD_REQUEST));
+        assert_eq!(error.request_id.as_deref(), Some("req_2"));
+        let (_, error) = results[2].as_ref().unwrap();
+        assert_eq!(error.as_ref().unwrap_err().message, "Expired");
+        assert!(matches!(results[3], Err(Error::Json(_))));
+
+        let embeddings = r#"{"custom_id": "e", "response": {"status_code": 200, "body": {"object": "list", "model": "text-embedding-3-small", "data": [{"object": "embedding", "index": 0, "embedding": [0.5, 0.25]}]}}}"#;
+        let (_, result) = parse_embeddings_output(embeddings).next().unwrap()?;
+        assert_eq!(result.unwrap().data[0].embedding, vec![0.5, 0.25]);
+        Ok(())
+    }
+}
@@ -0,0 +1,142 @@
+//! `openai`, a command line client of the API built on this crate.
+//!
+//! ```text
+//! openai chat "Who won the world series in 2020?"
+//! openai models
+//! openai embed -f sentences.txt
+//! ```
+//!
+//! The API key is read from the `OPENAI_API_KEY` environment variable, or `--api-key`.
+use std::{io::Write, process::ExitCode};
+
+use clap::{Parser, Subcommand};
+use futures::TryStreamExt;
+use openai_api::{
+    api::{ApiError, ChatArgs, ChatFormat, ChatRole},
+    embeddings::EmbeddingsArgs,
+    Client, Error,
+};
+
+#[derive(Parser)]
+#[command(name = "openai", about = "Command line client of the OpenAI API")]
+struct Cli {
+    /// API key to authenticate with
+    #[arg(long, env = "OPENAI_API_KEY", hide_env_values = true)]
+    api_key: String,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Answers a prompt with a chat model, printing the answer as it is generated
+    Chat {
+        /// The user message
+        prompt: String,
+        /// Model to answer with
+        #[arg(short, long, default_value = "gpt-3.5-turbo")]
+        model: String,
+        /// System message setting the behavior of the assistant
+        #[arg(short, long)]
+        system: Option<String>,
+        /// Sampling temperature, between 0 and 2
+        #[arg(short, long)]
+        temperature: Option<f64>,
+    },
+    /// Lists the available models
+    Models,
+    /// Prints the embedding of each line of a file, as one JSON array per line
+    Embed {
+        /// File of texts to embed, one per line
+        #[arg(short, long)]
+        file: std::path::PathBuf,
+        /// Embedding model
+        #[arg(short, long, default_value = "text-embedding-ada-002")]
+        model: String,
+        /// Most requests in flight at once
+        #[arg(long, default_value_t = 4)]
+        concurrency: usize,
+    },
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match run(cli).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("openai: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(cli: Cli) -> Result<(), Error> {
+    let client = Client::new(&cli.api_key)?;
+    match cli.command {
+        Command::Chat {
+            prompt,
+            model,
+            system,
+            temperature,
+        } => {
+            let mut messages = Vec::new();
+            if let Some(system) = system {
+                messages.push(ChatFormat::new(ChatRole::System, system));
+            }
+            messages.push(ChatFormat::new(ChatRole::User, prompt));
+            let mut args = ChatArgs::builder().model(model).messages(messages);
+            if let Some(temperature) = temperature {
+                args = args.temperature(temperature);
+            }
+            let mut deltas = Box::pin(client.chat_stream(args).await?);
+            let mut stdout = std::io::stdout();
+            let mut answered = false;
+            while let Some(delta) = deltas.try_next().await? {
+                answered |= !delta.choices.is_empty();
+                if let Some(content) = delta.content() {
+                    print!("{}", content);
+                    stdout.flush()?;
+                }
+            }
+            if !answered {
+                return Err(ApiError {
+                    message: "the answer has no choices".into(),
+                    ..ApiError::default()
+                }
+                .into());
+            }
+            println!();
+        }
+        Command::Models => {
+            let mut models = client.models().await?;
+            models.sort_by(|a, b| a.id.cmp(&b.id));
+            for model in models {
+                println!("{}", model.id);
+            }
+        }
+        Command::Embed {
+            file,
+            model,
+            concurrency,
+        } => {
+            let text = std::fs::read_to_string(&file).map_err(|e| {
+                Error::BadArguments(format!("can't read {}: {}", file.display(), e))
+            })?;
+            let input: Vec<String> = text
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(String::from)
+                .collect();
+            let args = EmbeddingsArgs::builder()
+                .model(model)
+                .input(input)
+                .build()?;
+            let embeddings = client.embed_all(args, concurrency).await?;
+            for embedding in embeddings.data {
+                println!("{}", serde_json::to_string(&embedding.embedding)?);
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,252 @@
+//! A builder gathering the settings of a `Client`, checked all at once when it's built.
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Duration;
+
+use crate::{retry::RetryPolicy, throttle::Throttle, Client, Result};
+
+/// Builds a `Client` with the settings most deployments change: where the API is, how
+/// long requests may take, and how they get there.
+///
+/// Every setting is also a `with_*` method of `Client`; the builder reports the first
+/// invalid one when built rather than at each step.
+///
+/// # Example
+/// ```
+/// # use std::time::Duration;
+/// # use openai_api::Client;
+/// # fn main() -> Result<(), openai_api::Error> {
+/// let client = Client::builder("sk-...")
+///     .base_url("http://localhost:8080/v1")
+///     .timeout(Duration::from_secs(120))
+///     .connect_timeout(Duration::from_secs(5))
+///     .user_agent("my-app/1.0")
+///     .build()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+#[must_use]
+pub struct ClientBuilder {
+    token: String,
+    base_url: Option<String>,
+    #[cfg(not(target_arch = "wasm32"))]
+    timeout: Option<Duration>,
+    #[cfg(not(target_arch = "wasm32"))]
+    connect_timeout: Option<Duration>,
+    #[cfg(not(target_arch = "wasm32"))]
+    proxy: Option<String>,
+    user_agent: Option<String>,
+    organization: Option<String>,
+    project: Option<String>,
+    throttle: Option<Throttle>,
+    retry_policy: Option<RetryPolicy>,
+}
+
+impl ClientBuilder {
+    /// A builder for a client authenticating with the API key `token`
+    pub fn new(token: impl Into<String>) -> Self {
+        Self {
+            token: token.into(),
+            base_url: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            timeout: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            connect_timeout: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            proxy: None,
+            user_agent: None,
+            organization: None,
+            project: None,
+            throttle: None,
+            retry_policy: None,
+        }
+    }
+
+    /// See `Client::with_base_url`
+    pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = Some(base_url.into());
+        self
+    }
+
+    /// See `Client::with_timeout`
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// See `Client::with_connect_timeout`
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// See `Client::with_proxy`
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn proxy(mut self, url: impl Into<String>) -> Self {
+        self.proxy = Some(url.into());
+        self
+    }
+
+    /// See `Client::with_user_agent`
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = Some(user_agent.into());
+        self
+    }
+
+    /// See `Client::with_organization`
+    pub fn organization(mut self, organization: impl Into<String>) -> Self {
+        self.organization = Some(organization.into());
+        self
+    }
+
+    /// See `Client::with_project`
+    pub fn project(mut self, project: impl Into<String>) -> Self {
+        self.project = Some(project.into());
+        self
+    }
+
+    /// See `Client::with_throttle`
+    pub fn throttle(mut self, throttle: Throttle) -> Self {
+        self.throttle = Some(throttle);
+        self
+    }
+
+    /// See `Client::with_retry_policy`
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = Some(policy);
+        self
+    }
+
+    /// Builds the client
+    ///
+    /// # Errors
+    /// - `Error::InvalidToken` if the token can't be sent in a header
+    /// - `Error::InvalidConfig` if the base URL, the proxy, the user agent, the
+    ///   organization, the project or the retry policy is invalid
+    /// - `Error::AsyncProtocol` if the HTTP client can't be created
+    pub fn build(self) -> Result<Client> {
+        let mut client = Client::new(&self.token)?;
+        if let Some(base_url) = &self.base_url {
+            client = client.with_base_url(base_url)?;
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(timeout) = self.timeout {
+            client = client.with_timeout(timeout)?;
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(timeout) = self.connect_timeout {
+            client = client.with_connect_timeout(timeout)?;
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(proxy) = &self.proxy {
+            client = client.with_proxy(proxy)?;
+        }
+        if let Some(user_agent) = &self.user_agent {
+            client = client.with_user_agent(user_agent)?;
+        }
+        if let Some(organization) = &self.organization {
+            client = client.with_organization(organization)?;
+        }
+        if let Some(project) = &self.project {
+            client = client.with_project(project)?;
+        }
+        if let Some(throttle) = self.throttle {
+            client = client.with_throttle(throttle);
+        }
+        if let Some(policy) = self.retry_policy {
+            policy.validate()?;
+            client = client.with_retry_policy(policy);
+        }
+        Ok(client)
+    }
+}
+
+impl Client {
+    /// A builder for a client authenticating with the API key `token`, see `ClientBuilder`
+    pub fn builder(token: impl Into<String>) -> ClientBuilder {
+        ClientBuilder::new(token)
+    }
+}
+
+#[cfg(test)]
+mod unit {
+    use std::time::Duration;
+
+    use mockito::Matcher;
+
+    use crate::{options::RequestOptions, retry::RetryPolicy, Client, Error};
+
+    #[tokio::test]
+    async fn builds_clients() -> crate::Result<()> {
+        let mock = mockito::mock("GET", "/v2/models")
+            .match_header("user-agent", "my-app/1.0")
+            .match_header("authorization", "Bearer sk-test")
+            .match_query(Matcher::Any)
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"object": "list", "data": []}"#)
+            .expect(1)
+            .create();
+        let client = Client::builder("sk-test")
+            .base_url(format!("{}/v2", mockito::server_url()))
+            .timeout(Duration::from_secs(30))
+            .connect_timeout(Duration::from_secs(5))
+            .user_agent("my-app/1.0")
+            .build()?;
+        assert!(client.models().await?.is_empty());
+        mock.assert();
+        assert_eq!(
+            client.inner.transport.timeout,
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            client.inner.transport.connect_timeout,
+            Some(Duration::from_secs(5))
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn overrides_organization_per_request() -> crate::Result<()> {
+        let mock = |organization| {
+            mockito::mock("GET", "/v3/models")
+                .match_header("openai-organization", organization)
+                .match_header("openai-project", "proj_1")
+                .with_status(200)
+                .with_header("content-type", "application/json")
+                .with_body(r#"{"object": "list", "data": []}"#)
+                .expect(1)
+                .create()
+        };
+        let (default, other) = (mock("org-default"), mock("org-other"));
+        let client = Client::builder("sk-test")
+            .base_url(format!("{}/v3", mockito::server_url()))
+            .organization("org-default")
+            .project("proj_1")
+            .build()?;
+        client.models().await?;
+        let options = RequestOptions::new().organization("org-other")?;
+        client.with_options(&options).models().await?;
+        default.assert();
+        other.assert();
+        Ok(())
+    }
+
+    #[test]
+    fn reports_invalid_settings() {
+        for builder in [
+            Client::builder("sk-test").base_url("not a url"),
+            Client::builder("sk-test").proxy("not a url"),
+            Client::builder("sk-test").user_agent("line\nbreak"),
+            Client::builder("sk-test").organization("line\nbreak"),
+            Client::builder("sk-test").retry_policy(RetryPolicy {
+                multiplier: 0.5,
+                ..RetryPolicy::default()
+            }),
+        ] {
+            assert!(matches!(builder.build(), Err(Error::InvalidConfig(_))));
+        }
+    }
+}
@@ -0,0 +1,296 @@
+//! An opt-in, in-memory cache of API responses.
+//!
+//! Only requests sampled at temperature `0.0` are cached, since any other temperature is
+//! expected to give a different answer every time.
+use std::{
+    any::Any,
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::{runtime::Instant, Result};
+
+/// A least-recently-used response cache with a time to live.
+///
+/// Clones share the same storage, so one cache can back several clients. Responses are
+/// only shared between clients sending the same request to the same server with the same
+/// credentials, organization and project.
+///
+/// # Example
+/// ```
+/// # use std::time::Duration;
+/// # use openai_api::{cache::ResponseCache, Client};
+/// let cache = ResponseCache::new(1000, Duration::from_secs(60 * 60));
+/// let client = Client::new("sk-...").unwrap().with_cache(cache);
+/// ```
+#[derive(Clone)]
+pub struct ResponseCache {
+    capacity: usize,
+    ttl: Duration,
+    entries: Arc<Mutex<Entries>>,
+}
+
+#[derive(Default)]
+struct Entries {
+    /// Entries by the key of their request, see `key`
+    map: HashMap<String, Entry>,
+    /// Monotonic counter recording the order entries were last used in
+    clock: u64,
+}
+
+struct Entry {
+    value: Arc<dyn Any + Send + Sync>,
+    expires_at: Instant,
+    last_used: u64,
+}
+
+impl ResponseCache {
+    /// Creates a cache holding at most `capacity` responses, each for at most `ttl`
+    #[must_use]
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        Self {
+            capacity,
+            ttl,
+            entries: Arc::default(),
+        }
+    }
+
+    /// Number of responses currently cached, including expired ones not yet evicted
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.lock().map.len()
+    }
+
+    /// Whether the cache holds no responses
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Drops every cached response
+    pub fn clear(&self) {
+        self.lock().map.clear();
+    }
+
+    pub(crate) fn get<T>(&self, key: &str) -> Option<T>
+    where
+        T: Clone + 'static,
+    {
+        let mut entries = self.lock();
+        entries.clock += 1;
+        let clock = entries.clock;
+        match entries.map.get_mut(key) {
+            Some(entry) if entry.expires_at > Instant::now() => {
+                entry.last_used = clock;
+                entry.value.downcast_ref::<T>().cloned()
+            }
+            Some(_) => {
+                entries.map.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    pub(crate) fn insert<T>(&self, key: String, value: T)
+    where
+        T: Send + Sync + 'static,
+    {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut entries = self.lock();
+        entries.clock += 1;
+        let clock = entries.clock;
+        if entries.map.len() >= self.capacity && !entries.map.contains_key(&key) {
+            let now = Instant::now();
+            entries.map.retain(|_, entry| entry.expires_at > now);
+            if entries.map.len() >= self.capacity {
+                let oldest = entries
+                    .map
+                    .iter()
+                    .min_by_key(|(_, entry)| entry.last_used)
+                    .map(|(key, _)| key.clone());
+                if let Some(oldest) = oldest {
+                    entries.map.remove(&oldest);
+                }
+            }
+        }
+        entries.map.insert(
+            key,
+            Entry {
+                value: Arc::new(value),
+                expires_at: Instant::now() + self.ttl,
+                last_used: clock,
+            },
+        );
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Entries> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl std::fmt::Debug for ResponseCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ResponseCache")
+            .field("capacity", &self.capacity)
+            .field("ttl", &self.ttl)
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+/// Cache key of a request: the SHA-256 digest of the `scope` of the client sending it,
+/// i.e. where it goes and as whom, the endpoint and the canonical form of the request
+/// body, see the `canonical` module. Hashing keeps the credentials of the scope out of
+/// memory, while different requests still never share a response.
+pub(crate) fn key<B: Serialize>(scope: &str, endpoint: &str, body: &B) -> Result<String> {
+    let body = crate::canonical::canonical_json(body)?;
+    let digest = Sha256::new()
+        .chain_update(scope)
+        .chain_update("\n")
+        .chain_update(endpoint)
+        .chain_update("\n")
+        .chain_update(body)
+        .finalize();
+    Ok(digest.iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+#[cfg(test)]
+mod unit {
+    use std::time::Duration;
+
+    use super::{key, ResponseCache};
+    use crate::{api::ChatArgs, options::RequestOptions, unit::mocked_client, Client};
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let cache = ResponseCache::new(2, Duration::from_secs(60));
+        cache.insert("1".into(), "one".to_string());
+        cache.insert("2".into(), "two".to_string());
+        assert_eq!(cache.get::<String>("1").as_deref(), Some("one"));
+        cache.insert("3".into(), "three".to_string());
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get::<String>("2"), None);
+        assert_eq!(cache.get::<String>("1").as_deref(), Some("one"));
+        assert_eq!(cache.get::<String>("3").as_deref(), Some("three"));
+        assert_eq!(cache.get::<u64>("3"), None);
+    }
+
+    #[test]
+    fn expires_entries() {
+        let cache = ResponseCache::new(2, Duration::ZERO);
+        cache.insert("1".into(), "one".to_string());
+        assert_eq!(cache.get::<String>("1"), None);
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn key_ignores_map_order() -> crate::Result<()> {
+        let bias = |pairs: &[(&str, f64)]| {
+            ChatArgs::builder()
+                .logit_bias(pairs.iter().map(|(k, v)| (k.to_string(), *v)).collect())
+                .build()
+                .unwrap()
+        };
+        let a = bias(&[("1", 1.0), ("2", -1.0), ("3", 0.5), ("4", 2.0)]);
+        let b = bias(&[("4", 2.0), ("3", 0.5), ("2", -1.0), ("1", 1.0)]);
+        assert_eq!(
+            key("", "chat/completions", &a)?,
+            key("", "chat/completions", &b)?
+        );
+        assert_ne!(
+            key("", "chat/completions", &a)?,
+            key("", "completions", &a)?
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn caches_deterministic_chats() -> crate::Result<()> {
+        let mock = mockito::mock("POST", "/chat/completions")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"{
+                "id": "chatcmpl-123",
+                "created": 1677652288,
+                "choices": [{
+                    "index": 0,
+                    "message": {"role": "assistant", "content": "Hi!"},
+                    "finish_reason": "stop"
+                }]
+            }"#,
+            )
+            .expect(4)
+            .create();
+        let cache = ResponseCache::new(10, Duration::from_secs(60));
+        let client = mocked_client().with_cache(cache.clone());
+        let args = ChatArgs::builder().temperature(0.0).build().unwrap();
+        for _ in 0..3 {
+            assert_eq!(client.chat(args.clone()).await?.id, "chatcmpl-123");
+        }
+        assert_eq!(cache.len(), 1);
+
+        let sampled = ChatArgs::builder().temperature(0.7).build().unwrap();
+        client.chat(sampled.clone()).await?;
+        client.chat(sampled).await?;
+        assert_eq!(cache.len(), 1);
+
+        // Another organization doesn't get the answers of the first
+        let other = client.with_options(&RequestOptions::new().organization("org-other")?);
+        other.chat(args.clone()).await?;
+        other.chat(args).await?;
+        assert_eq!(cache.len(), 2);
+        mock.assert();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn separates_api_keys() -> crate::Result<()> {
+        let mock = |key: &str| {
+            mockito::mock("POST", "/chat/completions")
+                .match_header("authorization", format!("Bearer {}", key).as_str())
+                .with_status(200)
+                .with_header("content-type", "application/json")
+                .with_body(format!(
+                    r#"{{"id": "chatcmpl-{}", "choices": [{{"index": 0, "finish_reason": "stop",
+                    "message": {{"role": "assistant", "content": "Hi!"}}}}]}}"#,
+                    key
+                ))
+                .expect(1)
+                .create()
+        };
+        let (alice, bob) = (mock("sk-alice"), mock("sk-bob"));
+        let cache = ResponseCache::new(10, Duration::from_secs(60));
+        let client = |key| -> crate::Result<Client> {
+            Ok(Client::new(key)?
+                .with_base_url(&mockito::server_url())?
+                .with_cache(cache.clone()))
+        };
+        let args = ChatArgs::builder()
+            .model("gpt-4o-cache-keys")
+            .temperature(0.0)
+            .build()
+            .unwrap();
+        for _ in 0..2 {
+            assert_eq!(
+                client("sk-alice")?.chat(args.clone()).await?.id,
+                "chatcmpl-sk-alice"
+            );
+            assert_eq!(
+                client("sk-bob")?.chat(args.clone()).await?.id,
+                "chatcmpl-sk-bob"
+            );
+        }
+        assert_eq!(cache.len(), 2);
+        alice.assert();
+        bob.assert();
+        Ok(())
+    }
+}
@@ -0,0 +1,168 @@
+//! A canonical serialization of requests, and a stable hash of it.
+//!
+//! Two requests asking the same thing have the same canonical form, whatever the order
+//! of their maps' keys or the way their numbers were written, e.g. a temperature of `1`
+//! or `1.0`. The hash is FNV-1a, which, unlike `std`'s hashers, is the same across
+//! processes, platforms and Rust versions, so it can key a persistent cache or
+//! deduplicate identical requests in flight.
+use std::fmt::Write;
+
+use serde::Serialize;
+use serde_json::{Number, Value};
+
+use crate::{
+    api::{ChatArgs, CompletionArgs},
+    Result,
+};
+
+/// The canonical JSON form of `value`: compact, with object keys sorted, and numbers
+/// with an integral value written as integers
+///
+/// # Errors
+/// - `Error::Json` if `value` can't be serialized
+pub fn canonical_json<T: Serialize + ?Sized>(value: &T) -> Result<String> {
+    let mut json = String::new();
+    write_value(&mut json, &serde_json::to_value(value)?)?;
+    Ok(json)
+}
+
+/// The stable hash of the canonical JSON form of `value`
+///
+/// # Errors
+/// - `Error::Json` if `value` can't be serialized
+pub fn canonical_hash<T: Serialize + ?Sized>(value: &T) -> Result<u64> {
+    Ok(fnv1a(canonical_json(value)?.as_bytes()))
+}
+
+impl ChatArgs {
+    /// The canonical JSON form of the arguments. See the `canonical` module.
+    ///
+    /// # Errors
+    /// - `Error::Json` if the arguments can't be serialized
+    pub fn canonical_json(&self) -> Result<String> {
+        canonical_json(self)
+    }
+
+    /// A hash of the arguments that is stable across processes. See the `canonical`
+    /// module.
+    ///
+    /// # Errors
+    /// - `Error::Json` if the arguments can't be serialized
+    pub fn canonical_hash(&self) -> Result<u64> {
+        canonical_hash(self)
+    }
+}
+
+impl CompletionArgs {
+    /// The canonical JSON form of the arguments. See the `canonical` module.
+    ///
+    /// # Errors
+    /// - `Error::Json` if the arguments can't be serialized
+    pub fn canonical_json(&self) -> Result<String> {
+        canonical_json(self)
+    }
+
+    /// A hash of the arguments that is stable across processes. See the `canonical`
+    /// module.
+    ///
+    /// # Errors
+    /// - `Error::Json` if the arguments can't be serialized
+    pub fn canonical_hash(&self) -> Result<u64> {
+        canonical_hash(self)
+    }
+}
+
+fn write_value(out: &mut String, value: &Value) -> Result<()> {
+    match value {
+        Value::Number(number) => write_number(out, number),
+        Value::Array(values) => {
+            out.push('[');
+            for (i, value) in values.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_value(out, value)?;
+            }
+            out.push(']');
+        }
+        Value::Object(map) => {
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_by_key(|(key, _)| *key);
+            out.push('{');
+            for (i, (key, value)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&serde_json::to_string(key)?);
+                out.push(':');
+                write_value(out, value)?;
+            }
+            out.push('}');
+        }
+        // Null, booleans and strings have a single JSON form already
+        other => out.push_str(&serde_json::to_string(other)?),
+    }
+    Ok(())
+}
+
+fn write_number(out: &mut String, number: &Number) {
+    /// Integers up to 2^53 are exactly representable as `f64`
+    const MAX_EXACT: f64 = 9_007_199_254_740_992.0;
+
+    match number.as_f64() {
+        Some(float) if number.is_f64() && float.fract() == 0.0 && float.abs() <= MAX_EXACT => {
+            // Also turns -0.0 into 0
+            let _ = write!(out, "{}", float as i64);
+        }
+        _ => {
+            let _ = write!(out, "{}", number);
+        }
+    }
+}
+
+/// 64-bit FNV-1a
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    bytes.iter().fold(OFFSET_BASIS, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(PRIME)
+    })
+}
+
+#[cfg(test)]
+mod unit {
+    use serde_json::json;
+
+    use super::{canonical_hash, canonical_json, fnv1a};
+    use crate::api::ChatArgs;
+
+    #[test]
+    fn canonical_numbers_and_keys() -> crate::Result<()> {
+        let a = json!({"b": [1.0, -0.0, 0.5], "a": {"y": 2, "x": 1e3}});
+        let b = json!({"a": {"x": 1000, "y": 2.0}, "b": [1, 0, 0.5]});
+        assert_eq!(
+            canonical_json(&a)?,
+            r#"{"a":{"x":1000,"y":2},"b":[1,0,0.5]}"#
+        );
+        assert_eq!(canonical_hash(&a)?, canonical_hash(&b)?);
+        Ok(())
+    }
+
+    #[test]
+    fn stable_hash() -> crate::Result<()> {
+        // Reference values of 64-bit FNV-1a
+        assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+
+        let args = |temperature| {
+            ChatArgs::builder()
+                .temperature(temperature)
+                .build()
+                .unwrap()
+        };
+        assert_eq!(args(0.0).canonical_hash()?, args(-0.0).canonical_hash()?);
+        assert_ne!(args(0.0).canonical_hash()?, args(0.5).canonical_hash()?);
+        Ok(())
+    }
+}
@@ -0,0 +1,148 @@
+//! Cloudflare AI Gateway, a proxy caching, logging and rate limiting API requests.
+//!
+//! [`Client::cloudflare_gateway`] points a client at a gateway. Caching and metadata are
+//! set per request through [`RequestOptions`], and whether a response came from the
+//! gateway's cache is read from [`ResponseMeta::cloudflare_cache_hit`].
+//!
+//! # Example
+//! ```
+//! # use std::time::Duration;
+//! # use openai_api::{options::RequestOptions, Client};
+//! # fn main() -> Result<(), openai_api::Error> {
+//! let client = Client::cloudflare_gateway("account-id", "my-gateway", "sk-...")?;
+//! let options = RequestOptions::new().cloudflare_cache_ttl(Duration::from_secs(3600));
+//! let cached = client.with_options(&options);
+//! # Ok(())
+//! # }
+//! ```
+use std::{collections::BTreeMap, time::Duration};
+
+use reqwest::header::HeaderValue;
+
+use crate::{options::RequestOptions, response::ResponseMeta, Client, Result};
+
+impl Client {
+    /// Creates a client of the OpenAI API going through the Cloudflare AI Gateway
+    /// `gateway_id` of the account `account_id`
+    ///
+    /// # Errors
+    /// - `Error::InvalidConfig` if the ids don't make a valid URL
+    /// - `Error::AsyncProtocol` if the HTTP client can't be created
+    pub fn cloudflare_gateway(
+        account_id: &str,
+        gateway_id: &str,
+        openai_key: &str,
+    ) -> Result<Self> {
+        Self::new(openai_key)?.with_base_url(&format!(
+            "https://gateway.ai.cloudflare.com/v1/{}/{}/openai/",
+            account_id, gateway_id
+        ))
+    }
+
+    /// Authenticates to a gateway requiring it, with a Cloudflare API token
+    ///
+    /// # Errors
+    /// - `Error::InvalidConfig` if the token isn't a valid header value
+    pub fn with_cloudflare_gateway_token(self, token: &str) -> Result<Self> {
+        self.with_header("cf-aig-authorization", &format!("Bearer {}", token))
+    }
+}
+
+impl RequestOptions {
+    /// Lets the gateway cache responses for `ttl`
+    #[must_use]
+    pub fn cloudflare_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.headers
+            .insert("cf-aig-cache-ttl", HeaderValue::from(ttl.as_secs()));
+        self
+    }
+
+    /// Makes the gateway forward requests even when it has a cached response
+    #[must_use]
+    pub fn cloudflare_skip_cache(mut self) -> Self {
+        self.headers
+            .insert("cf-aig-skip-cache", HeaderValue::from_static("true"));
+        self
+    }
+
+    /// Attaches metadata to the gateway's logs of requests, replacing any previous metadata
+    ///
+    /// # Errors
+    /// - `Error::InvalidConfig` if the metadata isn't valid in a header
+    pub fn cloudflare_metadata(self, metadata: &BTreeMap<String, String>) -> Result<Self> {
+        self.header("cf-aig-metadata", &serde_json::to_string(metadata)?)
+    }
+}
+
+impl ResponseMeta {
+    /// Whether the Cloudflare AI Gateway served the response from its cache, or `None` if
+    /// the response didn't go through a gateway
+    #[must_use]
+    pub fn cloudflare_cache_hit(&self) -> Option<bool> {
+        self.header("cf-aig-cache-status")
+            .map(|status| status.eq_ignore_ascii_case("HIT"))
+    }
+}
+
+#[cfg(test)]
+mod unit {
+    use std::time::Duration;
+
+    use crate::{
+        api::{ChatArgs, ChatRole},
+        options::RequestOptions,
+        unit::mocked_client,
+        Client,
+    };
+
+    #[tokio::test]
+    async fn surfaces_cache_status() -> crate::Result<()> {
+        let mock = mockito::mock("POST", "/chat/completions")
+            .match_header("cf-aig-cache-ttl", "3600")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_header("cf-aig-cache-status", "HIT")
+            .with_body(
+                r#"{
+                "id": "chatcmpl-cached",
+                "created": 1677652288,
+                "choices": [{
+                    "index": 0,
+                    "message": {"role": "assistant", "content": "Hi!"},
+                    "finish_reason": "stop"
+                }]
+            }"#,
+            )
+            .expect(1)
+            .create();
+        let client = mocked_client();
+        assert!(client.last_response_meta().is_none());
+        let options = RequestOptions::new().cloudflare_cache_ttl(Duration::from_secs(3600));
+        let args = ChatArgs::from(vec![(ChatRole::User, "Hello!".to_string())]);
+        assert_eq!(
+            client.with_options(&options).chat(args).await?.id,
+            "chatcmpl-cached"
+        );
+        mock.assert();
+
+        let meta = client.last_response_meta().unwrap();
+        assert_eq!(meta.status, 200);
+        assert_eq!(meta.cloudflare_cache_hit(), Some(true));
+        Ok(())
+    }
+
+    #[test]
+    fn gateway_url() -> crate::Result<()> {
+        let client = Client::cloudflare_gateway("acct", "gw", "sk-key")?
+            .with_cloudflare_gateway_token("cf-token")?;
+        assert_eq!(
+            client.inner.base_url.as_str(),
+            "https://gateway.ai.cloudflare.com/v1/acct/gw/openai/"
+        );
+        assert_eq!(
+            client.inner.headers["cf-aig-authorization"],
+            "Bearer cf-token"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,114 @@
+//! Gzip compression of request bodies, with the `gzip` feature.
+use std::io::Write;
+
+use flate2::{write::GzEncoder, Compression};
+use reqwest::header::{HeaderValue, CONTENT_ENCODING};
+
+use crate::Client;
+
+impl Client {
+    /// Gzips the bodies of requests of at least `min_size` bytes, and marks them with a
+    /// `Content-Encoding: gzip` header. Only for servers and gateways that accept
+    /// compressed requests: the OpenAI API itself doesn't.
+    ///
+    /// Large embeddings and batch requests shrink several times over, while compressing
+    /// small ones isn't worth it, so a `min_size` of a few kilobytes is a good start.
+    #[must_use]
+    pub fn with_gzip_requests(mut self, min_size: usize) -> Self {
+        self.inner_mut().gzip_from = Some(min_size);
+        self
+    }
+
+    /// Private helper gzipping the body of `request` when it is large enough
+    pub(crate) fn compress(&self, mut request: reqwest::Request) -> reqwest::Request {
+        let body = match (
+            self.inner.gzip_from,
+            request.body().and_then(reqwest::Body::as_bytes),
+        ) {
+            (Some(min_size), Some(body)) if body.len() >= min_size => body,
+            _ => return request,
+        };
+        let mut encoder = GzEncoder::new(Vec::with_capacity(body.len() / 4), Compression::fast());
+        let gzipped = encoder
+            .write_all(body)
+            .and_then(|()| encoder.finish())
+            .expect("writing to a Vec doesn't fail");
+        *request.body_mut() = Some(gzipped.into());
+        request
+            .headers_mut()
+            .insert(CONTENT_ENCODING, HeaderValue::from_static("gzip"));
+        request
+    }
+}
+
+#[cfg(test)]
+mod unit {
+    use std::{io::Read, time::Duration};
+
+    use flate2::read::GzDecoder;
+
+    use crate::{throttle::Throttle, Client};
+
+    #[test]
+    fn gzips_large_bodies_only() -> crate::Result<()> {
+        let client = Client::new("bogus")?.with_gzip_requests(1024);
+        let request = |body: &serde_json::Value| {
+            client
+                .build(
+                    client
+                        .inner
+                        .client
+                        .post("https://example.com/embeddings")
+                        .json(body),
+                )
+                .map(|request| client.compress(request))
+        };
+        let body = |request: &reqwest::Request| {
+            request
+                .body()
+                .and_then(reqwest::Body::as_bytes)
+                .unwrap()
+                .to_vec()
+        };
+
+        let small = serde_json::json!({"input": "Hello!"});
+        let request_small = request(&small)?;
+        assert!(request_small.headers().get("content-encoding").is_none());
+        assert_eq!(body(&request_small), serde_json::to_vec(&small)?);
+
+        let large = serde_json::json!({"input": vec!["Hello!"; 1000]});
+        let request_large = request(&large)?;
+        assert_eq!(request_large.headers()["content-encoding"], "gzip");
+        let gzipped = body(&request_large);
+        assert!(gzipped.len() < 1024);
+        let mut decompressed = Vec::new();
+        GzDecoder::new(&gzipped[..])
+            .read_to_end(&mut decompressed)
+            .unwrap();
+        assert_eq!(decompressed, serde_json::to_vec(&large)?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn throttles_uncompressed_tokens() -> crate::Result<()> {
+        let throttle = Throttle::new(None, Some(1000));
+        let client = Client::new("bogus")?
+            .with_gzip_requests(1024)
+            .with_throttle(throttle.clone());
+        let body = serde_json::json!({"input": vec!["Hello!"; 1000]});
+        let request = client
+            .prepare(
+                client
+                    .inner
+                    .client
+                    .post("https://example.com/embeddings")
+                    .json(&body),
+            )
+            .await?;
+        assert_eq!(request.headers()["content-encoding"], "gzip");
+        // The request spent the whole budget, although its gzipped body is tiny
+        let next = tokio::time::timeout(Duration::from_millis(50), throttle.acquire(1)).await;
+        assert!(next.is_err());
+        Ok(())
+    }
+}
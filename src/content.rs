@@ -0,0 +1,311 @@
+//! The content of chat messages: plain text, or parts mixing text and images for vision
+//! models.
+use std::borrow::Cow;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Tokens a low-detail image costs, whatever its size
+const LOW_DETAIL_IMAGE_TOKENS: usize = 85;
+/// Tokens a high-detail image costs, at the 1024x1024 pixels of most inputs once resized
+const HIGH_DETAIL_IMAGE_TOKENS: usize = 765;
+
+/// The content of a chat message: a string, or a list of parts with images
+///
+/// Plain text serializes as a string, as it always did, so servers without vision support
+/// keep working with text messages.
+///
+/// # Example
+/// ```
+/// # use openai_api::api::{ChatContent, ContentPart, ImageDetail};
+/// let text = ChatContent::from("What is in this image?");
+/// assert_eq!(serde_json::to_value(&text).unwrap(), "What is in this image?");
+///
+/// let parts = ChatContent::from(vec![
+///     ContentPart::text("What is in this image?"),
+///     ContentPart::image_url("https://example.com/cat.png", Some(ImageDetail::Low)),
+/// ]);
+/// let json = serde_json::to_value(&parts).unwrap();
+/// assert_eq!(json[1]["type"], "image_url");
+/// assert_eq!(json[1]["image_url"]["detail"], "low");
+/// assert_eq!(parts.text(), "What is in this image?");
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChatContent {
+    Text(String),
+    Parts(Vec<ContentPart>),
+}
+
+impl ChatContent {
+    /// The text of the content, its text parts joined with newlines
+    #[must_use]
+    pub fn text(&self) -> Cow<'_, str> {
+        match self {
+            ChatContent::Text(text) => Cow::Borrowed(text),
+            ChatContent::Parts(parts) => {
+                let mut texts = parts.iter().filter_map(|part| match part {
+                    ContentPart::Text { text } => Some(text.as_str()),
+                    ContentPart::ImageUrl { .. } => None,
+                });
+                match (texts.next(), texts.next()) {
+                    (None, _) => Cow::Borrowed(""),
+                    (Some(first), None) => Cow::Borrowed(first),
+                    (Some(first), Some(second)) => {
+                        let mut text = format!("{}\n{}", first, second);
+                        for next in texts {
+                            text.push('\n');
+                            text.push_str(next);
+                        }
+                        Cow::Owned(text)
+                    }
+                }
+            }
+        }
+    }
+
+    /// Whether there is neither text nor images
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        match self {
+            ChatContent::Text(text) => text.is_empty(),
+            ChatContent::Parts(parts) => parts.is_empty(),
+        }
+    }
+
+    /// The images of the content
+    pub fn images(&self) -> impl Iterator<Item = &ImageUrl> {
+        let parts = match self {
+            ChatContent::Text(_) => &[][..],
+            ChatContent::Parts(parts) => parts,
+        };
+        parts.iter().filter_map(|part| match part {
+            ContentPart::ImageUrl { image_url } => Some(image_url),
+            ContentPart::Text { .. } => None,
+        })
+    }
+
+    /// Estimated tokens of the images: their size isn't known without downloading them,
+    /// so high and automatic detail images count as 1024x1024 pixels
+    pub(crate) fn image_tokens(&self) -> usize {
+        self.images()
+            .map(|image| match image.detail {
+                Some(ImageDetail::Low) => LOW_DETAIL_IMAGE_TOKENS,
+                _ => HIGH_DETAIL_IMAGE_TOKENS,
+            })
+            .sum()
+    }
+}
+
+impl Default for ChatContent {
+    fn default() -> Self {
+        ChatContent::Text(String::new())
+    }
+}
+
+impl From<String> for ChatContent {
+    fn from(text: String) -> Self {
+        ChatContent::Text(text)
+    }
+}
+
+impl From<&str> for ChatContent {
+    fn from(text: &str) -> Self {
+        ChatContent::Text(text.into())
+    }
+}
+
+impl From<Vec<ContentPart>> for ChatContent {
+    fn from(parts: Vec<ContentPart>) -> Self {
+        ChatContent::Parts(parts)
+    }
+}
+
+impl PartialEq<str> for ChatContent {
+    fn eq(&self, other: &str) -> bool {
+        matches!(self, ChatContent::Text(text) if text == other)
+    }
+}
+
+impl PartialEq<&str> for ChatContent {
+    fn eq(&self, other: &&str) -> bool {
+        self == *other
+    }
+}
+
+impl std::fmt::Display for ChatContent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.text())
+    }
+}
+
+impl Serialize for ChatContent {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            ChatContent::Text(text) => serializer.serialize_str(text),
+            ChatContent::Parts(parts) => parts.serialize(serializer),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ChatContent {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw {
+            Text(String),
+            Parts(Vec<ContentPart>),
+        }
+
+        Ok(match Raw::deserialize(deserializer)? {
+            Raw::Text(text) => ChatContent::Text(text),
+            Raw::Parts(parts) => ChatContent::Parts(parts),
+        })
+    }
+}
+
+/// A part of the content of a chat message
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContentPart {
+    Text {
+        text: String,
+    },
+    /// An image, for vision models such as gpt-4o. Only user messages can hold images.
+    ImageUrl {
+        image_url: ImageUrl,
+    },
+}
+
+impl ContentPart {
+    #[must_use]
+    pub fn text(text: impl Into<String>) -> Self {
+        ContentPart::Text { text: text.into() }
+    }
+
+    /// An image at `url`, which may be a `data:` URL holding the image itself, see
+    /// `ImageUrl::from_bytes`
+    #[must_use]
+    pub fn image_url(url: impl Into<String>, detail: Option<ImageDetail>) -> Self {
+        ContentPart::ImageUrl {
+            image_url: ImageUrl {
+                url: url.into(),
+                detail,
+            },
+        }
+    }
+}
+
+/// An image of a `ContentPart`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ImageUrl {
+    /// An `https:` URL, or a `data:` URL with the base64-encoded image
+    pub url: String,
+    /// How closely the model looks at the image, `Auto` by default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<ImageDetail>,
+}
+
+impl ImageUrl {
+    /// An image sent inline, as a `data:` URL, e.g. from `std::fs::read("cat.png")`
+    #[must_use]
+    pub fn from_bytes(mime_type: &str, image: &[u8], detail: Option<ImageDetail>) -> Self {
+        use base64::Engine;
+
+        Self {
+            url: format!(
+                "data:{};base64,{}",
+                mime_type,
+                base64::engine::general_purpose::STANDARD.encode(image)
+            ),
+            detail,
+        }
+    }
+}
+
+impl From<ImageUrl> for ContentPart {
+    fn from(image_url: ImageUrl) -> Self {
+        ContentPart::ImageUrl { image_url }
+    }
+}
+
+/// How closely a vision model looks at an image
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageDetail {
+    /// Chosen by the model from the size of the image
+    Auto,
+    /// A 512x512 pixels version of the image, for 85 tokens
+    Low,
+    /// The image in 512x512 pixels tiles, on top of the low-detail version
+    High,
+}
+
+#[cfg(test)]
+mod unit {
+    use super::{ChatContent, ContentPart, ImageDetail, ImageUrl};
+    use crate::api::{ChatArgs, ChatFormat, ChatRole};
+
+    #[test]
+    fn content_round_trips() -> Result<(), serde_json::Error> {
+        let text: ChatContent = serde_json::from_str(r#""Hello""#)?;
+        assert_eq!(text, "Hello");
+
+        let json = r#"[{"type": "text", "text": "Compare"}, {"type": "image_url",
+            "image_url": {"url": "https://example.com/a.png"}}, {"type": "text", "text":
+            "and this"}, {"type": "image_url", "image_url": {"url": "data:image/png;base64,AAEC",
+            "detail": "low"}}]"#;
+        let parts: ChatContent = serde_json::from_str(json)?;
+        assert_eq!(parts.text(), "Compare\nand this");
+        assert_eq!(parts.images().count(), 2);
+        assert_eq!(parts.image_tokens(), 765 + 85);
+        assert_eq!(
+            serde_json::to_value(&parts)?,
+            serde_json::from_str::<serde_json::Value>(json)?
+        );
+
+        let inline = ImageUrl::from_bytes("image/png", &[0, 1, 2], Some(ImageDetail::Low));
+        assert_eq!(Some(&inline), parts.images().last());
+        let message = ChatContent::from(vec![ContentPart::text("Hi"), inline.into()]);
+        assert_ne!(message, "Hi");
+        Ok(())
+    }
+
+    #[test]
+    fn only_user_messages_hold_images() -> crate::Result<()> {
+        let image = || {
+            vec![
+                ContentPart::text("What is this?"),
+                ContentPart::image_url("https://example.com/cat.png", None),
+            ]
+        };
+        let args = ChatArgs::builder()
+            .model("gpt-4o")
+            .messages(vec![ChatFormat::with_parts(ChatRole::User, image())])
+            .build()?;
+        args.validate()?;
+        let body = serde_json::to_value(&args)?;
+        assert_eq!(
+            body["messages"][0]["content"][1]["image_url"]["url"],
+            "https://example.com/cat.png"
+        );
+
+        let args = ChatArgs::builder()
+            .model("gpt-4o")
+            .messages(vec![ChatFormat::with_parts(ChatRole::System, image())])
+            .build()?;
+        assert!(args.validate().is_err());
+
+        for model in ["gpt-3.5-turbo", "gpt-4-0613", "o1-mini"] {
+            let args = ChatArgs::builder()
+                .model(model)
+                .messages(vec![ChatFormat::with_parts(ChatRole::User, image())])
+                .build()?;
+            match args.validate() {
+                Err(crate::Error::BadArguments(message)) => {
+                    assert!(message.contains("doesn't accept images"))
+                }
+                other => panic!("expected BadArguments, got {:?}", other),
+            }
+        }
+        Ok(())
+    }
+}
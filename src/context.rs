@@ -0,0 +1,238 @@
+//! Trimming conversations to fit in a model's context window.
+//!
+//! A long conversation eventually outgrows the model's context, and the API rejects it. A
+//! [`ContextWindow`] drops its oldest messages until the rest fits, with room left for the
+//! answer. System messages are always kept.
+//!
+//! Messages are counted with the model's tokenizer with the `tiktoken` feature, and
+//! otherwise with a conservative estimate that may drop more messages than needed.
+use crate::{
+    api::{ChatFormat, ChatRole},
+    models,
+};
+
+/// Tokens the API adds around every message
+#[cfg(not(feature = "tiktoken"))]
+const TOKENS_PER_MESSAGE: u64 = 3;
+/// Tokens priming the answer of the assistant
+const TOKENS_PER_REPLY: u64 = 3;
+
+/// The context window of a model, and the part of it reserved for the answer
+///
+/// # Example
+/// ```
+/// # use openai_api::{api::{ChatFormat, ChatRole}, context::ContextWindow};
+/// let window = ContextWindow::for_model("gpt-4").unwrap().reserve(8000);
+/// let mut messages = vec![
+///     ChatFormat::new(ChatRole::System, "You are a helpful assistant.".into()),
+///     ChatFormat::new(ChatRole::User, "Tell me a story. ".repeat(50)),
+///     ChatFormat::new(ChatRole::Assistant, "Once upon a time... ".repeat(50)),
+///     ChatFormat::new(ChatRole::User, "Shorter, please.".into()),
+/// ];
+/// assert_eq!(window.trim(&mut messages), 2);
+/// assert_eq!(messages[0].role, ChatRole::System);
+/// assert_eq!(messages[1].content, "Shorter, please.");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContextWindow {
+    model: String,
+    context_length: u64,
+    reserved: u64,
+}
+
+impl ContextWindow {
+    /// The window of `model`, `context_length` tokens long
+    #[must_use]
+    pub fn new(model: impl Into<String>, context_length: u64) -> Self {
+        Self {
+            model: model.into(),
+            context_length,
+            reserved: 0,
+        }
+    }
+
+    /// The window of `model`, if its context length is in the `models` table
+    #[must_use]
+    pub fn for_model(model: &str) -> Option<Self> {
+        models::context_length(model).map(|length| Self::new(model, length))
+    }
+
+    /// Keeps `max_tokens` of the window free for the answer
+    #[must_use]
+    pub fn reserve(mut self, max_tokens: u64) -> Self {
+        self.reserved = max_tokens;
+        self
+    }
+
+    /// The tokens left for the messages
+    #[must_use]
+    pub fn budget(&self) -> u64 {
+        self.context_length.saturating_sub(self.reserved)
+    }
+
+    /// The prompt tokens of a request sending `messages`
+    #[must_use]
+    pub fn prompt_tokens(&self, messages: &[ChatFormat]) -> u64 {
+        messages.iter().map(|m| self.message_tokens(m)).sum::<u64>() + TOKENS_PER_REPLY
+    }
+
+    /// Whether `messages` fit in the budget
+    #[must_use]
+    pub fn fits(&self, messages: &[ChatFormat]) -> bool {
+        self.prompt_tokens(messages) <= self.budget()
+    }
+
+    /// Drops the oldest messages, other than system messages, until `messages` fit in the
+    /// budget, and returns how many were dropped.
+    ///
+    /// The results of a function or tool are dropped with the message calling it, since the
+    /// API rejects results without their call. The last message is never dropped, so
+    /// `messages` may still not fit when it is too long on its own.
+    pub fn trim(&self, messages: &mut Vec<ChatFormat>) -> usize {
+        let tokens: Vec<u64> = messages.iter().map(|m| self.message_tokens(m)).collect();
+        let mut total = tokens.iter().sum::<u64>() + TOKENS_PER_REPLY;
+        let mut dropped = vec![false; messages.len()];
+        let mut candidates = (0..messages.len().saturating_sub(1))
+            .filter(|&i| messages[i].role != ChatRole::System)
+            .peekable();
+        while total > self.budget() {
+            let Some(i) = candidates.next() else { break };
+            dropped[i] = true;
+            total -= tokens[i];
+            // The results answering the dropped call go with it
+            while let Some(&next) = candidates.peek() {
+                if !is_result(&messages[next]) {
+                    break;
+                }
+                dropped[next] = true;
+                total -= tokens[next];
+                candidates.next();
+            }
+        }
+        let mut index = 0;
+        messages.retain(|_| {
+            index += 1;
+            !dropped[index - 1]
+        });
+        dropped.iter().filter(|&&d| d).count()
+    }
+
+    fn message_tokens(&self, message: &ChatFormat) -> u64 {
+        #[cfg(feature = "tiktoken")]
+        {
+            crate::tokens::message_tokens(&self.model, message) as u64
+        }
+        #[cfg(not(feature = "tiktoken"))]
+        {
+            let calls = message.function_call.iter().chain(
+                message
+                    .tool_calls
+                    .iter()
+                    .flatten()
+                    .map(|call| &call.function),
+            );
+            let texts = message
+                .name
+                .iter()
+                .chain(calls.flat_map(|call| [&call.name, &call.arguments]));
+            let content = &message.content;
+            let text: usize = texts
+                .map(|text| crate::estimate_tokens(text))
+                .sum::<usize>()
+                + crate::estimate_tokens(&content.text())
+                + content.image_tokens();
+            // One token for the role
+            TOKENS_PER_MESSAGE + 1 + text as u64
+        }
+    }
+}
+
+/// Whether `message` holds the result of a function or tool call, which must follow it
+pub(crate) fn is_result(message: &ChatFormat) -> bool {
+    matches!(message.role, ChatRole::Function | ChatRole::Tool)
+}
+
+#[cfg(test)]
+mod unit {
+    use super::ContextWindow;
+    use crate::api::{ChatArgs, ChatFormat, ChatRole, ToolCall};
+
+    fn message(role: ChatRole, content: &str) -> ChatFormat {
+        ChatFormat::new(role, content.into())
+    }
+
+    #[test]
+    fn trims_oldest_messages_first() {
+        let mut messages = vec![
+            message(ChatRole::System, "Be brief."),
+            message(ChatRole::User, &"first question ".repeat(20)),
+            message(ChatRole::Assistant, &"first answer ".repeat(20)),
+            message(ChatRole::User, "second question"),
+        ];
+        let window = ContextWindow::new("gpt-4", 1000);
+        assert!(window.fits(&messages));
+        assert_eq!(window.trim(&mut messages), 0);
+
+        let needed = window.prompt_tokens(&messages);
+        let last_two = window.prompt_tokens(&[messages[0].clone(), messages[3].clone()]);
+        let window = ContextWindow::new("gpt-4", needed).reserve(needed - last_two);
+        assert!(!window.fits(&messages));
+        assert_eq!(window.trim(&mut messages), 2);
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].role, ChatRole::System);
+        assert_eq!(messages[1].content, "second question");
+        assert!(window.fits(&messages));
+
+        // The last message stays even when it doesn't fit
+        assert_eq!(ContextWindow::new("gpt-4", 1).trim(&mut messages), 0);
+        assert_eq!(messages.len(), 2);
+    }
+
+    #[test]
+    fn drops_tool_results_with_their_call() -> Result<(), serde_json::Error> {
+        let mut call = message(ChatRole::Assistant, "");
+        call.tool_calls = Some(vec![serde_json::from_value::<ToolCall>(
+            serde_json::json!({
+                "id": "call_1",
+                "type": "function",
+                "function": {"name": "get_time", "arguments": "{}"}
+            }),
+        )?]);
+        let mut messages = vec![
+            message(ChatRole::User, "What time is it?"),
+            call,
+            ChatFormat::tool_result("call_1", "12:00"),
+            message(ChatRole::Assistant, "It's noon."),
+            message(ChatRole::User, "Thanks!"),
+        ];
+        let window = ContextWindow::new("gpt-4", 0);
+        let keep = window.prompt_tokens(&messages[3..]);
+        assert_eq!(ContextWindow::new("gpt-4", keep).trim(&mut messages), 3);
+        assert_eq!(messages[0].content, "It's noon.");
+        Ok(())
+    }
+
+    #[test]
+    fn fits_chat_args() -> crate::Result<()> {
+        let mut args = ChatArgs::builder()
+            .model("gpt-4")
+            .max_tokens(8000)
+            .messages(vec![
+                message(ChatRole::User, &"old ".repeat(500)),
+                message(ChatRole::User, "new"),
+            ])
+            .build()?;
+        assert!(args.validate().is_err());
+        assert_eq!(args.fit_to_context(8192), 1);
+        assert_eq!(args.messages.len(), 1);
+        args.validate()?;
+        Ok(())
+    }
+
+    #[test]
+    fn knows_model_windows() {
+        let window = ContextWindow::for_model("gpt-4-0613").map(|w| w.reserve(192));
+        assert_eq!(window.map(|w| w.budget()), Some(8000));
+        assert_eq!(ContextWindow::for_model("llama3"), None);
+    }
+}
@@ -0,0 +1,802 @@
+//! Embeddings: vector representations of text, plus the vector math usually applied to them.
+use futures::TryStreamExt;
+use serde::{Deserialize, Serialize};
+
+use crate::{api::Problems, models::ModelKind, pool, response, retry, Client, Error, Result};
+
+/// The most inputs the API accepts in a single embeddings request
+pub const MAX_INPUTS_PER_REQUEST: usize = 2048;
+
+/// The most tokens the API accepts across all inputs of a single embeddings request
+pub const MAX_TOKENS_PER_REQUEST: usize = 300_000;
+
+/// Arguments of an embeddings request. Like the other args types, they can be deserialized
+/// as well as built; only `input` is required then.
+#[derive(Serialize, Deserialize, Debug, Builder, Clone)]
+#[builder(pattern = "immutable")]
+pub struct EmbeddingsArgs {
+    /// The id of the model to use for this request
+    ///
+    /// # Example
+    /// ```
+    /// # use openai_api::embeddings::EmbeddingsArgs;
+    /// EmbeddingsArgs::builder().model("text-embedding-ada-002");
+    /// ```
+    #[builder(setter(into), default = "default_model()")]
+    #[serde(default = "default_model")]
+    pub(crate) model: String,
+    /// The texts to embed. Each one gets its own vector in the response.
+    ///
+    /// # Example
+    /// ```
+    /// # use openai_api::embeddings::EmbeddingsArgs;
+    /// EmbeddingsArgs::builder().input(vec!["The food was delicious".into()]);
+    /// ```
+    #[builder(setter(into))]
+    pub(crate) input: Vec<String>,
+    /// The number of dimensions of the vectors, fewer than the model's own to store them
+    /// more cheaply. Only text-embedding-3 and later models support it, see
+    /// `models::max_dimensions`.
+    ///
+    /// # Example
+    /// ```
+    /// # use openai_api::embeddings::EmbeddingsArgs;
+    /// EmbeddingsArgs::builder().model("text-embedding-3-small").dimensions(512);
+    /// ```
+    #[builder(setter(strip_option), default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    dimensions: Option<u32>,
+    /// How the API encodes the vectors in its response. Either way they are decoded to
+    /// `Embedding::embedding`; `Base64` makes responses about half as large, and quicker
+    /// to parse.
+    ///
+    /// # Example
+    /// ```
+    /// # use openai_api::embeddings::{EmbeddingsArgs, EncodingFormat};
+    /// EmbeddingsArgs::builder().encoding_format(EncodingFormat::Base64);
+    /// ```
+    #[builder(setter(strip_option), default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    encoding_format: Option<EncodingFormat>,
+    /// A unique identifier representing your end-user, which can help OpenAI to monitor
+    /// and detect abuse.
+    #[builder(setter(into, strip_option), default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    user: Option<String>,
+}
+
+/// The encoding of the vectors of an embeddings response
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum EncodingFormat {
+    /// Arrays of JSON numbers, the API's default
+    #[default]
+    Float,
+    /// Base64 strings of packed little-endian `f32`s
+    Base64,
+}
+
+fn default_model() -> String {
+    "text-embedding-ada-002".into()
+}
+
+impl EmbeddingsArgs {
+    /// Build a `EmbeddingsArgs` from the defaults
+    #[must_use]
+    pub fn builder() -> EmbeddingsArgsBuilder {
+        EmbeddingsArgsBuilder::default()
+    }
+
+    /// Checks the arguments locally, without calling the API: the number of inputs,
+    /// whether the model is an embedding model, whether each input fits in the model's
+    /// context (estimating input sizes, unless the `tiktoken` feature counts them), and whether the model supports the `dimensions`
+    /// asked for.
+    ///
+    /// # Errors
+    /// - `Error::BadArguments` listing every problem found
+    pub fn validate(&self) -> Result<()> {
+        let mut problems = Problems::default();
+        problems.check(
+            !self.input.is_empty() && self.input.len() <= MAX_INPUTS_PER_REQUEST,
+            || {
+                format!(
+                    "between 1 and {} inputs are supported, got {}",
+                    MAX_INPUTS_PER_REQUEST,
+                    self.input.len()
+                )
+            },
+        );
+        let longest = self.input.iter().map(|i| self.input_tokens(i)).max();
+        problems.model(
+            &self.model,
+            ModelKind::Embedding,
+            longest.unwrap_or(0) as u64,
+        );
+        if let Some(dimensions) = self.dimensions {
+            problems.check(dimensions > 0, || "dimensions must be positive".into());
+            match crate::models::max_dimensions(&self.model) {
+                Some(max) => problems.check(dimensions <= max, || {
+                    format!(
+                        "{} makes at most {} dimensions, got {}",
+                        self.model, max, dimensions
+                    )
+                }),
+                None => problems.check(crate::models::lookup(&self.model).is_none(), || {
+                    format!("{} doesn't support dimensions", self.model)
+                }),
+            }
+        }
+        problems.into_result()
+    }
+
+    /// Tokens of `input`: counted with the model's tokenizer with the `tiktoken` feature,
+    /// and otherwise estimated, over-counting
+    fn input_tokens(&self, input: &str) -> usize {
+        #[cfg(feature = "tiktoken")]
+        {
+            crate::tokens::count_tokens(&self.model, input)
+        }
+        #[cfg(not(feature = "tiktoken"))]
+        {
+            crate::estimate_tokens(input)
+        }
+    }
+
+    /// Splits the inputs into requests that each stay within `MAX_INPUTS_PER_REQUEST`
+    /// and `MAX_TOKENS_PER_REQUEST`, keeping the input order.
+    fn into_chunks(mut self) -> Vec<EmbeddingsArgs> {
+        let mut chunks = Vec::new();
+        let mut current: Vec<String> = Vec::new();
+        let mut current_tokens = 0;
+        for input in std::mem::take(&mut self.input) {
+            let tokens = self.input_tokens(&input);
+            if !current.is_empty()
+                && (current.len() == MAX_INPUTS_PER_REQUEST
+                    || current_tokens + tokens > MAX_TOKENS_PER_REQUEST)
+            {
+                chunks.push(std::mem::take(&mut current));
+                current_tokens = 0;
+            }
+            current.push(input);
+            current_tokens += tokens;
+        }
+        if !current.is_empty() {
+            chunks.push(current);
+        }
+        chunks
+            .into_iter()
+            .map(|input| EmbeddingsArgs {
+                input,
+                model: self.model.clone(),
+                dimensions: self.dimensions,
+                encoding_format: self.encoding_format,
+                user: self.user.clone(),
+            })
+            .collect()
+    }
+}
+
+impl From<&str> for EmbeddingsArgs {
+    fn from(input: &str) -> Self {
+        vec![input.to_string()].into()
+    }
+}
+
+impl From<Vec<String>> for EmbeddingsArgs {
+    fn from(input: Vec<String>) -> Self {
+        EmbeddingsArgs::builder()
+            .input(input)
+            .build()
+            .expect("input is the only required field")
+    }
+}
+
+impl TryFrom<EmbeddingsArgsBuilder> for EmbeddingsArgs {
+    type Error = EmbeddingsArgsBuilderError;
+
+    fn try_from(builder: EmbeddingsArgsBuilder) -> std::result::Result<Self, Self::Error> {
+        builder.build()
+    }
+}
+
+/// Represents an embeddings response
+#[derive(Deserialize, Debug, Clone)]
+pub struct Embeddings {
+    /// Exact model type and version used for the embeddings
+    #[serde(default, deserialize_with = "crate::api::lenient")]
+    pub model: String,
+    /// One embedding per input, see `Embedding::index`
+    pub data: Vec<Embedding>,
+}
+
+/// A single embedding vector
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct Embedding {
+    /// Position of the input this embedding belongs to
+    pub index: u64,
+    /// The embedding vector, decoded if it was requested in `EncodingFormat::Base64`
+    #[serde(deserialize_with = "vector")]
+    pub embedding: Vec<f32>,
+}
+
+/// Deserializes a vector encoded in either `EncodingFormat`
+fn vector<'de, D>(deserializer: D) -> std::result::Result<Vec<f32>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    struct VectorVisitor;
+
+    impl<'de> serde::de::Visitor<'de> for VectorVisitor {
+        type Value = Vec<f32>;
+
+        fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            f.write_str("an array of floats, or a base64 string of little-endian f32s")
+        }
+
+        fn visit_seq<A>(self, mut seq: A) -> std::result::Result<Self::Value, A::Error>
+        where
+            A: serde::de::SeqAccess<'de>,
+        {
+            let mut vector = Vec::with_capacity(seq.size_hint().unwrap_or(1536));
+            while let Some(x) = seq.next_element()? {
+                vector.push(x);
+            }
+            Ok(vector)
+        }
+
+        fn visit_str<E>(self, encoded: &str) -> std::result::Result<Self::Value, E>
+        where
+            E: serde::de::Error,
+        {
+            decode_base64(encoded).map_err(E::custom)
+        }
+    }
+
+    deserializer.deserialize_any(VectorVisitor)
+}
+
+/// Decodes a vector in `EncodingFormat::Base64`
+fn decode_base64(encoded: &str) -> std::result::Result<Vec<f32>, String> {
+    use base64::Engine;
+
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(encoded)
+        .map_err(|e| e.to_string())?;
+    if bytes.len() % 4 != 0 {
+        return Err(format!(
+            "{} bytes aren't a whole number of f32s",
+            bytes.len()
+        ));
+    }
+    Ok(bytes
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect())
+}
+
+impl Embedding {
+    /// Dot product with another embedding. See [`dot`].
+    #[must_use]
+    pub fn dot(&self, other: &Embedding) -> f32 {
+        dot(&self.embedding, &other.embedding)
+    }
+
+    /// Cosine similarity with another embedding. See [`cosine_similarity`].
+    #[must_use]
+    pub fn cosine_similarity(&self, other: &Embedding) -> f32 {
+        cosine_similarity(&self.embedding, &other.embedding)
+    }
+
+    /// Scales the vector in place to unit length. See [`normalize`].
+    pub fn normalize(&mut self) {
+        normalize(&mut self.embedding);
+    }
+}
+
+impl AsRef<[f32]> for Embedding {
+    fn as_ref(&self) -> &[f32] {
+        &self.embedding
+    }
+}
+
+/// Dot product of two vectors.
+///
+/// For unit-length vectors (OpenAI embeddings are normalized) this equals the cosine
+/// similarity, and is cheaper to compute.
+///
+/// # Panics
+/// If the vectors have different lengths
+#[must_use]
+pub fn dot(a: &[f32], b: &[f32]) -> f32 {
+    assert_eq!(a.len(), b.len(), "vectors must have the same dimensions");
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+/// Cosine similarity of two vectors, in `[-1.0, 1.0]`.
+///
+/// Returns `0.0` when either vector is all zeroes.
+///
+/// # Panics
+/// If the vectors have different lengths
+#[must_use]
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let norms = l2_norm(a) * l2_norm(b);
+    if norms == 0.0 {
+        return 0.0;
+    }
+    dot(a, b) / norms
+}
+
+/// Euclidean length of a vector
+#[must_use]
+pub fn l2_norm(v: &[f32]) -> f32 {
+    v.iter().map(|x| x * x).sum::<f32>().sqrt()
+}
+
+/// Scales a vector in place to unit length. All-zero vectors are left untouched.
+pub fn normalize(v: &mut [f32]) {
+    let norm = l2_norm(v);
+    if norm > 0.0 {
+        v.iter_mut().for_each(|x| *x /= norm);
+    }
+}
+
+/// Finds the `k` vectors of `corpus` most similar to `query`.
+///
+/// Returns `(position in corpus, cosine similarity)` pairs, most similar first.
+///
+/// # Example
+/// ```
+/// # use openai_api::embeddings::top_k;
+/// let corpus = vec![vec![1.0, 0.0], vec![0.0, 1.0], vec![0.7, 0.7]];
+/// let best = top_k(&[1.0, 0.1], &corpus, 2);
+/// assert_eq!(best[0].0, 0);
+/// assert_eq!(best[1].0, 2);
+/// ```
+///
+/// # Panics
+/// If a corpus vector doesn't have the dimensions of `query`
+#[must_use]
+pub fn top_k<V: AsRef<[f32]>>(query: &[f32], corpus: &[V], k: usize) -> Vec<(usize, f32)> {
+    let mut scored: Vec<_> = corpus
+        .iter()
+        .enumerate()
+        .map(|(i, v)| (i, cosine_similarity(query, v.as_ref())))
+        .collect();
+    scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+    scored.truncate(k);
+    scored
+}
+
+impl Client {
+    /// Creates embedding vectors representing the input texts.
+    ///
+    /// Accepts anything convertible to `EmbeddingsArgs`, including an unbuilt
+    /// `EmbeddingsArgsBuilder`.
+    ///
+    /// # Errors
+    ///  - `Error::BadArguments` if the arguments can't be built, or are invalid in dry-run
+    ///    mode
+    ///  - `Error::DryRun` in dry-run mode, if the arguments are valid
+    ///  - `Error::Api` if the api returns an error
+    pub async fn embeddings<A>(&self, args: A) -> Result<Embeddings>
+    where
+        A: TryInto<EmbeddingsArgs>,
+        Error: From<<A as TryInto<EmbeddingsArgs>>::Error>,
+    {
+        let args: EmbeddingsArgs = args.try_into()?;
+        self.dry_run("embeddings", &args, || args.validate())?;
+        self.post_embeddings(&args).await
+    }
+
+    /// Private helper posting an embeddings request, parsing the response as it arrives
+    /// rather than buffering it: a response is about five times as large as its vectors.
+    async fn post_embeddings(&self, args: &EmbeddingsArgs) -> Result<Embeddings> {
+        let url = self.url("embeddings")?;
+        let request = self
+            .prepare(self.inner.client.post(url).json(&self.body(args)?))
+            .await?;
+        retry::with_retries(&self.inner.retry, || {
+            self.post_embeddings_once(retry::resend(&request))
+        })
+        .await
+    }
+
+    /// Private helper making a single attempt at `post_embeddings`
+    async fn post_embeddings_once(&self, request: reqwest::Request) -> Result<Embeddings> {
+        #[cfg(not(target_arch = "wasm32"))]
+        if !self.buffered() {
+            return self.post_embeddings_live(request).await;
+        }
+        let response = self.execute(request).await?;
+        self.remember(response.meta());
+        if response.status != reqwest::StatusCode::OK {
+            return Err(Self::api_error(&response));
+        }
+        let mut parser = EmbeddingsParser::default();
+        parser.feed(&response.body)?;
+        parser.finish()
+    }
+
+    /// Private helper making an attempt at `post_embeddings`, parsing the response as it
+    /// arrives over the network
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn post_embeddings_live(&self, request: reqwest::Request) -> Result<Embeddings> {
+        let mut response = self.inner.client.execute(request).await?;
+        if response.status() != reqwest::StatusCode::OK {
+            let response =
+                response::RawResponse::read(response, self.inner.max_response_size).await?;
+            self.remember(response.meta());
+            return Err(Self::api_error(&response));
+        }
+        self.remember(response::ResponseMeta {
+            status: response.status().as_u16(),
+            headers: response.headers().clone(),
+        });
+        let limit = self.inner.max_response_size.unwrap_or(usize::MAX);
+        response::check_size(response.content_length().unwrap_or(0), limit)?;
+        let mut parser = EmbeddingsParser::default();
+        let mut size = 0;
+        while let Some(chunk) = response.chunk().await? {
+            size += chunk.len();
+            response::check_size(size as u64, limit)?;
+            parser.feed(&chunk)?;
+        }
+        parser.finish()
+    }
+
+    /// Embeds any number of inputs, splitting them into as many requests as the API
+    /// limits require. Requests are sized by token counts with the `tiktoken` feature, and
+    /// otherwise by an estimate that may send more requests than needed.
+    ///
+    /// At most `concurrency` requests are in flight at once. The returned embeddings are in
+    /// input order, with `Embedding::index` referring to the position in the full input.
+    ///
+    /// # Errors
+    ///  - `Error::Api` if the api returns an error for any of the requests
+    pub async fn embed_all(
+        &self,
+        args: impl Into<EmbeddingsArgs>,
+        concurrency: usize,
+    ) -> Result<Embeddings> {
+        let chunks = args.into().into_chunks();
+        let responses: Vec<Embeddings> =
+            crate::ordered_concurrent(chunks.into_iter().map(|c| self.embeddings(c)), concurrency)
+                .try_collect()
+                .await?;
+
+        let mut model = String::new();
+        let mut data = Vec::new();
+        for mut response in responses {
+            response.data.sort_by_key(|e| e.index);
+            let offset = data.len() as u64;
+            data.extend(response.data.into_iter().map(|mut e| {
+                e.index += offset;
+                e
+            }));
+            model = response.model;
+        }
+        Ok(Embeddings { model, data })
+    }
+}
+
+/// Incremental parser of an `Embeddings` response. The elements of the `data` array are
+/// parsed one by one as their bytes arrive, and the rest of the response once it is
+/// complete. Its buffers come from the pool shared by every response being parsed.
+#[derive(Default)]
+struct EmbeddingsParser {
+    /// The response without the elements of `data`
+    rest: pool::Buffer,
+    /// The bytes of the element of `data` being received
+    element: pool::Buffer,
+    data: Vec<Embedding>,
+    /// Nesting level of objects and arrays
+    depth: usize,
+    in_string: bool,
+    escaped: bool,
+    in_data: bool,
+}
+
+impl EmbeddingsParser {
+    fn feed(&mut self, chunk: &[u8]) -> Result<()> {
+        for &byte in chunk {
+            let in_element = self.in_data && self.depth > 2;
+            if self.in_string {
+                match (self.escaped, byte) {
+                    (true, _) => self.escaped = false,
+                    (false, b'\\') => self.escaped = true,
+                    (false, b'"') => self.in_string = false,
+                    _ => {}
+                }
+            } else {
+                match byte {
+                    b'"' => self.in_string = true,
+                    b'{' | b'[' => {
+                        if self.depth == 1 && byte == b'[' && self.at_data_value() {
+                            self.in_data = true;
+                        }
+                        self.depth += 1;
+                    }
+                    b'}' | b']' => self.depth = self.depth.saturating_sub(1),
+                    _ => {}
+                }
+            }
+            if !self.in_data {
+                self.rest.push(byte);
+            } else if in_element || self.depth > 2 {
+                self.element.push(byte);
+                if self.depth == 2 {
+                    self.data.push(crate::json::from_slice(&mut self.element)?);
+                    self.element.clear();
+                }
+            } else if self.depth == 1 {
+                self.in_data = false;
+                self.rest.extend_from_slice(b"[]");
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether the top-level object's `data` key was just read, followed by its colon
+    fn at_data_value(&self) -> bool {
+        let rest = self.rest.trim_ascii_end();
+        rest.strip_suffix(b":")
+            .is_some_and(|key| key.trim_ascii_end().ends_with(b"\"data\""))
+    }
+
+    fn finish(self) -> Result<Embeddings> {
+        let mut embeddings: Embeddings = serde_json::from_slice(&self.rest)?;
+        embeddings.data = self.data;
+        Ok(embeddings)
+    }
+}
+
+#[cfg(test)]
+mod unit {
+    use super::{
+        cosine_similarity, dot, normalize, top_k, Embedding, EmbeddingsArgs, EmbeddingsParser,
+        EncodingFormat, MAX_INPUTS_PER_REQUEST, MAX_TOKENS_PER_REQUEST,
+    };
+    use crate::{unit::mocked_client, Error};
+
+    fn assert_close(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-6, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn vector_math() {
+        assert_close(dot(&[1.0, 2.0, 3.0], &[4.0, 5.0, 6.0]), 32.0);
+        assert_close(cosine_similarity(&[1.0, 0.0], &[0.0, 2.0]), 0.0);
+        assert_close(cosine_similarity(&[1.0, 1.0], &[3.0, 3.0]), 1.0);
+        assert_close(cosine_similarity(&[1.0, 1.0], &[0.0, 0.0]), 0.0);
+
+        let mut v = [3.0, 4.0];
+        normalize(&mut v);
+        assert_close(v[0], 0.6);
+        assert_close(v[1], 0.8);
+    }
+
+    #[test]
+    fn top_k_orders_by_similarity() {
+        let corpus = vec![
+            Embedding {
+                index: 0,
+                embedding: vec![0.0, 1.0],
+            },
+            Embedding {
+                index: 1,
+                embedding: vec![1.0, 0.0],
+            },
+            Embedding {
+                index: 2,
+                embedding: vec![1.0, 1.0],
+            },
+        ];
+        let best = top_k(&[1.0, 0.2], &corpus, 2);
+        assert_eq!(best.iter().map(|(i, _)| *i).collect::<Vec<_>>(), [1, 2]);
+        assert!(top_k(&[1.0, 0.2], &corpus, 10).len() == 3);
+    }
+
+    #[tokio::test]
+    async fn embeddings() -> crate::Result<()> {
+        let mock = mockito::mock("POST", "/embeddings")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"{
+                "object": "list",
+                "data": [
+                    {
+                        "object": "embedding",
+                        "embedding": [0.0023064255, -0.009327292, -0.0028842222],
+                        "index": 0
+                    }
+                ],
+                "model": "text-embedding-ada-002",
+                "usage": {
+                    "prompt_tokens": 8,
+                    "total_tokens": 8
+                }
+            }"#,
+            )
+            .expect(1)
+            .create();
+        let args = EmbeddingsArgs::builder().input(vec!["The food was delicious".into()]);
+        let response = mocked_client().embeddings(args).await?;
+        assert_eq!(response.model, "text-embedding-ada-002");
+        assert_eq!(
+            response.data,
+            vec![Embedding {
+                index: 0,
+                embedding: vec![0.0023064255, -0.009327292, -0.0028842222],
+            }]
+        );
+        mock.assert();
+
+        let missing_input = mocked_client()
+            .embeddings(EmbeddingsArgs::builder().model("text-embedding-3-small"))
+            .await;
+        assert!(matches!(missing_input, Err(Error::BadArguments(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn parses_incrementally() -> crate::Result<()> {
+        let body = br#"{
+            "object": "list",
+            "data": [
+                {"object": "embedding", "embedding": [0.5, -1e-3], "index": 0},
+                {"object": "embedding", "embedding": [0.25, 2], "index": 1, "note": "}]\""}
+            ],
+            "model": "text-embedding-3-small",
+            "usage": {"prompt_tokens": 8, "total_tokens": 8}
+        }"#;
+        for chunk_size in [1, 7, body.len()] {
+            let mut parser = EmbeddingsParser::default();
+            for chunk in body.chunks(chunk_size) {
+                parser.feed(chunk)?;
+            }
+            let embeddings = parser.finish()?;
+            assert_eq!(embeddings.model, "text-embedding-3-small");
+            assert_eq!(
+                embeddings.data,
+                [
+                    Embedding {
+                        index: 0,
+                        embedding: vec![0.5, -1e-3],
+                    },
+                    Embedding {
+                        index: 1,
+                        embedding: vec![0.25, 2.0],
+                    },
+                ]
+            );
+        }
+
+        let mut truncated = EmbeddingsParser::default();
+        truncated.feed(&body[..body.len() / 2])?;
+        assert!(matches!(truncated.finish(), Err(Error::Json(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn validate_args() {
+        let args: EmbeddingsArgs = vec!["hello".to_string()].into();
+        assert!(args.validate().is_ok());
+        let args: EmbeddingsArgs = Vec::<String>::new().into();
+        assert!(matches!(args.validate(), Err(Error::BadArguments(_))));
+        let args = EmbeddingsArgs::builder()
+            .model("gpt-4")
+            .input(vec!["hello".into()])
+            .build()
+            .unwrap();
+        assert!(matches!(args.validate(), Err(Error::BadArguments(_))));
+    }
+
+    #[test]
+    fn decodes_base64_vectors() {
+        use base64::Engine;
+
+        let floats = [0.5f32, -0.25, 1.0e-3];
+        let bytes: Vec<u8> = floats.iter().flat_map(|x| x.to_le_bytes()).collect();
+        let encoded = base64::engine::general_purpose::STANDARD.encode(bytes);
+        let mut parser = EmbeddingsParser::default();
+        let response = format!(
+            r#"{{"object": "list", "data": [{{"object": "embedding", "index": 0, "embedding": "{}"}}], "model": "text-embedding-3-small"}}"#,
+            encoded
+        );
+        parser.feed(response.as_bytes()).unwrap();
+        assert_eq!(parser.finish().unwrap().data[0].embedding, floats.to_vec());
+
+        let truncated: Result<Embedding, _> =
+            serde_json::from_str(r#"{"index": 0, "embedding": "AAAAAAA="}"#);
+        assert!(truncated.is_err());
+        let args = EmbeddingsArgs::builder()
+            .input(vec!["hi".into()])
+            .encoding_format(EncodingFormat::Base64)
+            .build()
+            .unwrap();
+        assert_eq!(
+            serde_json::to_value(args).unwrap()["encoding_format"],
+            "base64"
+        );
+    }
+
+    #[test]
+    fn validate_dimensions() {
+        let args = |model: &str, dimensions| {
+            EmbeddingsArgs::builder()
+                .model(model)
+                .input(vec!["hello".into()])
+                .dimensions(dimensions)
+                .build()
+                .unwrap()
+        };
+        assert!(args("text-embedding-3-small", 512).validate().is_ok());
+        assert!(args("text-embedding-3-small", 2048).validate().is_err());
+        assert!(args("text-embedding-3-large", 3072).validate().is_ok());
+        assert!(args("text-embedding-3-large", 0).validate().is_err());
+        assert!(args("text-embedding-ada-002", 512).validate().is_err());
+        assert!(args("nomic-embed-text", 256).validate().is_ok());
+        let json = serde_json::to_value(args("text-embedding-3-small", 256)).unwrap();
+        assert_eq!(json["dimensions"], 256);
+    }
+
+    #[test]
+    fn chunking_respects_limits() {
+        let args: EmbeddingsArgs = vec!["a".to_string(); MAX_INPUTS_PER_REQUEST + 1].into();
+        let chunks = args.into_chunks();
+        assert_eq!(
+            chunks.iter().map(|c| c.input.len()).collect::<Vec<_>>(),
+            [MAX_INPUTS_PER_REQUEST, 1]
+        );
+
+        // Over half the limit, both counted and estimated
+        let long = "a ".repeat(MAX_TOKENS_PER_REQUEST * 5 / 6);
+        let args: EmbeddingsArgs = vec![long.clone(), long.clone(), "tail".into()].into();
+        let chunks = args.into_chunks();
+        assert_eq!(
+            chunks.iter().map(|c| c.input.len()).collect::<Vec<_>>(),
+            [1, 2]
+        );
+        assert_eq!(chunks[1].input[1], "tail");
+    }
+
+    #[cfg(feature = "tiktoken")]
+    #[test]
+    fn chunks_by_counted_tokens() {
+        // One token per word, where the estimate counts two
+        let half = "hello ".repeat(MAX_TOKENS_PER_REQUEST / 3);
+        assert!(crate::estimate_tokens(&half) * 2 > MAX_TOKENS_PER_REQUEST);
+        let args: EmbeddingsArgs = vec![half.clone(), half].into();
+        assert_eq!(args.into_chunks().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn embed_all_reassembles_in_order() -> crate::Result<()> {
+        let mock = mockito::mock("POST", "/embeddings")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"{
+                "data": [
+                    {"embedding": [1.0], "index": 1},
+                    {"embedding": [0.0], "index": 0}
+                ],
+                "model": "text-embedding-ada-002"
+            }"#,
+            )
+            .expect(2)
+            .create();
+        let mut input = vec!["a".to_string(); MAX_INPUTS_PER_REQUEST - 1];
+        input.push("b".into());
+        input.push("c".into());
+        let response = mocked_client().embed_all(input, 2).await?;
+        let indices: Vec<_> = response.data.iter().map(|e| e.index).collect();
+        assert_eq!(indices, (0..4).collect::<Vec<_>>());
+        assert_eq!(response.data[2].embedding, [0.0]);
+        assert_eq!(response.data[3].embedding, [1.0]);
+        mock.assert();
+        Ok(())
+    }
+}
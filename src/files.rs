@@ -0,0 +1,378 @@
+//! Files API: files uploaded for fine-tuning, assistants, batches or vision, and the files
+//! the API writes back.
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::{Deleted, ListParams, Page, Problems},
+    json,
+    multipart::Form,
+    Client, Error, Result,
+};
+
+/// What a file is for
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FilePurpose {
+    /// Training data of a fine-tuning job, as JSONL
+    #[serde(rename = "fine-tune")]
+    FineTune,
+    /// Results of a fine-tuning job, written by the API
+    #[serde(rename = "fine-tune-results")]
+    FineTuneResults,
+    /// Documents for assistants' tools
+    #[serde(rename = "assistants")]
+    Assistants,
+    /// Files written by assistants' tools
+    #[serde(rename = "assistants_output")]
+    AssistantsOutput,
+    /// Input of a batch, as JSONL
+    #[serde(rename = "batch")]
+    Batch,
+    /// Output or errors of a batch, written by the API
+    #[serde(rename = "batch_output")]
+    BatchOutput,
+    /// Images for assistants' vision
+    #[serde(rename = "vision")]
+    Vision,
+    /// A purpose this version of the library doesn't know about
+    #[serde(other, skip_serializing)]
+    Other,
+}
+
+impl FilePurpose {
+    /// Whether files can be uploaded with this purpose, rather than only written by the API
+    #[must_use]
+    pub fn is_uploadable(self) -> bool {
+        matches!(
+            self,
+            FilePurpose::FineTune
+                | FilePurpose::Assistants
+                | FilePurpose::Batch
+                | FilePurpose::Vision
+        )
+    }
+
+    /// The file extensions accepted for this purpose, or `None` when any is
+    #[must_use]
+    pub fn extensions(self) -> Option<&'static [&'static str]> {
+        match self {
+            FilePurpose::FineTune | FilePurpose::Batch => Some(&["jsonl"]),
+            FilePurpose::Vision => Some(&["png", "jpeg", "jpg", "gif", "webp"]),
+            _ => None,
+        }
+    }
+
+    /// The API's name of the purpose, e.g. `"fine-tune"`
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FilePurpose::FineTune => "fine-tune",
+            FilePurpose::FineTuneResults => "fine-tune-results",
+            FilePurpose::Assistants => "assistants",
+            FilePurpose::AssistantsOutput => "assistants_output",
+            FilePurpose::Batch => "batch",
+            FilePurpose::BatchOutput => "batch_output",
+            FilePurpose::Vision => "vision",
+            FilePurpose::Other => "other",
+        }
+    }
+}
+
+impl std::fmt::Display for FilePurpose {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The processing state of a file
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum FileStatus {
+    #[default]
+    Uploaded,
+    Processed,
+    Error,
+    /// A status this version of the library doesn't know about
+    #[serde(other)]
+    Other,
+}
+
+/// A file stored by the API
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct FileObject {
+    pub id: String,
+    /// Size of the file, in bytes
+    pub bytes: u64,
+    /// Unix timestamp, in seconds
+    pub created_at: u64,
+    pub filename: String,
+    pub purpose: FilePurpose,
+    /// Deprecated by the API, which may no longer send it
+    #[serde(default, deserialize_with = "crate::api::lenient")]
+    pub status: FileStatus,
+    /// Why the file failed validation, if it did
+    #[serde(default)]
+    pub status_details: Option<String>,
+}
+
+/// Arguments of a file upload. Checked when built: the purpose must be one files can be
+/// uploaded for, and the file name must have an extension the purpose accepts.
+///
+/// # Example
+/// ```
+/// # use openai_api::files::{FilePurpose, FileUploadArgs};
+/// let upload = FileUploadArgs::builder()
+///     .file(b"{}\n".to_vec())
+///     .file_name("requests.jsonl")
+///     .purpose(FilePurpose::Batch)
+///     .build();
+/// assert!(upload.is_ok());
+/// let wrong_extension = FileUploadArgs::builder()
+///     .file(b"{}\n".to_vec())
+///     .file_name("requests.json")
+///     .purpose(FilePurpose::Batch)
+///     .build();
+/// assert!(wrong_extension.is_err());
+/// ```
+#[derive(Debug, Builder, Clone, Serialize)]
+#[builder(pattern = "immutable", build_fn(validate = "Self::check"))]
+pub struct FileUploadArgs {
+    /// The contents of the file. Not part of the args when they are serialized.
+    #[builder(setter(into))]
+    #[serde(skip)]
+    file: Vec<u8>,
+    /// Name of the file, e.g. `"training.jsonl"`
+    #[builder(setter(into))]
+    file_name: String,
+    purpose: FilePurpose,
+}
+
+impl FileUploadArgs {
+    /// Build a `FileUploadArgs` from the defaults
+    #[must_use]
+    pub fn builder() -> FileUploadArgsBuilder {
+        FileUploadArgsBuilder::default()
+    }
+
+    fn form(&self) -> Form {
+        Form::new()
+            .text("purpose", self.purpose.as_str())
+            .file("file", &self.file_name, &self.file)
+    }
+}
+
+impl FileUploadArgsBuilder {
+    fn check(&self) -> std::result::Result<(), String> {
+        match (self.file_name.as_deref(), self.purpose) {
+            (Some(file_name), Some(purpose)) => upload_problems(file_name, purpose).into_message(),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// The problems of uploading `file_name` for `purpose`
+fn upload_problems(file_name: &str, purpose: FilePurpose) -> Problems {
+    let mut problems = Problems::default();
+    problems.check(purpose.is_uploadable(), || {
+        format!("files can't be uploaded for the {} purpose", purpose)
+    });
+    if let Some(extensions) = purpose.extensions() {
+        let extension = std::path::Path::new(file_name)
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        problems.check(extensions.contains(&extension.as_str()), || {
+            format!(
+                "files for the {} purpose must end in .{}, got {:?}",
+                purpose,
+                extensions.join(" or ."),
+                file_name
+            )
+        });
+    }
+    problems
+}
+
+impl TryFrom<FileUploadArgsBuilder> for FileUploadArgs {
+    type Error = FileUploadArgsBuilderError;
+
+    fn try_from(builder: FileUploadArgsBuilder) -> std::result::Result<Self, Self::Error> {
+        builder.build()
+    }
+}
+
+impl From<FileUploadArgsBuilderError> for Error {
+    fn from(err: FileUploadArgsBuilderError) -> Self {
+        Error::BadArguments(err.to_string())
+    }
+}
+
+impl Client {
+    /// Uploads a file, e.g. the input of a batch
+    ///
+    /// # Errors
+    ///  - `Error::BadArguments` if the arguments can't be built
+    ///  - `Error::DryRun` in dry-run mode
+    ///  - `Error::Api` if the api returns an error
+    pub async fn upload_file<A>(&self, args: A) -> Result<FileObject>
+    where
+        A: TryInto<FileUploadArgs>,
+        Error: From<<A as TryInto<FileUploadArgs>>::Error>,
+    {
+        let args: FileUploadArgs = args.try_into()?;
+        self.dry_run("files", &args, || {
+            upload_problems(&args.file_name, args.purpose).into_result()
+        })?;
+        let request = self.inner.client.post(self.url("files")?);
+        json::from_bytes(self.send_raw(args.form().attach(request)).await?)
+    }
+
+    /// Lists the files of the organization, optionally only those for `purpose`
+    ///
+    /// # Errors
+    /// - `Error::Api` if the api returns an error
+    pub async fn list_files(
+        &self,
+        purpose: Option<FilePurpose>,
+        page: &ListParams,
+    ) -> Result<Page<FileObject>> {
+        #[derive(Serialize)]
+        struct Query<'a> {
+            #[serde(skip_serializing_if = "Option::is_none")]
+            purpose: Option<FilePurpose>,
+            #[serde(flatten)]
+            page: &'a ListParams,
+        }
+
+        self.get_with_query("files", &Query { purpose, page }).await
+    }
+
+    /// Fetches the description of a file
+    ///
+    /// # Errors
+    /// - `Error::Api` if the api returns an error
+    pub async fn retrieve_file(&self, file_id: &str) -> Result<FileObject> {
+        self.get(&format!("files/{}", file_id)).await
+    }
+
+    /// Deletes a file
+    ///
+    /// # Errors
+    /// - `Error::Api` if the api returns an error
+    pub async fn delete_file(&self, file_id: &str) -> Result<Deleted> {
+        let url = self.url(&format!("files/{}", file_id))?;
+        self.send(self.inner.client.delete(url)).await
+    }
+
+    /// Downloads the contents of a file, e.g. the output of a batch, see
+    /// `batch::parse_output`
+    ///
+    /// # Errors
+    /// - `Error::Api` if the api returns an error
+    /// - `Error::ResponseTooLarge` if the file is larger than `Client::with_max_response_size`
+    pub async fn file_content(&self, file_id: &str) -> Result<bytes::Bytes> {
+        let url = self.url(&format!("files/{}/content", file_id))?;
+        self.send_raw(self.inner.client.get(url)).await
+    }
+}
+
+#[cfg(test)]
+mod unit {
+    use mockito::Matcher;
+
+    use super::{FileObject, FilePurpose, FileStatus, FileUploadArgs};
+    use crate::{api::ListParams, unit::mocked_client};
+
+    #[tokio::test]
+    async fn uploads_files() -> crate::Result<()> {
+        let mock = mockito::mock("POST", "/files")
+            .match_body(Matcher::AllOf(vec![
+                Matcher::Regex("name=\"purpose\"\r\n\r\nfine-tune\r\n".into()),
+                Matcher::Regex("filename=\"train.jsonl\"".into()),
+            ]))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"{"id": "file-abc", "object": "file", "bytes": 120000, "created_at": 1677610602,
+                "filename": "train.jsonl", "purpose": "fine-tune", "status": "processed"}"#,
+            )
+            .expect(1)
+            .create();
+        let args = FileUploadArgs::builder()
+            .file(b"{}\n".to_vec())
+            .file_name("train.jsonl")
+            .purpose(FilePurpose::FineTune);
+        let file = mocked_client().upload_file(args).await?;
+        assert_eq!(file.bytes, 120_000);
+        assert_eq!(file.purpose, FilePurpose::FineTune);
+        assert_eq!(file.status, FileStatus::Processed);
+        mock.assert();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn manages_files() -> crate::Result<()> {
+        let file = r#"{"id": "file-abc", "object": "file", "bytes": 3, "created_at": 1,
+            "filename": "out.jsonl", "purpose": "batch_output"}"#;
+        let _list = mockito::mock("GET", "/files")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("purpose".into(), "batch_output".into()),
+                Matcher::UrlEncoded("limit".into(), "10".into()),
+            ]))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(format!(
+                r#"{{"object": "list", "data": [{}], "has_more": false}}"#,
+                file
+            ))
+            .create();
+        let _retrieve = mockito::mock("GET", "/files/file-abc")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(file)
+            .create();
+        let _content = mockito::mock("GET", "/files/file-abc/content")
+            .with_status(200)
+            .with_header("content-type", "application/octet-stream")
+            .with_body("{}\n")
+            .create();
+        let _delete = mockito::mock("DELETE", "/files/file-abc")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"id": "file-abc", "object": "file", "deleted": true}"#)
+            .create();
+        let client = mocked_client();
+        let page = ListParams::builder().limit(10).build().unwrap();
+        let files = client
+            .list_files(Some(FilePurpose::BatchOutput), &page)
+            .await?;
+        assert_eq!(files.data[0].purpose, FilePurpose::BatchOutput);
+        assert_eq!(client.retrieve_file("file-abc").await?.bytes, 3);
+        assert_eq!(&client.file_content("file-abc").await?[..], b"{}\n");
+        assert!(client.delete_file("file-abc").await?.deleted);
+        Ok(())
+    }
+
+    #[test]
+    fn checks_purposes() {
+        let upload = |name: &str, purpose| {
+            FileUploadArgs::builder()
+                .file(vec![0])
+                .file_name(name)
+                .purpose(purpose)
+                .build()
+        };
+        assert!(upload("cat.PNG", FilePurpose::Vision).is_ok());
+        assert!(upload("cat.txt", FilePurpose::Vision).is_err());
+        assert!(upload("notes.md", FilePurpose::Assistants).is_ok());
+        assert!(upload("out.jsonl", FilePurpose::BatchOutput).is_err());
+
+        let file: FileObject = serde_json::from_str(
+            r#"{"id": "file-x", "bytes": 1, "created_at": 1, "filename": "x",
+            "purpose": "user_data"}"#,
+        )
+        .unwrap();
+        assert_eq!(file.purpose, FilePurpose::Other);
+        assert_eq!(file.status, FileStatus::Uploaded);
+    }
+}
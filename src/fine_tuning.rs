@@ -0,0 +1,363 @@
+//! Fine-tuning jobs, which train a model further on the examples of an uploaded file.
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{api::Problems, Client, Error, Result};
+
+/// A hyperparameter that is either set, or left for the API to pick from the dataset
+///
+/// # Example
+/// ```
+/// # use openai_api::fine_tuning::Auto;
+/// assert_eq!(serde_json::to_string(&Auto::<u32>::Auto).unwrap(), r#""auto""#);
+/// assert_eq!(serde_json::to_string(&Auto::Value(3)).unwrap(), "3");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Auto<T> {
+    #[default]
+    Auto,
+    Value(T),
+}
+
+impl<T> From<T> for Auto<T> {
+    fn from(value: T) -> Self {
+        Auto::Value(value)
+    }
+}
+
+impl<T: Serialize> Serialize for Auto<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        match self {
+            Auto::Auto => serializer.serialize_str("auto"),
+            Auto::Value(value) => value.serialize(serializer),
+        }
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Auto<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw<T> {
+            Value(T),
+            Text(String),
+        }
+
+        match Raw::deserialize(deserializer)? {
+            Raw::Value(value) => Ok(Auto::Value(value)),
+            Raw::Text(text) if text == "auto" => Ok(Auto::Auto),
+            Raw::Text(text) => Err(serde::de::Error::custom(format!(
+                "expected a value or \"auto\", got {:?}",
+                text
+            ))),
+        }
+    }
+}
+
+/// The hyperparameters of a fine-tuning job. Checked when built.
+///
+/// # Example
+/// ```
+/// # use openai_api::fine_tuning::{Auto, Hyperparameters};
+/// let hyperparameters = Hyperparameters::builder()
+///     .n_epochs(4)
+///     .learning_rate_multiplier(Auto::Auto)
+///     .build()
+///     .unwrap();
+/// assert!(Hyperparameters::builder().batch_size(0).build().is_err());
+/// ```
+#[derive(Serialize, Deserialize, Debug, Builder, Clone, Copy, PartialEq, Default)]
+#[builder(pattern = "immutable", build_fn(validate = "Self::check"))]
+pub struct Hyperparameters {
+    /// Passes over the training file, from 1 to 50
+    #[builder(setter(into, strip_option), default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub n_epochs: Option<Auto<u32>>,
+    /// Examples per batch, from 1 to 256
+    #[builder(setter(into, strip_option), default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub batch_size: Option<Auto<u32>>,
+    /// Scaling of the learning rate, from 0 excluded to 10
+    #[builder(setter(into, strip_option), default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub learning_rate_multiplier: Option<Auto<f64>>,
+}
+
+impl Hyperparameters {
+    /// Build a `Hyperparameters` from the defaults
+    #[must_use]
+    pub fn builder() -> HyperparametersBuilder {
+        HyperparametersBuilder::default()
+    }
+
+    /// The problems of the hyperparameters that are set
+    fn problems(&self, problems: &mut Problems) {
+        if let Some(Auto::Value(n_epochs)) = self.n_epochs {
+            problems.range("n_epochs", n_epochs as f64, 1.0, 50.0);
+        }
+        if let Some(Auto::Value(batch_size)) = self.batch_size {
+            problems.range("batch_size", batch_size as f64, 1.0, 256.0);
+        }
+        if let Some(Auto::Value(multiplier)) = self.learning_rate_multiplier {
+            problems.check(multiplier > 0.0 && multiplier <= 10.0, || {
+                format!(
+                    "learning_rate_multiplier must be above 0 and at most 10, got {}",
+                    multiplier
+                )
+            });
+        }
+    }
+}
+
+impl HyperparametersBuilder {
+    fn check(&self) -> std::result::Result<(), String> {
+        let mut problems = Problems::default();
+        Hyperparameters {
+            n_epochs: self.n_epochs.flatten(),
+            batch_size: self.batch_size.flatten(),
+            learning_rate_multiplier: self.learning_rate_multiplier.flatten(),
+        }
+        .problems(&mut problems);
+        problems.into_message()
+    }
+}
+
+/// A service a fine-tuning job reports its metrics to
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Integration {
+    /// Weights and Biases, which needs the organization's W&B API key in OpenAI's settings
+    Wandb { wandb: WandbIntegration },
+}
+
+impl From<WandbIntegration> for Integration {
+    fn from(wandb: WandbIntegration) -> Self {
+        Integration::Wandb { wandb }
+    }
+}
+
+/// Where a fine-tuning job reports to Weights and Biases
+///
+/// # Example
+/// ```
+/// # use openai_api::fine_tuning::{Integration, WandbIntegration};
+/// let integration: Integration = WandbIntegration::builder()
+///     .project("support-bot")
+///     .tags(vec!["v2".into()])
+///     .build()
+///     .unwrap()
+///     .into();
+/// let json = serde_json::to_value(&integration).unwrap();
+/// assert_eq!(json["type"], "wandb");
+/// assert_eq!(json["wandb"]["project"], "support-bot");
+/// ```
+#[derive(Serialize, Deserialize, Debug, Builder, Clone, PartialEq, Eq)]
+#[builder(pattern = "immutable")]
+pub struct WandbIntegration {
+    /// The W&B project the run is created in
+    #[builder(setter(into))]
+    pub project: String,
+    /// Name of the run, the job's id by default
+    #[builder(setter(into, strip_option), default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// The W&B team or user the run belongs to, the key's default entity by default
+    #[builder(setter(into, strip_option), default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub entity: Option<String>,
+    /// Tags of the run, on top of `openai/finetune` and the model's ones
+    #[builder(setter(into), default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+impl WandbIntegration {
+    /// Build a `WandbIntegration` from the defaults
+    #[must_use]
+    pub fn builder() -> WandbIntegrationBuilder {
+        WandbIntegrationBuilder::default()
+    }
+}
+
+/// Arguments of a fine-tuning job's creation
+#[derive(Serialize, Deserialize, Debug, Builder, Clone)]
+#[builder(pattern = "immutable")]
+pub struct FineTuningJobArgs {
+    /// The model to fine-tune, e.g. `"gpt-4o-mini-2024-07-18"`
+    #[builder(setter(into))]
+    model: String,
+    /// Id of a file uploaded with the `fine-tune` purpose
+    #[builder(setter(into))]
+    training_file: String,
+    #[builder(setter(into, strip_option), default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    validation_file: Option<String>,
+    /// Up to 64 characters added to the fine-tuned model's name
+    #[builder(setter(into, strip_option), default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    suffix: Option<String>,
+    #[builder(setter(strip_option), default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    seed: Option<u64>,
+    #[builder(setter(strip_option), default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    hyperparameters: Option<Hyperparameters>,
+    #[builder(setter(into), default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    integrations: Vec<Integration>,
+}
+
+impl FineTuningJobArgs {
+    /// Build a `FineTuningJobArgs` from the defaults
+    #[must_use]
+    pub fn builder() -> FineTuningJobArgsBuilder {
+        FineTuningJobArgsBuilder::default()
+    }
+
+    /// Checks the arguments locally, without calling the API
+    ///
+    /// # Errors
+    /// - `Error::BadArguments` listing every problem found
+    pub fn validate(&self) -> Result<()> {
+        let mut problems = Problems::default();
+        if let Some(hyperparameters) = &self.hyperparameters {
+            hyperparameters.problems(&mut problems);
+        }
+        if let Some(suffix) = &self.suffix {
+            let chars = suffix.chars().count();
+            problems.check(chars <= 64, || {
+                format!("suffix is at most 64 characters long, got {}", chars)
+            });
+        }
+        problems.into_result()
+    }
+}
+
+impl TryFrom<FineTuningJobArgsBuilder> for FineTuningJobArgs {
+    type Error = FineTuningJobArgsBuilderError;
+
+    fn try_from(builder: FineTuningJobArgsBuilder) -> std::result::Result<Self, Self::Error> {
+        builder.build()
+    }
+}
+
+impl From<FineTuningJobArgsBuilderError> for Error {
+    fn from(err: FineTuningJobArgsBuilderError) -> Self {
+        Error::BadArguments(err.to_string())
+    }
+}
+
+/// Where a fine-tuning job is in its lifecycle
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum FineTuningStatus {
+    ValidatingFiles,
+    Queued,
+    Running,
+    Succeeded,
+    Failed,
+    Cancelled,
+    /// A status this version of the library doesn't know about
+    #[serde(other)]
+    Other,
+}
+
+/// A fine-tuning job
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct FineTuningJob {
+    pub id: String,
+    pub model: String,
+    pub status: FineTuningStatus,
+    /// Unix timestamp, in seconds
+    #[serde(default, deserialize_with = "crate::api::lenient")]
+    pub created_at: u64,
+    /// The model trained, once the job succeeded
+    #[serde(default)]
+    pub fine_tuned_model: Option<String>,
+    #[serde(default)]
+    pub training_file: String,
+    /// The hyperparameters used, with those left to `Auto` resolved once the job runs
+    #[serde(default)]
+    pub hyperparameters: Hyperparameters,
+    #[serde(default, deserialize_with = "crate::api::lenient")]
+    pub integrations: Vec<Integration>,
+}
+
+impl Client {
+    /// Creates a fine-tuning job
+    ///
+    /// # Errors
+    ///  - `Error::BadArguments` if the arguments can't be built, or are invalid
+    ///  - `Error::DryRun` in dry-run mode, if the arguments are valid
+    ///  - `Error::Api` if the api returns an error
+    pub async fn create_fine_tuning_job<A>(&self, args: A) -> Result<FineTuningJob>
+    where
+        A: TryInto<FineTuningJobArgs>,
+        Error: From<<A as TryInto<FineTuningJobArgs>>::Error>,
+    {
+        let args: FineTuningJobArgs = args.try_into()?;
+        args.validate()?;
+        self.dry_run("fine_tuning/jobs", &args, || Ok(()))?;
+        self.post("fine_tuning/jobs", args).await
+    }
+}
+
+#[cfg(test)]
+mod unit {
+    use mockito::Matcher;
+    use serde_json::json;
+
+    use super::{Auto, FineTuningJobArgs, FineTuningStatus, Hyperparameters, WandbIntegration};
+    use crate::unit::mocked_client;
+
+    #[tokio::test]
+    async fn creates_jobs() -> crate::Result<()> {
+        let mock = mockito::mock("POST", "/fine_tuning/jobs")
+            .match_body(Matcher::Json(json!({
+                "model": "gpt-4o-mini-2024-07-18",
+                "training_file": "file-abc",
+                "hyperparameters": {"n_epochs": 3, "batch_size": "auto"},
+                "integrations": [{"type": "wandb", "wandb": {"project": "bot"}}],
+            })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"{"object": "fine_tuning.job", "id": "ftjob-1", "model":
+                "gpt-4o-mini-2024-07-18", "created_at": 1721764800, "fine_tuned_model": null,
+                "status": "queued", "training_file": "file-abc", "hyperparameters":
+                {"n_epochs": 3, "batch_size": "auto", "learning_rate_multiplier": "auto"},
+                "integrations": [{"type": "wandb", "wandb": {"project": "bot"}}]}"#,
+            )
+            .expect(1)
+            .create();
+        let args = FineTuningJobArgs::builder()
+            .model("gpt-4o-mini-2024-07-18")
+            .training_file("file-abc")
+            .hyperparameters(
+                Hyperparameters::builder()
+                    .n_epochs(3)
+                    .batch_size(Auto::Auto)
+                    .build()
+                    .unwrap(),
+            )
+            .integrations(vec![WandbIntegration::builder()
+                .project("bot")
+                .build()
+                .unwrap()
+                .into()]);
+        let job = mocked_client().create_fine_tuning_job(args).await?;
+        assert_eq!(job.status, FineTuningStatus::Queued);
+        assert_eq!(job.hyperparameters.n_epochs, Some(Auto::Value(3)));
+        assert_eq!(
+            job.hyperparameters.learning_rate_multiplier,
+            Some(Auto::Auto)
+        );
+        mock.assert();
+
+        assert!(serde_json::from_str::<Auto<u32>>(r#""many""#).is_err());
+        assert!(Hyperparameters::builder()
+            .learning_rate_multiplier(0.0)
+            .build()
+            .is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,167 @@
+//! The format of chat answers: free text, any JSON object, or JSON following a schema.
+use serde::{Deserialize, Serialize};
+
+use crate::api::Problems;
+
+/// The format the model answers in, see `ChatArgs::response_format`
+///
+/// # Example
+/// ```
+/// # use openai_api::api::ResponseFormat;
+/// let format = ResponseFormat::json_schema(
+///     "weather",
+///     serde_json::json!({
+///         "type": "object",
+///         "properties": {"celsius": {"type": "number"}},
+///         "required": ["celsius"],
+///         "additionalProperties": false
+///     }),
+/// );
+/// let json = serde_json::to_value(&format).unwrap();
+/// assert_eq!(json["type"], "json_schema");
+/// assert_eq!(json["json_schema"]["strict"], true);
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ResponseFormat {
+    /// Free text, the default
+    Text,
+    /// JSON mode: any valid JSON object. The messages must ask for JSON, or the API
+    /// rejects the request.
+    JsonObject,
+    /// Structured outputs: JSON following a schema
+    JsonSchema { json_schema: JsonSchema },
+}
+
+impl ResponseFormat {
+    /// Answers following `schema`, strictly
+    #[must_use]
+    pub fn json_schema(name: impl Into<String>, schema: serde_json::Value) -> Self {
+        ResponseFormat::JsonSchema {
+            json_schema: JsonSchema::new(name, schema),
+        }
+    }
+
+    /// The problems of the format
+    pub(crate) fn problems(&self, problems: &mut Problems) {
+        if let ResponseFormat::JsonSchema { json_schema } = self {
+            let name = &json_schema.name;
+            problems.check(
+                !name.is_empty()
+                    && name.len() <= 64
+                    && name
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-'),
+                || {
+                    format!(
+                        "json_schema name must be 1 to 64 letters, digits, underscores or dashes, got {:?}",
+                        name
+                    )
+                },
+            );
+        }
+    }
+}
+
+/// A JSON schema the answers of the model follow
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct JsonSchema {
+    /// Letters, digits, underscores and dashes, at most 64 characters
+    pub name: String,
+    /// What the answer is, for the model to know how to fill it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub schema: serde_json::Value,
+    /// Whether the answer follows the schema exactly. Strict schemas must list every
+    /// property as required, and disallow additional properties.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strict: Option<bool>,
+}
+
+impl JsonSchema {
+    /// A strict schema
+    #[must_use]
+    pub fn new(name: impl Into<String>, schema: serde_json::Value) -> Self {
+        Self {
+            name: name.into(),
+            description: None,
+            schema,
+            strict: Some(true),
+        }
+    }
+
+    #[must_use]
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+}
+
+impl From<JsonSchema> for ResponseFormat {
+    fn from(json_schema: JsonSchema) -> Self {
+        ResponseFormat::JsonSchema { json_schema }
+    }
+}
+
+#[cfg(test)]
+mod unit {
+    use serde::Deserialize;
+
+    use super::ResponseFormat;
+    use crate::api::{ChatAnswer, ChatArgs, ChatFormat, ChatRole};
+
+    fn ask(question: &str) -> Vec<ChatFormat> {
+        vec![ChatFormat::new(ChatRole::User, question.into())]
+    }
+
+    #[test]
+    fn json_mode() -> crate::Result<()> {
+        let args = ChatArgs::builder()
+            .model("gpt-4o")
+            .messages(ask("List three colors as a JSON object."))
+            .build()?
+            .json_mode();
+        args.validate()?;
+        let body = serde_json::to_value(&args)?;
+        assert_eq!(
+            body["response_format"],
+            serde_json::json!({"type": "json_object"})
+        );
+
+        let unasked = ChatArgs::builder()
+            .model("gpt-4o")
+            .messages(ask("List three colors."))
+            .build()?
+            .json_mode();
+        assert!(unasked.validate().is_err());
+        let bad_name = ChatArgs::builder()
+            .model("gpt-4o")
+            .messages(ask("List three colors."))
+            .response_format(ResponseFormat::json_schema(
+                "the colors",
+                serde_json::json!({}),
+            ))
+            .build()?;
+        assert!(bad_name.validate().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn parses_json_answers() -> crate::Result<()> {
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Colors {
+            colors: Vec<String>,
+        }
+
+        let answer: ChatAnswer = serde_json::from_str(
+            r#"{"choices": [{"index": 0, "finish_reason": "stop", "message": {"role":
+            "assistant", "content": "{\"colors\": [\"red\", \"green\"]}"}}]}"#,
+        )?;
+        let colors: Colors = answer.parse_json()?;
+        assert_eq!(colors.colors, ["red", "green"]);
+
+        let empty: ChatAnswer = serde_json::from_str(r#"{"choices": []}"#)?;
+        assert!(empty.parse_json::<Colors>().is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,520 @@
+//! Chat function calling: functions the model may call, and the calls it answers with.
+//!
+//! The model doesn't run anything: it answers with the name of a function and JSON
+//! arguments, the caller runs it and sends the result back in a `ChatRole::Tool`
+//! message, or a `ChatRole::Function` one with the legacy `functions` parameter.
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{api::Problems, Result};
+
+/// A function the model may call, see `ChatArgs::functions`
+///
+/// # Example
+/// ```
+/// # use openai_api::api::FunctionSpec;
+/// let weather = FunctionSpec::new(
+///     "get_weather",
+///     serde_json::json!({
+///         "type": "object",
+///         "properties": {"city": {"type": "string"}},
+///         "required": ["city"]
+///     }),
+/// )
+/// .with_description("Current weather of a city");
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FunctionSpec {
+    /// Letters, digits, underscores and dashes, at most 64 characters
+    pub name: String,
+    /// What the function does, for the model to decide when to call it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// JSON schema of the arguments
+    pub parameters: serde_json::Value,
+}
+
+impl FunctionSpec {
+    #[must_use]
+    pub fn new(name: impl Into<String>, parameters: serde_json::Value) -> Self {
+        Self {
+            name: name.into(),
+            description: None,
+            parameters,
+        }
+    }
+
+    #[must_use]
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+}
+
+/// Whether the model calls a function, see `ChatArgs::function_call`
+///
+/// # Example
+/// ```
+/// # use openai_api::api::FunctionCallMode;
+/// let mode = FunctionCallMode::Named("get_weather".into());
+/// assert_eq!(serde_json::to_string(&mode).unwrap(), r#"{"name":"get_weather"}"#);
+/// assert_eq!(serde_json::to_string(&FunctionCallMode::Auto).unwrap(), r#""auto""#);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FunctionCallMode {
+    /// The model answers with a message
+    None,
+    /// The model picks between a message and a function call
+    Auto,
+    /// The model calls this function
+    Named(String),
+}
+
+#[derive(Serialize, Deserialize)]
+struct Named {
+    name: String,
+}
+
+impl Serialize for FunctionCallMode {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        match self {
+            FunctionCallMode::None => serializer.serialize_str("none"),
+            FunctionCallMode::Auto => serializer.serialize_str("auto"),
+            FunctionCallMode::Named(name) => Named { name: name.clone() }.serialize(serializer),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for FunctionCallMode {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw {
+            Named(Named),
+            Text(String),
+        }
+
+        match Raw::deserialize(deserializer)? {
+            Raw::Named(Named { name }) => Ok(FunctionCallMode::Named(name)),
+            Raw::Text(text) if text == "none" => Ok(FunctionCallMode::None),
+            Raw::Text(text) if text == "auto" => Ok(FunctionCallMode::Auto),
+            Raw::Text(text) => Err(serde::de::Error::custom(format!(
+                "expected \"none\", \"auto\" or a function name, got {:?}",
+                text
+            ))),
+        }
+    }
+}
+
+/// A call of a function by the model, in an assistant message
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct FunctionCall {
+    #[serde(default)]
+    pub name: String,
+    /// The arguments as a JSON string. The model may produce invalid JSON, or arguments
+    /// that don't match the schema of the function.
+    #[serde(default)]
+    pub arguments: String,
+}
+
+impl FunctionCall {
+    /// Parses the arguments
+    ///
+    /// # Errors
+    /// - `Error::Json` if the arguments don't deserialize as `T`
+    pub fn parse_arguments<T: DeserializeOwned>(&self) -> Result<T> {
+        Ok(serde_json::from_str(&self.arguments)?)
+    }
+}
+
+/// A tool the model may call, see `ChatArgs::tools`
+///
+/// # Example
+/// ```
+/// # use openai_api::api::{ChatTool, FunctionSpec};
+/// let tool = ChatTool::from(FunctionSpec::new("get_time", serde_json::json!({"type": "object"})));
+/// assert_eq!(
+///     serde_json::to_value(&tool).unwrap()["type"],
+///     serde_json::json!("function")
+/// );
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ChatTool {
+    Function { function: FunctionSpec },
+}
+
+impl From<FunctionSpec> for ChatTool {
+    fn from(function: FunctionSpec) -> Self {
+        ChatTool::Function { function }
+    }
+}
+
+/// Whether the model calls a tool, see `ChatArgs::tool_choice`
+///
+/// # Example
+/// ```
+/// # use openai_api::api::ToolChoice;
+/// assert_eq!(serde_json::to_string(&ToolChoice::Required).unwrap(), r#""required""#);
+/// assert_eq!(
+///     serde_json::to_string(&ToolChoice::Function("get_time".into())).unwrap(),
+///     r#"{"type":"function","function":{"name":"get_time"}}"#
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ToolChoice {
+    /// The model answers with a message
+    None,
+    /// The model picks between a message and tool calls
+    Auto,
+    /// The model calls one or more tools
+    Required,
+    /// The model calls this function
+    Function(String),
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum NamedTool {
+    Function { function: Named },
+}
+
+impl Serialize for ToolChoice {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        match self {
+            ToolChoice::None => serializer.serialize_str("none"),
+            ToolChoice::Auto => serializer.serialize_str("auto"),
+            ToolChoice::Required => serializer.serialize_str("required"),
+            ToolChoice::Function(name) => NamedTool::Function {
+                function: Named { name: name.clone() },
+            }
+            .serialize(serializer),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ToolChoice {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw {
+            Named(NamedTool),
+            Text(String),
+        }
+
+        match Raw::deserialize(deserializer)? {
+            Raw::Named(NamedTool::Function { function }) => Ok(ToolChoice::Function(function.name)),
+            Raw::Text(text) => match text.as_str() {
+                "none" => Ok(ToolChoice::None),
+                "auto" => Ok(ToolChoice::Auto),
+                "required" => Ok(ToolChoice::Required),
+                _ => Err(serde::de::Error::custom(format!(
+                    "expected \"none\", \"auto\", \"required\" or a function, got {:?}",
+                    text
+                ))),
+            },
+        }
+    }
+}
+
+/// The kind of a `ToolCall`
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolKind {
+    #[default]
+    Function,
+    #[serde(other)]
+    Other,
+}
+
+/// A call of a tool by the model, in an assistant message. Its result goes back in a
+/// message of `ChatFormat::tool_result` with the same `id`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct ToolCall {
+    pub id: String,
+    #[serde(rename = "type", default)]
+    pub kind: ToolKind,
+    #[serde(default)]
+    pub function: FunctionCall,
+}
+
+/// A piece of a `FunctionCall` in a streamed chat answer: the name comes in the first
+/// chunk, the arguments in fragments to concatenate
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct FunctionCallDelta {
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub arguments: Option<String>,
+}
+
+/// A piece of a `ToolCall` in a streamed chat answer. The first piece of a call has its
+/// `id` and function name, the next ones fragments of its arguments; pieces of the same
+/// call share its `index`.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct ToolCallDelta {
+    #[serde(default)]
+    pub index: u64,
+    #[serde(default)]
+    pub id: Option<String>,
+    #[serde(rename = "type", default)]
+    pub kind: Option<ToolKind>,
+    #[serde(default)]
+    pub function: Option<FunctionCallDelta>,
+}
+
+/// Checks the functions offered to the model, and the one it's forced to call, if any
+pub(crate) fn check(
+    problems: &mut Problems,
+    functions: Option<&[FunctionSpec]>,
+    mode: Option<&FunctionCallMode>,
+) {
+    let functions = functions.unwrap_or_default();
+    check_names(problems, functions.iter());
+    if let Some(FunctionCallMode::Named(name)) = mode {
+        problems.check(functions.iter().any(|f| &f.name == name), || {
+            format!("function_call names {:?}, which isn't in functions", name)
+        });
+    }
+}
+
+/// Checks the tools offered to the model, and the one it's forced to call, if any
+pub(crate) fn check_tools(
+    problems: &mut Problems,
+    tools: Option<&[ChatTool]>,
+    choice: Option<&ToolChoice>,
+) {
+    let functions = || {
+        tools
+            .unwrap_or_default()
+            .iter()
+            .map(|ChatTool::Function { function }| function)
+    };
+    check_names(problems, functions());
+    match choice {
+        Some(ToolChoice::Function(name)) => {
+            problems.check(functions().any(|f| &f.name == name), || {
+                format!("tool_choice names {:?}, which isn't in tools", name)
+            });
+        }
+        Some(ToolChoice::Required) => {
+            problems.check(functions().next().is_some(), || {
+                "tool_choice requires a tool, but there are no tools".into()
+            });
+        }
+        _ => {}
+    }
+}
+
+fn check_names<'a>(problems: &mut Problems, functions: impl Iterator<Item = &'a FunctionSpec>) {
+    for function in functions {
+        let valid = !function.name.is_empty()
+            && function.name.len() <= 64
+            && function
+                .name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        problems.check(valid, || {
+            format!(
+                "function name {:?} must be 1 to 64 letters, digits, underscores or dashes",
+                function.name
+            )
+        });
+    }
+}
+
+#[cfg(test)]
+mod unit {
+    use serde::Deserialize;
+
+    use super::{
+        check, check_tools, ChatTool, FunctionCall, FunctionCallMode, FunctionSpec, ToolCallDelta,
+        ToolChoice, ToolKind,
+    };
+    use crate::{
+        api::{ChatArgs, ChatFormat, ChatRole, Problems},
+        unit::mocked_client,
+    };
+
+    #[test]
+    fn parses_function_calls() -> crate::Result<()> {
+        let message: ChatFormat = serde_json::from_str(
+            r#"{"role": "assistant", "content": null,
+                "function_call": {"name": "get_weather", "arguments": "{\"city\": \"Paris\"}"}}"#,
+        )?;
+        assert_eq!(message.content, "");
+
+        #[derive(Deserialize)]
+        struct Weather {
+            city: String,
+        }
+        let call = message.function_call.unwrap();
+        assert_eq!(call.name, "get_weather");
+        assert_eq!(call.parse_arguments::<Weather>()?.city, "Paris");
+        assert!(FunctionCall::default()
+            .parse_arguments::<Weather>()
+            .is_err());
+
+        let result = ChatFormat::function_result("get_weather", "sunny");
+        assert_eq!(
+            serde_json::to_value(&result)?,
+            serde_json::json!({"role": "function", "name": "get_weather", "content": "sunny"})
+        );
+        assert_eq!(result.role, ChatRole::Function);
+        Ok(())
+    }
+
+    #[test]
+    fn round_trips_tool_calls() -> crate::Result<()> {
+        let message: ChatFormat = serde_json::from_str(
+            r#"{"role": "assistant", "content": null, "tool_calls": [
+                {"id": "call_1", "type": "function",
+                 "function": {"name": "get_time", "arguments": "{}"}},
+                {"id": "call_2", "type": "code_interpreter"}
+            ]}"#,
+        )?;
+        let calls = message.tool_calls.clone().unwrap();
+        assert_eq!(calls[0].function.name, "get_time");
+        assert_eq!(calls[1].kind, ToolKind::Other);
+
+        let reply = ChatFormat::tool_result("call_1", "12:00");
+        assert_eq!(reply.role, ChatRole::Tool);
+        assert_eq!(
+            serde_json::to_value(&reply)?,
+            serde_json::json!({"role": "tool", "tool_call_id": "call_1", "content": "12:00"})
+        );
+        let echoed = serde_json::to_value(&message)?;
+        assert_eq!(echoed["tool_calls"][0]["id"], "call_1");
+        assert!(echoed.get("tool_call_id").is_none());
+
+        for choice in [
+            ToolChoice::None,
+            ToolChoice::Auto,
+            ToolChoice::Required,
+            ToolChoice::Function("get_time".into()),
+        ] {
+            let json = serde_json::to_string(&choice)?;
+            assert_eq!(serde_json::from_str::<ToolChoice>(&json)?, choice);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn round_trips_modes() -> crate::Result<()> {
+        for mode in [
+            FunctionCallMode::None,
+            FunctionCallMode::Auto,
+            FunctionCallMode::Named("f".into()),
+        ] {
+            let json = serde_json::to_string(&mode)?;
+            assert_eq!(serde_json::from_str::<FunctionCallMode>(&json)?, mode);
+        }
+        assert!(serde_json::from_str::<FunctionCallMode>(r#""always""#).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn checks_functions() {
+        let functions = [
+            FunctionSpec::new("get_weather", serde_json::json!({"type": "object"})),
+            FunctionSpec::new("get weather", serde_json::json!({"type": "object"})),
+        ];
+        let mut problems = Problems::default();
+        check(
+            &mut problems,
+            Some(&functions),
+            Some(&FunctionCallMode::Named("get_time".into())),
+        );
+        let message = problems.into_message().unwrap_err();
+        assert!(message.contains("\"get weather\""));
+        assert!(message.contains("\"get_time\""));
+
+        let mut problems = Problems::default();
+        check(
+            &mut problems,
+            Some(&functions[..1]),
+            Some(&FunctionCallMode::Auto),
+        );
+        assert!(problems.into_message().is_ok());
+
+        let tools: Vec<ChatTool> = functions.iter().cloned().map(ChatTool::from).collect();
+        let mut problems = Problems::default();
+        check_tools(
+            &mut problems,
+            Some(&tools),
+            Some(&ToolChoice::Function("get_time".into())),
+        );
+        let message = problems.into_message().unwrap_err();
+        assert!(message.contains("\"get weather\""));
+        assert!(message.contains("tool_choice"));
+
+        let mut problems = Problems::default();
+        check_tools(&mut problems, None, Some(&ToolChoice::Required));
+        assert!(problems.into_message().is_err());
+    }
+
+    #[tokio::test]
+    async fn streams_tool_calls() -> crate::Result<()> {
+        use futures::TryStreamExt;
+
+        let chunk = |delta: serde_json::Value| {
+            format!(
+                "data: {}\n\n",
+                serde_json::json!({"id": "chatcmpl-1", "choices": [{"index": 0, "delta": delta}]})
+            )
+        };
+        let body = [
+            chunk(
+                serde_json::json!({"role": "assistant", "content": null, "tool_calls": [
+                    {"index": 0, "id": "call_1", "type": "function",
+                     "function": {"name": "get_weather", "arguments": ""}}
+                ]}),
+            ),
+            chunk(serde_json::json!({"tool_calls": [
+                {"index": 0, "function": {"arguments": "{\"city\": "}}
+            ]})),
+            chunk(serde_json::json!({"tool_calls": [
+                {"index": 0, "function": {"arguments": "\"Paris\"}"}}
+            ]})),
+            "data: [DONE]\n\n".to_string(),
+        ]
+        .concat();
+        let mock = mockito::mock("POST", "/chat/completions")
+            .match_body(mockito::Matcher::PartialJson(serde_json::json!({
+                "stream": true,
+                "messages": [{"content": "Weather in Paris?"}],
+            })))
+            .with_status(200)
+            .with_header("content-type", "text/event-stream")
+            .with_body(body)
+            .expect(1)
+            .create();
+        let args = ChatArgs::builder()
+            .messages(vec![ChatFormat::new(
+                ChatRole::User,
+                "Weather in Paris?".into(),
+            )])
+            .tools(vec![FunctionSpec::new(
+                "get_weather",
+                serde_json::json!({"type": "object"}),
+            )
+            .into()]);
+        let deltas: Vec<ToolCallDelta> = mocked_client()
+            .chat_stream(args)
+            .await?
+            .map_ok(|mut delta| delta.choices.remove(0).delta.tool_calls.unwrap_or_default())
+            .try_concat()
+            .await?;
+        mock.assert();
+        assert_eq!(deltas[0].id.as_deref(), Some("call_1"));
+        assert_eq!(deltas[0].kind, Some(ToolKind::Function));
+        let function = |delta: &ToolCallDelta| delta.function.clone().unwrap_or_default();
+        assert_eq!(function(&deltas[0]).name.as_deref(), Some("get_weather"));
+        let arguments: String = deltas
+            .iter()
+            .filter(|delta| delta.index == 0)
+            .filter_map(|delta| function(delta).arguments)
+            .collect();
+        assert_eq!(arguments, r#"{"city": "Paris"}"#);
+        Ok(())
+    }
+}
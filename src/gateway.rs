@@ -0,0 +1,157 @@
+//! LLM observability gateways: Helicone and Portkey.
+//!
+//! These proxies log and analyze the requests going through them. The client is pointed
+//! at a gateway once, e.g. with [`Client::helicone`], and requests are tagged with users,
+//! sessions or custom properties through [`RequestOptions`].
+//!
+//! # Example
+//! ```
+//! # use openai_api::{options::RequestOptions, Client};
+//! # fn main() -> Result<(), openai_api::Error> {
+//! let client = Client::helicone("sk-...", "sk-helicone-...")?;
+//! let options = RequestOptions::new()
+//!     .helicone_user("user-42")?
+//!     .helicone_session("conversation-7")?
+//!     .helicone_property("feature", "support-bot")?;
+//! let tagged = client.with_options(&options);
+//! # Ok(())
+//! # }
+//! ```
+use std::collections::BTreeMap;
+
+use crate::{options::RequestOptions, Client, Result};
+
+/// Base URL of Helicone's OpenAI proxy
+pub const HELICONE_BASE_URL: &str = "https://oai.helicone.ai/v1/";
+
+/// Base URL of Portkey's gateway
+pub const PORTKEY_BASE_URL: &str = "https://api.portkey.ai/v1/";
+
+impl Client {
+    /// Creates a client of the OpenAI API going through Helicone
+    ///
+    /// # Errors
+    /// - `Error::InvalidConfig` if the Helicone key isn't a valid header value
+    /// - `Error::AsyncProtocol` if the HTTP client can't be created
+    pub fn helicone(openai_key: &str, helicone_key: &str) -> Result<Self> {
+        Self::new(openai_key)?
+            .with_base_url(HELICONE_BASE_URL)?
+            .with_header("Helicone-Auth", &format!("Bearer {}", helicone_key))
+    }
+
+    /// Creates a client going through Portkey, to the provider configured by
+    /// `virtual_key` in Portkey
+    ///
+    /// # Errors
+    /// - `Error::InvalidConfig` if a key isn't a valid header value
+    /// - `Error::AsyncProtocol` if the HTTP client can't be created
+    pub fn portkey(portkey_key: &str, virtual_key: &str) -> Result<Self> {
+        Self::with_auth_header("x-portkey-api-key", portkey_key)?
+            .with_base_url(PORTKEY_BASE_URL)?
+            .with_header("x-portkey-virtual-key", virtual_key)
+    }
+}
+
+impl RequestOptions {
+    /// Tags requests with a Helicone custom property
+    ///
+    /// # Errors
+    /// - `Error::InvalidConfig` if the name or value isn't valid in a header
+    pub fn helicone_property(self, name: &str, value: &str) -> Result<Self> {
+        self.header(&format!("Helicone-Property-{}", name), value)
+    }
+
+    /// Attributes requests to a user in Helicone
+    ///
+    /// # Errors
+    /// - `Error::InvalidConfig` if the id isn't a valid header value
+    pub fn helicone_user(self, user_id: &str) -> Result<Self> {
+        self.header("Helicone-User-Id", user_id)
+    }
+
+    /// Groups requests into a Helicone session
+    ///
+    /// # Errors
+    /// - `Error::InvalidConfig` if the id isn't a valid header value
+    pub fn helicone_session(self, session_id: &str) -> Result<Self> {
+        self.header("Helicone-Session-Id", session_id)
+    }
+
+    /// Groups requests under a Portkey trace
+    ///
+    /// # Errors
+    /// - `Error::InvalidConfig` if the id isn't a valid header value
+    pub fn portkey_trace(self, trace_id: &str) -> Result<Self> {
+        self.header("x-portkey-trace-id", trace_id)
+    }
+
+    /// Tags requests with Portkey metadata, replacing any previous metadata. The `_user`
+    /// key attributes requests to a user.
+    ///
+    /// # Errors
+    /// - `Error::InvalidConfig` if the metadata isn't valid in a header
+    pub fn portkey_metadata(self, metadata: &BTreeMap<String, String>) -> Result<Self> {
+        self.header("x-portkey-metadata", &serde_json::to_string(metadata)?)
+    }
+}
+
+#[cfg(test)]
+mod unit {
+    use std::collections::BTreeMap;
+
+    use crate::{
+        api::{ChatArgs, ChatRole},
+        options::RequestOptions,
+        unit::mocked_client,
+        Client,
+    };
+
+    #[tokio::test]
+    async fn tags_single_requests() -> crate::Result<()> {
+        let tagged = mockito::mock("POST", "/chat/completions")
+            .match_header("helicone-user-id", "user-42")
+            .match_header("helicone-property-feature", "support-bot")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"{
+                "id": "chatcmpl-tagged",
+                "created": 1677652288,
+                "choices": [{
+                    "index": 0,
+                    "message": {"role": "assistant", "content": "Hi!"},
+                    "finish_reason": "stop"
+                }]
+            }"#,
+            )
+            .expect(1)
+            .create();
+        let client = mocked_client();
+        let options = RequestOptions::new()
+            .helicone_user("user-42")?
+            .helicone_property("feature", "support-bot")?;
+        let args = ChatArgs::from(vec![(ChatRole::User, "Hello!".to_string())]);
+        assert_eq!(
+            client.with_options(&options).chat(args).await?.id,
+            "chatcmpl-tagged"
+        );
+        tagged.assert();
+        assert!(client.inner.headers.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn gateway_clients() -> crate::Result<()> {
+        let client = Client::portkey("pk-key", "openai-vk")?;
+        assert_eq!(client.inner.base_url.as_str(), super::PORTKEY_BASE_URL);
+        assert_eq!(client.inner.headers["x-portkey-virtual-key"], "openai-vk");
+
+        let metadata: BTreeMap<_, _> = [("_user".to_string(), "user-42".to_string())].into();
+        let options = RequestOptions::new().portkey_metadata(&metadata)?;
+        assert_eq!(
+            options.headers["x-portkey-metadata"],
+            r#"{"_user":"user-42"}"#
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,77 @@
+//! Google Gemini, through its OpenAI-compatible endpoint.
+//!
+//! Gemini answers in the OpenAI format, with a few differences the client tolerates:
+//! finish reasons outside OpenAI's set (e.g. `"SAFETY"`) are kept as they are, and
+//! fields it leaves out of responses get their default value. It also rejects some
+//! request fields OpenAI accepts, which [`Client::gemini`] leaves out of requests.
+use crate::{Client, Result};
+
+/// Base URL of Gemini's OpenAI-compatible API
+pub const BASE_URL: &str = "https://generativelanguage.googleapis.com/v1beta/openai/";
+
+/// Request fields the endpoint rejects
+pub const UNSUPPORTED_FIELDS: &[&str] = &["logit_bias", "presence_penalty", "frequency_penalty"];
+
+impl Client {
+    /// Creates a client of Gemini's OpenAI-compatible endpoint, authenticated with a Gemini
+    /// API key, and leaving out the request fields it rejects
+    ///
+    /// # Errors
+    /// - `Error::AsyncProtocol` if the HTTP client can't be created
+    pub fn gemini(api_key: &str) -> Result<Self> {
+        Ok(Self::new(api_key)?
+            .with_base_url(BASE_URL)?
+            .with_omitted_fields(UNSUPPORTED_FIELDS))
+    }
+}
+
+#[cfg(test)]
+mod unit {
+    use std::collections::HashMap;
+
+    use crate::{
+        api::{ChatAnswer, ChatArgs, ChatFormat, ChatRole},
+        Client, Error,
+    };
+
+    #[tokio::test]
+    async fn leaves_out_rejected_fields() -> crate::Result<()> {
+        let client = Client::gemini("gemini-key")?.with_dry_run(true);
+        assert_eq!(client.inner.base_url.as_str(), super::BASE_URL);
+        let args = ChatArgs::builder()
+            .messages(vec![ChatFormat::new(ChatRole::User, "Hello!".into())])
+            .presence_penalty(0.5)
+            .frequency_penalty(0.5)
+            .logit_bias(HashMap::from([("50256".to_string(), -100.0)]));
+        match client.chat(args).await {
+            Err(Error::DryRun { body, .. }) => {
+                assert!(body.contains(r#""messages""#), "{}", body);
+                assert!(
+                    !body.contains("logit_bias") && !body.contains("penalty"),
+                    "{}",
+                    body
+                );
+            }
+            other => panic!("expected a dry run, got {:?}", other),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn parses_gemini_answers() -> crate::Result<()> {
+        let answer: ChatAnswer = serde_json::from_str(
+            r#"{
+            "choices": [{
+                "message": {"role": "assistant", "content": ""},
+                "finish_reason": "SAFETY"
+            }],
+            "created": 1736000000,
+            "model": "gemini-1.5-flash",
+            "object": "chat.completion"
+        }"#,
+        )?;
+        assert_eq!(answer.choices[0].finish_reason, "SAFETY");
+        assert_eq!(answer.choices[0].index, 0);
+        Ok(())
+    }
+}
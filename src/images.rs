@@ -0,0 +1,704 @@
+//! Image generation with DALL·E and GPT Image.
+//!
+//! The models differ in what they accept: DALL·E 2 makes up to 10 square images at a time,
+//! DALL·E 3 one image at a time, in more sizes, qualities and styles. GPT Image makes up
+//! to 10 at a time, always base64-encoded, with a choice of background, file format and
+//! moderation. `ImageArgs` checks the combination when it is built, so that unsupported
+//! requests fail locally.
+use std::path::Path;
+
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+
+use crate::{api::Problems, Client, Error, Result};
+
+/// An image generation model
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ImageModel {
+    #[default]
+    #[serde(rename = "dall-e-2")]
+    DallE2,
+    #[serde(rename = "dall-e-3")]
+    DallE3,
+    #[serde(rename = "gpt-image-1")]
+    GptImage1,
+}
+
+impl ImageModel {
+    /// Longest prompt the model accepts, in characters
+    fn max_prompt_chars(self) -> usize {
+        match self {
+            ImageModel::DallE2 => 1000,
+            ImageModel::DallE3 => 4000,
+            ImageModel::GptImage1 => 32000,
+        }
+    }
+
+    /// Most images the model makes at a time
+    fn max_n(self) -> u8 {
+        match self {
+            ImageModel::DallE2 | ImageModel::GptImage1 => 10,
+            ImageModel::DallE3 => 1,
+        }
+    }
+
+    fn supports_size(self, size: ImageSize) -> bool {
+        use ImageSize::*;
+        match self {
+            ImageModel::DallE2 => matches!(size, Square256 | Square512 | Square1024),
+            ImageModel::DallE3 => matches!(size, Square1024 | Landscape1792 | Portrait1792),
+            ImageModel::GptImage1 => {
+                matches!(size, Square1024 | Landscape1536 | Portrait1536 | Auto)
+            }
+        }
+    }
+
+    fn supports_quality(self, quality: ImageQuality) -> bool {
+        use ImageQuality::*;
+        match self {
+            ImageModel::DallE2 => quality == Standard,
+            ImageModel::DallE3 => matches!(quality, Standard | Hd),
+            ImageModel::GptImage1 => matches!(quality, Low | Medium | High | Auto),
+        }
+    }
+}
+
+/// Size of the generated images, in pixels
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageSize {
+    /// 256x256, DALL·E 2 only
+    #[serde(rename = "256x256")]
+    Square256,
+    /// 512x512, DALL·E 2 only
+    #[serde(rename = "512x512")]
+    Square512,
+    /// 1024x1024, the default
+    #[serde(rename = "1024x1024")]
+    Square1024,
+    /// 1792x1024, DALL·E 3 only
+    #[serde(rename = "1792x1024")]
+    Landscape1792,
+    /// 1024x1792, DALL·E 3 only
+    #[serde(rename = "1024x1792")]
+    Portrait1792,
+    /// 1536x1024, GPT Image only
+    #[serde(rename = "1536x1024")]
+    Landscape1536,
+    /// 1024x1536, GPT Image only
+    #[serde(rename = "1024x1536")]
+    Portrait1536,
+    /// Picked by the model from the prompt, GPT Image only
+    #[serde(rename = "auto")]
+    Auto,
+}
+
+/// Quality of the generated images: standard or hd with DALL·E 3, low to high with GPT
+/// Image
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageQuality {
+    Standard,
+    /// Finer details and greater consistency, at a higher price
+    Hd,
+    Low,
+    Medium,
+    High,
+    /// Picked by the model from the prompt
+    Auto,
+}
+
+impl ImageQuality {
+    fn as_str(self) -> &'static str {
+        match self {
+            ImageQuality::Standard => "standard",
+            ImageQuality::Hd => "hd",
+            ImageQuality::Low => "low",
+            ImageQuality::Medium => "medium",
+            ImageQuality::High => "high",
+            ImageQuality::Auto => "auto",
+        }
+    }
+}
+
+/// Background of the generated images, GPT Image only
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageBackground {
+    /// Needs the png or webp `ImageOutputFormat`
+    Transparent,
+    Opaque,
+    Auto,
+}
+
+/// File format of the generated images, GPT Image only: DALL·E makes PNGs
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageOutputFormat {
+    Png,
+    Jpeg,
+    Webp,
+}
+
+/// How strictly GPT Image filters what it generates
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageModeration {
+    /// Less restrictive filtering
+    Low,
+    Auto,
+}
+
+/// Style of the generated images, DALL·E 3 only
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageStyle {
+    /// Hyper-real and dramatic images
+    #[serde(rename = "vivid")]
+    Vivid,
+    /// More natural, less hyper-real looking images
+    #[serde(rename = "natural")]
+    Natural,
+}
+
+/// How generated images are returned
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageResponseFormat {
+    /// A URL to download the image from, valid for an hour
+    #[serde(rename = "url")]
+    Url,
+    /// The image itself, base64-encoded
+    #[serde(rename = "b64_json")]
+    B64Json,
+}
+
+/// Arguments of an image generation request.
+///
+/// Building fails with a message listing the problems if the model doesn't support the
+/// requested number of images, size, quality or style, or if the prompt is too long.
+///
+/// # Example
+/// ```
+/// # use openai_api::images::{ImageArgs, ImageModel, ImageQuality, ImageSize};
+/// let args = ImageArgs::builder()
+///     .model(ImageModel::DallE3)
+///     .prompt("A watercolor lighthouse at dawn")
+///     .size(ImageSize::Landscape1792)
+///     .quality(ImageQuality::Hd)
+///     .build()
+///     .unwrap();
+///
+/// let too_many = ImageArgs::builder()
+///     .model(ImageModel::DallE3)
+///     .prompt("A watercolor lighthouse at dawn")
+///     .n(4)
+///     .build();
+/// assert!(too_many.is_err());
+/// ```
+#[derive(Serialize, Deserialize, Debug, Builder, Clone)]
+#[builder(pattern = "immutable", build_fn(validate = "Self::check"))]
+pub struct ImageArgs {
+    /// The model generating the images, DALL·E 2 by default
+    #[builder(default)]
+    #[serde(default)]
+    model: ImageModel,
+    /// A text description of the desired images
+    #[builder(setter(into))]
+    prompt: String,
+    /// Number of images to generate: 1 to 10 with DALL·E 2, 1 with DALL·E 3
+    #[builder(default = "1")]
+    #[serde(default = "one")]
+    n: u8,
+    #[builder(setter(strip_option), default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    size: Option<ImageSize>,
+    #[builder(setter(strip_option), default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    quality: Option<ImageQuality>,
+    #[builder(setter(strip_option), default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    style: Option<ImageStyle>,
+    /// DALL·E only: GPT Image always returns base64-encoded images
+    #[builder(setter(strip_option), default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    response_format: Option<ImageResponseFormat>,
+    #[builder(setter(strip_option), default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    background: Option<ImageBackground>,
+    /// PNG by default
+    #[builder(setter(strip_option), default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    output_format: Option<ImageOutputFormat>,
+    /// Compression level of jpeg and webp images, from 0 to 100%
+    #[builder(setter(strip_option), default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    output_compression: Option<u8>,
+    #[builder(setter(strip_option), default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    moderation: Option<ImageModeration>,
+    /// A unique identifier representing your end-user, which can help OpenAI to monitor
+    /// and detect abuse.
+    #[builder(setter(into, strip_option), default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    user: Option<String>,
+}
+
+fn one() -> u8 {
+    1
+}
+
+impl ImageArgs {
+    /// Build a `ImageArgs` from the defaults
+    #[must_use]
+    pub fn builder() -> ImageArgsBuilder {
+        ImageArgsBuilder::default()
+    }
+
+    /// Checks the arguments locally, without calling the API. Built args are already
+    /// checked, deserialized ones aren't.
+    ///
+    /// # Errors
+    /// - `Error::BadArguments` listing every problem found
+    pub fn validate(&self) -> Result<()> {
+        Combination {
+            model: self.model,
+            prompt: &self.prompt,
+            n: self.n,
+            size: self.size,
+            quality: self.quality,
+            style: self.style,
+            response_format: self.response_format,
+            background: self.background,
+            output_format: self.output_format,
+            output_compression: self.output_compression,
+            moderation: self.moderation,
+        }
+        .problems()
+        .into_result()
+    }
+}
+
+impl ImageArgsBuilder {
+    fn check(&self) -> std::result::Result<(), String> {
+        Combination {
+            model: self.model.unwrap_or_default(),
+            prompt: self.prompt.as_deref().unwrap_or_default(),
+            n: self.n.unwrap_or(1),
+            size: self.size.flatten(),
+            quality: self.quality.flatten(),
+            style: self.style.flatten(),
+            response_format: self.response_format.flatten(),
+            background: self.background.flatten(),
+            output_format: self.output_format.flatten(),
+            output_compression: self.output_compression.flatten(),
+            moderation: self.moderation.flatten(),
+        }
+        .problems()
+        .into_message()
+    }
+}
+
+/// A combination of image arguments, set on `ImageArgs` or its builder
+struct Combination<'a> {
+    model: ImageModel,
+    prompt: &'a str,
+    n: u8,
+    size: Option<ImageSize>,
+    quality: Option<ImageQuality>,
+    style: Option<ImageStyle>,
+    response_format: Option<ImageResponseFormat>,
+    background: Option<ImageBackground>,
+    output_format: Option<ImageOutputFormat>,
+    output_compression: Option<u8>,
+    moderation: Option<ImageModeration>,
+}
+
+impl Combination<'_> {
+    fn problems(&self) -> Problems {
+        let mut problems = Problems::default();
+        let model = self.model;
+        let max_chars = model.max_prompt_chars();
+        let chars = self.prompt.chars().count();
+        problems.check(chars <= max_chars, || {
+            format!(
+                "prompts of {:?} are at most {} characters long, got {}",
+                model, max_chars, chars
+            )
+        });
+        let max_n = model.max_n();
+        problems.check((1..=max_n).contains(&self.n), || {
+            format!(
+                "{:?} makes between 1 and {} images at a time, got {}",
+                model, max_n, self.n
+            )
+        });
+        if let Some(size) = self.size {
+            problems.check(model.supports_size(size), || {
+                format!("{:?} doesn't make {:?} images", model, size)
+            });
+        }
+        if let Some(quality) = self.quality {
+            problems.check(model.supports_quality(quality), || {
+                format!(
+                    "{:?} doesn't support the {} quality",
+                    model,
+                    quality.as_str()
+                )
+            });
+        }
+        problems.check(model == ImageModel::DallE3 || self.style.is_none(), || {
+            "only DallE3 supports styles".into()
+        });
+        if model == ImageModel::GptImage1 {
+            problems.check(self.response_format.is_none(), || {
+                "GptImage1 always returns b64_json, and takes no response_format".into()
+            });
+        } else {
+            for (set, name) in [
+                (self.background.is_some(), "background"),
+                (self.output_format.is_some(), "output_format"),
+                (self.output_compression.is_some(), "output_compression"),
+                (self.moderation.is_some(), "moderation"),
+            ] {
+                problems.check(!set, || format!("only GptImage1 supports {}", name));
+            }
+        }
+        if let Some(compression) = self.output_compression {
+            problems.check(compression <= 100, || {
+                format!("output_compression is at most 100, got {}", compression)
+            });
+            problems.check(
+                matches!(
+                    self.output_format,
+                    Some(ImageOutputFormat::Jpeg | ImageOutputFormat::Webp)
+                ),
+                || "output_compression needs the jpeg or webp output_format".into(),
+            );
+        }
+        problems.check(
+            self.background != Some(ImageBackground::Transparent)
+                || self.output_format != Some(ImageOutputFormat::Jpeg),
+            || "transparent backgrounds need the png or webp output_format".into(),
+        );
+        problems
+    }
+}
+
+impl TryFrom<ImageArgsBuilder> for ImageArgs {
+    type Error = ImageArgsBuilderError;
+
+    fn try_from(builder: ImageArgsBuilder) -> std::result::Result<Self, Self::Error> {
+        builder.build()
+    }
+}
+
+impl From<ImageArgsBuilderError> for Error {
+    fn from(err: ImageArgsBuilderError) -> Self {
+        Error::BadArguments(err.to_string())
+    }
+}
+
+/// Represents an image generation response
+#[derive(Deserialize, Debug, Clone)]
+pub struct Images {
+    /// Unix timestamp when the images were generated
+    #[serde(default, deserialize_with = "crate::api::lenient")]
+    pub created: u64,
+    /// The generated images
+    pub data: Vec<ImageData>,
+    /// The background of the images, GPT Image only
+    #[serde(default)]
+    pub background: Option<ImageBackground>,
+    /// The file format of the images, GPT Image only
+    #[serde(default)]
+    pub output_format: Option<ImageOutputFormat>,
+    /// The tokens used, GPT Image only
+    #[serde(default)]
+    pub usage: Option<ImageUsage>,
+}
+
+/// Tokens used by an image generation, which GPT Image bills by
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(default)]
+pub struct ImageUsage {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub total_tokens: u64,
+    pub input_tokens_details: ImageInputTokens,
+}
+
+/// The kinds of input tokens of an image generation
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(default)]
+pub struct ImageInputTokens {
+    pub text_tokens: u64,
+    pub image_tokens: u64,
+}
+
+/// A generated image, as a URL or base64-encoded depending on the `response_format`
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct ImageData {
+    /// Where to download the image from, for an hour
+    pub url: Option<String>,
+    /// The base64-encoded image
+    pub b64_json: Option<String>,
+    /// The prompt DALL·E 3 actually used, after rewriting the given one
+    pub revised_prompt: Option<String>,
+}
+
+impl ImageData {
+    /// The image file, a PNG unless another `ImageOutputFormat` was requested, decoded
+    /// from `b64_json`
+    ///
+    /// # Errors
+    /// - `Error::BadArguments` if the image was returned as a URL, see
+    ///   `ImageResponseFormat::B64Json`
+    /// - `Error::Base64` if `b64_json` isn't valid base64
+    pub fn bytes(&self) -> Result<Vec<u8>> {
+        let b64 = self.b64_json.as_deref().ok_or_else(|| {
+            Error::BadArguments(
+                "the image has no b64_json data, request it with ImageResponseFormat::B64Json"
+                    .into(),
+            )
+        })?;
+        Ok(base64::engine::general_purpose::STANDARD.decode(b64)?)
+    }
+
+    /// Writes the image file to `path`, replacing any file there
+    ///
+    /// # Errors
+    /// - The errors of `bytes`
+    /// - `Error::Io` if the file can't be written
+    pub fn save_to(&self, path: impl AsRef<Path>) -> Result<()> {
+        Ok(std::fs::write(path, self.bytes()?)?)
+    }
+}
+
+impl Client {
+    /// Generates images from a prompt
+    ///
+    /// Accepts anything convertible to `ImageArgs`, including an unbuilt `ImageArgsBuilder`.
+    ///
+    /// # Errors
+    ///  - `Error::BadArguments` if the arguments can't be built, or are invalid
+    ///  - `Error::DryRun` in dry-run mode, if the arguments are valid
+    ///  - `Error::Api` if the api returns an error
+    pub async fn create_image<A>(&self, args: A) -> Result<Images>
+    where
+        A: TryInto<ImageArgs>,
+        Error: From<<A as TryInto<ImageArgs>>::Error>,
+    {
+        let args: ImageArgs = args.try_into()?;
+        args.validate()?;
+        self.dry_run("images/generations", &args, || Ok(()))?;
+        self.post("images/generations", args).await
+    }
+}
+
+#[cfg(test)]
+mod unit {
+    use super::{
+        ImageArgs, ImageBackground, ImageData, ImageModel, ImageModeration, ImageOutputFormat,
+        ImageQuality, ImageSize, ImageStyle,
+    };
+    use crate::{unit::mocked_client, Error};
+
+    #[test]
+    fn validates_model_combinations() {
+        let args = || ImageArgs::builder().prompt("A lighthouse");
+        assert!(args().n(10).size(ImageSize::Square256).build().is_ok());
+        assert!(args()
+            .model(ImageModel::DallE3)
+            .size(ImageSize::Portrait1792)
+            .quality(ImageQuality::Hd)
+            .style(ImageStyle::Natural)
+            .build()
+            .is_ok());
+
+        let err = args()
+            .model(ImageModel::DallE3)
+            .n(2)
+            .size(ImageSize::Square512)
+            .build()
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.contains("DallE3 makes between 1 and 1 images at a time, got 2"),
+            "{}",
+            err
+        );
+        assert!(
+            err.contains("DallE3 doesn't make Square512 images"),
+            "{}",
+            err
+        );
+
+        let err = args()
+            .quality(ImageQuality::Hd)
+            .style(ImageStyle::Vivid)
+            .n(11)
+            .build()
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("hd quality"), "{}", err);
+        assert!(err.contains("styles"), "{}", err);
+        assert!(err.contains("got 11"), "{}", err);
+
+        assert!(ImageArgs::builder()
+            .prompt("a".repeat(1001))
+            .build()
+            .is_err());
+        let deserialized: ImageArgs =
+            serde_json::from_str(r#"{"model": "dall-e-3", "prompt": "A lighthouse", "n": 3}"#)
+                .unwrap();
+        assert!(matches!(
+            deserialized.validate(),
+            Err(Error::BadArguments(_))
+        ));
+    }
+
+    #[test]
+    fn decodes_and_saves_images() -> crate::Result<()> {
+        let png = b"\x89PNG\r\n\x1a\n";
+        let image = ImageData {
+            url: None,
+            b64_json: Some("iVBORw0KGgo=".into()),
+            revised_prompt: None,
+        };
+        assert_eq!(image.bytes()?, png);
+        let path = std::env::temp_dir().join(format!("openai-api-{}.png", std::process::id()));
+        image.save_to(&path)?;
+        assert_eq!(std::fs::read(&path)?, png);
+        std::fs::remove_file(&path)?;
+
+        let url = ImageData {
+            url: Some("https://example.com/image.png".into()),
+            b64_json: None,
+            revised_prompt: None,
+        };
+        assert!(matches!(url.bytes(), Err(Error::BadArguments(_))));
+        let invalid = ImageData {
+            b64_json: Some("not base64!".into()),
+            ..url
+        };
+        assert!(matches!(invalid.bytes(), Err(Error::Base64(_))));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn create_image() -> crate::Result<()> {
+        let mock = mockito::mock("POST", "/images/generations")
+            .match_body(mockito::Matcher::Json(serde_json::json!({
+                "model": "dall-e-3",
+                "prompt": "A lighthouse",
+                "n": 1,
+                "size": "1792x1024",
+                "response_format": "url"
+            })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"{
+                "created": 1589478378,
+                "data": [{
+                    "url": "https://example.com/image.png",
+                    "revised_prompt": "A lighthouse on a cliff"
+                }]
+            }"#,
+            )
+            .expect(1)
+            .create();
+        let images = mocked_client()
+            .create_image(
+                ImageArgs::builder()
+                    .model(ImageModel::DallE3)
+                    .prompt("A lighthouse")
+                    .size(ImageSize::Landscape1792)
+                    .response_format(super::ImageResponseFormat::Url),
+            )
+            .await?;
+        assert_eq!(images.created, 1589478378);
+        assert_eq!(
+            images.data,
+            [ImageData {
+                url: Some("https://example.com/image.png".into()),
+                b64_json: None,
+                revised_prompt: Some("A lighthouse on a cliff".into()),
+            }]
+        );
+        mock.assert();
+        Ok(())
+    }
+
+    #[test]
+    fn validates_gpt_image_parameters() {
+        let args = || {
+            ImageArgs::builder()
+                .model(ImageModel::GptImage1)
+                .prompt("A lighthouse")
+        };
+        assert!(args()
+            .n(4)
+            .size(ImageSize::Portrait1536)
+            .quality(ImageQuality::High)
+            .background(ImageBackground::Transparent)
+            .output_format(ImageOutputFormat::Webp)
+            .output_compression(80)
+            .moderation(ImageModeration::Low)
+            .build()
+            .is_ok());
+
+        let err = args()
+            .quality(ImageQuality::Hd)
+            .response_format(super::ImageResponseFormat::Url)
+            .background(ImageBackground::Transparent)
+            .output_format(ImageOutputFormat::Jpeg)
+            .output_compression(101)
+            .build()
+            .unwrap_err()
+            .to_string();
+        for problem in [
+            "GptImage1 doesn't support the hd quality",
+            "response_format",
+            "at most 100",
+            "transparent backgrounds",
+        ] {
+            assert!(err.contains(problem), "{}", err);
+        }
+        assert!(args().output_compression(50).build().is_err());
+        let dall_e = ImageArgs::builder()
+            .prompt("A lighthouse")
+            .moderation(ImageModeration::Auto)
+            .build();
+        assert!(dall_e.unwrap_err().to_string().contains("moderation"));
+    }
+
+    #[tokio::test]
+    async fn create_gpt_image() -> crate::Result<()> {
+        let _m = mockito::mock("POST", "/images/generations")
+            .match_body(mockito::Matcher::PartialJson(serde_json::json!({
+                "model": "gpt-image-1",
+                "output_format": "webp",
+            })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"{"created": 1713833628, "background": "opaque", "output_format": "webp",
+                "data": [{"b64_json": "UklGRg=="}], "usage": {"total_tokens": 100,
+                "input_tokens": 50, "output_tokens": 50, "input_tokens_details":
+                {"text_tokens": 10, "image_tokens": 40}}}"#,
+            )
+            .create();
+        let images = mocked_client()
+            .create_image(
+                ImageArgs::builder()
+                    .model(ImageModel::GptImage1)
+                    .prompt("A lighthouse")
+                    .output_format(ImageOutputFormat::Webp),
+            )
+            .await?;
+        assert_eq!(images.data[0].bytes()?, b"RIFF");
+        let usage = images.usage.unwrap();
+        assert_eq!(usage.input_tokens_details.image_tokens, 40);
+        assert_eq!(images.output_format, Some(ImageOutputFormat::Webp));
+        Ok(())
+    }
+}
@@ -0,0 +1,64 @@
+//! Parsing of response bodies, with simd-json when the `simd-json` feature is enabled.
+use bytes::Bytes;
+use serde::de::DeserializeOwned;
+
+use crate::Result;
+
+/// Parses JSON from `bytes`, which simd-json uses as scratch space
+#[cfg(feature = "simd-json")]
+pub(crate) fn from_slice<T: DeserializeOwned>(bytes: &mut [u8]) -> Result<T> {
+    simd_json::serde::from_slice(bytes)
+        .map_err(|e| <serde_json::Error as serde::de::Error>::custom(e).into())
+}
+
+/// Parses JSON from `bytes`, which simd-json uses as scratch space
+#[cfg(not(feature = "simd-json"))]
+pub(crate) fn from_slice<T: DeserializeOwned>(bytes: &mut [u8]) -> Result<T> {
+    Ok(serde_json::from_slice(bytes)?)
+}
+
+/// Parses a whole response body. simd-json gets the body's buffer as scratch space, which
+/// is only copied when the body is shared.
+#[cfg(feature = "simd-json")]
+pub(crate) fn from_bytes<T: DeserializeOwned>(bytes: Bytes) -> Result<T> {
+    from_slice(&mut Vec::from(bytes))
+}
+
+/// Parses a whole response body, in place
+#[cfg(not(feature = "simd-json"))]
+pub(crate) fn from_bytes<T: DeserializeOwned>(bytes: Bytes) -> Result<T> {
+    Ok(serde_json::from_slice(&bytes)?)
+}
+
+#[cfg(test)]
+mod unit {
+    use bytes::Bytes;
+
+    use crate::{embeddings::Embedding, Error};
+
+    #[test]
+    fn from_slice() -> crate::Result<()> {
+        let mut body = br#"{"index": 3, "embedding": [0.5, -1e-3, 2]}"#.to_vec();
+        let embedding: Embedding = super::from_slice(&mut body)?;
+        assert_eq!(embedding.index, 3);
+        assert_eq!(embedding.embedding, [0.5, -1e-3, 2.0]);
+        assert!(matches!(
+            super::from_slice::<Embedding>(&mut b"{\"index\": ".to_vec()),
+            Err(Error::Json(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn from_bytes() -> crate::Result<()> {
+        let body = Bytes::from_static(br#"{"index": 1, "embedding": [1, 2]}"#);
+        let embedding: Embedding = super::from_bytes(body)?;
+        assert_eq!(embedding.index, 1);
+        assert_eq!(embedding.embedding, [1.0, 2.0]);
+        assert!(matches!(
+            super::from_bytes::<Embedding>(Bytes::from_static(b"[")),
+            Err(Error::Json(_))
+        ));
+        Ok(())
+    }
+}
@@ -1,3 +1,4 @@
+use futures::StreamExt;
 use thiserror::Error;
 
 #[macro_use]
@@ -5,12 +6,98 @@ extern crate derive_builder;
 
 type Result<T> = std::result::Result<T, Error>;
 
+pub mod assistants;
+pub mod audio;
+pub mod batch;
+mod builder;
+pub mod cache;
+pub mod canonical;
+pub mod cloudflare;
+#[cfg(feature = "gzip")]
+mod compression;
+mod content;
+pub mod context;
+pub mod embeddings;
+pub mod files;
+pub mod fine_tuning;
+mod format;
+mod functions;
+pub mod gateway;
+pub mod gemini;
+pub mod images;
+mod json;
+#[cfg(feature = "mock")]
+pub mod mock;
+pub mod models;
+pub mod moderations;
+mod multipart;
+pub mod openrouter;
+pub mod options;
+pub mod poll;
+mod pool;
+pub mod provider;
+pub mod queue;
+pub mod realtime;
+pub mod response;
+pub mod responses;
+pub mod retry;
+mod runtime;
+pub mod session;
+pub mod stored_completions;
+pub mod stream;
+#[cfg(feature = "schemars")]
+pub mod structured;
+#[cfg(feature = "test_support")]
+pub mod test_support;
+pub mod throttle;
+#[cfg(feature = "tiktoken")]
+pub mod tokens;
+pub mod traits;
+pub mod transport;
+#[cfg(feature = "vcr")]
+pub mod vcr;
+pub mod vector_stores;
+
+pub use builder::ClientBuilder;
+pub use traits::OpenAiApi;
+
 pub mod api {
     use std::collections::HashMap;
 
     use serde::{Deserialize, Serialize};
     use thiserror::Error;
 
+    use crate::models::ModelKind;
+
+    // Message contents with images live in the `content` module
+    pub use crate::content::{ChatContent, ContentPart, ImageDetail, ImageUrl};
+
+    // Response formats live in the `format` module
+    pub use crate::format::{JsonSchema, ResponseFormat};
+
+    // Function calling lives in the `functions` module
+    pub use crate::functions::{
+        ChatTool, FunctionCall, FunctionCallDelta, FunctionCallMode, FunctionSpec, ToolCall,
+        ToolCallDelta, ToolChoice, ToolKind,
+    };
+
+    // Image generation lives in the `images` module, with the types it needs here too
+    pub use crate::images::{
+        ImageArgs, ImageArgsBuilder, ImageData, ImageModel, ImageResponseFormat, ImageSize, Images,
+    };
+
+    /// Deserializes a missing or `null` field as its default value.
+    ///
+    /// Local OpenAI-compatible servers (Ollama, LM Studio, llama.cpp) leave out, or send
+    /// `null` for, fields the API always sets, such as `id` or `created`.
+    pub(crate) fn lenient<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+        T: Default + Deserialize<'de>,
+    {
+        Ok(Option::<T>::deserialize(deserializer)?.unwrap_or_default())
+    }
+
     /// Container type. Used in the api, but not useful for clients of this library
     #[derive(Deserialize, Debug)]
     pub(crate) struct Container<T> {
@@ -18,19 +105,118 @@ pub mod api {
         pub data: Vec<T>,
     }
 
+    /// A page of the results of a cursor-paginated list endpoint. Pass `last_id` as the
+    /// `after` of `ListParams` to get the next page.
+    #[derive(Deserialize, Debug, Clone, PartialEq)]
+    pub struct Page<T> {
+        /// Items in the page's results
+        pub data: Vec<T>,
+        /// Id of the first item of the page
+        #[serde(default)]
+        pub first_id: Option<String>,
+        /// Id of the last item of the page
+        #[serde(default)]
+        pub last_id: Option<String>,
+        /// Whether there are more results after this page
+        #[serde(default)]
+        pub has_more: bool,
+    }
+
+    /// The answer to the deletion of an object, e.g. a file
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+    pub struct Deleted {
+        pub id: String,
+        pub deleted: bool,
+    }
+
     /// Detailed information on a particular model.
     #[derive(Deserialize, Debug, Eq, PartialEq, Clone)]
     pub struct ModelInfo {
         /// The name of the model, e.g. `"davinci"` or `"ada"`
         pub id: String,
         /// The owner of the model. Usually (always?) `"openai"`
+        #[serde(default, deserialize_with = "lenient")]
         pub owned_by: String,
         ///  Usually (always?) `"model"`
+        #[serde(default, deserialize_with = "lenient")]
         pub object: String,
     }
 
-    #[derive(Serialize, Debug, Builder, Clone)]
+    /// Sort order for list endpoints, by the `created_at` timestamp of the objects
+    #[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum ListOrder {
+        #[serde(rename = "asc")]
+        Asc,
+        #[serde(rename = "desc")]
+        Desc,
+    }
+
+    /// Pagination parameters shared by every list endpoint.
+    ///
+    /// Unset fields are left out of the query string, so the server defaults apply.
+    ///
+    /// # Example
+    /// ```
+    /// # use openai_api::api::{ListOrder, ListParams};
+    /// let params = ListParams::builder()
+    ///     .limit(20)
+    ///     .order(ListOrder::Desc)
+    ///     .after("file-abc123")
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    #[derive(Serialize, Deserialize, Debug, Builder, Clone, Default, PartialEq, Eq)]
+    #[builder(pattern = "immutable")]
+    #[serde(default)]
+    pub struct ListParams {
+        /// A limit on the number of objects to be returned
+        #[builder(setter(strip_option), default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub limit: Option<u32>,
+        /// Sort order of the returned objects
+        #[builder(setter(strip_option), default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub order: Option<ListOrder>,
+        /// A cursor for pagination: the id of the object to start listing after
+        #[builder(setter(into, strip_option), default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub after: Option<String>,
+        /// A cursor for pagination: the id of the object to start listing before
+        #[builder(setter(into, strip_option), default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub before: Option<String>,
+    }
+
+    impl ListParams {
+        /// Build a `ListParams` from the defaults
+        #[must_use]
+        pub fn builder() -> ListParamsBuilder {
+            ListParamsBuilder::default()
+        }
+    }
+
+    impl TryFrom<ListParamsBuilder> for ListParams {
+        type Error = ListParamsBuilderError;
+
+        fn try_from(builder: ListParamsBuilder) -> Result<Self, Self::Error> {
+            builder.build()
+        }
+    }
+
+    /// Arguments of a completion request.
+    ///
+    /// Besides the builder, args can be deserialized, e.g. from a preset in a config file.
+    /// Fields missing from the input take their default values.
+    ///
+    /// # Example
+    /// ```
+    /// # use openai_api::api::CompletionArgs;
+    /// let args: CompletionArgs =
+    ///     serde_json::from_str(r#"{"model": "text-davinci-003", "temperature": 0.2}"#).unwrap();
+    /// ```
+    #[derive(Serialize, Deserialize, Debug, Builder, Clone)]
     #[builder(pattern = "immutable")]
+    #[serde(default)]
     pub struct CompletionArgs {
         /// The id of the model to use for this request
         ///
@@ -41,7 +227,8 @@ pub mod api {
         /// ```
         #[builder(setter(into), default = "\"text-davinci-003\".into()")]
         pub(super) model: String,
-        /// The prompt to complete from.
+        /// The prompt to complete from, or several prompts completed in one request, see
+        /// `Prompt`.
         ///
         /// Defaults to `"<|endoftext|>"` which is a special token seen during training.
         ///
@@ -49,9 +236,10 @@ pub mod api {
         /// ```
         /// # use openai_api::api::CompletionArgs;
         /// CompletionArgs::builder().prompt("Once upon a time...");
+        /// CompletionArgs::builder().prompt(vec!["Once upon a time...", "In a galaxy"]);
         /// ```
         #[builder(setter(into), default = "\"<|endoftext|>\".into()")]
-        prompt: String,
+        prompt: Prompt,
         /// Maximum number of tokens to complete.
         ///
         /// Defaults to 16
@@ -60,8 +248,9 @@ pub mod api {
         /// # use openai_api::api::CompletionArgs;
         /// CompletionArgs::builder().max_tokens(64);
         /// ```
-        #[builder(default = "16")]
-        max_tokens: u64,
+        #[builder(setter(strip_option), default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        max_tokens: Option<u64>,
         /// What sampling temperature to use.
         ///
         /// Default is `1.0`
@@ -82,24 +271,87 @@ pub mod api {
         /// # Ok::<(), _>(())
         /// # }
         /// ```
-        #[builder(default = "1.0")]
-        temperature: f64,
-        #[builder(default = "1.0")]
-        top_p: f64,
-        #[builder(default = "1")]
-        n: u64,
         #[builder(setter(strip_option), default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub(super) temperature: Option<f64>,
+        #[builder(setter(strip_option), default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        top_p: Option<f64>,
+        #[builder(setter(strip_option), default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        n: Option<u64>,
+        #[builder(setter(strip_option), default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
         logprobs: Option<u64>,
-        #[builder(default = "false")]
-        echo: bool,
         #[builder(setter(strip_option), default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        echo: Option<bool>,
+        #[builder(setter(strip_option), default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
         stop: Option<Vec<String>>,
-        #[builder(default = "0.0")]
-        presence_penalty: f64,
-        #[builder(default = "0.0")]
-        frequency_penalty: f64,
+        #[builder(setter(strip_option), default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        presence_penalty: Option<f64>,
+        #[builder(setter(strip_option), default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        frequency_penalty: Option<f64>,
         #[builder(default)]
+        #[serde(skip_serializing_if = "HashMap::is_empty")]
         logit_bias: HashMap<String, f64>,
+        /// Text after the completion, for the model to insert the completion between the
+        /// prompt and it
+        ///
+        /// # Example
+        /// ```
+        /// # use openai_api::api::CompletionArgs;
+        /// CompletionArgs::builder()
+        ///     .prompt("fn add(a: u32, b: u32) -> u32 {")
+        ///     .suffix("}");
+        /// ```
+        #[builder(setter(into, strip_option), default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        suffix: Option<String>,
+        /// Completions generated server-side for each prompt, of which the `n` most
+        /// likely are returned. At least `n`, at most 20, and not with streaming.
+        #[builder(setter(strip_option), default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub(super) best_of: Option<u64>,
+        /// Id of the end user, for OpenAI to track abuse
+        #[builder(setter(into, strip_option), default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        user: Option<String>,
+        /// Seed of the sampling, for repeated requests to return the same completions as
+        /// far as the API can
+        #[builder(setter(strip_option), default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        seed: Option<i64>,
+        /// Sampling parameters of self-hosted backends, see `BackendSampling`
+        ///
+        /// # Example
+        /// ```
+        /// # use openai_api::api::{BackendSampling, CompletionArgs};
+        /// CompletionArgs::builder().backend_sampling(BackendSampling {
+        ///     top_k: Some(40),
+        ///     ..BackendSampling::default()
+        /// });
+        /// ```
+        #[builder(default)]
+        #[serde(flatten)]
+        backend_sampling: BackendSampling,
+        /// Parameters the crate doesn't model yet, sent as they are next to the others,
+        /// e.g. those of a new API release. They must not repeat the parameters set on
+        /// the builder.
+        ///
+        /// # Example
+        /// ```
+        /// # use openai_api::api::CompletionArgs;
+        /// CompletionArgs::builder().extra(
+        ///     [("service_tier".to_string(), "flex".into())].into_iter().collect(),
+        /// );
+        /// ```
+        #[builder(default)]
+        #[serde(flatten)]
+        extra: serde_json::Map<String, serde_json::Value>,
     }
 
     impl CompletionArgs {
@@ -110,6 +362,14 @@ pub mod api {
         }
     }
 
+    impl Default for CompletionArgs {
+        fn default() -> Self {
+            CompletionArgsBuilder::default()
+                .build()
+                .expect("default should build")
+        }
+    }
+
     impl From<&str> for CompletionArgs {
         fn from(prompt_string: &str) -> Self {
             Self {
@@ -121,6 +381,280 @@ pub mod api {
         }
     }
 
+    impl CompletionArgs {
+        /// Rough number of tokens the request uses: its prompts and longest completions
+        pub(crate) fn estimated_tokens(&self) -> u64 {
+            let prompts = self.prompt.tokens();
+            let completions = self.best_of.unwrap_or(0).max(self.n.unwrap_or(1));
+            prompts.iter().sum::<u64>() + self.max_tokens() * completions * prompts.len() as u64
+        }
+
+        /// Size of the context each choice needs: the longest prompt, and room for
+        /// `max_tokens`. Prompts are counted exactly with the `tiktoken` feature, and
+        /// otherwise with `min_tokens`, so that requests aren't rejected on an estimate.
+        pub(crate) fn context_tokens(&self) -> u64 {
+            #[cfg(feature = "tiktoken")]
+            let count = |text: &str| crate::tokens::count_tokens(&self.model, text);
+            #[cfg(not(feature = "tiktoken"))]
+            let count = crate::min_tokens;
+            let longest = self.prompt.tokens_with(count).into_iter().max();
+            longest.unwrap_or(0) + self.max_tokens()
+        }
+
+        /// The completion tokens of each choice, 16 when unset as for the API
+        fn max_tokens(&self) -> u64 {
+            self.max_tokens.unwrap_or(16)
+        }
+
+        /// Checks the arguments locally, without calling the API: parameter ranges,
+        /// whether the model is a completion model, and whether the prompt and
+        /// `max_tokens` fit in the model's context. Without the `tiktoken` feature, the
+        /// prompt is counted low, so that only prompts sure not to fit are flagged.
+        ///
+        /// # Errors
+        /// - `Error::BadArguments` listing every problem found
+        pub fn validate(&self) -> crate::Result<()> {
+            let mut problems = Problems::default();
+            problems.check(!self.prompt.tokens().is_empty(), || {
+                "at least one prompt is required".into()
+            });
+            problems.sampling(self.temperature, self.top_p, self.n);
+            problems.range("logprobs", self.logprobs.unwrap_or(0) as f64, 0.0, 5.0);
+            if let Some(best_of) = self.best_of {
+                let n = self.n.unwrap_or(1);
+                problems.range("best_of", best_of as f64, n as f64, 20.0);
+            }
+            problems.penalties(self.presence_penalty, self.frequency_penalty);
+            problems.logit_bias(&self.logit_bias);
+            problems.stop(self.stop.as_deref());
+            self.backend_sampling.validate(&mut problems);
+            if !self.extra.is_empty() {
+                let set = Self {
+                    extra: serde_json::Map::new(),
+                    ..self.clone()
+                };
+                problems.extra(&self.extra, &set);
+            }
+            problems.model(&self.model, ModelKind::Completion, self.context_tokens());
+            problems.into_result()
+        }
+    }
+
+    /// The prompt of a completion request: text or token ids, one prompt or several.
+    ///
+    /// Several prompts are completed in a single request. The choices of the answer come
+    /// prompt after prompt: those of prompt `i` have indices `i * n` to `(i + 1) * n - 1`.
+    ///
+    /// # Example
+    /// ```
+    /// # use openai_api::api::Prompt;
+    /// let prompts = Prompt::from(vec!["Once upon a time", "In a galaxy"]);
+    /// assert_eq!(serde_json::to_string(&prompts).unwrap(), r#"["Once upon a time","In a galaxy"]"#);
+    /// let tokens = Prompt::from(vec![7454, 2402, 257, 640]);
+    /// assert_eq!(serde_json::to_string(&tokens).unwrap(), "[7454,2402,257,640]");
+    /// ```
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+    #[serde(untagged)]
+    pub enum Prompt {
+        String(String),
+        Strings(Vec<String>),
+        /// The token ids of a prompt, in the model's tokenizer
+        Tokens(Vec<u64>),
+        /// The token ids of several prompts
+        TokenBatches(Vec<Vec<u64>>),
+    }
+
+    impl Prompt {
+        /// Estimated tokens of each prompt
+        fn tokens(&self) -> Vec<u64> {
+            self.tokens_with(crate::estimate_tokens)
+        }
+
+        /// Tokens of each prompt, those of texts counted with `count`
+        fn tokens_with(&self, count: impl Fn(&str) -> usize) -> Vec<u64> {
+            let text = |text: &String| count(text) as u64;
+            match self {
+                Prompt::String(prompt) => vec![text(prompt)],
+                Prompt::Strings(prompts) => prompts.iter().map(text).collect(),
+                Prompt::Tokens(tokens) => vec![tokens.len() as u64],
+                Prompt::TokenBatches(batches) => batches.iter().map(|t| t.len() as u64).collect(),
+            }
+        }
+    }
+
+    impl From<String> for Prompt {
+        fn from(prompt: String) -> Self {
+            Prompt::String(prompt)
+        }
+    }
+
+    impl From<&str> for Prompt {
+        fn from(prompt: &str) -> Self {
+            Prompt::String(prompt.into())
+        }
+    }
+
+    impl From<Vec<String>> for Prompt {
+        fn from(prompts: Vec<String>) -> Self {
+            Prompt::Strings(prompts)
+        }
+    }
+
+    impl From<Vec<&str>> for Prompt {
+        fn from(prompts: Vec<&str>) -> Self {
+            Prompt::Strings(prompts.into_iter().map(Into::into).collect())
+        }
+    }
+
+    impl From<Vec<u64>> for Prompt {
+        fn from(tokens: Vec<u64>) -> Self {
+            Prompt::Tokens(tokens)
+        }
+    }
+
+    impl From<Vec<Vec<u64>>> for Prompt {
+        fn from(batches: Vec<Vec<u64>>) -> Self {
+            Prompt::TokenBatches(batches)
+        }
+    }
+
+    /// Sampling parameters that the OpenAI API doesn't have, but self-hosted backends
+    /// such as vLLM and llama.cpp accept next to the standard ones.
+    ///
+    /// Only the parameters that are set are sent.
+    #[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+    pub struct BackendSampling {
+        /// Sample among the `top_k` most likely tokens only
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub top_k: Option<u32>,
+        /// Drop tokens less likely than `min_p` times the most likely one, in `[0, 1]`
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub min_p: Option<f64>,
+        /// Penalty of tokens already present, above 0; `1.0` disables it
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub repetition_penalty: Option<f64>,
+    }
+
+    impl BackendSampling {
+        fn validate(&self, problems: &mut Problems) {
+            if let Some(min_p) = self.min_p {
+                problems.range("min_p", min_p, 0.0, 1.0);
+            }
+            if let Some(penalty) = self.repetition_penalty {
+                problems.check(penalty > 0.0, || {
+                    format!("repetition_penalty must be above 0, got {}", penalty)
+                });
+            }
+        }
+    }
+
+    /// Collects the problems found while validating args
+    #[derive(Default)]
+    pub(crate) struct Problems(Vec<String>);
+
+    impl Problems {
+        pub(crate) fn check(&mut self, ok: bool, problem: impl FnOnce() -> String) {
+            if !ok {
+                self.0.push(problem());
+            }
+        }
+
+        pub(crate) fn range(&mut self, name: &str, value: f64, min: f64, max: f64) {
+            self.check((min..=max).contains(&value), || {
+                format!(
+                    "{} must be between {} and {}, got {}",
+                    name, min, max, value
+                )
+            });
+        }
+
+        /// Checks the sampling parameters that are set
+        fn sampling(&mut self, temperature: Option<f64>, top_p: Option<f64>, n: Option<u64>) {
+            if let Some(temperature) = temperature {
+                self.range("temperature", temperature, 0.0, 2.0);
+            }
+            if let Some(top_p) = top_p {
+                self.range("top_p", top_p, 0.0, 1.0);
+            }
+            if let Some(n) = n {
+                self.range("n", n as f64, 1.0, 128.0);
+            }
+        }
+
+        /// Checks the penalties that are set
+        fn penalties(&mut self, presence: Option<f64>, frequency: Option<f64>) {
+            if let Some(presence) = presence {
+                self.range("presence_penalty", presence, -2.0, 2.0);
+            }
+            if let Some(frequency) = frequency {
+                self.range("frequency_penalty", frequency, -2.0, 2.0);
+            }
+        }
+
+        /// Checks the `extra` parameters don't repeat those set in `args`
+        fn extra(
+            &mut self,
+            extra: &serde_json::Map<String, serde_json::Value>,
+            args: &impl Serialize,
+        ) {
+            if let Ok(serde_json::Value::Object(set)) = serde_json::to_value(args) {
+                for key in extra.keys().filter(|key| set.contains_key(*key)) {
+                    self.check(false, || format!("extra parameter {} is already set", key));
+                }
+            }
+        }
+
+        fn logit_bias(&mut self, logit_bias: &HashMap<String, f64>) {
+            for (token, bias) in logit_bias {
+                self.range(
+                    &format!("logit_bias of token {}", token),
+                    *bias,
+                    -100.0,
+                    100.0,
+                );
+            }
+        }
+
+        fn stop(&mut self, stop: Option<&[String]>) {
+            let count = stop.map_or(0, <[String]>::len);
+            self.check(count <= 4, || {
+                format!("at most 4 stop sequences are supported, got {}", count)
+            });
+        }
+
+        /// Checks the model serves `kind` requests, and that `tokens` fit in its context.
+        /// Unknown models pass.
+        pub(crate) fn model(&mut self, model: &str, kind: ModelKind, tokens: u64) {
+            if let Some(spec) = crate::models::lookup(model) {
+                self.check(spec.kind == kind, || {
+                    format!(
+                        "{} is a {:?} model, not a {:?} model",
+                        model, spec.kind, kind
+                    )
+                });
+                self.check(tokens <= spec.context_length, || {
+                    format!(
+                        "the request needs about {} tokens, but {} has a context of {}",
+                        tokens, model, spec.context_length
+                    )
+                });
+            }
+        }
+
+        pub(crate) fn into_result(self) -> crate::Result<()> {
+            self.into_message().map_err(crate::Error::BadArguments)
+        }
+
+        /// The problems as a message, for builders validating their fields
+        pub(crate) fn into_message(self) -> Result<(), String> {
+            if self.0.is_empty() {
+                Ok(())
+            } else {
+                Err(self.0.join("; "))
+            }
+        }
+    }
+
     impl TryFrom<CompletionArgsBuilder> for CompletionArgs {
         type Error = CompletionArgsBuilderError;
 
@@ -133,13 +667,23 @@ pub mod api {
     #[derive(Deserialize, Debug, Clone)]
     pub struct Completion {
         /// Completion unique identifier
+        #[serde(default, deserialize_with = "lenient")]
         pub id: String,
         /// Unix timestamp when the completion was generated
+        #[serde(default, deserialize_with = "lenient")]
         pub created: u64,
         /// Exact model type and version used for the completion
+        #[serde(default, deserialize_with = "lenient")]
         pub model: String,
         /// List of completions generated by the model
         pub choices: Vec<Choice>,
+        /// The tokens billed for the completion
+        #[serde(default)]
+        pub usage: Option<Usage>,
+        /// The fields the crate doesn't model, e.g. those of a new API release or of a
+        /// compatible server
+        #[serde(flatten)]
+        pub extra: serde_json::Map<String, serde_json::Value>,
     }
 
     impl std::fmt::Display for Completion {
@@ -148,17 +692,166 @@ pub mod api {
         }
     }
 
+    /// The tokens a request consumed. Embeddings only have prompt tokens, so
+    /// `completion_tokens` is then 0.
+    #[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+    pub struct Usage {
+        #[serde(default)]
+        pub prompt_tokens: u32,
+        #[serde(default)]
+        pub completion_tokens: u32,
+        #[serde(default)]
+        pub total_tokens: u32,
+    }
+
+    impl std::ops::AddAssign for Usage {
+        fn add_assign(&mut self, other: Self) {
+            self.prompt_tokens += other.prompt_tokens;
+            self.completion_tokens += other.completion_tokens;
+            self.total_tokens += other.total_tokens;
+        }
+    }
+
+    /// A chunk of a streamed completion, see `Client::complete_prompt_stream`. The text of
+    /// each choice is the part generated since the previous chunk.
+    #[derive(Deserialize, Debug, Clone)]
+    pub struct CompletionChunk {
+        /// Completion unique identifier, the same in every chunk
+        #[serde(default, deserialize_with = "lenient")]
+        pub id: String,
+        /// Unix timestamp when the completion was generated
+        #[serde(default, deserialize_with = "lenient")]
+        pub created: u64,
+        /// Exact model type and version used for the completion
+        #[serde(default, deserialize_with = "lenient")]
+        pub model: String,
+        /// The parts of the choices generated since the previous chunk. `finish_reason` is
+        /// only set in the last chunk of each choice.
+        pub choices: Vec<Choice>,
+    }
+
+    impl std::fmt::Display for CompletionChunk {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            match self.choices.first() {
+                Some(choice) => choice.fmt(f),
+                None => Ok(()),
+            }
+        }
+    }
+
     /// A single completion result
     #[derive(Deserialize, Debug, Clone)]
     pub struct Choice {
         /// The text of the completion. Will contain the prompt if echo is True.
         pub text: String,
         /// Offset in the result where the completion began. Useful if using echo.
+        #[serde(default, deserialize_with = "lenient")]
         pub index: u64,
         /// If requested, the log probabilities of the completion tokens
         pub logprobs: Option<LogProbs>,
         /// Why the completion ended when it did
-        pub finish_reason: String,
+        #[serde(default, deserialize_with = "lenient")]
+        pub finish_reason: FinishReason,
+        /// The fields of the choice the crate doesn't model
+        #[serde(flatten)]
+        pub extra: serde_json::Map<String, serde_json::Value>,
+    }
+
+    /// Why the model stopped generating a choice.
+    ///
+    /// Reasons this crate doesn't know, such as those of OpenAI-compatible servers, are kept
+    /// as `Other`, as is a missing reason, as an empty string.
+    ///
+    /// # Example
+    /// ```
+    /// # use openai_api::api::FinishReason;
+    /// let reason: FinishReason = serde_json::from_str(r#""length""#).unwrap();
+    /// assert_eq!(reason, FinishReason::Length);
+    /// assert_eq!(FinishReason::from("SAFETY"), FinishReason::Other("SAFETY".into()));
+    /// ```
+    #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+    pub enum FinishReason {
+        /// A natural end, or a stop sequence
+        Stop,
+        /// `max_tokens`, or the end of the context
+        Length,
+        /// Content left out by the content filter
+        ContentFilter,
+        /// A call of one of `ChatArgs::functions`
+        FunctionCall,
+        /// Calls of `ChatArgs::tools`
+        ToolCalls,
+        Other(String),
+    }
+
+    impl FinishReason {
+        /// The reason as the API names it
+        #[must_use]
+        pub fn as_str(&self) -> &str {
+            match self {
+                FinishReason::Stop => "stop",
+                FinishReason::Length => "length",
+                FinishReason::ContentFilter => "content_filter",
+                FinishReason::FunctionCall => "function_call",
+                FinishReason::ToolCalls => "tool_calls",
+                FinishReason::Other(reason) => reason,
+            }
+        }
+    }
+
+    impl Default for FinishReason {
+        fn default() -> Self {
+            FinishReason::Other(String::new())
+        }
+    }
+
+    impl From<String> for FinishReason {
+        fn from(reason: String) -> Self {
+            match reason.as_str() {
+                "stop" => FinishReason::Stop,
+                "length" => FinishReason::Length,
+                "content_filter" => FinishReason::ContentFilter,
+                "function_call" => FinishReason::FunctionCall,
+                "tool_calls" => FinishReason::ToolCalls,
+                _ => FinishReason::Other(reason),
+            }
+        }
+    }
+
+    impl From<&str> for FinishReason {
+        fn from(reason: &str) -> Self {
+            reason.to_string().into()
+        }
+    }
+
+    impl PartialEq<str> for FinishReason {
+        fn eq(&self, other: &str) -> bool {
+            self.as_str() == other
+        }
+    }
+
+    impl PartialEq<&str> for FinishReason {
+        fn eq(&self, other: &&str) -> bool {
+            self.as_str() == *other
+        }
+    }
+
+    impl std::fmt::Display for FinishReason {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str(self.as_str())
+        }
+    }
+
+    impl Serialize for FinishReason {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.serialize_str(self.as_str())
+        }
+    }
+
+    impl<'de> Deserialize<'de> for FinishReason {
+        fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            Ok(String::deserialize(deserializer)?.into())
+        }
     }
 
     impl std::fmt::Display for Choice {
@@ -176,28 +869,147 @@ pub mod api {
         pub text_offset: Vec<u64>,
     }
 
-    /// Error response object from the server
-    #[derive(Deserialize, Debug, Eq, PartialEq, Clone, Error)]
-    pub struct ErrorMessage {
+    /// The log probabilities of the tokens of a chat answer, see `ChatArgs::logprobs`
+    #[derive(Deserialize, Debug, Clone, PartialEq)]
+    pub struct ChatLogProbs {
+        /// The tokens of the message content
+        #[serde(default)]
+        pub content: Option<Vec<TokenLogProb>>,
+        /// The tokens of the refusal, when the model refused to answer
+        #[serde(default)]
+        pub refusal: Option<Vec<TokenLogProb>>,
+    }
+
+    /// A token of a chat answer, and the alternatives the model considered
+    #[derive(Deserialize, Debug, Clone, PartialEq)]
+    pub struct TokenLogProb {
+        pub token: String,
+        /// Natural logarithm of the probability of the token
+        pub logprob: f64,
+        /// The UTF-8 bytes of the token, to rebuild characters split across tokens
+        #[serde(default)]
+        pub bytes: Option<Vec<u8>>,
+        /// The most likely tokens at this position, as many as `ChatArgs::top_logprobs`
+        #[serde(default)]
+        pub top_logprobs: Vec<TopLogProb>,
+    }
+
+    /// An alternative to a `TokenLogProb`
+    #[derive(Deserialize, Debug, Clone, PartialEq)]
+    pub struct TopLogProb {
+        pub token: String,
+        pub logprob: f64,
+        #[serde(default)]
+        pub bytes: Option<Vec<u8>>,
+    }
+
+    /// An error the API returned instead of a response
+    ///
+    /// # Example
+    /// ```
+    /// # use openai_api::{api::ApiError, Error};
+    /// fn should_shorten(err: &Error) -> bool {
+    ///     matches!(err, Error::Api(err) if err.is_context_length_exceeded())
+    /// }
+    /// ```
+    #[derive(Deserialize, Debug, Default, Eq, PartialEq, Clone, Error)]
+    pub struct ApiError {
         pub message: String,
-        #[serde(rename = "code")]
-        pub status_code: String,
+        /// The category of the error, its `type`, e.g. `invalid_request_error`
+        #[serde(rename = "type", default)]
+        pub kind: Option<String>,
+        /// The parameter of the request at fault, if any
+        #[serde(default)]
+        pub param: Option<String>,
+        /// What went wrong, e.g. `context_length_exceeded`. OpenRouter sends the HTTP
+        /// status here.
+        #[serde(default, deserialize_with = "error_code")]
+        pub code: Option<String>,
+        /// The HTTP status of the response, unless the error came mid-stream
+        #[serde(skip)]
+        pub status: Option<reqwest::StatusCode>,
+        /// The id the API gave the request, to quote when reporting a problem
+        #[serde(skip)]
+        pub request_id: Option<String>,
+        /// Details some providers add, e.g. OpenRouter's upstream provider error
+        #[serde(default)]
+        pub metadata: Option<serde_json::Value>,
+        /// How long the server asked to wait before retrying, from the `Retry-After`
+        /// headers of the response
+        #[serde(skip)]
+        pub retry_after: Option<std::time::Duration>,
+    }
+
+    impl ApiError {
+        /// Whether too many requests or tokens were sent lately, and waiting helps. Running
+        /// out of quota isn't a rate limit, see `is_quota_exceeded`.
+        #[must_use]
+        pub fn is_rate_limited(&self) -> bool {
+            self.code.as_deref() == Some("rate_limit_exceeded")
+                || (self.status == Some(reqwest::StatusCode::TOO_MANY_REQUESTS)
+                    && !self.is_quota_exceeded())
+        }
+
+        /// Whether the account ran out of credits, and waiting doesn't help
+        #[must_use]
+        pub fn is_quota_exceeded(&self) -> bool {
+            self.code.as_deref() == Some("insufficient_quota")
+                || self.kind.as_deref() == Some("insufficient_quota")
+        }
+
+        /// Whether the request was too long for the model's context, see
+        /// `ChatArgs::fit_to_context`
+        #[must_use]
+        pub fn is_context_length_exceeded(&self) -> bool {
+            self.code.as_deref() == Some("context_length_exceeded")
+        }
+
+        /// Whether the API key was rejected
+        #[must_use]
+        pub fn is_invalid_api_key(&self) -> bool {
+            self.code.as_deref() == Some("invalid_api_key")
+                || self.status == Some(reqwest::StatusCode::UNAUTHORIZED)
+        }
+
+        /// Whether the server failed, with a 5xx status
+        #[must_use]
+        pub fn is_server_error(&self) -> bool {
+            self.status.is_some_and(|status| status.is_server_error())
+        }
+    }
+
+    /// Deserializes an error code that may be a string, a number (OpenRouter) or `null`
+    fn error_code<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        Ok(match serde_json::Value::deserialize(deserializer)? {
+            serde_json::Value::Null => None,
+            serde_json::Value::String(code) => Some(code),
+            code => Some(code.to_string()),
+        })
     }
 
-    impl std::fmt::Display for ErrorMessage {
+    impl std::fmt::Display for ApiError {
         fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-            write!(
-                f,
-                "err code: {}, err msg: {})",
-                self.status_code, self.message
-            )
+            if let Some(status) = self.status {
+                write!(f, "{} ", status)?;
+            }
+            if let Some(code) = self.code.as_ref().or(self.kind.as_ref()) {
+                write!(f, "({}) ", code)?;
+            }
+            f.write_str(&self.message)?;
+            if let Some(request_id) = &self.request_id {
+                write!(f, " [request {}]", request_id)?;
+            }
+            Ok(())
         }
     }
 
     /// API-level wrapper used in deserialization
     #[derive(Deserialize, Debug)]
     pub(crate) struct ErrorWrapper {
-        pub error: ErrorMessage,
+        pub error: ApiError,
     }
 
     #[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
@@ -208,6 +1020,12 @@ pub mod api {
         User,
         #[serde(rename = "assistant")]
         Assistant,
+        /// The result of a function call, see `ChatFormat::function_result`
+        #[serde(rename = "function")]
+        Function,
+        /// The result of a tool call, see `ChatFormat::tool_result`
+        #[serde(rename = "tool")]
+        Tool,
     }
 
     /// The main input is the messages parameter. Messages must be an array of message objects, where each object has a role (either “system”, “user”, or “assistant”) and content (the content of the message). Conversations can be as short as 1 message or fill many pages.
@@ -229,13 +1047,66 @@ pub mod api {
         ///
         #[serde(rename = "role")]
         pub role: ChatRole,
-        #[serde(rename = "content")]
-        pub content: String,
+        /// Text, or text and images for vision models. Empty in the assistant messages that
+        /// call a function.
+        #[serde(rename = "content", default, deserialize_with = "lenient")]
+        pub content: ChatContent,
+        /// The function whose result a `ChatRole::Function` message holds
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub name: Option<String>,
+        /// The function an assistant message calls, see `ChatArgs::functions`
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub function_call: Option<FunctionCall>,
+        /// The tools an assistant message calls, see `ChatArgs::tools`
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub tool_calls: Option<Vec<ToolCall>>,
+        /// The tool call whose result a `ChatRole::Tool` message holds
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub tool_call_id: Option<String>,
     }
 
     impl ChatFormat {
         pub fn new(role: ChatRole, content: String) -> Self {
-            Self { role, content }
+            Self {
+                role,
+                content: content.into(),
+                name: None,
+                function_call: None,
+                tool_calls: None,
+                tool_call_id: None,
+            }
+        }
+
+        /// A message mixing text and images, e.g. a user question about an image
+        pub fn with_parts(role: ChatRole, parts: Vec<ContentPart>) -> Self {
+            Self {
+                content: parts.into(),
+                ..Self::new(role, String::new())
+            }
+        }
+
+        /// Parses the text of the message as JSON
+        ///
+        /// # Errors
+        /// - `Error::Json` if the text isn't a `T`
+        pub fn parse_json<T: serde::de::DeserializeOwned>(&self) -> crate::Result<T> {
+            Ok(serde_json::from_str(&self.content.text())?)
+        }
+
+        /// A message returning the result of the function `name` to the model
+        pub fn function_result(name: impl Into<String>, content: impl Into<String>) -> Self {
+            Self {
+                name: Some(name.into()),
+                ..Self::new(ChatRole::Function, content.into())
+            }
+        }
+
+        /// A message returning the result of the tool call `tool_call_id` to the model
+        pub fn tool_result(tool_call_id: impl Into<String>, content: impl Into<String>) -> Self {
+            Self {
+                tool_call_id: Some(tool_call_id.into()),
+                ..Self::new(ChatRole::Tool, content.into())
+            }
         }
     }
 
@@ -245,8 +1116,19 @@ pub mod api {
         }
     }
 
-    #[derive(Serialize, Debug, Builder, Clone)]
-    #[builder(pattern = "immutable")]
+    /// Arguments of a chat request.
+    ///
+    /// Besides the builder, args can be deserialized, e.g. from a preset in a config file.
+    /// Fields missing from the input take their default values.
+    ///
+    /// # Example
+    /// ```
+    /// # use openai_api::api::ChatArgs;
+    /// let args: ChatArgs = serde_json::from_str(r#"{"model": "gpt-4", "max_tokens": 256}"#).unwrap();
+    /// ```
+    #[derive(Serialize, Deserialize, Debug, Builder, Clone)]
+    #[builder(pattern = "immutable")]
+    #[serde(default)]
     pub struct ChatArgs {
         /// The id of the model to use for this request
         /// ID of the model to use. Currently, only gpt-3.5-turbo and gpt-3.5-turbo-0301 are supported.
@@ -260,14 +1142,17 @@ pub mod api {
         pub(super) model: String,
         /// The messages to generate chat completions for.
         ///
+        /// They are shared rather than copied when the args or their builder are cloned, so
+        /// long conversations are cheap to build requests from. Pass an `Arc<[ChatFormat]>`
+        /// to share them with other args as well.
         ///
         /// # Example
         /// ```
-        /// # use openai_api::api::{ChatArgs,ChatRole,MsgFormat};
-        /// ChatArgs::builder().messages(vec![MsgFormat{role: ChatRole::System, content: "You are a helpful assistant.".into()}]);
+        /// # use openai_api::api::{ChatArgs,ChatRole,ChatFormat};
+        /// ChatArgs::builder().messages(vec![ChatFormat::new(ChatRole::System, "You are a helpful assistant.".into())]);
         /// ```
-        #[builder(default)]
-        messages: Vec<ChatFormat>,
+        #[builder(setter(into), default)]
+        pub(super) messages: std::sync::Arc<[ChatFormat]>,
         /// Maximum number of tokens to complete.
         /// The maximum number of tokens allowed for the generated answer. By default, the number of tokens the model can return will be (4096 - prompt tokens).
         ///
@@ -277,6 +1162,7 @@ pub mod api {
         /// ChatArgs::builder().max_tokens(64);
         /// ```
         #[builder(setter(strip_option), default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
         max_tokens: Option<u64>,
         /// What sampling temperature to use.
         ///
@@ -298,41 +1184,183 @@ pub mod api {
         /// # Ok::<(), _>(())
         /// # }
         /// ```
-        #[builder(default = "1.0")]
-        temperature: f64,
-        #[builder(default = "1.0")]
-        top_p: f64,
+        #[builder(setter(strip_option), default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub(super) temperature: Option<f64>,
+        #[builder(setter(strip_option), default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        top_p: Option<f64>,
         /// How many chat completion choices to generate for each input message.
         ///
         /// Defaults to 1
         ///
-        #[builder(default = "1")]
-        n: u64,
+        #[builder(setter(strip_option), default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        n: Option<u64>,
         /// Up to 4 sequences where the API will stop generating further tokens.
         ///
         /// Defaults to null
         ///
         #[builder(setter(strip_option), default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
         stop: Option<Vec<String>>,
         /// Number between -2.0 and 2.0. Positive values penalize new tokens based on whether they appear in the text so far, increasing the model's likelihood to talk about new topics.
         ///
         /// Defaults to 0
         ///
-        #[builder(default = "0.0")]
-        presence_penalty: f64,
+        #[builder(setter(strip_option), default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        presence_penalty: Option<f64>,
         /// Number between -2.0 and 2.0. Positive values penalize new tokens based on their existing frequency in the text so far, decreasing the model's likelihood to repeat the same line verbatim.
         ///
         /// Defaults to 0
         ///
-        #[builder(default = "0.0")]
-        frequency_penalty: f64,
+        #[builder(setter(strip_option), default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        frequency_penalty: Option<f64>,
         /// Modify the likelihood of specified tokens appearing in the completion.
         ///
         /// Accepts a json object that maps tokens (specified by their token ID in the tokenizer) to an associated bias value from -100 to 100. Mathematically, the bias is added to the logits generated by the model prior to sampling. The exact effect will vary per model, but values between -1 and 1 should decrease or increase likelihood of selection; values like -100 or 100 should result in a ban or exclusive selection of the relevant token.
         ///
         /// Defaults to null
         #[builder(default)]
+        #[serde(skip_serializing_if = "HashMap::is_empty")]
         logit_bias: HashMap<String, f64>,
+        /// OpenRouter: models to fall back to, in order, if `model` is unavailable or
+        /// refuses the request
+        ///
+        /// # Example
+        /// ```
+        /// # use openai_api::api::ChatArgs;
+        /// ChatArgs::builder().models(vec!["anthropic/claude-3.5-sonnet".into()]);
+        /// ```
+        #[builder(setter(strip_option), default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        models: Option<Vec<String>>,
+        /// OpenRouter: how to pick the provider serving the request
+        #[builder(setter(strip_option), default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        provider: Option<crate::openrouter::ProviderPreferences>,
+        /// Sampling parameters of self-hosted backends, see `BackendSampling`
+        ///
+        /// # Example
+        /// ```
+        /// # use openai_api::api::{BackendSampling, ChatArgs};
+        /// ChatArgs::builder().backend_sampling(BackendSampling {
+        ///     top_k: Some(40),
+        ///     ..BackendSampling::default()
+        /// });
+        /// ```
+        #[builder(default)]
+        #[serde(flatten)]
+        backend_sampling: BackendSampling,
+        /// Whether OpenAI keeps the completion, to be fetched later, see
+        /// `Client::stored_completion`
+        #[builder(setter(strip_option), default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        store: Option<bool>,
+        /// Up to 16 key-value pairs tagging a stored completion, to filter listings by
+        ///
+        /// # Example
+        /// ```
+        /// # use openai_api::api::ChatArgs;
+        /// ChatArgs::builder()
+        ///     .store(true)
+        ///     .metadata([("user".to_string(), "alice".to_string())].into());
+        /// ```
+        #[builder(setter(strip_option), default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        metadata: Option<HashMap<String, String>>,
+        /// Functions the model may call instead of answering with a message
+        ///
+        /// # Example
+        /// ```
+        /// # use openai_api::api::{ChatArgs, FunctionCallMode, FunctionSpec};
+        /// ChatArgs::builder()
+        ///     .functions(vec![FunctionSpec::new("get_time", serde_json::json!({"type": "object"}))])
+        ///     .function_call(FunctionCallMode::Named("get_time".into()));
+        /// ```
+        #[builder(setter(strip_option), default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        functions: Option<Vec<FunctionSpec>>,
+        /// Whether the model calls one of `functions`. The API defaults to `Auto` when
+        /// there are functions.
+        #[builder(setter(strip_option), default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        function_call: Option<FunctionCallMode>,
+        /// Tools the model may call instead of answering with a message. Supersedes
+        /// `functions`; the model may call several tools in one answer.
+        ///
+        /// # Example
+        /// ```
+        /// # use openai_api::api::{ChatArgs, FunctionSpec, ToolChoice};
+        /// ChatArgs::builder()
+        ///     .tools(vec![FunctionSpec::new("get_time", serde_json::json!({"type": "object"})).into()])
+        ///     .tool_choice(ToolChoice::Required);
+        /// ```
+        #[builder(setter(strip_option), default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        tools: Option<Vec<ChatTool>>,
+        /// Whether the model calls one of `tools`
+        #[builder(setter(strip_option), default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        tool_choice: Option<ToolChoice>,
+        /// The format of the answer: text by default, or JSON. See `ChatArgs::json_mode`,
+        /// and `ChatAnswer::parse_json` to read the answer.
+        ///
+        /// # Example
+        /// ```
+        /// # use openai_api::api::{ChatArgs, ResponseFormat};
+        /// ChatArgs::builder().response_format(ResponseFormat::json_schema(
+        ///     "answer",
+        ///     serde_json::json!({"type": "object", "properties": {}, "additionalProperties": false}),
+        /// ));
+        /// ```
+        #[builder(setter(into, strip_option), default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub(super) response_format: Option<ResponseFormat>,
+        /// Seed of the sampling, for repeated requests to return the same answers as far
+        /// as the API can. Compare the `system_fingerprint` of the answers to know when the
+        /// backend changed, and answers may differ despite the seed.
+        ///
+        /// # Example
+        /// ```
+        /// # use openai_api::api::ChatArgs;
+        /// ChatArgs::builder().seed(42).temperature(0.0);
+        /// ```
+        #[builder(setter(strip_option), default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        seed: Option<i64>,
+        /// Whether the answer comes with the log probabilities of its tokens, see
+        /// `ChatChoice::logprobs`
+        ///
+        /// # Example
+        /// ```
+        /// # use openai_api::api::ChatArgs;
+        /// ChatArgs::builder().logprobs(true).top_logprobs(3);
+        /// ```
+        #[builder(default)]
+        #[serde(skip_serializing_if = "std::ops::Not::not")]
+        logprobs: bool,
+        /// How many of the most likely alternatives to each token come with it, at most 20.
+        /// Needs `logprobs`.
+        #[builder(setter(strip_option), default)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        top_logprobs: Option<u8>,
+        /// Parameters the crate doesn't model yet, sent as they are next to the others,
+        /// e.g. those of a new API release. They must not repeat the parameters set on
+        /// the builder.
+        ///
+        /// # Example
+        /// ```
+        /// # use openai_api::api::ChatArgs;
+        /// ChatArgs::builder().extra(
+        ///     [("service_tier".to_string(), "flex".into())].into_iter().collect(),
+        /// );
+        /// ```
+        #[builder(default)]
+        #[serde(flatten)]
+        extra: serde_json::Map<String, serde_json::Value>,
     }
 
     impl ChatArgs {
@@ -341,13 +1369,28 @@ pub mod api {
         pub fn builder() -> ChatArgsBuilder {
             ChatArgsBuilder::default()
         }
+
+        /// Has the model answer with a JSON object, see `ResponseFormat::JsonObject`
+        #[must_use]
+        pub fn json_mode(mut self) -> Self {
+            self.response_format = Some(ResponseFormat::JsonObject);
+            self
+        }
+    }
+
+    impl Default for ChatArgs {
+        fn default() -> Self {
+            ChatArgsBuilder::default()
+                .build()
+                .expect("default should build")
+        }
     }
 
     impl From<Vec<(ChatRole, String)>> for ChatArgs {
         fn from(msg: Vec<(ChatRole, String)>) -> Self {
             let msg = msg
                 .into_iter()
-                .map(|(role, content)| ChatFormat { role, content })
+                .map(|(role, content)| ChatFormat::new(role, content))
                 .collect();
             Self {
                 messages: msg,
@@ -358,6 +1401,129 @@ pub mod api {
         }
     }
 
+    impl ChatArgs {
+        /// Rough number of tokens the request uses: its messages and, when capped, its
+        /// longest answers
+        pub(crate) fn estimated_tokens(&self) -> u64 {
+            self.prompt_tokens() + self.max_tokens.unwrap_or(0) * self.n.unwrap_or(1)
+        }
+
+        /// Size of the context each choice needs: the messages, and room for `max_tokens`.
+        /// Messages are counted as `ContextWindow` does with the `tiktoken` feature, and
+        /// otherwise with `min_tokens`, so that requests aren't rejected on an estimate.
+        pub(crate) fn context_tokens(&self) -> u64 {
+            #[cfg(feature = "tiktoken")]
+            let prompt = crate::tokens::chat_token_estimate(&self.model, &self.messages);
+            #[cfg(not(feature = "tiktoken"))]
+            let prompt: usize = self
+                .messages
+                .iter()
+                .map(|m| crate::min_tokens(&m.content.text()) + m.content.image_tokens())
+                .sum();
+            prompt as u64 + self.max_tokens.unwrap_or(0)
+        }
+
+        fn prompt_tokens(&self) -> u64 {
+            let prompt: usize = self
+                .messages
+                .iter()
+                .map(|m| crate::estimate_tokens(&m.content.text()) + m.content.image_tokens())
+                .sum();
+            prompt as u64
+        }
+
+        /// Drops the oldest messages, other than system messages, until the conversation
+        /// fits in a context of `model_limit` tokens with room for `max_tokens`, and returns
+        /// how many were dropped. See [`ContextWindow::trim`].
+        ///
+        /// The limit of a known model is `models::context_length(&args.model)`.
+        ///
+        /// [`ContextWindow::trim`]: crate::context::ContextWindow::trim
+        pub fn fit_to_context(&mut self, model_limit: u64) -> usize {
+            let window = crate::context::ContextWindow::new(&self.model, model_limit)
+                .reserve(self.max_tokens.unwrap_or(0));
+            if window.fits(&self.messages) {
+                return 0;
+            }
+            let mut messages = self.messages.to_vec();
+            let dropped = window.trim(&mut messages);
+            self.messages = messages.into();
+            dropped
+        }
+
+        /// Checks the arguments locally, without calling the API: parameter ranges,
+        /// whether the model is a chat model, and whether the messages and `max_tokens`
+        /// fit in the model's context. Without the `tiktoken` feature, the messages are
+        /// counted low, so that only conversations sure not to fit are flagged.
+        ///
+        /// # Errors
+        /// - `Error::BadArguments` listing every problem found
+        pub fn validate(&self) -> crate::Result<()> {
+            let mut problems = Problems::default();
+            problems.check(!self.messages.is_empty(), || {
+                "at least one message is required".into()
+            });
+            problems.check(
+                self.messages
+                    .iter()
+                    .all(|m| m.role == ChatRole::User || m.content.images().next().is_none()),
+                || "only user messages can hold images".into(),
+            );
+            if let Some(spec) = crate::models::lookup(&self.model) {
+                problems.check(
+                    spec.vision
+                        || self
+                            .messages
+                            .iter()
+                            .all(|m| m.content.images().next().is_none()),
+                    || format!("{} doesn't accept images", self.model),
+                );
+            }
+            if let Some(format) = &self.response_format {
+                format.problems(&mut problems);
+            }
+            problems.check(
+                self.response_format != Some(ResponseFormat::JsonObject)
+                    || self
+                        .messages
+                        .iter()
+                        .any(|m| m.content.text().to_lowercase().contains("json")),
+                || "JSON mode needs a message asking for JSON".into(),
+            );
+            problems.sampling(self.temperature, self.top_p, self.n);
+            if let Some(top_logprobs) = self.top_logprobs {
+                problems.range("top_logprobs", top_logprobs.into(), 0.0, 20.0);
+                problems.check(self.logprobs, || "top_logprobs needs logprobs".into());
+            }
+            problems.check(self.max_tokens != Some(0), || {
+                "max_tokens must be at least 1".into()
+            });
+            problems.penalties(self.presence_penalty, self.frequency_penalty);
+            problems.logit_bias(&self.logit_bias);
+            problems.stop(self.stop.as_deref());
+            self.backend_sampling.validate(&mut problems);
+            if !self.extra.is_empty() {
+                let set = Self {
+                    extra: serde_json::Map::new(),
+                    ..self.clone()
+                };
+                problems.extra(&self.extra, &set);
+            }
+            crate::functions::check(
+                &mut problems,
+                self.functions.as_deref(),
+                self.function_call.as_ref(),
+            );
+            crate::functions::check_tools(
+                &mut problems,
+                self.tools.as_deref(),
+                self.tool_choice.as_ref(),
+            );
+            problems.model(&self.model, ModelKind::Chat, self.context_tokens());
+            problems.into_result()
+        }
+    }
+
     impl TryFrom<ChatArgsBuilder> for ChatArgs {
         type Error = ChatArgsBuilderError;
 
@@ -369,11 +1535,40 @@ pub mod api {
     #[derive(Deserialize, Debug, Clone)]
     pub struct ChatAnswer {
         /// Completion unique identifier
+        #[serde(default, deserialize_with = "lenient")]
         pub id: String,
         /// Unix timestamp when the completion was generated
+        #[serde(default, deserialize_with = "lenient")]
         pub created: u64,
         /// List of completions generated by the model
         pub choices: Vec<ChatChoice>,
+        /// The tokens billed for the completion
+        #[serde(default)]
+        pub usage: Option<Usage>,
+        /// The configuration of the backend that generated the completion. Answers to
+        /// requests with the same `seed` may only differ when it changes.
+        #[serde(default)]
+        pub system_fingerprint: Option<String>,
+        /// The fields the crate doesn't model, e.g. those of a new API release or of a
+        /// compatible server
+        #[serde(flatten)]
+        pub extra: serde_json::Map<String, serde_json::Value>,
+    }
+
+    impl ChatAnswer {
+        /// Parses the answer of the first choice as JSON, e.g. in JSON mode
+        ///
+        /// # Errors
+        /// - `Error::Json` if there is no choice, or its content isn't a `T`
+        pub fn parse_json<T: serde::de::DeserializeOwned>(&self) -> crate::Result<T> {
+            match self.choices.first() {
+                Some(choice) => choice.message.parse_json(),
+                None => Err(<serde_json::Error as serde::de::Error>::custom(
+                    "the answer has no choices",
+                )
+                .into()),
+            }
+        }
     }
 
     impl std::fmt::Display for ChatAnswer {
@@ -382,15 +1577,87 @@ pub mod api {
         }
     }
 
+    /// A chunk of a streamed chat completion, see `Client::chat_stream`
+    #[derive(Deserialize, Debug, Clone)]
+    pub struct ChatDelta {
+        /// Completion unique identifier, the same in every chunk
+        #[serde(default, deserialize_with = "lenient")]
+        pub id: String,
+        /// Unix timestamp when the completion was generated
+        #[serde(default, deserialize_with = "lenient")]
+        pub created: u64,
+        /// Exact model type and version used for the completion
+        #[serde(default, deserialize_with = "lenient")]
+        pub model: String,
+        /// The parts of the choices generated since the previous chunk
+        #[serde(default)]
+        pub choices: Vec<ChatDeltaChoice>,
+        /// The configuration of the backend, see `ChatAnswer::system_fingerprint`
+        #[serde(default)]
+        pub system_fingerprint: Option<String>,
+    }
+
+    impl ChatDelta {
+        /// The content added to the first choice by this chunk, if any
+        #[must_use]
+        pub fn content(&self) -> Option<&str> {
+            self.choices.first()?.delta.content.as_deref()
+        }
+    }
+
+    impl std::fmt::Display for ChatDelta {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str(self.content().unwrap_or_default())
+        }
+    }
+
+    /// The part of a choice in a `ChatDelta`
+    #[derive(Deserialize, Debug, Clone)]
+    pub struct ChatDeltaChoice {
+        #[serde(default, deserialize_with = "lenient")]
+        pub index: u64,
+        pub delta: ChatFormatDelta,
+        /// The log probabilities of the tokens of the chunk, if requested
+        #[serde(default)]
+        pub logprobs: Option<ChatLogProbs>,
+        /// Why the completion ended, in the last chunk of the choice only
+        #[serde(default)]
+        pub finish_reason: Option<FinishReason>,
+    }
+
+    /// The part of a message in a `ChatDeltaChoice`: the role comes in the first chunk, the
+    /// content and the calls of functions or tools in pieces
+    #[derive(Deserialize, Debug, Clone, Default, PartialEq)]
+    pub struct ChatFormatDelta {
+        #[serde(default)]
+        pub role: Option<ChatRole>,
+        #[serde(default)]
+        pub content: Option<String>,
+        /// A piece of the function call of the message, see `ChatArgs::functions`
+        #[serde(default)]
+        pub function_call: Option<FunctionCallDelta>,
+        /// Pieces of the tool calls of the message, see `ChatArgs::tools`
+        #[serde(default)]
+        pub tool_calls: Option<Vec<ToolCallDelta>>,
+    }
+
     /// A single completion result
     #[derive(Deserialize, Debug, Clone)]
     pub struct ChatChoice {
         /// The text of the completion. Will contain the prompt if echo is True.
         pub message: ChatFormat,
         /// Offset in the result where the completion began. Useful if using echo.
+        #[serde(default, deserialize_with = "lenient")]
         pub index: u64,
+        /// If requested with `ChatArgs::logprobs`, the log probabilities of the tokens
+        #[serde(default)]
+        pub logprobs: Option<ChatLogProbs>,
         /// Why the completion ended when it did
-        pub finish_reason: String,
+        #[serde(default, deserialize_with = "lenient")]
+        pub finish_reason: FinishReason,
+        /// The fields of the choice the crate doesn't model
+        #[serde(flatten)]
+        pub extra: serde_json::Map<String, serde_json::Value>,
     }
 
     impl std::fmt::Display for ChatChoice {
@@ -402,47 +1669,426 @@ pub mod api {
 
 #[derive(Error, Debug)]
 pub enum Error {
-    /// An error returned by the API itself
+    /// An error returned by the API itself, boxed to keep `Result`s small
     #[error("API returned an Error: {0}")]
-    Api(#[from] api::ErrorMessage),
+    Api(Box<api::ApiError>),
     /// An error the client discovers before talking to the API
-    // #[error("Bad arguments: {0}")]
-    // BadArguments(String),
-
+    #[error("Bad arguments: {0}")]
+    BadArguments(String),
+    /// An API key that can't be sent in a header, e.g. with non-ASCII characters or line
+    /// breaks. The key itself isn't included.
+    #[error("Invalid API key: it isn't a valid header value")]
+    InvalidToken,
+    /// A client setting that can't be used, e.g. an invalid base URL, proxy or header
+    #[error("Invalid configuration: {0}")]
+    InvalidConfig(String),
     #[error("Build Client arguments: {0}")]
-    AsyncProtocol(#[from] reqwest::Error),
+    AsyncProtocol(reqwest::Error),
+    /// A request that took longer than its timeout, see `options::RequestOptions::timeout`
+    /// and `Client::with_timeout`
+    #[error("Request timed out: {0}")]
+    Timeout(reqwest::Error),
+    /// Data that couldn't be (de)serialized, e.g. a malformed saved session
+    #[error("Invalid JSON: {0}")]
+    Json(#[from] serde_json::Error),
+    /// A request that passed validation in dry-run mode, see `Client::with_dry_run`
+    #[error("Dry run: {endpoint} request not sent")]
+    DryRun {
+        /// The endpoint the request is for
+        endpoint: String,
+        /// The JSON body that would have been sent
+        body: String,
+    },
+    /// A request that doesn't fit in its model's context, see `Client::with_context_guard`
+    #[error("Context length exceeded: the request needs about {needed} tokens, but the model has {available}")]
+    ContextLengthExceeded {
+        /// Estimated tokens of the prompt, plus the tokens requested for the completion
+        needed: u64,
+        /// Context length of the model
+        available: u64,
+    },
+    /// Base64 data of the API that couldn't be decoded, e.g. an image
+    #[error("Invalid base64: {0}")]
+    Base64(#[from] base64::DecodeError),
+    /// A file that couldn't be read or written, e.g. when saving an image
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    /// A cassette that couldn't be loaded or saved, or has no recording of a request
+    #[cfg(feature = "vcr")]
+    #[error("Cassette error: {0}")]
+    Cassette(String),
+    /// A `mock::MockClient` call with no canned response queued
+    #[cfg(feature = "mock")]
+    #[error("Mock client: {0}")]
+    Mock(String),
+    /// A structured answer that isn't the requested type, see `Client::chat_structured`
+    #[cfg(feature = "schemars")]
+    #[error("Invalid structured output: {source}")]
+    InvalidOutput {
+        /// The answer of the model
+        content: String,
+        source: serde_json::Error,
+    },
+    /// Credentials rejected by `Client::validate`, or an API it can't reach
+    #[error("Invalid credentials ({failure:?}): {message}")]
+    Credentials {
+        /// What is wrong
+        failure: CredentialFailure,
+        /// Explanation from the API or the HTTP client
+        message: String,
+    },
+    /// A response larger than the client accepts, see `Client::with_max_response_size`
+    #[error("Response too large: more than {limit} bytes")]
+    ResponseTooLarge {
+        /// The maximum size of a response, in bytes
+        limit: usize,
+    },
+    /// The `queue::RequestQueue` stopped before running the job
+    #[error("The request queue was closed")]
+    QueueClosed,
+    /// An error reported by a `session::ConversationStore` implementation
+    #[error("Conversation store error: {0}")]
+    Store(Box<dyn std::error::Error + Send + Sync>),
+    /// A `poll::PollConfig` ran out of time before the polled object was done
+    #[error("Gave up waiting after {waited:?}")]
+    PollTimeout {
+        /// How long the object was polled for
+        waited: std::time::Duration,
+    },
+}
+
+impl From<api::ApiError> for Error {
+    fn from(err: api::ApiError) -> Self {
+        Error::Api(Box::new(err))
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(err: reqwest::Error) -> Self {
+        if err.is_timeout() {
+            Error::Timeout(err)
+        } else {
+            Error::AsyncProtocol(err)
+        }
+    }
+}
+
+impl From<std::convert::Infallible> for Error {
+    fn from(never: std::convert::Infallible) -> Self {
+        match never {}
+    }
+}
+
+impl From<api::CompletionArgsBuilderError> for Error {
+    fn from(err: api::CompletionArgsBuilderError) -> Self {
+        Error::BadArguments(err.to_string())
+    }
+}
+
+impl From<api::ChatArgsBuilderError> for Error {
+    fn from(err: api::ChatArgsBuilderError) -> Self {
+        Error::BadArguments(err.to_string())
+    }
+}
+
+impl From<embeddings::EmbeddingsArgsBuilderError> for Error {
+    fn from(err: embeddings::EmbeddingsArgsBuilderError) -> Self {
+        Error::BadArguments(err.to_string())
+    }
+}
+
+/// Why `Client::validate` failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CredentialFailure {
+    /// The API key is wrong, revoked or missing
+    InvalidKey,
+    /// The key doesn't belong to the organization the requests name
+    WrongOrganization,
+    /// The key is valid, but not allowed to list models
+    InsufficientPermissions,
+    /// The API can't be reached: network, DNS, TLS or proxy trouble
+    Unreachable,
 }
 
 /// Client object. Must be constructed to talk to the API.
+///
+/// Cloning a client is cheap, so one configured client can be handed to many tasks: clones
+/// share its settings, its connection pool, its response cache and the metadata of the
+/// last response. Changing the settings of a clone with the `with_*` methods leaves the
+/// other clones as they are.
 #[derive(Debug, Clone)]
 pub struct Client {
+    inner: std::sync::Arc<ClientInner>,
+}
+
+/// The settings and state of a `Client`, shared by its clones
+#[derive(Debug, Clone)]
+struct ClientInner {
     client: reqwest::Client,
-    base_url: String,
+    /// Parsed once, endpoints are joined to it
+    base_url: reqwest::Url,
+    cache: Option<cache::ResponseCache>,
+    dry_run: bool,
+    context_guard: bool,
+    headers: reqwest::header::HeaderMap,
+    omitted_fields: std::sync::Arc<[String]>,
+    /// Query parameters added to every request, e.g. Azure's `api-version`
+    query: Vec<(String, String)>,
+    last_response_meta: std::sync::Arc<std::sync::Mutex<Option<response::ResponseMeta>>>,
+    /// Size in bytes beyond which responses are abandoned
+    max_response_size: Option<usize>,
+    /// Limit of each request, see `options::RequestOptions::timeout`
+    #[cfg(not(target_arch = "wasm32"))]
+    request_timeout: Option<std::time::Duration>,
+    /// Budget of requests and tokens per minute, see `Client::with_throttle`
+    throttle: Option<throttle::Throttle>,
+    /// How requests failing transiently are retried, see `Client::with_retry_policy`
+    retry: retry::RetryPolicy,
+    /// Size in bytes from which request bodies are gzipped
+    #[cfg(feature = "gzip")]
+    gzip_from: Option<usize>,
+    transport: transport::Transport,
+    #[cfg(feature = "vcr")]
+    cassette: Option<std::sync::Arc<vcr::Cassette>>,
+    /// Sends the requests instead of `client`, see `Client::with_http_transport`
+    http: Option<std::sync::Arc<dyn transport::HttpTransport>>,
 }
 
 impl Client {
-    // Creates a new `Client` given an api token
-    #[must_use]
+    /// Creates a new `Client` given an api token
+    ///
+    /// # Errors
+    /// - `Error::InvalidToken` if the token can't be sent in a header
+    /// - `Error::AsyncProtocol` if the HTTP client can't be created
     pub fn new(token: &str) -> Result<Self> {
-        let mut headers = reqwest::header::HeaderMap::new();
-        headers.insert(
-            "Authorization",
-            reqwest::header::HeaderValue::from_str(&format!("Bearer {}", token))
-                .expect("invalid token"),
-        );
+        Self::with_auth_header("Authorization", &format!("Bearer {}", token))
+    }
 
+    /// Private helper creating a client authenticating with the `name` header
+    fn with_auth_header(name: &'static str, value: &str) -> Result<Self> {
+        // Headers may hold some non-ASCII bytes, but servers wouldn't read them as sent
+        if !value.is_ascii() {
+            return Err(Error::InvalidToken);
+        }
+        let mut value =
+            reqwest::header::HeaderValue::from_str(value).map_err(|_| Error::InvalidToken)?;
+        value.set_sensitive(true);
+        let mut transport = transport::Transport::default();
+        transport.auth.insert(name, value);
+
+        let inner = ClientInner {
+            client: transport.build()?,
+            base_url: reqwest::Url::parse("https://api.openai.com/v1/").expect("valid URL"),
+            cache: None,
+            dry_run: false,
+            context_guard: false,
+            headers: reqwest::header::HeaderMap::new(),
+            omitted_fields: std::sync::Arc::new([]),
+            query: Vec::new(),
+            last_response_meta: std::sync::Arc::default(),
+            max_response_size: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            request_timeout: None,
+            throttle: None,
+            retry: retry::RetryPolicy::none(),
+            #[cfg(feature = "gzip")]
+            gzip_from: None,
+            transport,
+            #[cfg(feature = "vcr")]
+            cassette: None,
+            http: None,
+        };
         Ok(Self {
-            client: reqwest::Client::builder()
-                .default_headers(headers)
-                .build()?,
-            base_url: "https://api.openai.com/v1/".into(),
+            inner: std::sync::Arc::new(inner),
         })
     }
 
+    /// Private helper giving access to the settings to change, copying them first if
+    /// other clones share them
+    fn inner_mut(&mut self) -> &mut ClientInner {
+        std::sync::Arc::make_mut(&mut self.inner)
+    }
+
+    /// Records the client's API interactions to, or replays them from, a cassette. See the
+    /// `vcr` module.
+    #[cfg(feature = "vcr")]
+    #[must_use]
+    pub fn with_cassette(mut self, cassette: vcr::Cassette) -> Self {
+        self.inner_mut().cassette = Some(std::sync::Arc::new(cassette));
+        self
+    }
+
+    /// In dry-run mode, requests are validated locally (see e.g. `ChatArgs::validate`) and
+    /// never sent. Requests passing validation fail with `Error::DryRun`, which holds the
+    /// body that would have been sent.
+    #[must_use]
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.inner_mut().dry_run = dry_run;
+        self
+    }
+
+    /// With the context guard, chat and completion requests that don't fit in their model's
+    /// context, according to the `models` table, fail locally with
+    /// `Error::ContextLengthExceeded` instead of making the API reject them. Prompts are
+    /// counted exactly with the `tiktoken` feature, and otherwise never over-counted, so
+    /// the guard doesn't reject requests that would fit.
+    #[must_use]
+    pub fn with_context_guard(mut self, context_guard: bool) -> Self {
+        self.inner_mut().context_guard = context_guard;
+        self
+    }
+
+    /// Caches the responses of temperature `0.0` completions and chats, so repeating an
+    /// identical request doesn't call the API again
+    #[must_use]
+    pub fn with_cache(mut self, cache: cache::ResponseCache) -> Self {
+        self.inner_mut().cache = Some(cache);
+        self
+    }
+
+    /// Sends requests to another server implementing the API, e.g. a local Ollama, LM
+    /// Studio or llama.cpp server at `"http://localhost:11434/v1"`. A missing trailing
+    /// slash is added.
+    ///
+    /// # Errors
+    /// - `Error::InvalidConfig` if `base_url` isn't a valid URL
+    pub fn with_base_url(mut self, base_url: &str) -> Result<Self> {
+        let invalid = |reason: &dyn std::fmt::Display| {
+            Error::InvalidConfig(format!("invalid base URL {:?}: {}", base_url, reason))
+        };
+        let mut url = reqwest::Url::parse(base_url).map_err(|e| invalid(&e))?;
+        if url.cannot_be_a_base() {
+            return Err(invalid(&"not a hierarchical URL"));
+        }
+        if !url.path().ends_with('/') {
+            let path = format!("{}/", url.path());
+            url.set_path(&path);
+        }
+        self.inner_mut().base_url = url;
+        Ok(self)
+    }
+
+    /// Adds a header to every request, e.g. one a proxy or gateway requires
+    ///
+    /// # Errors
+    /// - `Error::InvalidConfig` if the name or value isn't a valid header
+    pub fn with_header(mut self, name: &str, value: &str) -> Result<Self> {
+        let (name, value) = options::header(name, value)?;
+        self.inner_mut().headers.insert(name, value);
+        Ok(self)
+    }
+
+    /// Bills requests to the organization `organization`, sending it as the
+    /// `OpenAI-Organization` header, for keys belonging to several organizations
+    ///
+    /// # Errors
+    /// - `Error::InvalidConfig` if `organization` isn't a valid header value
+    pub fn with_organization(self, organization: &str) -> Result<Self> {
+        self.with_header(options::ORGANIZATION_HEADER, organization)
+    }
+
+    /// Bills requests to the project `project`, sending it as the `OpenAI-Project` header
+    ///
+    /// # Errors
+    /// - `Error::InvalidConfig` if `project` isn't a valid header value
+    pub fn with_project(self, project: &str) -> Result<Self> {
+        self.with_header(options::PROJECT_HEADER, project)
+    }
+
+    /// Abandons the reading of responses larger than `bytes`, failing with
+    /// `Error::ResponseTooLarge`, so that a misbehaving OpenAI-compatible server can't
+    /// exhaust the memory. Responses have no size limit by default.
+    #[must_use]
+    pub fn with_max_response_size(mut self, bytes: usize) -> Self {
+        self.inner_mut().max_response_size = Some(bytes);
+        self
+    }
+
+    /// Retries requests failing transiently, e.g. on rate limits, according to `policy`.
+    /// Requests aren't retried by default. `ClientBuilder::retry_policy` also checks the
+    /// policy, see `RetryPolicy::validate`.
+    ///
+    /// # Example
+    /// ```
+    /// # use openai_api::{retry::RetryPolicy, Client};
+    /// # fn main() -> Result<(), openai_api::Error> {
+    /// let client = Client::new("sk-...")?.with_retry_policy(RetryPolicy::default());
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn with_retry_policy(mut self, policy: retry::RetryPolicy) -> Self {
+        self.inner_mut().retry = policy;
+        self
+    }
+
+    /// Delays requests to keep them within the budgets of `throttle`, see the `throttle`
+    /// module
+    #[must_use]
+    pub fn with_throttle(mut self, throttle: throttle::Throttle) -> Self {
+        self.inner_mut().throttle = Some(throttle);
+        self
+    }
+
+    /// Status and headers of the last response the client, or a clone of it, received
+    /// from the API. Responses served from the response cache don't count.
+    #[must_use]
+    pub fn last_response_meta(&self) -> Option<response::ResponseMeta> {
+        self.inner
+            .last_response_meta
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// The rate limits reported by the last response the client, or a clone of it,
+    /// received, to throttle requests ahead of the API. See `last_response_meta`.
+    #[must_use]
+    pub fn last_rate_limit(&self) -> Option<response::RateLimitInfo> {
+        self.last_response_meta()?.rate_limit()
+    }
+
+    /// A client making its requests with `options`, on top of this client's settings.
+    ///
+    /// The client is cheap to create: it shares this client's connection pool.
+    ///
+    /// # Example
+    /// ```
+    /// # use openai_api::{options::RequestOptions, Client};
+    /// # fn main() -> Result<(), openai_api::Error> {
+    /// let client = Client::new("sk-...")?;
+    /// let options = RequestOptions::new().header("X-Request-Source", "nightly-job")?;
+    /// let tagged = client.with_options(&options);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn with_options(&self, options: &options::RequestOptions) -> Self {
+        let mut client = self.clone();
+        let inner = client.inner_mut();
+        for (name, value) in &options.headers {
+            // Replaces the client's value, where extending would send both
+            inner.headers.insert(name, value.clone());
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(timeout) = options.timeout {
+            inner.request_timeout = Some(timeout);
+        }
+        client
+    }
+
+    /// Leaves the given top-level fields out of every request body, for servers rejecting
+    /// fields they don't support, e.g. `&["logit_bias"]`. Replaces any previous list.
+    #[must_use]
+    pub fn with_omitted_fields(mut self, fields: &[&str]) -> Self {
+        self.inner_mut().omitted_fields = fields.iter().map(|f| f.to_string()).collect();
+        self
+    }
+
     // Allow setting the api root in the tests
     #[cfg(test)]
     fn set_api_root(mut self, base_url: &str) -> Self {
-        self.base_url = base_url.to_string();
+        self.inner_mut().base_url = reqwest::Url::parse(base_url).expect("valid URL");
         self
     }
 
@@ -451,17 +2097,18 @@ impl Client {
     where
         T: serde::de::DeserializeOwned,
     {
-        let url = &format!("{}{}", self.base_url, endpoint);
-        let response = self.client.get(url).send().await?;
-        match response.status() {
-            reqwest::StatusCode::OK => Ok(response.json::<T>().await?),
-            code => {
-                let status_code = code.to_string();
-                let mut err = response.json::<api::ErrorWrapper>().await?.error;
-                err.status_code = status_code;
-                Err(Error::Api(err))
-            }
-        }
+        self.get_with_query(endpoint, &()).await
+    }
+
+    /// Private helper for making gets with query parameters, e.g. `api::ListParams` on list
+    /// endpoints
+    async fn get_with_query<T, Q>(&self, endpoint: &str, query: &Q) -> Result<T>
+    where
+        T: serde::de::DeserializeOwned,
+        Q: serde::ser::Serialize + ?Sized,
+    {
+        self.send(self.inner.client.get(self.url(endpoint)?).query(query))
+            .await
     }
 
     /// Lists the currently available models.
@@ -469,63 +2116,459 @@ impl Client {
     /// Provides basic information about each one such as the owner and availability.
     ///
     /// # Errors
-    /// - `Error::APIError` if the server returns an error
+    /// - `Error::Api` if the server returns an error
     pub async fn models(&self) -> Result<Vec<api::ModelInfo>> {
         self.get("models").await.map(|r: api::Container<_>| r.data)
     }
 
-    /// Retrieves an model instance
+    /// Checks that the client can reach the API and is accepted by it, with a cheap
+    /// authenticated request. Meant to fail fast at startup.
+    ///
+    /// # Errors
+    /// - `Error::Credentials` if the key or organization is rejected, or the API can't be
+    ///   reached
+    /// - Any other error of the request as it is
+    pub async fn validate(&self) -> Result<()> {
+        let credentials = |failure, message| Error::Credentials { failure, message };
+        match self.get::<api::Container<api::ModelInfo>>("models").await {
+            Ok(_) => Ok(()),
+            Err(Error::Api(err)) => match err.status.map(|status| status.as_u16()) {
+                Some(401) if err.message.to_lowercase().contains("organization") => Err(
+                    credentials(CredentialFailure::WrongOrganization, err.message),
+                ),
+                Some(401) => Err(credentials(CredentialFailure::InvalidKey, err.message)),
+                Some(403) => Err(credentials(
+                    CredentialFailure::InsufficientPermissions,
+                    err.message,
+                )),
+                _ => Err(Error::Api(err)),
+            },
+            Err(Error::AsyncProtocol(err)) if retry::is_connect(&err) => {
+                Err(credentials(CredentialFailure::Unreachable, err.to_string()))
+            }
+            Err(Error::Timeout(err)) => {
+                Err(credentials(CredentialFailure::Unreachable, err.to_string()))
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Retrieves an model instance
+    ///
+    /// Provides basic information about the model such as the owner and availability.
+    ///
+    /// # Errors
+    /// - `Error::Api` if the server returns an error
+    pub async fn model(&self, model: &str) -> Result<api::ModelInfo> {
+        self.get(&format!("models/{}", model)).await
+    }
+
+    // Private helper to generate post requests. Those streaming their response back as
+    // server-sent events go through `post_stream` instead
+    async fn post<B, R>(&self, endpoint: &str, body: B) -> Result<R>
+    where
+        B: serde::ser::Serialize,
+        R: serde::de::DeserializeOwned,
+    {
+        let url = self.url(endpoint)?;
+        let body = self.body(&body)?;
+        self.send(self.inner.client.post(url).json(&body)).await
+    }
+
+    /// Private helper resolving an endpoint, e.g. `"chat/completions"`, against the base URL
+    fn url(&self, endpoint: &str) -> Result<reqwest::Url> {
+        self.inner
+            .base_url
+            .join(endpoint)
+            .map_err(|e| Error::BadArguments(format!("invalid endpoint {:?}: {}", endpoint, e)))
+    }
+
+    /// Private helper serializing a request body, without the omitted fields
+    fn body<B>(&self, body: &B) -> Result<serde_json::Value>
+    where
+        B: serde::ser::Serialize,
+    {
+        let mut body = serde_json::to_value(body)?;
+        if let Some(body) = body.as_object_mut() {
+            for field in self.inner.omitted_fields.iter() {
+                body.remove(field);
+            }
+        }
+        Ok(body)
+    }
+
+    /// Private helper sending a request, retried according to the client's policy, then
+    /// parsing its response, or the error the API returned instead
+    async fn send<R>(&self, request: reqwest::RequestBuilder) -> Result<R>
+    where
+        R: serde::de::DeserializeOwned,
+    {
+        json::from_bytes(self.send_raw(request).await?)
+    }
+
+    /// Private helper sending a request like `send`, for responses that aren't JSON, e.g.
+    /// subtitles or audio
+    async fn send_raw(&self, request: reqwest::RequestBuilder) -> Result<bytes::Bytes> {
+        let request = self.prepare(request).await?;
+        retry::with_retries(&self.inner.retry, || {
+            self.send_once(retry::resend(&request))
+        })
+        .await
+    }
+
+    /// Private helper making a single attempt at `send_raw`
+    async fn send_once(&self, request: reqwest::Request) -> Result<bytes::Bytes> {
+        let response = self.execute(request).await?;
+        self.remember(response.meta());
+        match response.status {
+            reqwest::StatusCode::OK => Ok(response.body),
+            _ => Err(Self::api_error(&response)),
+        }
+    }
+
+    /// Private helper getting a request ready to send: built, throttled on its tokens,
+    /// counted before the body is compressed, then compressed
+    async fn prepare(&self, request: reqwest::RequestBuilder) -> Result<reqwest::Request> {
+        let request = self.build(request)?;
+        self.throttle(&request).await;
+        #[cfg(feature = "gzip")]
+        let request = self.compress(request);
+        Ok(request)
+    }
+
+    /// Private helper building a request with the client's headers and query
+    fn build(&self, request: reqwest::RequestBuilder) -> Result<reqwest::Request> {
+        let mut request = request
+            .headers(self.inner.headers.clone())
+            .query(&self.inner.query);
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(timeout) = self.inner.request_timeout {
+            request = request.timeout(timeout);
+        }
+        Ok(request.build()?)
+    }
+
+    /// Private helper keeping the metadata of the last response
+    fn remember(&self, meta: response::ResponseMeta) {
+        *self
+            .inner
+            .last_response_meta
+            .lock()
+            .unwrap_or_else(|e| e.into_inner()) = Some(meta);
+    }
+
+    /// Private helper parsing the error the API returned instead of a response. Bodies
+    /// that aren't an API error, e.g. the HTML page of a proxy, become its message.
+    fn api_error(response: &response::RawResponse) -> Error {
+        let mut err = match serde_json::from_slice::<api::ErrorWrapper>(&response.body) {
+            Ok(wrapper) => wrapper.error,
+            Err(_) => api::ApiError {
+                message: String::from_utf8_lossy(&response.body).trim().to_string(),
+                ..api::ApiError::default()
+            },
+        };
+        err.status = Some(response.status);
+        err.request_id = response
+            .headers
+            .get("x-request-id")
+            .and_then(|id| id.to_str().ok())
+            .map(String::from);
+        err.retry_after = retry::retry_after(&response.headers);
+        err.into()
+    }
+
+    /// Private helper executing a request: over the network, or through the cassette or
+    /// the custom transport when there is one
+    async fn execute(&self, request: reqwest::Request) -> Result<response::RawResponse> {
+        #[cfg(feature = "vcr")]
+        if let Some(cassette) = &self.inner.cassette {
+            return cassette
+                .execute(
+                    &self.inner.client,
+                    self.inner.base_url.as_str(),
+                    request,
+                    self.inner.max_response_size,
+                )
+                .await;
+        }
+        if let Some(transport) = &self.inner.http {
+            return self.execute_custom(transport.as_ref(), request).await;
+        }
+        let response = self.inner.client.execute(request).await?;
+        response::RawResponse::read(response, self.inner.max_response_size).await
+    }
+
+    /// Private helper telling whether responses are read whole by `execute`, by the
+    /// cassette or the custom transport, rather than streamed from reqwest
+    fn buffered(&self) -> bool {
+        #[cfg(feature = "vcr")]
+        if self.inner.cassette.is_some() {
+            return true;
+        }
+        self.inner.http.is_some()
+    }
+
+    /// Private helper stopping requests in dry-run mode, after validating them
+    fn dry_run<B>(
+        &self,
+        endpoint: &str,
+        body: &B,
+        validate: impl FnOnce() -> Result<()>,
+    ) -> Result<()>
+    where
+        B: serde::ser::Serialize,
+    {
+        if !self.inner.dry_run {
+            return Ok(());
+        }
+        validate()?;
+        Err(Error::DryRun {
+            endpoint: endpoint.into(),
+            body: self.body(body)?.to_string(),
+        })
+    }
+
+    /// Private helper rejecting requests that can't fit in the model's context, when the
+    /// context guard is on
+    fn check_context(&self, model: &str, needed: u64) -> Result<()> {
+        if !self.inner.context_guard {
+            return Ok(());
+        }
+        match models::context_length(model) {
+            Some(available) if needed > available => {
+                Err(Error::ContextLengthExceeded { needed, available })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Private helper describing where requests go and as whom: the base URL and query,
+    /// the headers, i.e. the credentials, organization and project, and the omitted
+    /// fields. It holds secrets: the cache only keeps its digest.
+    fn cache_scope(&self) -> String {
+        let inner = &self.inner;
+        let mut headers: Vec<_> = inner
+            .transport
+            .auth
+            .iter()
+            .chain(inner.headers.iter())
+            .map(|(name, value)| format!("{}: {}", name, String::from_utf8_lossy(value.as_bytes())))
+            .collect();
+        headers.sort();
+        format!(
+            "{}?{:?}\n{}\n{:?}",
+            inner.base_url,
+            inner.query,
+            headers.join("\n"),
+            inner.omitted_fields
+        )
+    }
+
+    /// Private helper for posts whose response may be served from, and stored in, the
+    /// response cache
+    async fn post_cached<B, R>(&self, endpoint: &str, body: B, cacheable: bool) -> Result<R>
+    where
+        B: serde::ser::Serialize,
+        R: serde::de::DeserializeOwned + Clone + Send + Sync + 'static,
+    {
+        let cache = match &self.inner.cache {
+            Some(cache) if cacheable => cache,
+            _ => return self.post(endpoint, body).await,
+        };
+        let key = cache::key(&self.cache_scope(), endpoint, &body)?;
+        if let Some(response) = cache.get(&key) {
+            return Ok(response);
+        }
+        let response: R = self.post(endpoint, body).await?;
+        cache.insert(key, response.clone());
+        Ok(response)
+    }
+
+    /// Get predicted completion of the prompt
+    ///
+    /// Accepts anything convertible to `CompletionArgs`, including a prompt string and an
+    /// unbuilt `CompletionArgsBuilder`.
+    ///
+    /// # Errors
+    ///  - `Error::BadArguments` if the arguments can't be built, or are invalid in dry-run
+    ///    mode
+    ///  - `Error::DryRun` in dry-run mode, if the arguments are valid
+    ///  - `Error::ContextLengthExceeded` with the context guard on, if the request is too
+    ///    large for the model
+    ///  - `Error::Api` if the api returns an error
+    pub async fn complete_prompt<A>(&self, prompt: A) -> Result<api::Completion>
+    where
+        A: TryInto<api::CompletionArgs>,
+        Error: From<<A as TryInto<api::CompletionArgs>>::Error>,
+    {
+        let args: api::CompletionArgs = prompt.try_into()?;
+        self.dry_run("completions", &args, || args.validate())?;
+        self.check_context(&args.model, args.context_tokens())?;
+        let cacheable = args.temperature == Some(0.0);
+        self.post_cached("completions", args, cacheable).await
+    }
+
+    /// Like `complete_prompt`, but streams the completion back as it is generated
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use futures::StreamExt;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), openai_api::Error> {
+    /// let client = openai_api::Client::new("sk-...")?;
+    /// let mut chunks = Box::pin(client.complete_prompt_stream("Once upon a time").await?);
+    /// while let Some(chunk) = chunks.next().await {
+    ///     print!("{}", chunk?);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///  - The errors of `complete_prompt`, before the stream starts
+    ///  - `Error::BadArguments` if `best_of` is above 1, which the API can't stream
+    ///  - `Error::Api` in the stream, if the api reports an error mid-stream
+    pub async fn complete_prompt_stream<A>(
+        &self,
+        prompt: A,
+    ) -> Result<impl futures::Stream<Item = Result<api::CompletionChunk>>>
+    where
+        A: TryInto<api::CompletionArgs>,
+        Error: From<<A as TryInto<api::CompletionArgs>>::Error>,
+    {
+        let args: api::CompletionArgs = prompt.try_into()?;
+        if args.best_of.is_some_and(|best_of| best_of > 1) {
+            return Err(Error::BadArguments(
+                "best_of can't be used when streaming".into(),
+            ));
+        }
+        self.dry_run("completions", &args, || args.validate())?;
+        self.check_context(&args.model, args.context_tokens())?;
+        let mut body = self.body(&args)?;
+        body["stream"] = true.into();
+        self.post_stream("completions", &body).await
+    }
+
+    /// Runs many completion requests, with at most `concurrency` of them in flight at once.
+    ///
+    /// Results are returned in the order of `prompts`. A failed request doesn't stop the
+    /// others, its error takes its place in the results.
+    pub async fn complete_batch(
+        &self,
+        prompts: Vec<api::CompletionArgs>,
+        concurrency: usize,
+    ) -> Vec<Result<api::Completion>> {
+        ordered_concurrent(
+            prompts.into_iter().map(|args| self.complete_prompt(args)),
+            concurrency,
+        )
+        .collect()
+        .await
+    }
+
+    /// Given a chat conversation, the model will return a chat completion response.
     ///
-    /// Provides basic information about the model such as the owner and availability.
+    /// Accepts anything convertible to `ChatArgs`, including an unbuilt `ChatArgsBuilder`.
     ///
     /// # Errors
-    /// - `Error::APIError` if the server returns an error
-    pub async fn model(&self, model: &str) -> Result<api::ModelInfo> {
-        self.get(&format!("models/{}", model)).await
-    }
-
-    // Private helper to generate post requests. Needs to be a bit more flexible than
-    // get because it should support SSE eventually
-    async fn post<B, R>(&self, endpoint: &str, body: B) -> Result<R>
+    ///  - `Error::BadArguments` if the arguments can't be built, or are invalid in dry-run
+    ///    mode
+    ///  - `Error::DryRun` in dry-run mode, if the arguments are valid
+    ///  - `Error::ContextLengthExceeded` with the context guard on, if the request is too
+    ///    large for the model
+    ///  - `Error::Api` if the api returns an error
+    pub async fn chat<A>(&self, msg: A) -> Result<api::ChatAnswer>
     where
-        B: serde::ser::Serialize,
-        R: serde::de::DeserializeOwned,
+        A: TryInto<api::ChatArgs>,
+        Error: From<<A as TryInto<api::ChatArgs>>::Error>,
     {
-        let url = &format!("{}{}", self.base_url, endpoint);
-        let response = self.client.post(url).json(&body).send().await?;
-        match response.status() {
-            reqwest::StatusCode::OK => Ok(response.json::<R>().await?),
-            code => {
-                let status_code = code.to_string();
-                let mut err = response.json::<api::ErrorWrapper>().await?.error;
-                err.status_code = status_code;
-                Err(Error::Api(err))
-            }
-        }
+        let args: api::ChatArgs = msg.try_into()?;
+        self.dry_run("chat/completions", &args, || args.validate())?;
+        self.check_context(&args.model, args.context_tokens())?;
+        let cacheable = args.temperature == Some(0.0);
+        self.post_cached("chat/completions", args, cacheable).await
     }
 
-    /// Get predicted completion of the prompt
+    /// Like `chat`, but streams the answer back as it is generated, ending on the API's
+    /// `[DONE]` event
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use futures::StreamExt;
+    /// # use openai_api::api::{ChatArgs, ChatRole};
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), openai_api::Error> {
+    /// let client = openai_api::Client::new("sk-...")?;
+    /// let args = ChatArgs::from(vec![(ChatRole::User, "Hello!".to_string())]);
+    /// let mut deltas = Box::pin(client.chat_stream(args).await?);
+    /// while let Some(delta) = deltas.next().await {
+    ///     print!("{}", delta?);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
     ///
     /// # Errors
-    ///  - `Error::APIError` if the api returns an error
-    pub async fn complete_prompt(
+    ///  - The errors of `chat`, before the stream starts
+    ///  - `Error::Api` in the stream, if the api reports an error mid-stream
+    pub async fn chat_stream<A>(
         &self,
-        prompt: impl Into<api::CompletionArgs>,
-    ) -> Result<api::Completion> {
-        let args = prompt.into();
-        Ok(self.post("completions", args).await?)
+        msg: A,
+    ) -> Result<impl futures::Stream<Item = Result<api::ChatDelta>>>
+    where
+        A: TryInto<api::ChatArgs>,
+        Error: From<<A as TryInto<api::ChatArgs>>::Error>,
+    {
+        let args: api::ChatArgs = msg.try_into()?;
+        self.dry_run("chat/completions", &args, || args.validate())?;
+        self.check_context(&args.model, args.context_tokens())?;
+        let mut body = self.body(&args)?;
+        body["stream"] = true.into();
+        self.post_stream("chat/completions", &body).await
     }
 
-    /// Given a chat conversation, the model will return a chat completion response.
+    /// Runs many chat requests, with at most `concurrency` of them in flight at once.
     ///
-    /// # Errors
-    ///  - `Error::APIError` if the api returns an error
-    pub async fn chat(&self, msg: impl Into<api::ChatArgs>) -> Result<api::ChatAnswer> {
-        let args = msg.into();
-        Ok(self.post("chat/completions", args).await?)
+    /// Results are returned in the order of `args`. A failed request doesn't stop the
+    /// others, its error takes its place in the results.
+    pub async fn chat_many(
+        &self,
+        args: Vec<api::ChatArgs>,
+        concurrency: usize,
+    ) -> Vec<Result<api::ChatAnswer>> {
+        ordered_concurrent(args.into_iter().map(|args| self.chat(args)), concurrency)
+            .collect()
+            .await
     }
 }
 
+/// Conservative token estimate of a text, for budgeting purposes: one token per three
+/// bytes, which over-counts typical English text (about four characters per token).
+fn estimate_tokens(text: &str) -> usize {
+    text.len().div_ceil(3)
+}
+
+/// Token estimate of a text that seldom over-counts, for checks rejecting requests: one
+/// token per four bytes, the average of English text. Code and most other languages have
+/// more tokens per byte.
+#[cfg(not(feature = "tiktoken"))]
+fn min_tokens(text: &str) -> usize {
+    text.len() / 4
+}
+
+/// Drives `tasks` with at most `concurrency` of them in flight, yielding their outputs in
+/// the order of `tasks`
+fn ordered_concurrent<I>(
+    tasks: I,
+    concurrency: usize,
+) -> impl futures::Stream<Item = <I::Item as std::future::Future>::Output>
+where
+    I: IntoIterator,
+    I::Item: std::future::Future,
+{
+    futures::stream::iter(tasks).buffered(concurrency.max(1))
+}
+
 #[cfg(test)]
 mod unit {
 
@@ -533,12 +2576,13 @@ mod unit {
 
     use crate::{
         api::{
-            self, ChatAnswer, ChatArgs, ChatFormat, ChatRole, Completion, CompletionArgs, ModelInfo,
+            self, BackendSampling, ChatAnswer, ChatArgs, ChatFormat, ChatRole, Completion,
+            CompletionArgs, ModelInfo,
         },
-        Client, Error,
+        Client, CredentialFailure, Error,
     };
 
-    fn mocked_client() -> Client {
+    pub(crate) fn mocked_client() -> Client {
         let _ = env_logger::builder().is_test(true).try_init();
         Client::new("bogus")
             .unwrap()
@@ -550,6 +2594,71 @@ mod unit {
         let _c = mocked_client();
     }
 
+    #[test]
+    fn rejects_invalid_tokens() {
+        for token in ["clé", "sk-\nbogus"] {
+            assert!(matches!(Client::new(token), Err(Error::InvalidToken)));
+        }
+        assert!(matches!(
+            Client::new("bogus").and_then(|c| c.with_base_url("not a url")),
+            Err(Error::InvalidConfig(_))
+        ));
+    }
+
+    #[test]
+    fn clones_share_settings_until_changed() -> crate::Result<()> {
+        let client = Client::new("bogus")?.with_header("X-Team", "search")?;
+        let clone = client.clone();
+        assert!(std::sync::Arc::ptr_eq(&client.inner, &clone.inner));
+        let changed = clone.with_header("X-Team", "ads")?;
+        assert_eq!(client.inner.headers["x-team"], "search");
+        assert_eq!(changed.inner.headers["x-team"], "ads");
+        assert!(std::sync::Arc::ptr_eq(
+            &client.inner.last_response_meta,
+            &changed.inner.last_response_meta
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn remembers_rate_limits() -> crate::Result<()> {
+        let _m = mockito::mock("GET", "/models/rate-limited")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_header("x-ratelimit-remaining-tokens", "1000")
+            .with_header("x-ratelimit-reset-tokens", "250ms")
+            .with_body(r#"{"id": "rate-limited"}"#)
+            .create();
+        let client = mocked_client();
+        client.model("rate-limited").await?;
+        let limits = client.last_rate_limit().unwrap();
+        assert_eq!(limits.remaining_tokens, Some(1000));
+        assert_eq!(
+            limits.reset_tokens,
+            Some(std::time::Duration::from_millis(250))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn parses_finish_reasons() -> crate::Result<()> {
+        let choice: api::ChatChoice = serde_json::from_str(
+            r#"{"index": 0, "message": {"role": "assistant", "content": null},
+                "finish_reason": "tool_calls"}"#,
+        )?;
+        assert_eq!(choice.finish_reason, api::FinishReason::ToolCalls);
+        for reason in ["stop", "length", "content_filter", "function_call", "eos"] {
+            let parsed: api::FinishReason = serde_json::from_value(reason.into())?;
+            assert_eq!(parsed, reason);
+            assert_eq!(serde_json::to_value(&parsed)?, reason);
+        }
+        assert_eq!(
+            api::FinishReason::from("eos"),
+            api::FinishReason::Other("eos".into())
+        );
+        Ok(())
+    }
+
     #[test]
     fn parse_model_info() -> Result<(), Box<dyn std::error::Error>> {
         let example = r#"{
@@ -569,6 +2678,30 @@ mod unit {
         Ok(())
     }
 
+    #[test]
+    fn list_params_query() -> Result<(), Box<dyn std::error::Error>> {
+        let params = api::ListParams::builder()
+            .limit(2)
+            .order(api::ListOrder::Asc)
+            .after("file-abc123")
+            .build()?;
+        let request = reqwest::Client::new()
+            .get("https://example.com/files")
+            .query(&params)
+            .build()?;
+        assert_eq!(
+            request.url().query(),
+            Some("limit=2&order=asc&after=file-abc123")
+        );
+
+        let request = reqwest::Client::new()
+            .get("https://example.com/files")
+            .query(&api::ListParams::default())
+            .build()?;
+        assert_eq!(request.url().query(), None);
+        Ok(())
+    }
+
     fn mock_models() -> (Mock, Vec<ModelInfo>) {
         let mock = mockito::mock("GET", "/models")
             .with_status(200)
@@ -595,6 +2728,100 @@ mod unit {
         (mock, expected)
     }
 
+    #[test]
+    fn args_from_presets() -> Result<(), Box<dyn std::error::Error>> {
+        let args: ChatArgs = serde_json::from_str(
+            r#"{
+            "model": "gpt-4",
+            "temperature": 0.2,
+            "messages": [{"role": "system", "content": "Be brief."}]
+        }"#,
+        )?;
+        let expected = ChatArgs::builder()
+            .model("gpt-4")
+            .temperature(0.2)
+            .messages(vec![ChatFormat::new(ChatRole::System, "Be brief.".into())])
+            .build()?;
+        assert_eq!(serde_json::to_value(args)?, serde_json::to_value(expected)?);
+
+        let args: CompletionArgs = serde_json::from_str(r#"{"max_tokens": 64}"#)?;
+        let expected = CompletionArgs::builder().max_tokens(64).build()?;
+        assert_eq!(serde_json::to_value(args)?, serde_json::to_value(expected)?);
+        Ok(())
+    }
+
+    #[test]
+    fn validate_args() {
+        let valid = ChatArgs::builder()
+            .model("gpt-4")
+            .messages(vec![ChatFormat::new(ChatRole::User, "Hello!".into())])
+            .max_tokens(100)
+            .build()
+            .unwrap();
+        assert!(valid.validate().is_ok());
+
+        let invalid = ChatArgs::builder()
+            .model("text-davinci-003")
+            .temperature(3.0)
+            .max_tokens(5000)
+            .build()
+            .unwrap();
+        match invalid.validate() {
+            Err(Error::BadArguments(msg)) => {
+                assert!(msg.contains("at least one message"), "{}", msg);
+                assert!(
+                    msg.contains("temperature must be between 0 and 2"),
+                    "{}",
+                    msg
+                );
+                assert!(msg.contains("not a Chat model"), "{}", msg);
+                assert!(msg.contains("context of 4097"), "{}", msg);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+
+        let local_model = CompletionArgs::builder()
+            .model("llama3:8b")
+            .max_tokens(100_000)
+            .build()
+            .unwrap();
+        assert!(local_model.validate().is_ok());
+        let too_many_stops = CompletionArgs::builder()
+            .stop(vec![
+                "a".into(),
+                "b".into(),
+                "c".into(),
+                "d".into(),
+                "e".into(),
+            ])
+            .build()
+            .unwrap();
+        assert!(too_many_stops.validate().is_err());
+    }
+
+    #[tokio::test]
+    async fn dry_run_sends_nothing() {
+        let client = Client::new("bogus")
+            .unwrap()
+            .set_api_root("http://127.0.0.1:9/")
+            .with_dry_run(true);
+        match client
+            .chat(
+                ChatArgs::builder()
+                    .messages(vec![ChatFormat::new(ChatRole::User, "Hello!".into())]),
+            )
+            .await
+        {
+            Err(Error::DryRun { endpoint, body }) => {
+                assert_eq!(endpoint, "chat/completions");
+                assert!(body.contains("Hello!"));
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        let invalid = client.complete_prompt(CompletionArgs::builder().n(0)).await;
+        assert!(matches!(invalid, Err(Error::BadArguments(_))));
+    }
+
     #[tokio::test]
     async fn parse_models() -> crate::Result<()> {
         let (_m, expected) = mock_models();
@@ -603,23 +2830,44 @@ mod unit {
         Ok(())
     }
 
-    fn mock_model() -> (Mock, api::ErrorMessage) {
+    #[tokio::test]
+    async fn max_response_size() -> crate::Result<()> {
+        let (_m, expected) = mock_models();
+        let response = mocked_client().with_max_response_size(16).models().await;
+        assert!(matches!(
+            response,
+            Err(Error::ResponseTooLarge { limit: 16 })
+        ));
+        let response = mocked_client()
+            .with_max_response_size(1 << 10)
+            .models()
+            .await?;
+        assert_eq!(response, expected);
+        Ok(())
+    }
+
+    fn mock_model() -> (Mock, api::ApiError) {
         let mock = mockito::mock("GET", "/models/davinci")
             .with_status(404)
             .with_header("content-type", "application/json")
+            .with_header("x-request-id", "req_123")
             .with_body(
                 r#"{
                 "error": {
                     "code": null,
                     "message": "Some kind of error happened",
-                    "type": "some_error_type"
+                    "type": "some_error_type",
+                    "param": null
                 }
             }"#,
             )
             .create();
-        let expected = api::ErrorMessage {
+        let expected = api::ApiError {
             message: "Some kind of error happened".into(),
-            status_code: "400".into(),
+            kind: Some("some_error_type".into()),
+            status: Some(reqwest::StatusCode::NOT_FOUND),
+            request_id: Some("req_123".into()),
+            ..api::ApiError::default()
         };
         (mock, expected)
     }
@@ -627,13 +2875,58 @@ mod unit {
     #[tokio::test]
     async fn model_error_response() -> crate::Result<()> {
         let (_m, expected) = mock_model();
-        let response = mocked_client().model("text-davinci-003").await;
-        if let Result::Err(Error::Api(msg)) = response {
-            assert_eq!(expected, msg);
+        match mocked_client().model("davinci").await {
+            Err(Error::Api(err)) => {
+                assert_eq!(expected, *err);
+                assert_eq!(
+                    err.to_string(),
+                    "404 Not Found (some_error_type) Some kind of error happened [request req_123]"
+                );
+            }
+            other => panic!("expected an API error, got {:?}", other),
         }
         Ok(())
     }
 
+    #[test]
+    fn classifies_api_errors() -> Result<(), serde_json::Error> {
+        let error = |status: u16, body: &str| -> Result<api::ApiError, serde_json::Error> {
+            Ok(api::ApiError {
+                status: reqwest::StatusCode::from_u16(status).ok(),
+                ..serde_json::from_str(body)?
+            })
+        };
+        let too_long = error(
+            400,
+            r#"{"message": "This model's maximum context length is 8192 tokens.", "type":
+            "invalid_request_error", "param": "messages", "code": "context_length_exceeded"}"#,
+        )?;
+        assert!(too_long.is_context_length_exceeded());
+        assert_eq!(too_long.param.as_deref(), Some("messages"));
+        assert!(!too_long.is_rate_limited());
+
+        let limited = error(
+            429,
+            r#"{"message": "Rate limit reached", "type": "requests"}"#,
+        )?;
+        assert!(limited.is_rate_limited() && !limited.is_quota_exceeded());
+        let quota = error(
+            429,
+            r#"{"message": "You exceeded your current quota", "type": "insufficient_quota",
+            "code": "insufficient_quota"}"#,
+        )?;
+        assert!(quota.is_quota_exceeded() && !quota.is_rate_limited());
+
+        let bad_key = error(
+            401,
+            r#"{"message": "Incorrect API key provided", "type": "invalid_request_error",
+            "code": "invalid_api_key"}"#,
+        )?;
+        assert!(bad_key.is_invalid_api_key() && !bad_key.is_server_error());
+        assert!(error(503, r#"{"message": "Overloaded"}"#)?.is_server_error());
+        Ok(())
+    }
+
     fn mock_completion() -> crate::Result<(Mock, CompletionArgs, Completion)> {
         let mock = mockito::mock("POST", "/completions")
             .with_status(200)
@@ -651,7 +2944,8 @@ mod unit {
                     "logprobs": null,
                     "finish_reason": "length"
                     }
-                ]
+                ],
+                "usage": {"prompt_tokens": 4, "completion_tokens": 5, "total_tokens": 9}
                 }"#,
             )
             .expect(1)
@@ -675,7 +2969,14 @@ mod unit {
                 index: 0,
                 logprobs: None,
                 finish_reason: "length".into(),
+                extra: Default::default(),
             }],
+            usage: Some(api::Usage {
+                prompt_tokens: 4,
+                completion_tokens: 5,
+                total_tokens: 9,
+            }),
+            extra: Default::default(),
         };
         Ok((mock, args, expected))
     }
@@ -686,6 +2987,7 @@ mod unit {
         assert_eq!(a.model, b.model);
         assert_eq!(a.id, b.id);
         assert_eq!(a.created, b.created);
+        assert_eq!(a.usage, b.usage);
         let (a_choice, b_choice) = (&a.choices[0], &b.choices[0]);
         assert_eq!(a_choice.text, b_choice.text);
         assert_eq!(a_choice.index, b_choice.index);
@@ -702,6 +3004,282 @@ mod unit {
         Ok(())
     }
 
+    #[test]
+    fn multiple_prompts() -> crate::Result<()> {
+        let args = CompletionArgs::builder()
+            .model("davinci-002")
+            .prompt(vec!["Once upon a time", "In a galaxy"])
+            .max_tokens(5)
+            .n(2)
+            .build()?;
+        args.validate()?;
+        let body = serde_json::to_value(&args)?;
+        assert_eq!(
+            body["prompt"],
+            serde_json::json!(["Once upon a time", "In a galaxy"])
+        );
+        assert_eq!(args.estimated_tokens(), 6 + 4 + 5 * 2 * 2);
+        assert_eq!(args.context_tokens(), 4 + 5);
+
+        let args: CompletionArgs =
+            serde_json::from_str(r#"{"model": "davinci-002", "prompt": [[1, 2, 3], [4]]}"#)?;
+        assert_eq!(
+            serde_json::to_value(&args)?["prompt"],
+            serde_json::json!([[1, 2, 3], [4]])
+        );
+        assert_eq!(args.context_tokens(), 3 + 16);
+
+        let none = CompletionArgs::builder()
+            .model("davinci-002")
+            .prompt(Vec::<String>::new())
+            .build()?;
+        assert!(none.validate().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn serializes_only_set_fields() -> crate::Result<()> {
+        assert_eq!(
+            serde_json::to_value(CompletionArgs::default())?,
+            serde_json::json!({"model": "text-davinci-003", "prompt": "<|endoftext|>"})
+        );
+        assert_eq!(
+            serde_json::to_value(ChatArgs::default())?,
+            serde_json::json!({"model": "gpt-3.5-turbo", "messages": []})
+        );
+
+        let completion = CompletionArgs::builder()
+            .prompt("Say hi")
+            .max_tokens(8)
+            .temperature(0.0)
+            .top_p(0.5)
+            .n(2)
+            .logprobs(1)
+            .echo(true)
+            .stop(vec!["\n".into()])
+            .presence_penalty(0.5)
+            .frequency_penalty(-0.5)
+            .logit_bias(maplit::hashmap! {"50256".into() => -100.0})
+            .build()?;
+        assert_eq!(
+            serde_json::to_value(completion)?,
+            serde_json::json!({
+                "model": "text-davinci-003",
+                "prompt": "Say hi",
+                "max_tokens": 8,
+                "temperature": 0.0,
+                "top_p": 0.5,
+                "n": 2,
+                "logprobs": 1,
+                "echo": true,
+                "stop": ["\n"],
+                "presence_penalty": 0.5,
+                "frequency_penalty": -0.5,
+                "logit_bias": {"50256": -100.0},
+            })
+        );
+
+        let chat = ChatArgs::builder()
+            .model("gpt-4o")
+            .messages(vec![ChatFormat::new(ChatRole::User, "Hi".into())])
+            .max_tokens(8)
+            .temperature(0.2)
+            .n(1)
+            .stop(vec!["END".into()])
+            .build()?;
+        assert_eq!(
+            serde_json::to_value(chat)?,
+            serde_json::json!({
+                "model": "gpt-4o",
+                "messages": [{"role": "user", "content": "Hi"}],
+                "max_tokens": 8,
+                "temperature": 0.2,
+                "n": 1,
+                "stop": ["END"],
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn passes_extra_parameters() -> crate::Result<()> {
+        let extra = |pairs: serde_json::Value| pairs.as_object().cloned().unwrap_or_default();
+        let chat = ChatArgs::builder()
+            .messages(vec![ChatFormat::new(ChatRole::User, "Hi".into())])
+            .temperature(0.5)
+            .extra(extra(
+                serde_json::json!({"service_tier": "flex", "top_k": 20}),
+            ))
+            .build()?;
+        chat.validate()?;
+        let body = serde_json::to_value(&chat)?;
+        assert_eq!(
+            (&body["service_tier"], &body["top_k"]),
+            (&"flex".into(), &20.into())
+        );
+
+        // Unknown fields of saved args come back as extra parameters
+        let saved: ChatArgs = serde_json::from_value(body.clone())?;
+        assert_eq!(serde_json::to_value(saved)?, body);
+
+        let repeated = CompletionArgs::builder()
+            .temperature(0.5)
+            .extra(extra(serde_json::json!({"temperature": 0.0, "top_p": 0.9})))
+            .build()?;
+        match repeated.validate() {
+            Err(Error::BadArguments(msg)) => {
+                assert!(msg.contains("extra parameter temperature"), "{}", msg);
+                assert!(!msg.contains("top_p"), "{}", msg);
+            }
+            other => panic!("expected BadArguments, got {:?}", other),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn reproducible_chats() -> crate::Result<()> {
+        let plain = serde_json::to_value(ChatArgs::default())?;
+        assert!(plain.get("seed").is_none());
+        let seeded = serde_json::to_value(ChatArgs::builder().seed(7).build()?)?;
+        assert_eq!(seeded["seed"], 7);
+
+        let delta: api::ChatDelta = serde_json::from_str(
+            r#"{"id": "chatcmpl-1", "system_fingerprint": "fp_44709d6fcb", "choices": []}"#,
+        )?;
+        assert_eq!(delta.system_fingerprint.as_deref(), Some("fp_44709d6fcb"));
+        Ok(())
+    }
+
+    #[test]
+    fn chat_logprobs() -> crate::Result<()> {
+        let plain = serde_json::to_value(ChatArgs::default())?;
+        assert!(plain.get("logprobs").is_none());
+        let args = ChatArgs::builder()
+            .messages(vec![ChatFormat::new(ChatRole::User, "Hi".into())])
+            .logprobs(true)
+            .top_logprobs(2)
+            .build()?;
+        args.validate()?;
+        let body = serde_json::to_value(&args)?;
+        assert_eq!(
+            (&body["logprobs"], &body["top_logprobs"]),
+            (&true.into(), &2.into())
+        );
+        let without = ChatArgs::builder()
+            .messages(vec![ChatFormat::new(ChatRole::User, "Hi".into())])
+            .top_logprobs(21)
+            .build()?;
+        match without.validate() {
+            Err(Error::BadArguments(msg)) => {
+                assert!(msg.contains("top_logprobs needs logprobs"), "{}", msg);
+                assert!(msg.contains("top_logprobs must be"), "{}", msg);
+            }
+            other => panic!("expected BadArguments, got {:?}", other),
+        }
+
+        let answer: api::ChatAnswer = serde_json::from_str(
+            r#"{"choices": [{"index": 0, "finish_reason": "stop",
+            "message": {"role": "assistant", "content": "Hello"},
+            "logprobs": {"content": [{"token": "Hello", "logprob": -0.01,
+            "bytes": [72, 101, 108, 108, 111], "top_logprobs": [{"token": "Hello",
+            "logprob": -0.01, "bytes": [72, 101, 108, 108, 111]}, {"token": "Hi",
+            "logprob": -4.6, "bytes": null}]}], "refusal": null}}]}"#,
+        )?;
+        let logprobs = answer.choices[0]
+            .logprobs
+            .as_ref()
+            .and_then(|l| l.content.as_ref());
+        let token = &logprobs.expect("logprobs")[0];
+        assert_eq!(token.bytes.as_deref(), Some(&b"Hello"[..]));
+        assert_eq!(token.top_logprobs[1].token, "Hi");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn completion_insertion_and_tracking() -> crate::Result<()> {
+        let plain = serde_json::to_value(CompletionArgs::default())?;
+        for field in ["suffix", "best_of", "user", "seed"] {
+            assert!(plain.get(field).is_none(), "{} sent unset", field);
+        }
+
+        let args = CompletionArgs::builder()
+            .model("gpt-3.5-turbo-instruct")
+            .prompt("fn add(a: u32, b: u32) -> u32 {")
+            .suffix("}")
+            .n(2)
+            .best_of(3)
+            .user("user-1")
+            .seed(42)
+            .build()?;
+        args.validate()?;
+        let body = serde_json::to_value(&args)?;
+        assert_eq!(body["suffix"], "}");
+        assert_eq!(body["best_of"], 3);
+        assert_eq!(body["user"], "user-1");
+        assert_eq!(body["seed"], 42);
+        assert!(matches!(
+            mocked_client().complete_prompt_stream(args).await,
+            Err(Error::BadArguments(_))
+        ));
+
+        let fewer = CompletionArgs::builder().n(2).best_of(1).build()?;
+        assert!(fewer.validate().is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn completion_stream() -> crate::Result<()> {
+        use futures::{StreamExt, TryStreamExt};
+
+        let chunk = |text: &str, finish: &str| {
+            format!(
+                "data: {{\"id\": \"cmpl-1\", \"object\": \"text_completion\", \"created\": 1, \
+                \"model\": \"davinci-002\", \"choices\": [{{\"text\": \"{}\", \"index\": 0, \
+                \"logprobs\": null, \"finish_reason\": {}}}]}}\n\n",
+                text, finish
+            )
+        };
+        let body = format!(
+            "{}{}data: [DONE]\n\n",
+            chunk("Once", "null"),
+            chunk(" upon", "\"stop\"")
+        );
+        let m = mockito::mock("POST", "/completions")
+            .match_body(mockito::Matcher::PartialJson(
+                serde_json::json!({"stream": true}),
+            ))
+            .with_status(200)
+            .with_header("content-type", "text/event-stream")
+            .with_body(body)
+            .expect(1)
+            .create();
+        let chunks: Vec<_> = mocked_client()
+            .complete_prompt_stream(
+                CompletionArgs::builder()
+                    .model("davinci-002")
+                    .prompt("Tell"),
+            )
+            .await?
+            .try_collect()
+            .await?;
+        let text: String = chunks.iter().map(|c| c.to_string()).collect();
+        assert_eq!(text, "Once upon");
+        assert_eq!(chunks[1].choices[0].finish_reason, "stop");
+        m.assert();
+
+        let _error = mockito::mock("POST", "/completions")
+            .with_status(200)
+            .with_body("data: {\"error\": {\"message\": \"overloaded\", \"code\": null}}\n\n")
+            .create();
+        let mid_stream: Vec<_> = mocked_client()
+            .complete_prompt_stream("Tell")
+            .await?
+            .collect()
+            .await;
+        assert!(matches!(&mid_stream[..], [Err(Error::Api(err))] if err.message == "overloaded"));
+        Ok(())
+    }
+
     fn mock_chat() -> crate::Result<(Mock, ChatArgs, ChatAnswer)> {
         let mock = mockito::mock("POST", "/chat/completions")
             .with_status(200)
@@ -727,10 +3305,10 @@ mod unit {
             .create();
         let args = api::ChatArgs::builder()
             .model("gpt-3.5-turbo")
-            .messages(vec![ChatFormat {
-                role: ChatRole::System,
-                content: "You are a helpful assistant.".into(),
-            }])
+            .messages(vec![ChatFormat::new(
+                ChatRole::System,
+                "You are a helpful assistant.".into(),
+            )])
             .max_tokens(5)
             .temperature(1.0)
             .top_p(1.0)
@@ -742,13 +3320,18 @@ mod unit {
             id: "cmpl-uqkvlQyYK7bGYrRHQ0eXlWi7".into(),
             created: 1589478378,
             choices: vec![api::ChatChoice {
-                message: ChatFormat {
-                    role: ChatRole::Assistant,
-                    content: "\n\nHello there, how may I assist you today?".into(),
-                },
+                message: ChatFormat::new(
+                    ChatRole::Assistant,
+                    "\n\nHello there, how may I assist you today?".into(),
+                ),
                 index: 0,
+                logprobs: None,
                 finish_reason: "stop".into(),
+                extra: Default::default(),
             }],
+            usage: None,
+            system_fingerprint: None,
+            extra: Default::default(),
         };
         Ok((mock, args, expected))
     }
@@ -758,6 +3341,7 @@ mod unit {
     fn assert_chat_equal(a: ChatAnswer, b: ChatAnswer) {
         assert_eq!(a.id, b.id);
         assert_eq!(a.created, b.created);
+        assert_eq!(a.usage, b.usage);
         let (a_choice, b_choice) = (&a.choices[0], &b.choices[0]);
         assert_eq!(a_choice.message, b_choice.message);
         assert_eq!(a_choice.index, b_choice.index);
@@ -772,6 +3356,278 @@ mod unit {
         m.assert();
         Ok(())
     }
+
+    #[tokio::test]
+    async fn context_guard_rejects_oversized_requests() {
+        let client = mocked_client().with_context_guard(true);
+        let args = ChatArgs::builder()
+            .model("gpt-4-0613")
+            .messages(vec![ChatFormat::new(ChatRole::User, "a".repeat(3000))])
+            .max_tokens(8000)
+            .build()
+            .unwrap();
+        match client.chat(args).await {
+            Err(Error::ContextLengthExceeded { needed, available }) => {
+                assert!(needed > available && available == 8192, "{}", needed);
+            }
+            other => panic!("expected ContextLengthExceeded, got {:?}", other),
+        }
+
+        // About 6000 tokens of English prose and 1000 more for the answer fit
+        let prose = "The quick brown fox jumps over the lazy dog. ".repeat(600);
+        let args = ChatArgs::builder()
+            .model("gpt-4-0613")
+            .messages(vec![ChatFormat::new(ChatRole::User, prose)])
+            .max_tokens(1000)
+            .build()
+            .unwrap();
+        assert!(matches!(
+            client.clone().with_dry_run(true).chat(args).await,
+            Err(Error::DryRun { .. })
+        ));
+
+        // Unknown models aren't checked
+        let args = CompletionArgs::builder()
+            .model("llama3:8b")
+            .max_tokens(1_000_000)
+            .build()
+            .unwrap();
+        assert!(!matches!(
+            client.with_dry_run(true).complete_prompt(args).await,
+            Err(Error::ContextLengthExceeded { .. })
+        ));
+    }
+
+    #[test]
+    fn backend_sampling_params() -> crate::Result<()> {
+        let args = ChatArgs::builder()
+            .messages(vec![ChatFormat::new(ChatRole::User, "Hello!".into())])
+            .backend_sampling(BackendSampling {
+                top_k: Some(40),
+                min_p: Some(0.05),
+                ..BackendSampling::default()
+            })
+            .build()?;
+        let json = serde_json::to_value(&args)?;
+        assert_eq!(
+            (json["top_k"].as_u64(), json["min_p"].as_f64()),
+            (Some(40), Some(0.05))
+        );
+        assert!(json.get("repetition_penalty").is_none());
+        assert!(args.validate().is_ok());
+
+        let plain = serde_json::to_value(CompletionArgs::from("Hello"))?;
+        assert!(plain.get("top_k").is_none());
+
+        let invalid = CompletionArgs::builder()
+            .backend_sampling(BackendSampling {
+                min_p: Some(2.0),
+                repetition_penalty: Some(0.0),
+                ..BackendSampling::default()
+            })
+            .build()?;
+        match invalid.validate() {
+            Err(Error::BadArguments(msg)) => {
+                assert!(
+                    msg.contains("min_p") && msg.contains("repetition_penalty"),
+                    "{}",
+                    msg
+                )
+            }
+            other => panic!("expected BadArguments, got {:?}", other),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn parses_local_server_responses() -> crate::Result<()> {
+        // As sent by llama.cpp: no id, null created, extra fields
+        let answer: ChatAnswer = serde_json::from_str(
+            r#"{
+            "created": null,
+            "model": "llama-3-8b",
+            "system_fingerprint": "b3000",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "Hi!"},
+                "finish_reason": null,
+                "logprobs": null
+            }],
+            "timings": {"predicted_ms": 12.5}
+        }"#,
+        )?;
+        assert_eq!((answer.id.as_str(), answer.created), ("", 0));
+        assert_eq!(answer.choices[0].finish_reason, "");
+        assert_eq!(answer.system_fingerprint.as_deref(), Some("b3000"));
+        assert_eq!(answer.extra["model"], "llama-3-8b");
+        assert_eq!(answer.extra["timings"]["predicted_ms"], 12.5);
+        assert!(answer.choices[0].extra.is_empty());
+
+        let model: ModelInfo = serde_json::from_str(r#"{"id": "llama3:8b"}"#)?;
+        assert_eq!(model.id, "llama3:8b");
+
+        let client = Client::new("ollama")?.with_base_url("http://localhost:11434/v1")?;
+        assert_eq!(client.inner.base_url.as_str(), "http://localhost:11434/v1/");
+        assert!(matches!(
+            Client::new("ollama")?.with_base_url("localhost:11434"),
+            Err(Error::InvalidConfig(_))
+        ));
+        assert!(matches!(
+            Client::new("ollama")?.with_base_url("not a url"),
+            Err(Error::InvalidConfig(_))
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn validate_credentials() {
+        let _m = mockito::mock("GET", "/models")
+            .with_status(401)
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"{
+                "error": {
+                    "message": "Incorrect API key provided: bogus.",
+                    "type": "invalid_request_error",
+                    "code": "invalid_api_key"
+                }
+            }"#,
+            )
+            .create();
+        assert!(matches!(
+            mocked_client().validate().await,
+            Err(Error::Credentials {
+                failure: CredentialFailure::InvalidKey,
+                ..
+            })
+        ));
+
+        let unreachable = Client::new("bogus")
+            .unwrap()
+            .with_base_url("http://127.0.0.1:1/")
+            .unwrap();
+        assert!(matches!(
+            unreachable.validate().await,
+            Err(Error::Credentials {
+                failure: CredentialFailure::Unreachable,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn chat_args_share_messages() -> crate::Result<()> {
+        let history: std::sync::Arc<[ChatFormat]> = (0..100)
+            .map(|i| ChatFormat::new(ChatRole::User, i.to_string()))
+            .collect();
+        let builder = ChatArgs::builder().messages(history.clone());
+        let args = builder.temperature(0.0).max_tokens(16).build()?;
+        assert!(std::sync::Arc::ptr_eq(&args.messages, &history));
+        assert!(std::sync::Arc::ptr_eq(&args.clone().messages, &history));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn chat_accepts_builder() -> crate::Result<()> {
+        let (m, _, expected) = mock_chat()?;
+        let builder = ChatArgs::builder()
+            .messages(vec![ChatFormat::new(ChatRole::User, "Hello!".into())])
+            .max_tokens(5);
+        let response = mocked_client().chat(builder).await?;
+        assert_chat_equal(response, expected);
+        m.assert();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn chat_many_keeps_order_and_errors() -> crate::Result<()> {
+        let ok = mockito::mock("POST", "/chat/completions")
+            .match_body(mockito::Matcher::Regex("hello".into()))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"{
+                "id": "chatcmpl-123",
+                "created": 1677652288,
+                "choices": [{
+                    "index": 0,
+                    "message": {"role": "assistant", "content": "Hi!"},
+                    "finish_reason": "stop"
+                }]
+            }"#,
+            )
+            .expect(2)
+            .create();
+        let failed = mockito::mock("POST", "/chat/completions")
+            .match_body(mockito::Matcher::Regex("boom".into()))
+            .with_status(500)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"error": {"message": "boom", "code": "server_error"}}"#)
+            .create();
+        let args = ["hello", "boom", "hello"]
+            .iter()
+            .map(|content| {
+                ChatArgs::builder()
+                    .messages(vec![ChatFormat::new(ChatRole::User, content.to_string())])
+                    .build()
+                    .unwrap()
+            })
+            .collect();
+
+        let mut responses = mocked_client().chat_many(args, 2).await.into_iter();
+        assert_eq!(responses.next().unwrap()?.choices[0].message.content, "Hi!");
+        assert!(matches!(responses.next(), Some(Err(Error::Api(_)))));
+        assert_eq!(responses.next().unwrap()?.choices[0].message.content, "Hi!");
+        ok.assert();
+        failed.assert();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn complete_batch_keeps_order_and_errors() -> crate::Result<()> {
+        let completion = |prompt: &str, text: &str| {
+            mockito::mock("POST", "/completions")
+                .match_body(mockito::Matcher::PartialJsonString(format!(
+                    r#"{{"prompt": "{}"}}"#,
+                    prompt
+                )))
+                .with_status(200)
+                .with_header("content-type", "application/json")
+                .with_body(format!(
+                    r#"{{"id": "cmpl-1", "created": 1, "model": "davinci-002",
+                        "choices": [{{"text": "{}", "index": 0, "finish_reason": "stop"}}]}}"#,
+                    text
+                ))
+                .expect(1)
+                .create()
+        };
+        let (first, second) = (
+            completion("batch one", " is first"),
+            completion("batch three", " is third"),
+        );
+        let failed = mockito::mock("POST", "/completions")
+            .match_body(mockito::Matcher::PartialJsonString(
+                r#"{"prompt": "batch two"}"#.into(),
+            ))
+            .with_status(400)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"error": {"message": "bad prompt", "code": null}}"#)
+            .create();
+        let prompts = ["batch one", "batch two", "batch three"]
+            .iter()
+            .map(|prompt| CompletionArgs::builder().prompt(*prompt).build())
+            .collect::<std::result::Result<_, _>>()?;
+
+        let responses = mocked_client().complete_batch(prompts, 3).await;
+        assert_eq!(responses.len(), 3);
+        assert_eq!(responses[0].as_ref().unwrap().to_string(), " is first");
+        assert!(matches!(responses[1], Err(Error::Api(_))));
+        assert_eq!(responses[2].as_ref().unwrap().to_string(), " is third");
+        first.assert();
+        second.assert();
+        failed.assert();
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -875,10 +3731,10 @@ A:"#,
 
     fn stop_chat_args() -> api::ChatArgs {
         api::ChatArgs::builder()
-            .messages(vec![api::ChatFormat {
-                role: api::ChatRole::System,
-                content: "Hello there, how may I assist you today?".into(),
-            }])
+            .messages(vec![api::ChatFormat::new(
+                api::ChatRole::System,
+                "Hello there, how may I assist you today?".into(),
+            )])
             // turn temp & top_p way down to prevent test flakiness
             .temperature(0.0)
             .top_p(0.0)
@@ -0,0 +1,355 @@
+//! A scriptable fake of the API, for testing code written against [`OpenAiApi`].
+//!
+//! A [`MockClient`] answers each request with the next canned response queued for its
+//! method, after an optional latency, unless a failure was injected first. It records
+//! every call, so tests can check what their code asked for.
+//!
+//! # Example
+//! ```
+//! # use openai_api::{api::{ChatArgs, ChatRole}, mock::MockClient, OpenAiApi};
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), openai_api::Error> {
+//! let mock = MockClient::new();
+//! mock.push_chat_reply("Hello!");
+//! let args = ChatArgs::from(vec![(ChatRole::User, "Hi".to_string())]);
+//! assert_eq!(mock.chat(args).await?.choices[0].message.content, "Hello!");
+//! assert_eq!(mock.calls().len(), 1);
+//! # Ok(())
+//! # }
+//! ```
+use std::{collections::VecDeque, sync::Mutex, time::Duration};
+
+use async_trait::async_trait;
+use futures::StreamExt;
+
+use crate::{
+    api::{
+        ChatAnswer, ChatArgs, ChatChoice, ChatDelta, ChatDeltaChoice, ChatFormat, ChatFormatDelta,
+        ChatRole, Choice, Completion, CompletionArgs, FinishReason, ModelInfo,
+    },
+    embeddings::{Embeddings, EmbeddingsArgs},
+    traits::ChatDeltaStream,
+    Error, OpenAiApi, Result,
+};
+
+/// A call received by a `MockClient`, with its arguments
+// Calls are recorded once per request, so their size doesn't matter
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone)]
+pub enum Call {
+    Models,
+    Model(String),
+    CompletePrompt(CompletionArgs),
+    Chat(ChatArgs),
+    ChatStream(ChatArgs),
+    Embeddings(EmbeddingsArgs),
+}
+
+/// A fake `OpenAiApi` answering from queues of canned responses. See the module
+/// documentation.
+#[derive(Debug, Default)]
+pub struct MockClient {
+    latency: Duration,
+    state: Mutex<State>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    models: VecDeque<Result<Vec<ModelInfo>>>,
+    model: VecDeque<Result<ModelInfo>>,
+    completions: VecDeque<Result<Completion>>,
+    chats: VecDeque<Result<ChatAnswer>>,
+    chat_streams: VecDeque<Result<Vec<Result<ChatDelta>>>>,
+    embeddings: VecDeque<Result<Embeddings>>,
+    /// Errors to fail the next calls with, whatever their method
+    failures: VecDeque<Error>,
+    calls: Vec<Call>,
+}
+
+impl MockClient {
+    /// Creates a mock with no canned responses, answering without delay
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Delays every answer by `latency`
+    #[must_use]
+    pub fn with_latency(mut self, latency: Duration) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    /// Queues the response of a future `models` call
+    pub fn push_models(&self, response: Result<Vec<ModelInfo>>) {
+        self.lock().models.push_back(response);
+    }
+
+    /// Queues the response of a future `model` call
+    pub fn push_model(&self, response: Result<ModelInfo>) {
+        self.lock().model.push_back(response);
+    }
+
+    /// Queues the response of a future `complete_prompt` call
+    pub fn push_completion(&self, response: Result<Completion>) {
+        self.lock().completions.push_back(response);
+    }
+
+    /// Queues a completion of `text` as the response of a future `complete_prompt` call
+    pub fn push_completion_text(&self, text: impl Into<String>) {
+        self.push_completion(Ok(Completion {
+            id: "cmpl-mock".into(),
+            created: 0,
+            model: "mock".into(),
+            choices: vec![Choice {
+                text: text.into(),
+                index: 0,
+                logprobs: None,
+                finish_reason: "stop".into(),
+                extra: Default::default(),
+            }],
+            usage: None,
+            extra: Default::default(),
+        }));
+    }
+
+    /// Queues the response of a future `chat` call
+    pub fn push_chat(&self, response: Result<ChatAnswer>) {
+        self.lock().chats.push_back(response);
+    }
+
+    /// Queues an assistant message as the response of a future `chat` call
+    pub fn push_chat_reply(&self, content: impl Into<String>) {
+        self.push_chat(Ok(ChatAnswer {
+            id: "chatcmpl-mock".into(),
+            created: 0,
+            choices: vec![ChatChoice {
+                message: ChatFormat::new(ChatRole::Assistant, content.into()),
+                index: 0,
+                logprobs: None,
+                finish_reason: "stop".into(),
+                extra: Default::default(),
+            }],
+            usage: None,
+            system_fingerprint: None,
+            extra: Default::default(),
+        }));
+    }
+
+    /// Queues the response of a future `chat_stream` call: the deltas it streams, or the
+    /// error it fails with before streaming
+    pub fn push_chat_stream(&self, response: Result<Vec<Result<ChatDelta>>>) {
+        self.lock().chat_streams.push_back(response);
+    }
+
+    /// Queues an assistant message streamed in `pieces` as the response of a future
+    /// `chat_stream` call
+    pub fn push_chat_stream_reply(&self, pieces: &[&str]) {
+        let delta = |delta, finish_reason| ChatDelta {
+            id: "chatcmpl-mock".into(),
+            created: 0,
+            model: "mock".into(),
+            choices: vec![ChatDeltaChoice {
+                index: 0,
+                delta,
+                logprobs: None,
+                finish_reason,
+            }],
+            system_fingerprint: None,
+        };
+        let mut deltas = vec![Ok(delta(
+            ChatFormatDelta {
+                role: Some(ChatRole::Assistant),
+                ..ChatFormatDelta::default()
+            },
+            None,
+        ))];
+        deltas.extend(pieces.iter().map(|piece| {
+            Ok(delta(
+                ChatFormatDelta {
+                    content: Some(piece.to_string()),
+                    ..ChatFormatDelta::default()
+                },
+                None,
+            ))
+        }));
+        deltas.push(Ok(delta(
+            ChatFormatDelta::default(),
+            Some(FinishReason::Stop),
+        )));
+        self.push_chat_stream(Ok(deltas));
+    }
+
+    /// Queues the response of a future `embeddings` call
+    pub fn push_embeddings(&self, response: Result<Embeddings>) {
+        self.lock().embeddings.push_back(response);
+    }
+
+    /// Fails the next call, whatever its method, with `error`. Injected failures take
+    /// precedence over canned responses, which stay queued.
+    pub fn fail_next(&self, error: Error) {
+        self.lock().failures.push_back(error);
+    }
+
+    /// The calls received so far, oldest first
+    #[must_use]
+    pub fn calls(&self) -> Vec<Call> {
+        self.lock().calls.clone()
+    }
+
+    /// Records `call`, waits for the latency, then answers with an injected failure or
+    /// the response `next` takes from its queue
+    async fn respond<T>(
+        &self,
+        call: Call,
+        next: impl FnOnce(&mut State) -> Option<Result<T>>,
+    ) -> Result<T> {
+        let method = match &call {
+            Call::Models => "models",
+            Call::Model(_) => "model",
+            Call::CompletePrompt(_) => "complete_prompt",
+            Call::Chat(_) => "chat",
+            Call::ChatStream(_) => "chat_stream",
+            Call::Embeddings(_) => "embeddings",
+        };
+        self.lock().calls.push(call);
+        if !self.latency.is_zero() {
+            crate::runtime::sleep(self.latency).await;
+        }
+        let mut state = self.lock();
+        if let Some(error) = state.failures.pop_front() {
+            return Err(error);
+        }
+        next(&mut state)
+            .unwrap_or_else(|| Err(Error::Mock(format!("no response queued for {}", method))))
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl OpenAiApi for MockClient {
+    async fn models(&self) -> Result<Vec<ModelInfo>> {
+        self.respond(Call::Models, |s| s.models.pop_front()).await
+    }
+
+    async fn model(&self, model: &str) -> Result<ModelInfo> {
+        self.respond(Call::Model(model.into()), |s| s.model.pop_front())
+            .await
+    }
+
+    async fn complete_prompt(&self, args: CompletionArgs) -> Result<Completion> {
+        self.respond(Call::CompletePrompt(args), |s| s.completions.pop_front())
+            .await
+    }
+
+    async fn chat(&self, args: ChatArgs) -> Result<ChatAnswer> {
+        self.respond(Call::Chat(args), |s| s.chats.pop_front())
+            .await
+    }
+
+    async fn chat_stream(&self, args: ChatArgs) -> Result<ChatDeltaStream> {
+        let deltas = self
+            .respond(Call::ChatStream(args), |s| s.chat_streams.pop_front())
+            .await?;
+        #[cfg(not(target_arch = "wasm32"))]
+        return Ok(futures::stream::iter(deltas).boxed());
+        #[cfg(target_arch = "wasm32")]
+        return Ok(futures::stream::iter(deltas).boxed_local());
+    }
+
+    async fn embeddings(&self, args: EmbeddingsArgs) -> Result<Embeddings> {
+        self.respond(Call::Embeddings(args), |s| s.embeddings.pop_front())
+            .await
+    }
+}
+
+#[cfg(test)]
+mod unit {
+    use std::time::Duration;
+
+    use super::{Call, MockClient};
+    use crate::{
+        api::{ApiError, ChatArgs, ChatRole},
+        Error, OpenAiApi,
+    };
+
+    fn hello() -> ChatArgs {
+        ChatArgs::from(vec![(ChatRole::User, "Hello!".to_string())])
+    }
+
+    #[tokio::test]
+    async fn scripted_responses_and_failures() -> crate::Result<()> {
+        let mock = MockClient::new();
+        mock.push_chat_reply("first");
+        mock.push_chat_reply("second");
+        mock.push_completion_text("completed");
+        mock.fail_next(
+            ApiError {
+                message: "Rate limit reached".into(),
+                status: Some(reqwest::StatusCode::TOO_MANY_REQUESTS),
+                ..ApiError::default()
+            }
+            .into(),
+        );
+
+        assert!(matches!(mock.chat(hello()).await, Err(Error::Api(_))));
+        assert_eq!(
+            mock.chat(hello()).await?.choices[0].message.content,
+            "first"
+        );
+        assert_eq!(
+            mock.chat(hello()).await?.choices[0].message.content,
+            "second"
+        );
+        assert!(matches!(mock.chat(hello()).await, Err(Error::Mock(_))));
+        assert_eq!(
+            mock.complete_prompt("Hi".into()).await?.to_string(),
+            "completed"
+        );
+
+        let calls = mock.calls();
+        assert_eq!(calls.len(), 5);
+        assert!(matches!(calls[4], Call::CompletePrompt(_)));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn scripted_streams() -> crate::Result<()> {
+        use futures::TryStreamExt;
+
+        let mock = MockClient::new();
+        mock.push_chat_stream_reply(&["Hel", "lo!"]);
+        let deltas: Vec<_> = mock.chat_stream(hello()).await?.try_collect().await?;
+        let text: String = deltas.iter().map(|delta| delta.to_string()).collect();
+        assert_eq!(text, "Hello!");
+        assert_eq!(deltas.len(), 4);
+
+        mock.push_chat_stream(Ok(vec![Err(ApiError {
+            message: "overloaded".into(),
+            ..ApiError::default()
+        }
+        .into())]));
+        let mut stream = mock.chat_stream(hello()).await?;
+        assert!(matches!(stream.try_next().await, Err(Error::Api(_))));
+        assert!(matches!(
+            mock.chat_stream(hello()).await,
+            Err(Error::Mock(_))
+        ));
+        assert!(matches!(mock.calls()[2], Call::ChatStream(_)));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn injects_latency() -> crate::Result<()> {
+        let mock = MockClient::new().with_latency(Duration::from_millis(50));
+        mock.push_chat_reply("slow");
+        let start = tokio::time::Instant::now();
+        mock.chat(hello()).await?;
+        assert!(start.elapsed() >= Duration::from_millis(50));
+        Ok(())
+    }
+}
@@ -0,0 +1,166 @@
+//! Metadata about well-known models, used to check requests locally.
+//!
+//! The table only covers OpenAI models. Lookups of other models, e.g. those of a
+//! self-hosted server, return `None`, and checks relying on them are skipped.
+
+/// The endpoint family a model is served on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModelKind {
+    /// `chat/completions`
+    Chat,
+    /// The legacy `completions`
+    Completion,
+    /// `embeddings`
+    Embedding,
+}
+
+/// What is known about a family of models
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ModelSpec {
+    /// Prefix of the model ids in the family, e.g. `"gpt-4o"` for `"gpt-4o-2024-08-06"`
+    pub prefix: &'static str,
+    /// Endpoint the models are served on
+    pub kind: ModelKind,
+    /// Most tokens of prompt and completion combined
+    pub context_length: u64,
+    /// Whether the models accept images in their messages
+    pub vision: bool,
+}
+
+const fn spec(prefix: &'static str, kind: ModelKind, context_length: u64) -> ModelSpec {
+    ModelSpec {
+        prefix,
+        kind,
+        context_length,
+        vision: false,
+    }
+}
+
+/// A family of chat models accepting images
+const fn vision(prefix: &'static str, context_length: u64) -> ModelSpec {
+    ModelSpec {
+        vision: true,
+        ..spec(prefix, ModelKind::Chat, context_length)
+    }
+}
+
+const MODELS: &[ModelSpec] = &[
+    vision("gpt-4.1", 1_047_576),
+    vision("gpt-4o", 128_000),
+    vision("gpt-4-turbo", 128_000),
+    spec("gpt-4-turbo-preview", ModelKind::Chat, 128_000),
+    vision("gpt-4-vision-preview", 128_000),
+    vision("gpt-4.5-preview", 128_000),
+    vision("gpt-4-1106-vision-preview", 128_000),
+    spec("gpt-4-1106", ModelKind::Chat, 128_000),
+    spec("gpt-4-0125", ModelKind::Chat, 128_000),
+    spec("gpt-4-32k", ModelKind::Chat, 32_768),
+    spec("gpt-4", ModelKind::Chat, 8_192),
+    spec("gpt-3.5-turbo-instruct", ModelKind::Completion, 4_096),
+    spec("gpt-3.5-turbo-0613", ModelKind::Chat, 4_096),
+    spec("gpt-3.5-turbo-0301", ModelKind::Chat, 4_096),
+    spec("gpt-3.5-turbo", ModelKind::Chat, 16_385),
+    spec("o1-mini", ModelKind::Chat, 128_000),
+    spec("o1-preview", ModelKind::Chat, 128_000),
+    vision("o1", 200_000),
+    spec("o3-mini", ModelKind::Chat, 200_000),
+    vision("o3", 200_000),
+    vision("o4-mini", 200_000),
+    spec("text-davinci", ModelKind::Completion, 4_097),
+    spec("code-davinci", ModelKind::Completion, 8_001),
+    spec("davinci-002", ModelKind::Completion, 16_384),
+    spec("babbage-002", ModelKind::Completion, 16_384),
+    spec("text-curie", ModelKind::Completion, 2_049),
+    spec("text-babbage", ModelKind::Completion, 2_049),
+    spec("text-ada", ModelKind::Completion, 2_049),
+    spec("text-embedding", ModelKind::Embedding, 8_191),
+];
+
+/// Embedding models whose vectors can be shortened with the `dimensions` parameter, with
+/// their full number of dimensions
+const REDUCIBLE_EMBEDDINGS: &[(&str, u32)] = &[
+    ("text-embedding-3-small", 1_536),
+    ("text-embedding-3-large", 3_072),
+];
+
+/// Looks up the metadata of a model by its id.
+///
+/// Fine-tuned models, e.g. `"ft:gpt-3.5-turbo-0613:my-org::abc123"`, are looked up by their
+/// base model.
+///
+/// # Example
+/// ```
+/// # use openai_api::models::{lookup, ModelKind};
+/// let spec = lookup("gpt-4-0613").unwrap();
+/// assert_eq!(spec.kind, ModelKind::Chat);
+/// assert_eq!(spec.context_length, 8192);
+/// ```
+#[must_use]
+pub fn lookup(model: &str) -> Option<&'static ModelSpec> {
+    let model = model.strip_prefix("ft:").unwrap_or(model);
+    MODELS
+        .iter()
+        .filter(|spec| model.starts_with(spec.prefix))
+        .max_by_key(|spec| spec.prefix.len())
+}
+
+/// The context length of a model, if it is known
+#[must_use]
+pub fn context_length(model: &str) -> Option<u64> {
+    lookup(model).map(|spec| spec.context_length)
+}
+
+/// The most dimensions an embedding model can be asked for, if it supports the `dimensions`
+/// parameter
+///
+/// # Example
+/// ```
+/// # use openai_api::models::max_dimensions;
+/// assert_eq!(max_dimensions("text-embedding-3-large"), Some(3072));
+/// assert_eq!(max_dimensions("text-embedding-ada-002"), None);
+/// ```
+#[must_use]
+pub fn max_dimensions(model: &str) -> Option<u32> {
+    REDUCIBLE_EMBEDDINGS
+        .iter()
+        .find(|(prefix, _)| model.starts_with(prefix))
+        .map(|&(_, dimensions)| dimensions)
+}
+
+#[cfg(test)]
+mod unit {
+    use super::{context_length, lookup, ModelKind};
+
+    #[test]
+    fn lookup_by_longest_prefix() {
+        assert_eq!(context_length("gpt-4"), Some(8_192));
+        assert_eq!(context_length("gpt-4-32k-0613"), Some(32_768));
+        assert_eq!(context_length("gpt-4o-mini"), Some(128_000));
+        assert_eq!(context_length("gpt-4-vision-preview"), Some(128_000));
+        assert_eq!(context_length("gpt-4.5-preview"), Some(128_000));
+        assert_eq!(context_length("o1-preview-2024-09-12"), Some(128_000));
+        assert_eq!(context_length("gpt-3.5-turbo-0613"), Some(4_096));
+        assert_eq!(context_length("gpt-3.5-turbo-0125"), Some(16_385));
+        assert_eq!(
+            lookup("gpt-3.5-turbo-instruct").map(|s| s.kind),
+            Some(ModelKind::Completion)
+        );
+        assert_eq!(
+            lookup("ft:gpt-3.5-turbo-0613:my-org::abc123").map(|s| s.kind),
+            Some(ModelKind::Chat)
+        );
+        assert_eq!(lookup("llama3:8b"), None);
+    }
+
+    #[test]
+    fn vision_models() {
+        let vision = |model| lookup(model).map(|s| s.vision);
+        assert_eq!(vision("gpt-4o-mini"), Some(true));
+        assert_eq!(vision("gpt-4-turbo-2024-04-09"), Some(true));
+        assert_eq!(vision("gpt-4-vision-preview"), Some(true));
+        assert_eq!(vision("gpt-4-turbo-preview"), Some(false));
+        assert_eq!(vision("gpt-4-0613"), Some(false));
+        assert_eq!(vision("gpt-3.5-turbo"), Some(false));
+        assert_eq!(vision("o3-mini"), Some(false));
+    }
+}
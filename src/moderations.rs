@@ -0,0 +1,370 @@
+//! Moderations: whether texts violate OpenAI's usage policies, category by category.
+use serde::{Deserialize, Serialize};
+
+use crate::{api::Problems, Client, Error, Result};
+
+/// Arguments of a moderations request. Like the other args types, they can be deserialized
+/// as well as built; only `input` is required then.
+#[derive(Serialize, Deserialize, Debug, Builder, Clone)]
+#[builder(pattern = "immutable")]
+pub struct ModerationArgs {
+    /// The id of the moderation model, the API's default one if `None`
+    ///
+    /// # Example
+    /// ```
+    /// # use openai_api::moderations::ModerationArgs;
+    /// ModerationArgs::builder().model("omni-moderation-latest");
+    /// ```
+    #[builder(setter(into, strip_option), default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    model: Option<String>,
+    /// The texts to classify. Each one gets its own result in the response.
+    #[builder(setter(into))]
+    input: Vec<String>,
+}
+
+impl ModerationArgs {
+    /// Build a `ModerationArgs` from the defaults
+    #[must_use]
+    pub fn builder() -> ModerationArgsBuilder {
+        ModerationArgsBuilder::default()
+    }
+
+    /// Checks the arguments locally, without calling the API
+    ///
+    /// # Errors
+    /// - `Error::BadArguments` if there is no input
+    pub fn validate(&self) -> Result<()> {
+        let mut problems = Problems::default();
+        problems.check(!self.input.is_empty(), || {
+            "at least one input is required".into()
+        });
+        problems.into_result()
+    }
+}
+
+impl From<&str> for ModerationArgs {
+    fn from(input: &str) -> Self {
+        vec![input.to_string()].into()
+    }
+}
+
+impl From<Vec<String>> for ModerationArgs {
+    fn from(input: Vec<String>) -> Self {
+        ModerationArgs::builder()
+            .input(input)
+            .build()
+            .expect("input is the only required field")
+    }
+}
+
+impl TryFrom<ModerationArgsBuilder> for ModerationArgs {
+    type Error = ModerationArgsBuilderError;
+
+    fn try_from(builder: ModerationArgsBuilder) -> std::result::Result<Self, Self::Error> {
+        builder.build()
+    }
+}
+
+impl From<ModerationArgsBuilderError> for Error {
+    fn from(err: ModerationArgsBuilderError) -> Self {
+        Error::BadArguments(err.to_string())
+    }
+}
+
+/// Represents a moderations response
+#[derive(Deserialize, Debug, Clone)]
+pub struct Moderation {
+    /// Moderation unique identifier
+    #[serde(default, deserialize_with = "crate::api::lenient")]
+    pub id: String,
+    /// Exact model type and version used for the moderation
+    #[serde(default, deserialize_with = "crate::api::lenient")]
+    pub model: String,
+    /// One result per input, in input order
+    pub results: Vec<ModerationResult>,
+}
+
+/// The classification of one input
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct ModerationResult {
+    /// Whether the input violates any category
+    pub flagged: bool,
+    /// Whether the input violates each category
+    pub categories: Categories<bool>,
+    /// The model's confidence that the input violates each category, in `[0, 1]`
+    pub category_scores: Categories<f64>,
+}
+
+impl ModerationResult {
+    /// Whether the input violates `category`
+    #[must_use]
+    pub fn is_flagged_for(&self, category: Category) -> bool {
+        self.categories.get(category)
+    }
+
+    /// The model's confidence that the input violates `category`
+    #[must_use]
+    pub fn score(&self, category: Category) -> f64 {
+        self.category_scores.get(category)
+    }
+
+    /// The categories the input violates
+    pub fn flagged_categories(&self) -> impl Iterator<Item = Category> + '_ {
+        Category::ALL
+            .into_iter()
+            .filter(|category| self.is_flagged_for(*category))
+    }
+}
+
+/// A category of content the usage policies restrict
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Category {
+    Harassment,
+    HarassmentThreatening,
+    Hate,
+    HateThreatening,
+    Illicit,
+    IllicitViolent,
+    SelfHarm,
+    SelfHarmIntent,
+    SelfHarmInstructions,
+    Sexual,
+    SexualMinors,
+    Violence,
+    ViolenceGraphic,
+}
+
+impl Category {
+    /// Every category, in the order the API lists them
+    pub const ALL: [Category; 13] = [
+        Category::Harassment,
+        Category::HarassmentThreatening,
+        Category::Hate,
+        Category::HateThreatening,
+        Category::Illicit,
+        Category::IllicitViolent,
+        Category::SelfHarm,
+        Category::SelfHarmIntent,
+        Category::SelfHarmInstructions,
+        Category::Sexual,
+        Category::SexualMinors,
+        Category::Violence,
+        Category::ViolenceGraphic,
+    ];
+
+    /// The API's name of the category, e.g. `"self-harm/intent"`
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Category::Harassment => "harassment",
+            Category::HarassmentThreatening => "harassment/threatening",
+            Category::Hate => "hate",
+            Category::HateThreatening => "hate/threatening",
+            Category::Illicit => "illicit",
+            Category::IllicitViolent => "illicit/violent",
+            Category::SelfHarm => "self-harm",
+            Category::SelfHarmIntent => "self-harm/intent",
+            Category::SelfHarmInstructions => "self-harm/instructions",
+            Category::Sexual => "sexual",
+            Category::SexualMinors => "sexual/minors",
+            Category::Violence => "violence",
+            Category::ViolenceGraphic => "violence/graphic",
+        }
+    }
+}
+
+impl std::fmt::Display for Category {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A value per category: flags or scores. Categories a model doesn't support, e.g.
+/// `illicit` with the older text models, are `false` or `0.0`.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(bound(deserialize = "T: Default + Deserialize<'de>"))]
+pub struct Categories<T> {
+    #[serde(default, deserialize_with = "crate::api::lenient")]
+    pub harassment: T,
+    #[serde(
+        rename = "harassment/threatening",
+        default,
+        deserialize_with = "crate::api::lenient"
+    )]
+    pub harassment_threatening: T,
+    #[serde(default, deserialize_with = "crate::api::lenient")]
+    pub hate: T,
+    #[serde(
+        rename = "hate/threatening",
+        default,
+        deserialize_with = "crate::api::lenient"
+    )]
+    pub hate_threatening: T,
+    #[serde(default, deserialize_with = "crate::api::lenient")]
+    pub illicit: T,
+    #[serde(
+        rename = "illicit/violent",
+        default,
+        deserialize_with = "crate::api::lenient"
+    )]
+    pub illicit_violent: T,
+    #[serde(
+        rename = "self-harm",
+        default,
+        deserialize_with = "crate::api::lenient"
+    )]
+    pub self_harm: T,
+    #[serde(
+        rename = "self-harm/intent",
+        default,
+        deserialize_with = "crate::api::lenient"
+    )]
+    pub self_harm_intent: T,
+    #[serde(
+        rename = "self-harm/instructions",
+        default,
+        deserialize_with = "crate::api::lenient"
+    )]
+    pub self_harm_instructions: T,
+    #[serde(default, deserialize_with = "crate::api::lenient")]
+    pub sexual: T,
+    #[serde(
+        rename = "sexual/minors",
+        default,
+        deserialize_with = "crate::api::lenient"
+    )]
+    pub sexual_minors: T,
+    #[serde(default, deserialize_with = "crate::api::lenient")]
+    pub violence: T,
+    #[serde(
+        rename = "violence/graphic",
+        default,
+        deserialize_with = "crate::api::lenient"
+    )]
+    pub violence_graphic: T,
+}
+
+impl<T: Copy> Categories<T> {
+    /// The value of `category`
+    #[must_use]
+    pub fn get(&self, category: Category) -> T {
+        match category {
+            Category::Harassment => self.harassment,
+            Category::HarassmentThreatening => self.harassment_threatening,
+            Category::Hate => self.hate,
+            Category::HateThreatening => self.hate_threatening,
+            Category::Illicit => self.illicit,
+            Category::IllicitViolent => self.illicit_violent,
+            Category::SelfHarm => self.self_harm,
+            Category::SelfHarmIntent => self.self_harm_intent,
+            Category::SelfHarmInstructions => self.self_harm_instructions,
+            Category::Sexual => self.sexual,
+            Category::SexualMinors => self.sexual_minors,
+            Category::Violence => self.violence,
+            Category::ViolenceGraphic => self.violence_graphic,
+        }
+    }
+}
+
+impl Client {
+    /// Classifies whether texts violate OpenAI's usage policies
+    ///
+    /// Accepts anything convertible to `ModerationArgs`, including a text and an unbuilt
+    /// `ModerationArgsBuilder`.
+    ///
+    /// # Errors
+    ///  - `Error::BadArguments` if the arguments can't be built, or are invalid in dry-run
+    ///    mode
+    ///  - `Error::DryRun` in dry-run mode, if the arguments are valid
+    ///  - `Error::Api` if the api returns an error
+    pub async fn moderations<A>(&self, args: A) -> Result<Moderation>
+    where
+        A: TryInto<ModerationArgs>,
+        Error: From<<A as TryInto<ModerationArgs>>::Error>,
+    {
+        let args: ModerationArgs = args.try_into()?;
+        self.dry_run("moderations", &args, || args.validate())?;
+        self.post("moderations", args).await
+    }
+}
+
+#[cfg(test)]
+mod unit {
+    use super::{Category, ModerationArgs};
+    use crate::{unit::mocked_client, Error};
+
+    #[tokio::test]
+    async fn moderations() -> crate::Result<()> {
+        let mock = mockito::mock("POST", "/moderations")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"{
+                "id": "modr-XXXXX",
+                "model": "text-moderation-007",
+                "results": [{
+                    "flagged": true,
+                    "categories": {
+                        "sexual": false,
+                        "hate": false,
+                        "harassment": true,
+                        "self-harm": false,
+                        "sexual/minors": false,
+                        "hate/threatening": false,
+                        "violence/graphic": false,
+                        "self-harm/intent": false,
+                        "self-harm/instructions": false,
+                        "harassment/threatening": true,
+                        "violence": true,
+                        "illicit": null
+                    },
+                    "category_scores": {
+                        "sexual": 1.2282071e-06,
+                        "hate": 0.010696256,
+                        "harassment": 0.29842457,
+                        "self-harm": 1.5236925e-08,
+                        "sexual/minors": 5.7246268e-08,
+                        "hate/threatening": 0.0060676364,
+                        "violence/graphic": 4.435014e-06,
+                        "self-harm/intent": 8.098441e-10,
+                        "self-harm/instructions": 2.8498655e-11,
+                        "harassment/threatening": 0.63055265,
+                        "violence": 0.99011886
+                    }
+                }]
+            }"#,
+            )
+            .expect(1)
+            .create();
+        let moderation = mocked_client().moderations("I want to kill them.").await?;
+        assert_eq!(moderation.model, "text-moderation-007");
+        let result = &moderation.results[0];
+        assert!(result.flagged);
+        assert!(result.is_flagged_for(Category::Violence));
+        assert!(!result.is_flagged_for(Category::Illicit));
+        assert_eq!(result.score(Category::Violence), 0.99011886);
+        assert_eq!(result.score(Category::IllicitViolent), 0.0);
+        assert_eq!(
+            result.flagged_categories().collect::<Vec<_>>(),
+            [
+                Category::Harassment,
+                Category::HarassmentThreatening,
+                Category::Violence
+            ]
+        );
+        mock.assert();
+
+        let missing_input = mocked_client()
+            .moderations(ModerationArgs::builder().model("omni-moderation-latest"))
+            .await;
+        assert!(matches!(missing_input, Err(Error::BadArguments(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn category_names() {
+        assert_eq!(Category::SelfHarmIntent.to_string(), "self-harm/intent");
+        assert_eq!(Category::ALL.len(), 13);
+    }
+}
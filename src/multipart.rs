@@ -0,0 +1,118 @@
+//! `multipart/form-data` bodies, for the endpoints uploading files.
+//!
+//! The body is assembled in memory rather than streamed, so that it can be re-sent by
+//! retries like a JSON body.
+use reqwest::header::{HeaderValue, CONTENT_TYPE};
+
+/// A form being assembled
+pub(crate) struct Form {
+    boundary: String,
+    body: Vec<u8>,
+}
+
+impl Form {
+    pub(crate) fn new() -> Self {
+        let nanos = crate::runtime::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        Self {
+            boundary: format!("openai-api-{:x}", nanos),
+            body: Vec::new(),
+        }
+    }
+
+    /// Adds a text field
+    pub(crate) fn text(mut self, name: &str, value: &str) -> Self {
+        self.part_header(name, None);
+        self.body.extend_from_slice(value.as_bytes());
+        self.body.extend_from_slice(b"\r\n");
+        self
+    }
+
+    /// Adds a text field if there is a value
+    pub(crate) fn text_opt(self, name: &str, value: Option<&str>) -> Self {
+        match value {
+            Some(value) => self.text(name, value),
+            None => self,
+        }
+    }
+
+    /// Adds a file, whose `file_name` tells the server its format, e.g. `"speech.mp3"`
+    pub(crate) fn file(mut self, name: &str, file_name: &str, contents: &[u8]) -> Self {
+        self.part_header(name, Some(file_name));
+        self.body.extend_from_slice(contents);
+        self.body.extend_from_slice(b"\r\n");
+        self
+    }
+
+    fn part_header(&mut self, name: &str, file_name: Option<&str>) {
+        let mut header = format!(
+            "--{}\r\nContent-Disposition: form-data; name=\"{}\"",
+            self.boundary,
+            escape(name)
+        );
+        if let Some(file_name) = file_name {
+            header.push_str(&format!(
+                "; filename=\"{}\"\r\nContent-Type: application/octet-stream",
+                escape(file_name)
+            ));
+        }
+        header.push_str("\r\n\r\n");
+        self.body.extend_from_slice(header.as_bytes());
+    }
+
+    /// Sets the form as the body of `request`
+    pub(crate) fn attach(mut self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        self.body
+            .extend_from_slice(format!("--{}--\r\n", self.boundary).as_bytes());
+        let content_type = format!("multipart/form-data; boundary={}", self.boundary);
+        request
+            .header(
+                CONTENT_TYPE,
+                HeaderValue::from_str(&content_type).expect("the boundary is ASCII"),
+            )
+            .body(self.body)
+    }
+}
+
+/// Escapes the quotes and line breaks of a header parameter
+fn escape(value: &str) -> String {
+    value
+        .replace('"', "%22")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+#[cfg(test)]
+mod unit {
+    use super::Form;
+
+    #[test]
+    fn form_body() -> crate::Result<()> {
+        let form = Form::new()
+            .text("model", "whisper-1")
+            .text_opt("language", None)
+            .file("file", "a \"b\".mp3", b"ID3");
+        let boundary = form.boundary.clone();
+        let request = form
+            .attach(reqwest::Client::new().post("https://example.com/audio"))
+            .build()?;
+        assert_eq!(
+            request.headers()["content-type"],
+            format!("multipart/form-data; boundary={}", boundary).as_str()
+        );
+        let body = request.body().and_then(reqwest::Body::as_bytes).unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(body),
+            format!(
+                "--{b}\r\nContent-Disposition: form-data; name=\"model\"\r\n\r\nwhisper-1\r\n\
+                 --{b}\r\nContent-Disposition: form-data; name=\"file\"; \
+                 filename=\"a %22b%22.mp3\"\r\nContent-Type: application/octet-stream\r\n\r\n\
+                 ID3\r\n--{b}--\r\n",
+                b = boundary
+            )
+        );
+        Ok(())
+    }
+}
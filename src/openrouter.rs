@@ -0,0 +1,157 @@
+//! OpenRouter, a gateway routing OpenAI-style requests to many model providers.
+//!
+//! Point a client at OpenRouter with [`Client::openrouter`]. Its routing options are set on
+//! `ChatArgs`: `models` lists fallback models, and `provider` takes the
+//! [`ProviderPreferences`] picking who serves the request. Both are left out of requests
+//! when unset, so the same args work against the OpenAI API. OpenRouter's error details
+//! end up in `ApiError::metadata`.
+//!
+//! # Example
+//! ```
+//! # use openai_api::{api::ChatArgs, openrouter::ProviderPreferences, Client};
+//! # fn main() -> Result<(), openai_api::Error> {
+//! let client = Client::openrouter("sk-or-...")?
+//!     .with_openrouter_app("https://example.com", "My App")?;
+//! let args = ChatArgs::builder()
+//!     .model("openai/gpt-4o")
+//!     .models(vec!["anthropic/claude-3.5-sonnet".into()])
+//!     .provider(ProviderPreferences {
+//!         order: Some(vec!["OpenAI".into(), "Azure".into()]),
+//!         allow_fallbacks: Some(false),
+//!         ..ProviderPreferences::default()
+//!     })
+//!     .build()?;
+//! # Ok(())
+//! # }
+//! ```
+use serde::{Deserialize, Serialize};
+
+use crate::{Client, Result};
+
+/// Base URL of OpenRouter's OpenAI-compatible API
+pub const BASE_URL: &str = "https://openrouter.ai/api/v1/";
+
+/// Whether providers that may store or train on prompts can serve a request
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DataCollection {
+    Allow,
+    Deny,
+}
+
+/// OpenRouter's provider routing preferences. Unset fields keep OpenRouter's defaults.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct ProviderPreferences {
+    /// Providers to try first, in order, e.g. `["OpenAI", "Together"]`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub order: Option<Vec<String>>,
+    /// Whether to use providers not in `order` when those fail
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allow_fallbacks: Option<bool>,
+    /// Only use providers supporting every parameter of the request
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub require_parameters: Option<bool>,
+    /// Whether providers that collect data may serve the request
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data_collection: Option<DataCollection>,
+    /// Providers never to use
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ignore: Option<Vec<String>>,
+}
+
+impl Client {
+    /// Creates a client of OpenRouter, authenticated with an OpenRouter key
+    ///
+    /// # Errors
+    /// - `Error::AsyncProtocol` if the HTTP client can't be created
+    pub fn openrouter(token: &str) -> Result<Self> {
+        Self::new(token)?.with_base_url(BASE_URL)
+    }
+
+    /// Identifies the calling app to OpenRouter, with the `HTTP-Referer` and `X-Title`
+    /// headers used for its rankings
+    ///
+    /// # Errors
+    /// - `Error::InvalidConfig` if the URL or title aren't valid header values
+    pub fn with_openrouter_app(self, url: &str, title: &str) -> Result<Self> {
+        self.with_header("HTTP-Referer", url)?
+            .with_header("X-Title", title)
+    }
+}
+
+#[cfg(test)]
+mod unit {
+    use super::{DataCollection, ProviderPreferences};
+    use crate::{
+        api::{ApiError, ChatArgs, ChatRole},
+        unit::mocked_client,
+        Error,
+    };
+
+    #[test]
+    fn routing_fields_only_when_set() -> crate::Result<()> {
+        let plain = serde_json::to_value(ChatArgs::builder().build()?)?;
+        assert!(plain.get("models").is_none() && plain.get("provider").is_none());
+
+        let routed = ChatArgs::builder()
+            .models(vec!["mistralai/mixtral-8x7b".into()])
+            .provider(ProviderPreferences {
+                data_collection: Some(DataCollection::Deny),
+                ..ProviderPreferences::default()
+            })
+            .build()?;
+        let routed = serde_json::to_value(routed)?;
+        assert_eq!(routed["models"][0], "mistralai/mixtral-8x7b");
+        assert_eq!(
+            routed["provider"],
+            serde_json::json!({"data_collection": "deny"})
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn parses_extended_errors() -> crate::Result<()> {
+        let error: ApiError = serde_json::from_str(
+            r#"{
+            "code": 402,
+            "message": "Insufficient credits",
+            "metadata": {"provider_name": "OpenAI"}
+        }"#,
+        )?;
+        assert_eq!(error.code.as_deref(), Some("402"));
+        assert_eq!(error.metadata.unwrap()["provider_name"], "OpenAI");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn sends_app_headers() -> crate::Result<()> {
+        let mock = mockito::mock("POST", "/chat/completions")
+            .match_header("http-referer", "https://example.com")
+            .match_header("x-title", "Example")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"{
+                "id": "gen-123",
+                "created": 1677652288,
+                "choices": [{
+                    "index": 0,
+                    "message": {"role": "assistant", "content": "Routed!"},
+                    "finish_reason": "stop"
+                }]
+            }"#,
+            )
+            .expect(1)
+            .create();
+        let client = mocked_client().with_openrouter_app("https://example.com", "Example")?;
+        let args = ChatArgs::from(vec![(ChatRole::User, "Hello!".to_string())]);
+        assert_eq!(client.chat(args).await?.id, "gen-123");
+        mock.assert();
+
+        assert!(matches!(
+            mocked_client().with_header("X-Title", "line\nbreak"),
+            Err(Error::InvalidConfig(_))
+        ));
+        Ok(())
+    }
+}
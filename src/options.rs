@@ -0,0 +1,155 @@
+//! Options of individual requests, see [`Client::with_options`].
+//!
+//! Requests are cancelled when their future is dropped, e.g. by `tokio::select!` or
+//! `tokio::time::timeout`: the connection is closed, without waiting for the response.
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Duration;
+
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+
+use crate::{Error, Result};
+
+/// Header naming the organization billed for a request
+pub(crate) const ORGANIZATION_HEADER: &str = "OpenAI-Organization";
+/// Header naming the project billed for a request
+pub(crate) const PROJECT_HEADER: &str = "OpenAI-Project";
+
+/// Settings applied to some requests only, such as tags read by an observability gateway.
+///
+/// Options are applied by [`Client::with_options`], which returns a client making its
+/// requests with them.
+///
+/// [`Client::with_options`]: crate::Client::with_options
+#[derive(Debug, Clone, Default)]
+pub struct RequestOptions {
+    pub(crate) headers: HeaderMap,
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) timeout: Option<Duration>,
+}
+
+impl RequestOptions {
+    /// Options changing nothing
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sends the `name` header with `value`, replacing any value the client would send
+    ///
+    /// # Errors
+    /// - `Error::InvalidConfig` if the name or value isn't a valid header
+    pub fn header(mut self, name: &str, value: &str) -> Result<Self> {
+        let (name, value) = header(name, value)?;
+        self.headers.insert(name, value);
+        Ok(self)
+    }
+
+    /// Fails the requests with `Error::Timeout` when they take longer than `timeout`,
+    /// from connecting to reading the response. Retries, see `Client::with_retry_policy`,
+    /// each get the whole timeout.
+    ///
+    /// # Example
+    /// ```
+    /// # use std::time::Duration;
+    /// # use openai_api::{api::{ChatArgs, ChatRole}, options::RequestOptions, Client};
+    /// # async fn run(client: Client) -> Result<(), openai_api::Error> {
+    /// let args = ChatArgs::from(vec![(ChatRole::User, "Hello!".to_string())]);
+    /// let options = RequestOptions::new().timeout(Duration::from_secs(10));
+    /// let answer = client.with_options(&options).chat(args).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[must_use]
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Bills the requests to `organization` instead of the client's organization, see
+    /// [`Client::with_organization`]
+    ///
+    /// [`Client::with_organization`]: crate::Client::with_organization
+    ///
+    /// # Errors
+    /// - `Error::InvalidConfig` if `organization` isn't a valid header value
+    pub fn organization(self, organization: &str) -> Result<Self> {
+        self.header(ORGANIZATION_HEADER, organization)
+    }
+
+    /// Bills the requests to `project` instead of the client's project, see
+    /// [`Client::with_project`]
+    ///
+    /// [`Client::with_project`]: crate::Client::with_project
+    ///
+    /// # Errors
+    /// - `Error::InvalidConfig` if `project` isn't a valid header value
+    pub fn project(self, project: &str) -> Result<Self> {
+        self.header(PROJECT_HEADER, project)
+    }
+}
+
+/// Parses a header name and value
+pub(crate) fn header(name: &str, value: &str) -> Result<(HeaderName, HeaderValue)> {
+    let name = HeaderName::from_bytes(name.as_bytes())
+        .map_err(|e| Error::InvalidConfig(format!("invalid header name {:?}: {}", name, e)))?;
+    let value = HeaderValue::from_str(value)
+        .map_err(|e| Error::InvalidConfig(format!("invalid value of {}: {}", name, e)))?;
+    Ok((name, value))
+}
+
+#[cfg(test)]
+mod unit {
+    use std::time::Duration;
+
+    use tokio::{io::AsyncReadExt, net::TcpListener};
+
+    use super::RequestOptions;
+    use crate::{Client, Error};
+
+    /// A server reading requests and never answering. Returns its URL, and a receiver of
+    /// the number of bytes read until each connection was closed.
+    async fn silent_server() -> (String, tokio::sync::mpsc::UnboundedReceiver<usize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/v1/", listener.local_addr().unwrap());
+        let (sender, receiver) = tokio::sync::mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let sender = sender.clone();
+                tokio::spawn(async move {
+                    let mut buffer = [0; 4096];
+                    let mut read = 0;
+                    while let Ok(n @ 1..) = stream.read(&mut buffer).await {
+                        read += n;
+                    }
+                    let _ = sender.send(read);
+                });
+            }
+        });
+        (url, receiver)
+    }
+
+    #[tokio::test]
+    async fn times_out() -> crate::Result<()> {
+        let (url, mut closed) = silent_server().await;
+        let client = Client::new("bogus")?.with_base_url(&url)?;
+        let options = RequestOptions::new().timeout(Duration::from_millis(50));
+        assert!(matches!(
+            client.with_options(&options).models().await,
+            Err(Error::Timeout(_))
+        ));
+        assert!(closed.recv().await.unwrap() > 0);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn dropped_requests_are_cancelled() -> crate::Result<()> {
+        let (url, mut closed) = silent_server().await;
+        let client = Client::new("bogus")?.with_base_url(&url)?;
+        let request = tokio::time::timeout(Duration::from_millis(50), client.models()).await;
+        assert!(request.is_err());
+        let closed = tokio::time::timeout(Duration::from_secs(5), closed.recv()).await;
+        assert!(closed.unwrap().unwrap() > 0);
+        Ok(())
+    }
+}
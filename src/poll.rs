@@ -0,0 +1,138 @@
+//! Polling of the objects the API works on in the background, e.g. assistant runs.
+use std::{future::Future, time::Duration};
+
+use crate::{Error, Result};
+
+/// How often, and for how long, to poll an object until it is done.
+///
+/// The interval before poll `n + 1` is `initial_interval * multiplier^(n - 1)`, capped at
+/// `max_interval`: short jobs are noticed quickly, long ones don't burn requests.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PollConfig {
+    /// Interval before the second poll
+    pub initial_interval: Duration,
+    /// Upper bound of the interval between two polls
+    pub max_interval: Duration,
+    /// Growth factor of the interval between successive polls
+    pub multiplier: f64,
+    /// How long to wait in total before giving up with `Error::PollTimeout`, or `None` to
+    /// wait forever
+    pub timeout: Option<Duration>,
+}
+
+impl Default for PollConfig {
+    fn default() -> Self {
+        Self {
+            initial_interval: Duration::from_millis(500),
+            max_interval: Duration::from_secs(10),
+            multiplier: 1.5,
+            timeout: Some(Duration::from_secs(10 * 60)),
+        }
+    }
+}
+
+impl PollConfig {
+    /// Interval to wait after poll number `poll`, counting from 1
+    #[must_use]
+    pub fn interval(&self, poll: u32) -> Duration {
+        let exponent = poll.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let secs = self.initial_interval.as_secs_f64() * self.multiplier.powi(exponent);
+        // Intervals too long for a `Duration` are capped as well
+        Duration::try_from_secs_f64(secs).map_or(self.max_interval, |interval| {
+            interval.min(self.max_interval)
+        })
+    }
+}
+
+/// Calls `fetch` until `done` holds for what it returns, reporting each intermediate state
+/// to `progress`.
+///
+/// `first` is the state already known, e.g. the object just created: it is checked before
+/// any request is made.
+pub(crate) async fn until<T, F, Fut>(
+    config: &PollConfig,
+    first: T,
+    mut fetch: F,
+    done: impl Fn(&T) -> bool,
+    mut progress: impl FnMut(&T),
+) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let start = crate::runtime::Instant::now();
+    let mut state = first;
+    let mut poll = 1;
+    while !done(&state) {
+        progress(&state);
+        let mut interval = config.interval(poll);
+        if let Some(timeout) = config.timeout {
+            let left = timeout.saturating_sub(start.elapsed());
+            if left.is_zero() {
+                return Err(Error::PollTimeout {
+                    waited: start.elapsed(),
+                });
+            }
+            interval = interval.min(left);
+        }
+        crate::runtime::sleep(interval).await;
+        state = fetch().await?;
+        poll += 1;
+    }
+    Ok(state)
+}
+
+#[cfg(test)]
+mod unit {
+    use std::time::Duration;
+
+    use super::PollConfig;
+    use crate::Error;
+
+    #[test]
+    fn intervals_grow_to_the_max() {
+        let config = PollConfig {
+            initial_interval: Duration::from_secs(1),
+            max_interval: Duration::from_secs(3),
+            multiplier: 2.0,
+            timeout: None,
+        };
+        let intervals: Vec<_> = (1..=4)
+            .map(|poll| config.interval(poll).as_secs())
+            .collect();
+        assert_eq!(intervals, vec![1, 2, 3, 3]);
+        assert_eq!(config.interval(10_000), Duration::from_secs(3));
+        assert_eq!(
+            PollConfig::default().interval(10_000),
+            Duration::from_secs(10)
+        );
+    }
+
+    #[tokio::test]
+    async fn polls_until_done_or_timeout() {
+        let config = PollConfig {
+            initial_interval: Duration::from_millis(1),
+            max_interval: Duration::from_millis(1),
+            multiplier: 1.0,
+            timeout: Some(Duration::from_millis(200)),
+        };
+        let mut seen = vec![];
+        let mut next = 0;
+        let done = super::until(
+            &config,
+            0,
+            || {
+                next += 1;
+                std::future::ready(Ok(next))
+            },
+            |n| *n == 3,
+            |n| seen.push(*n),
+        )
+        .await;
+        assert_eq!(done.unwrap(), 3);
+        assert_eq!(seen, vec![0, 1, 2]);
+
+        let never = super::until(&config, 0, || std::future::ready(Ok(0)), |_| false, |_| {}).await;
+        assert!(matches!(never, Err(Error::PollTimeout { .. })));
+    }
+}
@@ -0,0 +1,115 @@
+//! A pool of the byte buffers in which streamed responses are assembled, so that many
+//! concurrent streams reuse a few allocations rather than making their own.
+use std::{
+    ops::{Deref, DerefMut},
+    sync::Mutex,
+};
+
+/// Most buffers kept for reuse
+const MAX_POOLED: usize = 64;
+/// Buffers grown larger than this are freed rather than kept, so that one huge response
+/// doesn't pin its memory
+const MAX_CAPACITY: usize = 1 << 20;
+
+/// The pool shared by every response
+static POOL: BufferPool = BufferPool::new();
+
+#[derive(Debug)]
+pub(crate) struct BufferPool {
+    buffers: Mutex<Vec<Vec<u8>>>,
+}
+
+impl BufferPool {
+    const fn new() -> Self {
+        Self {
+            buffers: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// An empty buffer, reusing a pooled one if there is any
+    fn take(&'static self) -> Buffer {
+        let pooled = self.buffers.lock().unwrap_or_else(|e| e.into_inner()).pop();
+        Buffer {
+            bytes: pooled.unwrap_or_default(),
+            pool: self,
+        }
+    }
+
+    fn put(&self, mut buffer: Vec<u8>) {
+        if buffer.capacity() == 0 || buffer.capacity() > MAX_CAPACITY {
+            return;
+        }
+        buffer.clear();
+        let mut buffers = self.buffers.lock().unwrap_or_else(|e| e.into_inner());
+        if buffers.len() < MAX_POOLED {
+            buffers.push(buffer);
+        }
+    }
+}
+
+/// A byte buffer returned to its pool when dropped
+#[derive(Debug)]
+pub(crate) struct Buffer {
+    bytes: Vec<u8>,
+    pool: &'static BufferPool,
+}
+
+impl Default for Buffer {
+    /// An empty buffer from the shared pool
+    fn default() -> Self {
+        POOL.take()
+    }
+}
+
+impl Drop for Buffer {
+    fn drop(&mut self) {
+        self.pool.put(std::mem::take(&mut self.bytes));
+    }
+}
+
+impl Deref for Buffer {
+    type Target = Vec<u8>;
+
+    fn deref(&self) -> &Vec<u8> {
+        &self.bytes
+    }
+}
+
+impl DerefMut for Buffer {
+    fn deref_mut(&mut self) -> &mut Vec<u8> {
+        &mut self.bytes
+    }
+}
+
+#[cfg(test)]
+mod unit {
+    use super::{BufferPool, MAX_CAPACITY, MAX_POOLED};
+
+    #[test]
+    fn reuses_buffers_within_limits() {
+        static POOL: BufferPool = BufferPool::new();
+        let mut buffer = POOL.take();
+        buffer.extend_from_slice(b"data: {}");
+        let allocation = buffer.as_ptr();
+        drop(buffer);
+        let buffer = POOL.take();
+        assert!(buffer.is_empty());
+        assert_eq!(buffer.as_ptr(), allocation);
+        drop(buffer);
+
+        let mut huge = POOL.take();
+        huge.reserve(MAX_CAPACITY + 1);
+        drop(huge);
+        assert_eq!(POOL.buffers.lock().unwrap().len(), 0);
+
+        let buffers: Vec<_> = (0..2 * MAX_POOLED)
+            .map(|_| {
+                let mut buffer = POOL.take();
+                buffer.push(0);
+                buffer
+            })
+            .collect();
+        drop(buffers);
+        assert_eq!(POOL.buffers.lock().unwrap().len(), MAX_POOLED);
+    }
+}
@@ -0,0 +1,231 @@
+//! Presets of OpenAI-compatible providers.
+use crate::{Client, Result};
+
+/// A provider of an OpenAI-compatible API. [`Client::for_provider`] configures its base
+/// URL, how it authenticates, and its known quirks.
+///
+/// # Example
+/// ```
+/// # use openai_api::{provider::Provider, Client};
+/// # fn main() -> Result<(), openai_api::Error> {
+/// let client = Client::for_provider(Provider::Groq, "gsk_...")?;
+/// let azure = Client::for_provider(
+///     Provider::Azure {
+///         resource: "my-resource".into(),
+///         deployment: "gpt-4o".into(),
+///         api_version: "2024-06-01".into(),
+///     },
+///     "azure-key",
+/// )?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Provider {
+    OpenAI,
+    Groq,
+    Mistral,
+    DeepSeek,
+    Together,
+    /// See the `gemini` module
+    Gemini,
+    /// A deployment of Azure OpenAI. Requests go to the deployment whatever their
+    /// `model`.
+    Azure {
+        /// Name of the Azure OpenAI resource, as in `https://{resource}.openai.azure.com`
+        resource: String,
+        /// Name of the model deployment
+        deployment: String,
+        /// Version of the API, e.g. `"2024-06-01"`
+        api_version: String,
+    },
+}
+
+impl Provider {
+    /// Base URL of the provider's API, ending with a slash
+    #[must_use]
+    pub fn base_url(&self) -> String {
+        match self {
+            Provider::OpenAI => "https://api.openai.com/v1/".into(),
+            Provider::Groq => "https://api.groq.com/openai/v1/".into(),
+            Provider::Mistral => "https://api.mistral.ai/v1/".into(),
+            Provider::DeepSeek => "https://api.deepseek.com/v1/".into(),
+            Provider::Together => "https://api.together.xyz/v1/".into(),
+            Provider::Gemini => crate::gemini::BASE_URL.into(),
+            Provider::Azure {
+                resource,
+                deployment,
+                ..
+            } => format!(
+                "https://{}.openai.azure.com/openai/deployments/{}/",
+                resource, deployment
+            ),
+        }
+    }
+
+    /// Name of the header carrying the key: `Authorization` with a bearer token, or
+    /// Azure's `api-key` with the bare key
+    #[must_use]
+    pub fn auth_header(&self) -> &'static str {
+        match self {
+            Provider::Azure { .. } => "api-key",
+            _ => "Authorization",
+        }
+    }
+
+    /// Request fields the provider rejects, which are left out of requests
+    #[must_use]
+    pub fn omitted_fields(&self) -> &'static [&'static str] {
+        match self {
+            Provider::Groq | Provider::Mistral | Provider::DeepSeek => &["logit_bias"],
+            Provider::Gemini => crate::gemini::UNSUPPORTED_FIELDS,
+            _ => &[],
+        }
+    }
+}
+
+impl Client {
+    /// Creates a client of `provider`, authenticated with `key`
+    ///
+    /// # Errors
+    /// - `Error::AsyncProtocol` if the HTTP client can't be created
+    pub fn for_provider(provider: Provider, key: &str) -> Result<Self> {
+        let client = match &provider {
+            Provider::Azure { api_version, .. } => Self::azure(key, api_version)?,
+            _ => Self::new(key)?,
+        };
+        Ok(client
+            .with_base_url(&provider.base_url())?
+            .with_omitted_fields(provider.omitted_fields()))
+    }
+
+    /// Creates a client of the Azure OpenAI `deployment` served at `endpoint`, e.g.
+    /// `https://my-resource.openai.azure.com` or a custom domain, authenticated with
+    /// `api_key`. Requests go to the deployment whatever their `model`. For resources on
+    /// the default domain, `Provider::Azure` does the same.
+    ///
+    /// # Example
+    /// ```
+    /// # use openai_api::Client;
+    /// # fn main() -> Result<(), openai_api::Error> {
+    /// let client = Client::new_azure(
+    ///     "https://my-resource.openai.azure.com",
+    ///     "azure-key",
+    ///     "gpt-4o",
+    ///     "2024-06-01",
+    /// )?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    /// - `Error::InvalidConfig` if `endpoint` isn't a valid URL
+    /// - `Error::AsyncProtocol` if the HTTP client can't be created
+    pub fn new_azure(
+        endpoint: &str,
+        api_key: &str,
+        deployment: &str,
+        api_version: &str,
+    ) -> Result<Self> {
+        let base_url = format!(
+            "{}/openai/deployments/{}/",
+            endpoint.trim_end_matches('/'),
+            deployment
+        );
+        Self::azure(api_key, api_version)?.with_base_url(&base_url)
+    }
+
+    /// Private helper creating a client authenticating with Azure's `api-key` header, and
+    /// passing the `api-version` of every request
+    fn azure(api_key: &str, api_version: &str) -> Result<Self> {
+        let mut client = Self::with_auth_header("api-key", api_key)?;
+        client
+            .inner_mut()
+            .query
+            .push(("api-version".into(), api_version.into()));
+        Ok(client)
+    }
+}
+
+#[cfg(test)]
+mod unit {
+    use super::Provider;
+    use crate::{
+        api::{ChatArgs, ChatRole},
+        Client,
+    };
+
+    #[tokio::test]
+    async fn azure_deployment() -> crate::Result<()> {
+        let mock = mockito::mock("POST", "/chat/completions?api-version=2024-06-01")
+            .match_header("api-key", "azure-key")
+            .match_header("authorization", mockito::Matcher::Missing)
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"{
+                "id": "chatcmpl-azure",
+                "created": 1677652288,
+                "choices": [{
+                    "index": 0,
+                    "message": {"role": "assistant", "content": "Hi!"},
+                    "finish_reason": "stop"
+                }]
+            }"#,
+            )
+            .expect(1)
+            .create();
+        let provider = Provider::Azure {
+            resource: "res".into(),
+            deployment: "gpt-4o".into(),
+            api_version: "2024-06-01".into(),
+        };
+        assert_eq!(
+            provider.base_url(),
+            "https://res.openai.azure.com/openai/deployments/gpt-4o/"
+        );
+        let client =
+            Client::for_provider(provider, "azure-key")?.with_base_url(&mockito::server_url())?;
+        let args = ChatArgs::from(vec![(ChatRole::User, "Hello!".to_string())]);
+        assert_eq!(client.chat(args).await?.id, "chatcmpl-azure");
+        mock.assert();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn azure_endpoint() -> crate::Result<()> {
+        let mock = mockito::mock(
+            "POST",
+            "/openai/deployments/gpt-4o/chat/completions?api-version=2024-06-01",
+        )
+        .match_header("api-key", "azure-key")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(r#"{"id": "chatcmpl-azure", "created": 1677652288, "choices": []}"#)
+        .expect(1)
+        .create();
+        let endpoint = format!("{}/", mockito::server_url());
+        let client = Client::new_azure(&endpoint, "azure-key", "gpt-4o", "2024-06-01")?;
+        let args = ChatArgs::from(vec![(ChatRole::User, "Hello!".to_string())]);
+        assert_eq!(client.chat(args).await?.id, "chatcmpl-azure");
+        mock.assert();
+
+        assert!(matches!(
+            Client::new_azure("not a url", "azure-key", "gpt-4o", "2024-06-01"),
+            Err(crate::Error::InvalidConfig(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn provider_quirks() -> crate::Result<()> {
+        let client = Client::for_provider(Provider::Groq, "gsk_key")?;
+        assert_eq!(
+            client.inner.base_url.as_str(),
+            "https://api.groq.com/openai/v1/"
+        );
+        assert_eq!(&*client.inner.omitted_fields, ["logit_bias"]);
+        assert!(client.inner.query.is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,336 @@
+//! A background queue draining chat and completion requests within rate budgets.
+//!
+//! Jobs are submitted with a [`Priority`] and return a [`Ticket`], a future resolving to the
+//! job's result. A background task starts the highest priority jobs first, keeps the
+//! requests and (estimated) tokens sent in any minute within the configured budgets, and
+//! retries transient failures according to the queue's [`RetryPolicy`].
+use std::{
+    cmp::Ordering,
+    collections::BinaryHeap,
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use tokio::sync::{mpsc, oneshot, Semaphore};
+
+use crate::{
+    api::{ChatAnswer, ChatArgs, Completion, CompletionArgs},
+    retry::RetryPolicy,
+    runtime::Instant,
+    throttle::Budget,
+    Client, Error, Result,
+};
+
+/// Scheduling priority of a queued job. Higher priorities are started first, jobs of equal
+/// priority in submission order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum Priority {
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+/// Configuration of a [`RequestQueue`]
+#[derive(Debug, Clone)]
+pub struct QueueConfig {
+    /// Most requests to start in any one minute, unlimited if `None`
+    pub requests_per_minute: Option<u32>,
+    /// Most tokens to spend in any one minute, unlimited if `None`.
+    ///
+    /// The tokens of a request are estimated before sending it, from the size of the prompt
+    /// and `max_tokens`.
+    pub tokens_per_minute: Option<u64>,
+    /// Most requests in flight at once
+    pub concurrency: usize,
+    /// How to retry requests failing transiently
+    pub retry: RetryPolicy,
+}
+
+impl Default for QueueConfig {
+    fn default() -> Self {
+        Self {
+            requests_per_minute: None,
+            tokens_per_minute: None,
+            concurrency: 8,
+            retry: RetryPolicy::default(),
+        }
+    }
+}
+
+/// Handle to a queue of chat and completion jobs. Clones submit to the same queue.
+///
+/// The queue's background task runs until every handle is dropped and the submitted jobs
+/// are finished.
+///
+/// # Example
+/// ```no_run
+/// # use openai_api::{api::ChatArgs, queue::{Priority, QueueConfig, RequestQueue}, Client};
+/// # async fn run() -> Result<(), openai_api::Error> {
+/// let config = QueueConfig {
+///     requests_per_minute: Some(500),
+///     tokens_per_minute: Some(90_000),
+///     ..QueueConfig::default()
+/// };
+/// let queue = RequestQueue::new(Client::new("sk-...")?, config);
+/// let tickets: Vec<_> = (0..100)
+///     .map(|_| queue.chat(ChatArgs::builder().build().unwrap(), Priority::Normal))
+///     .collect();
+/// for ticket in tickets {
+///     println!("{}", ticket.await?);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct RequestQueue {
+    jobs: mpsc::UnboundedSender<Queued>,
+}
+
+impl RequestQueue {
+    /// Creates a queue sending its requests through `client`.
+    ///
+    /// # Panics
+    /// If called outside of a tokio runtime, which runs the queue's background task, except
+    /// on `wasm32` where it runs on the JavaScript event loop
+    #[must_use]
+    pub fn new(client: Client, config: QueueConfig) -> Self {
+        let (jobs, receiver) = mpsc::unbounded_channel();
+        crate::runtime::spawn(drain(client, config, receiver));
+        Self { jobs }
+    }
+
+    /// Queues a chat request
+    pub fn chat(&self, args: ChatArgs, priority: Priority) -> Ticket<ChatAnswer> {
+        let (sender, receiver) = oneshot::channel();
+        self.submit(Job::Chat(args, sender), priority);
+        Ticket { receiver }
+    }
+
+    /// Queues a completion request
+    pub fn complete_prompt(&self, args: CompletionArgs, priority: Priority) -> Ticket<Completion> {
+        let (sender, receiver) = oneshot::channel();
+        self.submit(Job::Completion(args, sender), priority);
+        Ticket { receiver }
+    }
+
+    fn submit(&self, job: Job, priority: Priority) {
+        // If the background task is gone, the job's sender is dropped with it and its
+        // ticket resolves to `Error::QueueClosed`.
+        let _ = self.jobs.send(Queued { job, priority });
+    }
+}
+
+/// The pending result of a queued job. Dropping it before the job starts cancels the job.
+#[derive(Debug)]
+pub struct Ticket<T> {
+    receiver: oneshot::Receiver<Result<T>>,
+}
+
+impl<T> Future for Ticket<T> {
+    type Output = Result<T>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.receiver)
+            .poll(cx)
+            .map(|result| result.unwrap_or(Err(Error::QueueClosed)))
+    }
+}
+
+// Jobs are queued once per request, so their size doesn't matter
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug)]
+enum Job {
+    Chat(ChatArgs, oneshot::Sender<Result<ChatAnswer>>),
+    Completion(CompletionArgs, oneshot::Sender<Result<Completion>>),
+}
+
+impl Job {
+    fn estimated_tokens(&self) -> u64 {
+        match self {
+            Job::Chat(args, _) => args.estimated_tokens(),
+            Job::Completion(args, _) => args.estimated_tokens(),
+        }
+    }
+
+    fn is_cancelled(&self) -> bool {
+        match self {
+            Job::Chat(_, sender) => sender.is_closed(),
+            Job::Completion(_, sender) => sender.is_closed(),
+        }
+    }
+
+    /// Runs the job. The client retries it according to the queue's policy.
+    async fn run(self, client: &Client) {
+        match self {
+            Job::Chat(args, sender) => {
+                let _ = sender.send(client.chat(args).await);
+            }
+            Job::Completion(args, sender) => {
+                let _ = sender.send(client.complete_prompt(args).await);
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Queued {
+    job: Job,
+    priority: Priority,
+}
+
+/// A queued job in the scheduling heap
+#[derive(Debug)]
+struct Pending {
+    priority: Priority,
+    /// Submission order, to keep equal priorities first in first out
+    seq: u64,
+    tokens: u64,
+    job: Job,
+}
+
+impl Ord for Pending {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.priority
+            .cmp(&other.priority)
+            .then_with(|| other.seq.cmp(&self.seq))
+    }
+}
+
+impl PartialOrd for Pending {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Pending {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Pending {}
+
+async fn drain(mut client: Client, config: QueueConfig, mut jobs: mpsc::UnboundedReceiver<Queued>) {
+    let permits = Arc::new(Semaphore::new(config.concurrency.max(1)));
+    client.inner_mut().retry = config.retry;
+    let mut budget = Budget::new(config.requests_per_minute, config.tokens_per_minute);
+    let mut pending = BinaryHeap::new();
+    let mut seq = 0;
+    let mut push = |pending: &mut BinaryHeap<Pending>, Queued { job, priority }| {
+        seq += 1;
+        pending.push(Pending {
+            priority,
+            seq,
+            tokens: job.estimated_tokens(),
+            job,
+        });
+    };
+
+    loop {
+        if pending.is_empty() {
+            match jobs.recv().await {
+                Some(queued) => push(&mut pending, queued),
+                None => return,
+            }
+        }
+        while let Ok(queued) = jobs.try_recv() {
+            push(&mut pending, queued);
+        }
+
+        let next = pending.pop().expect("pending isn't empty");
+        if next.job.is_cancelled() {
+            continue;
+        }
+        if let Some(wait) = budget.wait_time(next.tokens, Instant::now()) {
+            // Jobs submitted in the meantime may take precedence, so reschedule.
+            pending.push(next);
+            crate::runtime::sleep(wait).await;
+            continue;
+        }
+        let permit = Arc::clone(&permits)
+            .acquire_owned()
+            .await
+            .expect("the semaphore is never closed");
+        budget.record(next.tokens, Instant::now());
+        let client = client.clone();
+        crate::runtime::spawn(async move {
+            next.job.run(&client).await;
+            drop(permit);
+        });
+    }
+}
+
+#[cfg(test)]
+mod unit {
+    use std::{collections::BinaryHeap, time::Duration};
+
+    use tokio::sync::oneshot;
+
+    use super::{Job, Pending, Priority, QueueConfig, RequestQueue};
+    use crate::{api::ChatArgs, retry::RetryPolicy, unit::mocked_client};
+
+    #[test]
+    fn higher_priorities_first() {
+        let pending = |priority, seq| Pending {
+            priority,
+            seq,
+            tokens: 0,
+            job: Job::Chat(ChatArgs::builder().build().unwrap(), oneshot::channel().0),
+        };
+        let mut heap: BinaryHeap<_> = vec![
+            pending(Priority::Normal, 1),
+            pending(Priority::Low, 2),
+            pending(Priority::High, 3),
+            pending(Priority::Normal, 4),
+        ]
+        .into();
+        let order: Vec<_> = std::iter::from_fn(|| heap.pop().map(|p| p.seq)).collect();
+        assert_eq!(order, [3, 1, 4, 2]);
+    }
+
+    #[tokio::test]
+    async fn retries_rate_limited_jobs() -> crate::Result<()> {
+        let limited = mockito::mock("POST", "/chat/completions")
+            .with_status(429)
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"{"error": {"message": "Rate limit reached", "code": "rate_limit_exceeded"}}"#,
+            )
+            .expect(1)
+            .create();
+        let ok = mockito::mock("POST", "/chat/completions")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"{
+                "id": "chatcmpl-123",
+                "created": 1677652288,
+                "choices": [{
+                    "index": 0,
+                    "message": {"role": "assistant", "content": "Hi!"},
+                    "finish_reason": "stop"
+                }]
+            }"#,
+            )
+            .expect(1)
+            .create();
+        let config = QueueConfig {
+            retry: RetryPolicy {
+                initial_delay: Duration::from_millis(1),
+                ..RetryPolicy::default()
+            },
+            ..QueueConfig::default()
+        };
+        let queue = RequestQueue::new(mocked_client(), config);
+        let answer = queue
+            .chat(ChatArgs::builder().build().unwrap(), Priority::High)
+            .await?;
+        assert_eq!(answer.id, "chatcmpl-123");
+        limited.assert();
+        ok.assert();
+        Ok(())
+    }
+}
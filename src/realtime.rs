@@ -0,0 +1,218 @@
+//! Audio plumbing for the Realtime API: PCM16 frames to and from its base64 audio events,
+//! and resampling to the rate it speaks.
+//!
+//! The WebSocket connection itself is left to the caller's WebSocket library: these
+//! helpers make the text of the events it sends, and read those it receives.
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+
+use crate::{Error, Result};
+
+/// The sample rate of the `pcm16` audio format, in Hz: mono, signed 16-bit little-endian
+pub const SAMPLE_RATE: u32 = 24_000;
+
+/// Encodes samples as the base64 of their little-endian bytes, as audio events carry them
+#[must_use]
+pub fn encode_pcm16(samples: &[i16]) -> String {
+    let bytes: Vec<u8> = samples.iter().flat_map(|s| s.to_le_bytes()).collect();
+    base64::engine::general_purpose::STANDARD.encode(bytes)
+}
+
+/// Decodes the base64 audio of an event into samples
+///
+/// # Errors
+/// - `Error::Base64` if `audio` isn't valid base64
+/// - `Error::BadArguments` if it decodes to an odd number of bytes
+pub fn decode_pcm16(audio: &str) -> Result<Vec<i16>> {
+    let bytes = base64::engine::general_purpose::STANDARD.decode(audio)?;
+    if bytes.len() % 2 != 0 {
+        return Err(Error::BadArguments(format!(
+            "{} bytes of audio aren't a whole number of 16-bit samples",
+            bytes.len()
+        )));
+    }
+    Ok(bytes
+        .chunks_exact(2)
+        .map(|b| i16::from_le_bytes([b[0], b[1]]))
+        .collect())
+}
+
+/// Converts samples in `[-1.0, 1.0]`, as most audio APIs capture them, to PCM16. Samples
+/// out of range are clipped.
+#[must_use]
+pub fn f32_to_pcm16(samples: &[f32]) -> Vec<i16> {
+    samples
+        .iter()
+        .map(|s| (s.clamp(-1.0, 1.0) * i16::MAX as f32).round() as i16)
+        .collect()
+}
+
+/// Converts PCM16 samples to `[-1.0, 1.0]`, as most audio APIs play them
+#[must_use]
+pub fn pcm16_to_f32(samples: &[i16]) -> Vec<f32> {
+    samples
+        .iter()
+        .map(|&s| (s as f32 / i16::MAX as f32).max(-1.0))
+        .collect()
+}
+
+/// Resamples mono audio from `from_rate` to `to_rate` Hz by linear interpolation, e.g.
+/// microphone audio at 44.1 or 48kHz, or telephony audio at 8kHz, to `SAMPLE_RATE`.
+///
+/// Good enough for speech. Chunks resampled one by one may click at their boundaries:
+/// resample longer stretches where that matters.
+///
+/// # Example
+/// ```
+/// # use openai_api::realtime::{resample, SAMPLE_RATE};
+/// let microphone = vec![0i16; 4800];
+/// assert_eq!(resample(&microphone, 48_000, SAMPLE_RATE).len(), 2400);
+/// ```
+#[must_use]
+pub fn resample(samples: &[i16], from_rate: u32, to_rate: u32) -> Vec<i16> {
+    if from_rate == to_rate || samples.is_empty() || from_rate == 0 {
+        return samples.to_vec();
+    }
+    let len = (samples.len() as u64 * to_rate as u64 / from_rate as u64) as usize;
+    let step = from_rate as f64 / to_rate as f64;
+    (0..len)
+        .map(|i| {
+            let position = i as f64 * step;
+            let index = position as usize;
+            let fraction = position - index as f64;
+            let current = samples[index.min(samples.len() - 1)] as f64;
+            let next = samples[(index + 1).min(samples.len() - 1)] as f64;
+            (current + (next - current) * fraction).round() as i16
+        })
+        .collect()
+}
+
+/// The `input_audio_buffer.append` event, streaming microphone audio to the API
+///
+/// # Example
+/// ```
+/// # use openai_api::realtime::InputAudioAppend;
+/// let event = InputAudioAppend::new(&[0, 1, -1]).to_json().unwrap();
+/// assert!(event.starts_with(r#"{"type":"input_audio_buffer.append""#));
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "type", rename = "input_audio_buffer.append")]
+pub struct InputAudioAppend {
+    /// Caller-chosen id of the event, echoed in the errors it causes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub event_id: Option<String>,
+    /// The audio, see `encode_pcm16`
+    pub audio: String,
+}
+
+impl InputAudioAppend {
+    /// An event appending these `SAMPLE_RATE` samples
+    #[must_use]
+    pub fn new(samples: &[i16]) -> Self {
+        Self {
+            event_id: None,
+            audio: encode_pcm16(samples),
+        }
+    }
+
+    /// The text of the event, to send as a WebSocket text message
+    ///
+    /// # Errors
+    /// - `Error::Json` if the event can't be serialized
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+}
+
+/// A chunk of the audio of a response, from a `response.audio.delta` event
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct AudioDelta {
+    #[serde(default)]
+    pub response_id: String,
+    #[serde(default)]
+    pub item_id: String,
+    #[serde(default)]
+    pub output_index: u32,
+    #[serde(default)]
+    pub content_index: u32,
+    /// The audio, see `samples`
+    pub delta: String,
+}
+
+impl AudioDelta {
+    /// Reads the event in a WebSocket text message, or `None` if it is another event
+    ///
+    /// # Errors
+    /// - `Error::Json` if the message isn't a JSON event, or a malformed audio delta
+    pub fn parse(message: &str) -> Result<Option<Self>> {
+        #[derive(Deserialize)]
+        struct Kind<'a> {
+            #[serde(rename = "type", borrow)]
+            kind: std::borrow::Cow<'a, str>,
+        }
+
+        let kind: Kind = serde_json::from_str(message)?;
+        match kind.kind.as_ref() {
+            "response.audio.delta" | "response.output_audio.delta" => {
+                Ok(Some(serde_json::from_str(message)?))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// The samples of the chunk, at `SAMPLE_RATE`
+    ///
+    /// # Errors
+    /// - The errors of `decode_pcm16`
+    pub fn samples(&self) -> Result<Vec<i16>> {
+        decode_pcm16(&self.delta)
+    }
+}
+
+#[cfg(test)]
+mod unit {
+    use super::{
+        decode_pcm16, encode_pcm16, f32_to_pcm16, pcm16_to_f32, resample, AudioDelta,
+        InputAudioAppend,
+    };
+    use crate::Error;
+
+    #[test]
+    fn round_trips_audio_events() -> crate::Result<()> {
+        let samples = [0, 1, -1, i16::MAX, i16::MIN];
+        assert_eq!(decode_pcm16(&encode_pcm16(&samples))?, samples);
+        assert!(matches!(decode_pcm16("AAAA"), Err(Error::BadArguments(_))));
+
+        let event: serde_json::Value =
+            serde_json::from_str(&InputAudioAppend::new(&samples).to_json()?)?;
+        assert_eq!(event["type"], "input_audio_buffer.append");
+        assert_eq!(event["audio"], encode_pcm16(&samples));
+
+        let message = format!(
+            r#"{{"type": "response.audio.delta", "event_id": "e1", "response_id": "r1",
+            "item_id": "i1", "output_index": 0, "content_index": 0, "delta": "{}"}}"#,
+            encode_pcm16(&samples)
+        );
+        let delta = AudioDelta::parse(&message)?.unwrap();
+        assert_eq!(delta.response_id, "r1");
+        assert_eq!(delta.samples()?, samples);
+        assert_eq!(AudioDelta::parse(r#"{"type": "response.done"}"#)?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn converts_and_resamples() {
+        assert_eq!(
+            f32_to_pcm16(&[0.0, 1.0, -2.0]),
+            vec![0, i16::MAX, -i16::MAX]
+        );
+        assert_eq!(pcm16_to_f32(&[i16::MIN, 0]), vec![-1.0, 0.0]);
+
+        let ramp: Vec<i16> = (0..8).map(|i| i * 100).collect();
+        assert_eq!(resample(&ramp, 8_000, 16_000).len(), 16);
+        assert_eq!(resample(&ramp, 8_000, 16_000)[..4], [0, 50, 100, 150]);
+        assert_eq!(resample(&ramp, 16_000, 8_000), vec![0, 200, 400, 600]);
+        assert_eq!(resample(&ramp, 24_000, 24_000), ramp);
+        assert_eq!(resample(&vec![0; 441], 44_100, 24_000).len(), 240);
+    }
+}
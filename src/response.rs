@@ -0,0 +1,211 @@
+//! Metadata of API responses, beyond their parsed body.
+use std::time::Duration;
+
+use bytes::Bytes;
+use reqwest::header::HeaderMap;
+
+use crate::{Error, Result};
+
+/// Status and headers of a response, see [`Client::last_response_meta`].
+///
+/// [`Client::last_response_meta`]: crate::Client::last_response_meta
+#[derive(Debug, Clone, Default)]
+pub struct ResponseMeta {
+    /// HTTP status code
+    pub status: u16,
+    /// Response headers
+    pub headers: HeaderMap,
+}
+
+impl ResponseMeta {
+    /// Value of the `name` header, if it is present and valid UTF-8
+    #[must_use]
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name)?.to_str().ok()
+    }
+
+    /// Id the API gave the request, to quote when reporting a problem
+    #[must_use]
+    pub fn request_id(&self) -> Option<&str> {
+        self.header("x-request-id")
+    }
+
+    /// The rate limits of the `x-ratelimit-*` headers, if the response has any
+    #[must_use]
+    pub fn rate_limit(&self) -> Option<RateLimitInfo> {
+        let number = |name| self.header(name)?.trim().parse().ok();
+        let duration = |name| parse_duration(self.header(name)?);
+        let info = RateLimitInfo {
+            limit_requests: number("x-ratelimit-limit-requests"),
+            limit_tokens: number("x-ratelimit-limit-tokens"),
+            remaining_requests: number("x-ratelimit-remaining-requests"),
+            remaining_tokens: number("x-ratelimit-remaining-tokens"),
+            reset_requests: duration("x-ratelimit-reset-requests"),
+            reset_tokens: duration("x-ratelimit-reset-tokens"),
+        };
+        (info != RateLimitInfo::default()).then_some(info)
+    }
+}
+
+/// The state of the rate limits after a response, see [`ResponseMeta::rate_limit`] and
+/// [`Client::last_rate_limit`]. Each field is `None` when the API didn't send it.
+///
+/// [`Client::last_rate_limit`]: crate::Client::last_rate_limit
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RateLimitInfo {
+    /// Requests allowed per period
+    pub limit_requests: Option<u64>,
+    /// Tokens allowed per period
+    pub limit_tokens: Option<u64>,
+    pub remaining_requests: Option<u64>,
+    pub remaining_tokens: Option<u64>,
+    /// Time until the request limit is fully restored
+    pub reset_requests: Option<Duration>,
+    /// Time until the token limit is fully restored
+    pub reset_tokens: Option<Duration>,
+}
+
+/// Parses a duration as the API writes them, e.g. `"20ms"`, `"1s"` or `"6m0.5s"`
+fn parse_duration(text: &str) -> Option<Duration> {
+    let mut rest = text.trim();
+    let mut total = Duration::ZERO;
+    if rest.is_empty() {
+        return None;
+    }
+    while !rest.is_empty() {
+        let number_end = rest.find(|c: char| !c.is_ascii_digit() && c != '.')?;
+        let (number, tail) = rest.split_at(number_end);
+        let number: f64 = number.parse().ok()?;
+        let unit_end = tail
+            .find(|c: char| c.is_ascii_digit())
+            .unwrap_or(tail.len());
+        let (unit, tail) = tail.split_at(unit_end);
+        let seconds = match unit {
+            "h" => 3600.0,
+            "m" => 60.0,
+            "s" => 1.0,
+            "ms" => 1e-3,
+            "us" | "µs" => 1e-6,
+            "ns" => 1e-9,
+            _ => return None,
+        };
+        total += Duration::try_from_secs_f64(number * seconds).ok()?;
+        rest = tail;
+    }
+    Some(total)
+}
+
+/// A response as received, before parsing. The body is kept as the `Bytes` reqwest
+/// read, so that parsing it, or keeping it around, doesn't copy it.
+pub(crate) struct RawResponse {
+    pub status: reqwest::StatusCode,
+    pub headers: HeaderMap,
+    pub body: Bytes,
+}
+
+impl RawResponse {
+    /// Reads the whole response, failing as soon as it exceeds `limit` bytes
+    pub(crate) async fn read(response: reqwest::Response, limit: Option<usize>) -> Result<Self> {
+        let status = response.status();
+        let headers = response.headers().clone();
+        let body = match limit {
+            None => response.bytes().await?,
+            Some(limit) => read_limited(response, limit).await?,
+        };
+        Ok(Self {
+            status,
+            headers,
+            body,
+        })
+    }
+
+    pub(crate) fn meta(&self) -> ResponseMeta {
+        ResponseMeta {
+            status: self.status.as_u16(),
+            headers: self.headers.clone(),
+        }
+    }
+}
+
+/// Reads the body of `response`, failing as soon as it exceeds `limit` bytes
+#[cfg(not(target_arch = "wasm32"))]
+async fn read_limited(mut response: reqwest::Response, limit: usize) -> Result<Bytes> {
+    check_size(response.content_length().unwrap_or(0), limit)?;
+    let mut body = bytes::BytesMut::new();
+    while let Some(chunk) = response.chunk().await? {
+        check_size((body.len() + chunk.len()) as u64, limit)?;
+        body.extend_from_slice(&chunk);
+    }
+    Ok(body.freeze())
+}
+
+/// Reads the body of `response`, failing if it exceeds `limit` bytes. Browsers only hand
+/// over whole bodies.
+#[cfg(target_arch = "wasm32")]
+async fn read_limited(response: reqwest::Response, limit: usize) -> Result<Bytes> {
+    check_size(response.content_length().unwrap_or(0), limit)?;
+    let body = response.bytes().await?;
+    check_size(body.len() as u64, limit)?;
+    Ok(body)
+}
+
+/// Fails with `Error::ResponseTooLarge` when `size` bytes exceed `limit`
+pub(crate) fn check_size(size: u64, limit: usize) -> Result<()> {
+    if size > limit as u64 {
+        return Err(Error::ResponseTooLarge { limit });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod unit {
+    use std::time::Duration;
+
+    use reqwest::header::{HeaderMap, HeaderValue};
+
+    use super::{parse_duration, RateLimitInfo, ResponseMeta};
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_duration("20ms"), Some(Duration::from_millis(20)));
+        assert_eq!(parse_duration("1s"), Some(Duration::from_secs(1)));
+        assert_eq!(
+            parse_duration("6m0.5s"),
+            Some(Duration::from_millis(360_500))
+        );
+        assert_eq!(parse_duration("1h2m"), Some(Duration::from_secs(3720)));
+        for invalid in ["", "12", "1d", "s"] {
+            assert_eq!(parse_duration(invalid), None, "{:?}", invalid);
+        }
+    }
+
+    #[test]
+    fn parses_rate_limits() {
+        let mut headers = HeaderMap::new();
+        for (name, value) in [
+            ("x-ratelimit-limit-requests", "60"),
+            ("x-ratelimit-remaining-requests", "59"),
+            ("x-ratelimit-remaining-tokens", "149984"),
+            ("x-ratelimit-reset-requests", "1s"),
+            ("x-ratelimit-reset-tokens", "6m0s"),
+        ] {
+            headers.insert(name, HeaderValue::from_static(value));
+        }
+        let meta = ResponseMeta {
+            status: 200,
+            headers,
+        };
+        assert_eq!(
+            meta.rate_limit(),
+            Some(RateLimitInfo {
+                limit_requests: Some(60),
+                limit_tokens: None,
+                remaining_requests: Some(59),
+                remaining_tokens: Some(149_984),
+                reset_requests: Some(Duration::from_secs(1)),
+                reset_tokens: Some(Duration::from_secs(360)),
+            })
+        );
+        assert_eq!(ResponseMeta::default().rate_limit(), None);
+    }
+}
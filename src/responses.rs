@@ -0,0 +1,422 @@
+//! Responses API, with its built-in tools: web search, file search and computer use.
+//!
+//! The tools are configured on `ResponseArgs`, and what they did comes back as typed
+//! `OutputItem`s next to the model's messages.
+use serde::{Deserialize, Serialize};
+
+use crate::{Client, Error, Result};
+
+/// A tool built into the Responses API
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ResponseTool {
+    /// Searches the web, citing its sources as `Annotation::UrlCitation`s
+    WebSearch {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        search_context_size: Option<SearchContextSize>,
+        /// Where the user is, to localize results
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        user_location: Option<UserLocation>,
+    },
+    /// Searches vector stores, see `vector_stores`
+    FileSearch {
+        vector_store_ids: Vec<String>,
+        /// Most results to return, from 1 to 50
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        max_num_results: Option<u32>,
+    },
+    /// Operates a computer through screenshots and `ComputerAction`s, which the caller
+    /// performs
+    #[serde(rename = "computer_use_preview")]
+    ComputerUse {
+        display_width: u32,
+        display_height: u32,
+        environment: ComputerEnvironment,
+    },
+}
+
+impl ResponseTool {
+    /// Web search with the default settings
+    #[must_use]
+    pub fn web_search() -> Self {
+        ResponseTool::WebSearch {
+            search_context_size: None,
+            user_location: None,
+        }
+    }
+
+    /// File search in these vector stores
+    #[must_use]
+    pub fn file_search(vector_store_ids: Vec<String>) -> Self {
+        ResponseTool::FileSearch {
+            vector_store_ids,
+            max_num_results: None,
+        }
+    }
+}
+
+/// How much context web search gathers, trading cost and latency for quality
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchContextSize {
+    Low,
+    Medium,
+    High,
+}
+
+/// The approximate location of the user, for web search
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+#[serde(tag = "type", rename = "approximate")]
+pub struct UserLocation {
+    /// Two-letter ISO country code, e.g. `"GB"`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub country: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub city: Option<String>,
+    /// IANA timezone, e.g. `"Europe/London"`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<String>,
+}
+
+/// The kind of computer operated by the computer use tool
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ComputerEnvironment {
+    Browser,
+    Mac,
+    Windows,
+    Ubuntu,
+}
+
+/// Arguments of a response request
+#[derive(Serialize, Deserialize, Debug, Builder, Clone)]
+#[builder(pattern = "immutable")]
+pub struct ResponseArgs {
+    /// The id of the model to use for this request
+    #[builder(setter(into))]
+    model: String,
+    /// The user's input
+    #[builder(setter(into))]
+    input: String,
+    /// The system instructions of the response
+    #[builder(setter(into, strip_option), default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    instructions: Option<String>,
+    #[builder(setter(into), default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<ResponseTool>,
+    /// Continues the conversation of a previous response, without resending it
+    #[builder(setter(into, strip_option), default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    previous_response_id: Option<String>,
+}
+
+impl ResponseArgs {
+    /// Build a `ResponseArgs` from the defaults
+    #[must_use]
+    pub fn builder() -> ResponseArgsBuilder {
+        ResponseArgsBuilder::default()
+    }
+}
+
+impl TryFrom<ResponseArgsBuilder> for ResponseArgs {
+    type Error = ResponseArgsBuilderError;
+
+    fn try_from(builder: ResponseArgsBuilder) -> std::result::Result<Self, Self::Error> {
+        builder.build()
+    }
+}
+
+impl From<ResponseArgsBuilderError> for Error {
+    fn from(err: ResponseArgsBuilderError) -> Self {
+        Error::BadArguments(err.to_string())
+    }
+}
+
+/// A response
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct Response {
+    pub id: String,
+    /// E.g. `"completed"`, or `"incomplete"`
+    #[serde(default, deserialize_with = "crate::api::lenient")]
+    pub status: String,
+    #[serde(default, deserialize_with = "crate::api::lenient")]
+    pub model: String,
+    /// The messages of the model and the calls of its tools, in order
+    pub output: Vec<OutputItem>,
+}
+
+impl Response {
+    /// The text of the output messages, concatenated
+    #[must_use]
+    pub fn output_text(&self) -> String {
+        self.output
+            .iter()
+            .filter_map(|item| match item {
+                OutputItem::Message { content, .. } => Some(content),
+                _ => None,
+            })
+            .flatten()
+            .filter_map(|content| match content {
+                OutputContent::OutputText { text, .. } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// An item of the output of a response
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum OutputItem {
+    /// A message of the model
+    Message {
+        id: String,
+        #[serde(default)]
+        content: Vec<OutputContent>,
+    },
+    /// A web search the model made; its results are cited in the following message
+    WebSearchCall { id: String, status: String },
+    /// A file search the model made
+    FileSearchCall {
+        id: String,
+        status: String,
+        #[serde(default)]
+        queries: Vec<String>,
+        /// The chunks found, when requested with the `file_search_call.results` include
+        #[serde(default)]
+        results: Option<Vec<FileSearchResult>>,
+    },
+    /// An action the model wants performed on the computer, answered with a screenshot
+    ComputerCall {
+        id: String,
+        call_id: String,
+        action: ComputerAction,
+        /// Checks the caller must acknowledge before performing the action
+        #[serde(default)]
+        pending_safety_checks: Vec<SafetyCheck>,
+        status: String,
+    },
+    /// An item this version of the library doesn't know about
+    #[serde(other)]
+    Other,
+}
+
+/// A part of an output message
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum OutputContent {
+    OutputText {
+        text: String,
+        /// The sources of the text
+        #[serde(default)]
+        annotations: Vec<Annotation>,
+    },
+    /// The model refused to answer
+    Refusal { refusal: String },
+    /// A part this version of the library doesn't know about
+    #[serde(other)]
+    Other,
+}
+
+/// A source cited in an output text
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Annotation {
+    /// A web page, cited for the characters `start_index..end_index` of the text
+    UrlCitation {
+        url: String,
+        #[serde(default)]
+        title: String,
+        start_index: usize,
+        end_index: usize,
+    },
+    /// A file found by file search, cited at `index` in the text
+    FileCitation {
+        file_id: String,
+        #[serde(default)]
+        filename: String,
+        index: usize,
+    },
+    /// An annotation this version of the library doesn't know about
+    #[serde(other)]
+    Other,
+}
+
+/// A chunk of a file found by file search
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct FileSearchResult {
+    pub file_id: String,
+    #[serde(default)]
+    pub filename: String,
+    /// Relevance, between 0 and 1
+    #[serde(default)]
+    pub score: f64,
+    #[serde(default)]
+    pub text: String,
+}
+
+/// A check of a computer action, e.g. against prompt injection
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SafetyCheck {
+    pub id: String,
+    pub code: String,
+    pub message: String,
+}
+
+/// An action on the computer, with coordinates in pixels of the display
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ComputerAction {
+    Click {
+        x: i32,
+        y: i32,
+        /// `"left"`, `"right"`, `"wheel"`, `"back"` or `"forward"`
+        button: String,
+    },
+    DoubleClick {
+        x: i32,
+        y: i32,
+    },
+    Scroll {
+        x: i32,
+        y: i32,
+        scroll_x: i32,
+        scroll_y: i32,
+    },
+    Type {
+        text: String,
+    },
+    Keypress {
+        keys: Vec<String>,
+    },
+    Move {
+        x: i32,
+        y: i32,
+    },
+    Drag {
+        path: Vec<Point>,
+    },
+    Wait,
+    Screenshot,
+    /// An action this version of the library doesn't know about
+    #[serde(other)]
+    Other,
+}
+
+/// A position on the display
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Point {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl Client {
+    /// Creates a response, running the built-in tools it is given
+    ///
+    /// # Errors
+    ///  - `Error::BadArguments` if the arguments can't be built
+    ///  - `Error::DryRun` in dry-run mode
+    ///  - `Error::Api` if the api returns an error
+    pub async fn create_response<A>(&self, args: A) -> Result<Response>
+    where
+        A: TryInto<ResponseArgs>,
+        Error: From<<A as TryInto<ResponseArgs>>::Error>,
+    {
+        let args: ResponseArgs = args.try_into()?;
+        self.dry_run("responses", &args, || Ok(()))?;
+        self.post("responses", args).await
+    }
+}
+
+#[cfg(test)]
+mod unit {
+    use mockito::Matcher;
+    use serde_json::json;
+
+    use super::{
+        Annotation, ComputerAction, ComputerEnvironment, OutputContent, OutputItem, ResponseArgs,
+        ResponseTool, SearchContextSize,
+    };
+    use crate::unit::mocked_client;
+
+    #[test]
+    fn serializes_tools() {
+        let tools = vec![
+            ResponseTool::WebSearch {
+                search_context_size: Some(SearchContextSize::Low),
+                user_location: None,
+            },
+            ResponseTool::file_search(vec!["vs_1".into()]),
+            ResponseTool::ComputerUse {
+                display_width: 1024,
+                display_height: 768,
+                environment: ComputerEnvironment::Browser,
+            },
+        ];
+        assert_eq!(
+            serde_json::to_value(tools).unwrap(),
+            json!([
+                {"type": "web_search", "search_context_size": "low"},
+                {"type": "file_search", "vector_store_ids": ["vs_1"]},
+                {"type": "computer_use_preview", "display_width": 1024,
+                    "display_height": 768, "environment": "browser"},
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn reads_tool_outputs() -> crate::Result<()> {
+        let _m = mockito::mock("POST", "/responses")
+            .match_body(Matcher::PartialJson(
+                json!({"tools": [{"type": "web_search"}]}),
+            ))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"{"id": "resp_1", "object": "response", "status": "completed",
+                "model": "gpt-4.1", "output": [
+                    {"type": "web_search_call", "id": "ws_1", "status": "completed"},
+                    {"type": "computer_call", "id": "cu_1", "call_id": "call_1", "status":
+                        "completed", "action": {"type": "click", "button": "left", "x": 10,
+                        "y": 20}, "pending_safety_checks": []},
+                    {"type": "reasoning", "id": "rs_1", "summary": []},
+                    {"type": "message", "id": "msg_1", "status": "completed", "role":
+                        "assistant", "content": [{"type": "output_text", "text": "Sunny.",
+                        "annotations": [{"type": "url_citation", "start_index": 0,
+                        "end_index": 6, "url": "https://example.com", "title": "Weather"}]}]}
+                ]}"#,
+            )
+            .create();
+        let response = mocked_client()
+            .create_response(
+                ResponseArgs::builder()
+                    .model("gpt-4.1")
+                    .input("Weather in Paris?")
+                    .tools(vec![ResponseTool::web_search()]),
+            )
+            .await?;
+        assert_eq!(response.output_text(), "Sunny.");
+        assert!(matches!(
+            &response.output[1],
+            OutputItem::ComputerCall {
+                action: ComputerAction::Click { x: 10, y: 20, .. },
+                ..
+            }
+        ));
+        assert_eq!(response.output[2], OutputItem::Other);
+        match &response.output[3] {
+            OutputItem::Message { content, .. } => match &content[0] {
+                OutputContent::OutputText { annotations, .. } => assert!(matches!(
+                    &annotations[0],
+                    Annotation::UrlCitation { end_index: 6, .. }
+                )),
+                other => panic!("unexpected content {:?}", other),
+            },
+            other => panic!("unexpected item {:?}", other),
+        }
+        Ok(())
+    }
+}
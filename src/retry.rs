@@ -0,0 +1,356 @@
+//! Retry policies for transient API failures.
+use std::{future::Future, time::Duration};
+
+use reqwest::header::HeaderMap;
+
+use crate::{Error, Result};
+
+/// How often, and how patiently, to retry requests that failed transiently: rate limits
+/// (429), server errors (5xx) and connection problems.
+///
+/// The delay before retry `n` (counting from 1) is `initial_delay * multiplier^(n - 1)`,
+/// capped at `max_delay`. With `jitter`, each delay is instead picked at random between
+/// half of that and all of it, so that many clients throttled together don't retry
+/// together.
+///
+/// When the API says how long to wait, with the `Retry-After` or `retry-after-ms` headers,
+/// the client waits that long instead. It gives up right away if that's longer than
+/// `max_delay`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// Total number of attempts, including the first one. `1` disables retries.
+    pub max_attempts: u32,
+    /// Delay before the first retry
+    pub initial_delay: Duration,
+    /// Upper bound of the delay between two attempts
+    pub max_delay: Duration,
+    /// Growth factor of the delay between successive retries
+    pub multiplier: f64,
+    /// Whether to randomize delays
+    pub jitter: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            multiplier: 2.0,
+            jitter: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy that never retries
+    #[must_use]
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Self::default()
+        }
+    }
+
+    /// Checks the policy can be followed: delays can't shrink between retries
+    ///
+    /// # Errors
+    /// - `Error::InvalidConfig` if `multiplier` is less than 1, or NaN
+    pub fn validate(&self) -> Result<()> {
+        if self.multiplier >= 1.0 {
+            Ok(())
+        } else {
+            Err(Error::InvalidConfig(format!(
+                "retry multiplier must be at least 1, got {}",
+                self.multiplier
+            )))
+        }
+    }
+
+    /// Delay to wait before retry number `retry`, counting from 1. A `multiplier` less
+    /// than 1, which `validate` rejects, counts as 1.
+    #[must_use]
+    pub fn delay(&self, retry: u32) -> Duration {
+        let exponent = retry.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let secs = self.initial_delay.as_secs_f64() * self.multiplier.max(1.0).powi(exponent);
+        // Delays too long for a `Duration` are capped as well
+        let delay = Duration::try_from_secs_f64(secs)
+            .map_or(self.max_delay, |delay| delay.min(self.max_delay));
+        if self.jitter {
+            delay.mul_f64(0.5 + random_fraction() / 2.0)
+        } else {
+            delay
+        }
+    }
+
+    /// Whether `error` is transient, and worth retrying
+    #[must_use]
+    pub fn is_retryable(error: &Error) -> bool {
+        match error {
+            Error::Api(err) => err.status.is_some_and(|status| {
+                status == reqwest::StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+            }),
+            Error::AsyncProtocol(err) => is_connect(err),
+            Error::Timeout(_) => true,
+            _ => false,
+        }
+    }
+}
+
+/// Runs `request` until it succeeds, fails for good, or runs out of attempts
+pub(crate) async fn with_retries<T, F, Fut>(policy: &RetryPolicy, mut request: F) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let mut attempt = 1;
+    loop {
+        match request().await {
+            Err(err) if attempt < policy.max_attempts && RetryPolicy::is_retryable(&err) => {
+                let delay = match &err {
+                    Error::Api(err) => err.retry_after,
+                    _ => None,
+                };
+                let delay = match delay {
+                    Some(delay) if delay > policy.max_delay => return Err(err),
+                    Some(delay) => delay,
+                    None => policy.delay(attempt),
+                };
+                crate::runtime::sleep(delay).await;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// A copy of `request` for another attempt. Its body was serialized once, when the request
+/// was built: the copy shares the bytes.
+pub(crate) fn resend(request: &reqwest::Request) -> reqwest::Request {
+    request
+        .try_clone()
+        .expect("request bodies are buffered, not streamed")
+}
+
+/// How long a response asks to wait before retrying: `retry-after-ms` milliseconds, or
+/// `Retry-After` seconds. HTTP dates in `Retry-After` aren't supported.
+pub(crate) fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let header = |name| headers.get(name)?.to_str().ok()?.trim().parse::<f64>().ok();
+    let delay = match header("retry-after-ms") {
+        Some(ms) => ms / 1000.0,
+        None => header("retry-after")?,
+    };
+    Duration::try_from_secs_f64(delay).ok()
+}
+
+/// Whether `error` happened before the request reached the server, so that sending it
+/// again can't repeat it
+pub(crate) fn is_connect(error: &reqwest::Error) -> bool {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        error.is_connect()
+    }
+    // Browsers don't tell why a fetch failed, only that it did
+    #[cfg(target_arch = "wasm32")]
+    {
+        error.is_request()
+    }
+}
+
+/// A number in `[0, 1)` that is random enough for jitter
+fn random_fraction() -> f64 {
+    use std::hash::{BuildHasher, Hasher};
+
+    let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+    hasher.write_u128(
+        crate::runtime::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos(),
+    );
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod unit {
+    use std::time::Duration;
+
+    use reqwest::header::{HeaderMap, HeaderValue};
+
+    use super::RetryPolicy;
+    use crate::{api::ApiError, unit::mocked_client, Error};
+
+    #[test]
+    fn resent_requests_share_their_body() -> crate::Result<()> {
+        let request = reqwest::Client::new()
+            .post("https://example.com/embeddings")
+            .json(&serde_json::json!({"input": vec!["a"; 1000]}))
+            .build()?;
+        let resent = super::resend(&request);
+        let body = |request: &reqwest::Request| {
+            request
+                .body()
+                .and_then(reqwest::Body::as_bytes)
+                .map(<[u8]>::as_ptr)
+        };
+        assert!(body(&request).is_some());
+        assert_eq!(body(&request), body(&resent));
+        Ok(())
+    }
+
+    #[test]
+    fn exponential_delays() {
+        let policy = RetryPolicy {
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(5),
+            jitter: false,
+            ..RetryPolicy::default()
+        };
+        let delays: Vec<_> = (1..=4).map(|n| policy.delay(n).as_secs()).collect();
+        assert_eq!(delays, [1, 2, 4, 5]);
+
+        let policy = RetryPolicy {
+            jitter: true,
+            ..policy
+        };
+        for _ in 0..100 {
+            let delay = policy.delay(2);
+            assert!(delay >= Duration::from_secs(1) && delay <= Duration::from_secs(2));
+        }
+    }
+
+    #[test]
+    fn delays_stay_capped() {
+        let policy = RetryPolicy {
+            jitter: false,
+            ..RetryPolicy::default()
+        };
+        assert_eq!(policy.delay(10_000), policy.max_delay);
+        for multiplier in [-2.0, 0.5, f64::NAN] {
+            let policy = RetryPolicy {
+                multiplier,
+                ..policy.clone()
+            };
+            assert!(policy.validate().is_err());
+            assert_eq!(policy.delay(3), policy.initial_delay);
+        }
+        policy.validate().unwrap();
+    }
+
+    #[test]
+    fn parses_retry_after() {
+        let headers = |pairs: &[(&'static str, &'static str)]| {
+            let mut headers = HeaderMap::new();
+            for (name, value) in pairs {
+                headers.insert(*name, HeaderValue::from_static(value));
+            }
+            headers
+        };
+        let retry_after = |pairs| super::retry_after(&headers(pairs));
+        assert_eq!(
+            retry_after(&[("retry-after", "2")]),
+            Some(Duration::from_secs(2))
+        );
+        assert_eq!(
+            retry_after(&[("retry-after", "2"), ("retry-after-ms", "150")]),
+            Some(Duration::from_millis(150))
+        );
+        assert_eq!(
+            retry_after(&[("retry-after", "Wed, 21 Oct 2015 07:28:00 GMT")]),
+            None
+        );
+        assert_eq!(retry_after(&[("retry-after", "-1")]), None);
+        assert_eq!(retry_after(&[]), None);
+    }
+
+    #[tokio::test]
+    async fn retries_errors_of_proxies() -> crate::Result<()> {
+        let unavailable = mockito::mock("GET", "/models/gpt-4-proxied")
+            .with_status(503)
+            .with_header("content-type", "text/html")
+            .with_body("<html><body><h1>503 Service Temporarily Unavailable</h1></body></html>")
+            .expect(1)
+            .create();
+        let ok = mockito::mock("GET", "/models/gpt-4-proxied")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"id": "gpt-4-proxied", "object": "model", "owned_by": "openai"}"#)
+            .expect(1)
+            .create();
+        let client = mocked_client().with_retry_policy(RetryPolicy {
+            initial_delay: Duration::from_millis(1),
+            ..RetryPolicy::default()
+        });
+        assert_eq!(client.model("gpt-4-proxied").await?.id, "gpt-4-proxied");
+        unavailable.assert();
+        ok.assert();
+
+        let _unavailable = mockito::mock("GET", "/models/gpt-3-proxied")
+            .with_status(503)
+            .with_body("<html>Service Unavailable</html>")
+            .create();
+        match mocked_client().model("gpt-3-proxied").await {
+            Err(Error::Api(err)) => {
+                assert_eq!(err.status, Some(reqwest::StatusCode::SERVICE_UNAVAILABLE));
+                assert_eq!(err.message, "<html>Service Unavailable</html>");
+            }
+            other => panic!("expected an API error, got {:?}", other),
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn honors_retry_after() -> crate::Result<()> {
+        let limited = mockito::mock("GET", "/models/gpt-4")
+            .with_status(429)
+            .with_header("content-type", "application/json")
+            .with_header("retry-after-ms", "10")
+            .with_body(r#"{"error": {"message": "Rate limit reached"}}"#)
+            .expect(1)
+            .create();
+        let ok = mockito::mock("GET", "/models/gpt-4")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"id": "gpt-4", "object": "model", "owned_by": "openai"}"#)
+            .expect(1)
+            .create();
+        let client = mocked_client().with_retry_policy(RetryPolicy {
+            initial_delay: Duration::from_secs(60),
+            ..RetryPolicy::default()
+        });
+        let started = std::time::Instant::now();
+        assert_eq!(client.model("gpt-4").await?.id, "gpt-4");
+        assert!(started.elapsed() < Duration::from_secs(10));
+        limited.assert();
+        ok.assert();
+
+        let _limited = mockito::mock("GET", "/models/gpt-3")
+            .with_status(429)
+            .with_header("content-type", "application/json")
+            .with_header("retry-after", "3600")
+            .with_body(r#"{"error": {"message": "Rate limit reached"}}"#)
+            .expect(1)
+            .create();
+        match client.model("gpt-3").await {
+            Err(Error::Api(err)) => assert_eq!(err.retry_after, Some(Duration::from_secs(3600))),
+            other => panic!("expected a rate limit error, got {:?}", other),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn retryable_errors() {
+        let api_error = |status: Option<u16>| {
+            Error::from(ApiError {
+                message: "error".into(),
+                status: status.and_then(|status| reqwest::StatusCode::from_u16(status).ok()),
+                ..ApiError::default()
+            })
+        };
+        assert!(RetryPolicy::is_retryable(&api_error(Some(429))));
+        assert!(RetryPolicy::is_retryable(&api_error(Some(503))));
+        assert!(!RetryPolicy::is_retryable(&api_error(Some(400))));
+        // Errors sent mid-stream have no status
+        assert!(!RetryPolicy::is_retryable(&api_error(None)));
+    }
+}
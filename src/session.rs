@@ -0,0 +1,677 @@
+//! Chat sessions: a conversation history that can be persisted and replayed.
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::{ChatArgs, ChatFormat, ChatRole},
+    OpenAiApi, Result,
+};
+
+/// The message history of a chat conversation.
+///
+/// Sessions serialize to JSON as `{"messages": [...]}`, which is also the line format of
+/// OpenAI's chat fine-tuning files, see [`ChatSession::to_fine_tune_jsonl`].
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct ChatSession {
+    /// The messages of the conversation, oldest first
+    pub messages: Vec<ChatFormat>,
+}
+
+impl ChatSession {
+    /// Creates an empty session
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a message to the history
+    pub fn push(&mut self, role: ChatRole, content: impl Into<String>) {
+        self.messages.push(ChatFormat::new(role, content.into()));
+    }
+
+    /// Serializes the session as a JSON document
+    ///
+    /// # Errors
+    /// - `Error::Json` if the session can't be serialized
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    /// Loads a session from a JSON document written by [`ChatSession::to_json`]
+    ///
+    /// # Errors
+    /// - `Error::Json` if the document isn't a valid session
+    pub fn from_json(json: &str) -> Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// Serializes the transcript as JSON lines, one message per line
+    ///
+    /// # Errors
+    /// - `Error::Json` if a message can't be serialized
+    pub fn to_jsonl(&self) -> Result<String> {
+        let mut jsonl = String::new();
+        for message in &self.messages {
+            jsonl.push_str(&serde_json::to_string(message)?);
+            jsonl.push('\n');
+        }
+        Ok(jsonl)
+    }
+
+    /// Loads a transcript written by [`ChatSession::to_jsonl`]. Blank lines are skipped.
+    ///
+    /// # Errors
+    /// - `Error::Json` if a line isn't a valid message
+    pub fn from_jsonl(jsonl: &str) -> Result<Self> {
+        let messages = jsonl
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(serde_json::from_str)
+            .collect::<std::result::Result<_, _>>()?;
+        Ok(Self { messages })
+    }
+
+    /// Serializes several sessions as a chat fine-tuning file: one `{"messages": [...]}`
+    /// object per line
+    ///
+    /// # Errors
+    /// - `Error::Json` if a session can't be serialized
+    pub fn to_fine_tune_jsonl<'a>(
+        sessions: impl IntoIterator<Item = &'a ChatSession>,
+    ) -> Result<String> {
+        let mut jsonl = String::new();
+        for session in sessions {
+            jsonl.push_str(&session.to_json()?);
+            jsonl.push('\n');
+        }
+        Ok(jsonl)
+    }
+}
+
+impl ChatSession {
+    /// Loads the session saved under `id`, if there is one
+    ///
+    /// # Errors
+    /// - Whatever error the store reports
+    pub async fn load(store: &impl ConversationStore, id: &str) -> Result<Option<Self>> {
+        store.load(id).await
+    }
+
+    /// Saves the session under `id`, replacing any session previously saved there
+    ///
+    /// # Errors
+    /// - Whatever error the store reports
+    pub async fn save(&self, store: &impl ConversationStore, id: &str) -> Result<()> {
+        store.save(id, self).await
+    }
+}
+
+/// Prefix of the system message holding the summary of older turns
+const SUMMARY_PREFIX: &str = "Summary of the earlier conversation: ";
+
+fn is_summary(message: &ChatFormat) -> bool {
+    message.role == ChatRole::System && message.content.text().starts_with(SUMMARY_PREFIX)
+}
+
+/// When and how [`ChatSession::summarize_if_needed`] condenses a long history.
+///
+/// Once the history exceeds `threshold_tokens`, the turns between the leading system
+/// messages and the `keep_recent` most recent messages are summarized by `model` into a
+/// single system note, which replaces them. A previous note is summarized again along
+/// with the turns following it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Summarization {
+    /// Estimated size of the history above which it is summarized
+    pub threshold_tokens: u64,
+    /// Number of most recent messages kept as they are. When they start with the results
+    /// of a function or tool call, the call is kept too.
+    pub keep_recent: usize,
+    /// Model writing the summary
+    pub model: String,
+    /// Instructions given to the model, followed by the transcript to summarize
+    pub instructions: String,
+}
+
+impl Default for Summarization {
+    fn default() -> Self {
+        Self {
+            threshold_tokens: 3000,
+            keep_recent: 6,
+            model: "gpt-3.5-turbo".into(),
+            instructions: "Summarize the following conversation in a few sentences, keeping \
+                           the facts, names, decisions and open questions a participant would \
+                           need to carry on with it."
+                .into(),
+        }
+    }
+}
+
+impl ChatSession {
+    /// Estimated number of tokens of the history, see `estimate_tokens`
+    #[must_use]
+    pub fn estimated_tokens(&self) -> u64 {
+        self.messages
+            .iter()
+            .map(|m| (crate::estimate_tokens(&m.content.text()) + m.content.image_tokens()) as u64)
+            .sum()
+    }
+
+    /// Summarizes older turns into a system note if the history is over the threshold of
+    /// `strategy`, and returns whether it did. The history is left unchanged when the
+    /// summary comes back empty.
+    ///
+    /// # Errors
+    /// - Whatever error the API returns for the summary request. The history is left
+    ///   unchanged.
+    pub async fn summarize_if_needed(
+        &mut self,
+        api: &(impl OpenAiApi + ?Sized),
+        strategy: &Summarization,
+    ) -> Result<bool> {
+        if self.estimated_tokens() <= strategy.threshold_tokens {
+            return Ok(false);
+        }
+        let head = self
+            .messages
+            .iter()
+            .take_while(|m| m.role == ChatRole::System && !is_summary(m))
+            .count();
+        let mut tail = self
+            .messages
+            .len()
+            .saturating_sub(strategy.keep_recent)
+            .max(head);
+        // Recent results keep the call they answer
+        while tail > head
+            && self
+                .messages
+                .get(tail)
+                .is_some_and(crate::context::is_result)
+        {
+            tail -= 1;
+        }
+        // Summarizing a lone previous summary wouldn't shrink anything
+        let older = &self.messages[head..tail];
+        if older.is_empty() || (older.len() == 1 && is_summary(&older[0])) {
+            return Ok(false);
+        }
+
+        let transcript: Vec<String> = older
+            .iter()
+            .map(|m| format!("{:?}: {}", m.role, m.content))
+            .collect();
+        let args = crate::api::ChatArgs::builder()
+            .model(strategy.model.as_str())
+            .messages(vec![
+                ChatFormat::new(ChatRole::System, strategy.instructions.clone()),
+                ChatFormat::new(ChatRole::User, transcript.join("\n")),
+            ])
+            .temperature(0.0)
+            .build()?;
+        let answer = api.chat(args).await?;
+        let summary = answer
+            .choices
+            .into_iter()
+            .next()
+            .map(|choice| choice.message.content.text().trim().to_string())
+            .unwrap_or_default();
+        if summary.is_empty() {
+            return Ok(false);
+        }
+
+        self.messages.splice(
+            head..tail,
+            [ChatFormat::new(
+                ChatRole::System,
+                format!("{}{}", SUMMARY_PREFIX, summary),
+            )],
+        );
+        Ok(true)
+    }
+}
+
+/// Storage for chat sessions, keyed by a session id.
+///
+/// Implement this over Redis, Postgres, etc. to keep sessions across restarts or share
+/// them between instances of a service. Storage failures should be reported as
+/// `Error::Store`.
+#[async_trait]
+pub trait ConversationStore: Send + Sync {
+    /// Loads the session saved under `id`, or `None` if there is none
+    async fn load(&self, id: &str) -> Result<Option<ChatSession>>;
+
+    /// Saves `session` under `id`, replacing any session previously saved there
+    async fn save(&self, id: &str, session: &ChatSession) -> Result<()>;
+
+    /// Deletes the session saved under `id`, if any
+    async fn delete(&self, id: &str) -> Result<()>;
+}
+
+/// A `ConversationStore` keeping sessions in memory. Clones share the same sessions.
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryStore {
+    sessions: Arc<RwLock<HashMap<String, ChatSession>>>,
+}
+
+impl InMemoryStore {
+    /// Creates an empty store
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl ConversationStore for InMemoryStore {
+    async fn load(&self, id: &str) -> Result<Option<ChatSession>> {
+        let sessions = self.sessions.read().unwrap_or_else(|e| e.into_inner());
+        Ok(sessions.get(id).cloned())
+    }
+
+    async fn save(&self, id: &str, session: &ChatSession) -> Result<()> {
+        let mut sessions = self.sessions.write().unwrap_or_else(|e| e.into_inner());
+        sessions.insert(id.to_string(), session.clone());
+        Ok(())
+    }
+
+    async fn delete(&self, id: &str) -> Result<()> {
+        let mut sessions = self.sessions.write().unwrap_or_else(|e| e.into_inner());
+        sessions.remove(id);
+        Ok(())
+    }
+}
+
+/// A chat with a model: the history of a [`ChatSession`], a system prompt, and the
+/// arguments of every request.
+///
+/// # Example
+/// ```no_run
+/// # use openai_api::{api::ChatArgs, session::Conversation, Client};
+/// # async fn example(client: Client) -> Result<(), openai_api::Error> {
+/// let args = ChatArgs::builder().model("gpt-4o-mini").temperature(0.2).build()?;
+/// let mut conversation = Conversation::new(args).with_system("You are a helpful assistant.");
+/// let reply = conversation.send(&client, "Who won the world series in 2020?").await?;
+/// println!("{}", reply.content);
+/// conversation.send(&client, "Where was it played?").await?;
+/// assert_eq!(conversation.session().messages.len(), 4);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Conversation {
+    system: Option<String>,
+    session: ChatSession,
+    args: ChatArgs,
+}
+
+impl Conversation {
+    /// Starts an empty conversation sending requests with `args`. The messages of `args`
+    /// are replaced by those of the conversation.
+    #[must_use]
+    pub fn new(args: ChatArgs) -> Self {
+        Self {
+            system: None,
+            session: ChatSession::new(),
+            args,
+        }
+    }
+
+    /// Sends `prompt` as a system message before the history in every request
+    #[must_use]
+    pub fn with_system(mut self, prompt: impl Into<String>) -> Self {
+        self.system = Some(prompt.into());
+        self
+    }
+
+    /// Carries on the conversation of `session`
+    #[must_use]
+    pub fn with_session(mut self, session: ChatSession) -> Self {
+        self.session = session;
+        self
+    }
+
+    /// The system prompt, if any
+    #[must_use]
+    pub fn system(&self) -> Option<&str> {
+        self.system.as_deref()
+    }
+
+    /// The history, without the system prompt
+    #[must_use]
+    pub fn session(&self) -> &ChatSession {
+        &self.session
+    }
+
+    /// The history, to edit it or summarize it
+    pub fn session_mut(&mut self) -> &mut ChatSession {
+        &mut self.session
+    }
+
+    /// The arguments of every request
+    #[must_use]
+    pub fn args(&self) -> &ChatArgs {
+        &self.args
+    }
+
+    /// The messages of the next request: the system prompt and the history
+    #[must_use]
+    pub fn messages(&self) -> Vec<ChatFormat> {
+        let system = self
+            .system
+            .iter()
+            .map(|prompt| ChatFormat::new(ChatRole::System, prompt.clone()));
+        system
+            .chain(self.session.messages.iter().cloned())
+            .collect()
+    }
+
+    /// Appends `text` as a user message, sends the conversation, appends the reply of the
+    /// assistant to the history and returns it. The reply is empty if the API returns no
+    /// choice.
+    ///
+    /// # Errors
+    /// - Whatever error the API returns. The history is left unchanged.
+    pub async fn send(
+        &mut self,
+        api: &(impl OpenAiApi + ?Sized),
+        text: impl Into<String>,
+    ) -> Result<ChatFormat> {
+        let user = ChatFormat::new(ChatRole::User, text.into());
+        let mut args = self.args.clone();
+        let mut messages = self.messages();
+        messages.push(user.clone());
+        args.messages = messages.into();
+
+        let answer = api.chat(args).await?;
+        let reply = answer.choices.into_iter().next().map_or_else(
+            || ChatFormat::new(ChatRole::Assistant, String::new()),
+            |c| c.message,
+        );
+        self.session.messages.push(user);
+        self.session.messages.push(reply.clone());
+        Ok(reply)
+    }
+}
+
+impl From<Vec<ChatFormat>> for ChatSession {
+    fn from(messages: Vec<ChatFormat>) -> Self {
+        Self { messages }
+    }
+}
+
+#[cfg(test)]
+mod unit {
+    use super::{ChatSession, Conversation, ConversationStore, InMemoryStore, Summarization};
+    use crate::{
+        api::{ChatArgs, ChatFormat, ChatRole, FunctionCall, ToolCall, ToolKind},
+        unit::mocked_client,
+        Error,
+    };
+
+    fn session() -> ChatSession {
+        let mut session = ChatSession::new();
+        session.push(ChatRole::System, "You are a helpful assistant.");
+        session.push(ChatRole::User, "Who won the world series in 2020?");
+        session.push(
+            ChatRole::Assistant,
+            "The Los Angeles Dodgers won the World Series in 2020.",
+        );
+        session
+    }
+
+    fn summarizer(model: &str, content: &str) -> mockito::Mock {
+        let choices = match content {
+            "" => serde_json::json!([]),
+            content => serde_json::json!([{"index": 0, "finish_reason": "stop",
+                "message": {"role": "assistant", "content": content}}]),
+        };
+        mockito::mock("POST", "/chat/completions")
+            .match_body(mockito::Matcher::PartialJson(
+                serde_json::json!({"model": model}),
+            ))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                serde_json::json!({"id": "chatcmpl-summary", "choices": choices}).to_string(),
+            )
+            .expect(1)
+            .create()
+    }
+
+    #[test]
+    fn jsonl_round_trip() -> crate::Result<()> {
+        let session = session();
+        let jsonl = session.to_jsonl()?;
+        assert_eq!(jsonl.lines().count(), 3);
+        assert_eq!(
+            jsonl.lines().next(),
+            Some(r#"{"role":"system","content":"You are a helpful assistant."}"#)
+        );
+        assert_eq!(ChatSession::from_jsonl(&format!("{}\n\n", jsonl))?, session);
+        assert!(matches!(
+            ChatSession::from_jsonl("{\"role\": \"nobody\"}"),
+            Err(Error::Json(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn fine_tune_format() -> crate::Result<()> {
+        let session = session();
+        assert_eq!(ChatSession::from_json(&session.to_json()?)?, session);
+
+        let jsonl = ChatSession::to_fine_tune_jsonl([&session, &session])?;
+        let lines: Vec<_> = jsonl.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with(r#"{"messages":[{"role":"system""#));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn summarizes_older_turns() -> crate::Result<()> {
+        let mock = summarizer("summarizer", "The Dodgers won in 2020.");
+        let client = mocked_client();
+        let strategy = Summarization {
+            threshold_tokens: 20,
+            keep_recent: 1,
+            model: "summarizer".into(),
+            ..Summarization::default()
+        };
+
+        let mut session = session();
+        let untouched = session.clone();
+        let lenient = Summarization {
+            threshold_tokens: 1000,
+            ..strategy.clone()
+        };
+        assert!(!session.summarize_if_needed(&client, &lenient).await?);
+        assert_eq!(session, untouched);
+
+        session.push(ChatRole::User, "Where was it played?");
+        assert!(session.summarize_if_needed(&client, &strategy).await?);
+        let contents: Vec<_> = session.messages.iter().map(|m| m.content.text()).collect();
+        assert_eq!(
+            contents,
+            [
+                "You are a helpful assistant.",
+                "Summary of the earlier conversation: The Dodgers won in 2020.",
+                "Where was it played?",
+            ]
+        );
+        mock.assert();
+
+        // A lone summary isn't summarized again
+        assert!(
+            !session
+                .summarize_if_needed(
+                    &client,
+                    &Summarization {
+                        threshold_tokens: 0,
+                        ..strategy
+                    }
+                )
+                .await?
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn keeps_results_with_their_call() -> crate::Result<()> {
+        let mock = summarizer("summarizer-tools", "Asked about the weather.");
+        let mut session = session();
+        let mut call = ChatFormat::new(ChatRole::Assistant, String::new());
+        call.tool_calls = Some(vec![ToolCall {
+            id: "call_1".into(),
+            kind: ToolKind::Function,
+            function: FunctionCall {
+                name: "get_weather".into(),
+                arguments: r#"{"city": "Paris"}"#.into(),
+            },
+        }]);
+        session.messages.push(call);
+        session
+            .messages
+            .push(ChatFormat::tool_result("call_1", "Sunny, 24°C"));
+        let strategy = Summarization {
+            threshold_tokens: 0,
+            keep_recent: 1,
+            model: "summarizer-tools".into(),
+            ..Summarization::default()
+        };
+        assert!(
+            session
+                .summarize_if_needed(&mocked_client(), &strategy)
+                .await?
+        );
+        let roles: Vec<_> = session.messages.iter().map(|m| m.role.clone()).collect();
+        assert_eq!(
+            roles,
+            [
+                ChatRole::System,
+                ChatRole::System,
+                ChatRole::Assistant,
+                ChatRole::Tool
+            ]
+        );
+        mock.assert();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn keeps_history_without_summary() -> crate::Result<()> {
+        let mock = summarizer("summarizer-empty", "");
+        let mut session = session();
+        let untouched = session.clone();
+        let strategy = Summarization {
+            threshold_tokens: 0,
+            keep_recent: 1,
+            model: "summarizer-empty".into(),
+            ..Summarization::default()
+        };
+        assert!(
+            !session
+                .summarize_if_needed(&mocked_client(), &strategy)
+                .await?
+        );
+        assert_eq!(session, untouched);
+        mock.assert();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn in_memory_store() -> crate::Result<()> {
+        let store = InMemoryStore::new();
+        assert_eq!(ChatSession::load(&store, "alice").await?, None);
+
+        let mut session = session();
+        session.save(&store, "alice").await?;
+        session.push(ChatRole::User, "Where was it played?");
+        session.save(&store.clone(), "alice").await?;
+        assert_eq!(ChatSession::load(&store, "alice").await?, Some(session));
+        assert_eq!(ChatSession::load(&store, "bob").await?, None);
+
+        store.delete("alice").await?;
+        assert_eq!(ChatSession::load(&store, "alice").await?, None);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn conversation_keeps_history() -> crate::Result<()> {
+        let reply = |messages: &str, content: &str| {
+            mockito::mock("POST", "/chat/completions")
+                .match_body(mockito::Matcher::PartialJsonString(format!(
+                    r#"{{"model": "conversation", "temperature": 0.5, "messages": {}}}"#,
+                    messages
+                )))
+                .with_status(200)
+                .with_header("content-type", "application/json")
+                .with_body(format!(
+                    r#"{{"id": "chatcmpl-1", "created": 1677652288, "choices": [{{
+                        "index": 0,
+                        "message": {{"role": "assistant", "content": "{}"}},
+                        "finish_reason": "stop"
+                    }}]}}"#,
+                    content
+                ))
+                .expect(1)
+                .create()
+        };
+        let first = reply(
+            r#"[{"role": "system", "content": "Be brief."}, {"role": "user", "content": "Hi"}]"#,
+            "Hello!",
+        );
+        let second = reply(
+            r#"[{"role": "system", "content": "Be brief."}, {"role": "user", "content": "Hi"},
+                {"role": "assistant", "content": "Hello!"}, {"role": "user", "content": "Bye"}]"#,
+            "Goodbye!",
+        );
+        let failure = mockito::mock("POST", "/chat/completions")
+            .match_body(mockito::Matcher::PartialJsonString(
+                r#"{"model": "conversation", "temperature": 0.5, "messages": [{"role": "system", "content": "Be brief."}, {"role": "user", "content": "Hi"},
+                    {"role": "assistant", "content": "Hello!"}, {"role": "user", "content": "Fail"}]}"#
+                    .into(),
+            ))
+            .with_status(400)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"error": {"message": "nope", "type": "invalid_request_error"}}"#)
+            .create();
+        let client = mocked_client();
+        let args = ChatArgs::builder()
+            .model("conversation")
+            .temperature(0.5)
+            .build()?;
+        let mut conversation = Conversation::new(args).with_system("Be brief.");
+
+        assert_eq!(conversation.send(&client, "Hi").await?.content, "Hello!");
+        assert!(matches!(
+            conversation.send(&client, "Fail").await,
+            Err(Error::Api(_))
+        ));
+        assert_eq!(conversation.session().messages.len(), 2);
+        assert_eq!(conversation.send(&client, "Bye").await?.content, "Goodbye!");
+        let roles: Vec<_> = conversation
+            .messages()
+            .into_iter()
+            .map(|m| m.role)
+            .collect();
+        assert_eq!(
+            roles,
+            [
+                ChatRole::System,
+                ChatRole::User,
+                ChatRole::Assistant,
+                ChatRole::User,
+                ChatRole::Assistant
+            ]
+        );
+        first.assert();
+        second.assert();
+        failure.assert();
+        Ok(())
+    }
+}
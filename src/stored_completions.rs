@@ -0,0 +1,200 @@
+//! Stored chat completions: those requested with `store: true`, kept by OpenAI to be
+//! fetched, audited and deleted later.
+use std::collections::HashMap;
+
+use serde::Deserialize;
+
+use crate::{
+    api::{ChatAnswer, Deleted, ListParams, Page},
+    Client, Result,
+};
+
+/// A stored chat completion
+#[derive(Deserialize, Debug, Clone)]
+pub struct StoredCompletion {
+    /// The completion, as it was returned
+    #[serde(flatten)]
+    pub answer: ChatAnswer,
+    /// The model that generated it
+    #[serde(default, deserialize_with = "crate::api::lenient")]
+    pub model: String,
+    /// The metadata it was requested with
+    #[serde(default, deserialize_with = "crate::api::lenient")]
+    pub metadata: HashMap<String, String>,
+}
+
+/// A message of the request of a stored completion
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct StoredMessage {
+    pub id: String,
+    /// E.g. `"user"`. Kept as sent, as stored requests may use roles `api::ChatRole` lacks.
+    pub role: String,
+    #[serde(default, deserialize_with = "crate::api::lenient")]
+    pub content: String,
+}
+
+/// Filters of a listing of stored completions, on top of the pagination of `ListParams`
+///
+/// # Example
+/// ```
+/// # use openai_api::{api::ListParams, stored_completions::StoredCompletionsQuery};
+/// let query = StoredCompletionsQuery::default()
+///     .model("gpt-4o")
+///     .metadata("user", "alice")
+///     .page(ListParams::builder().limit(50).build().unwrap());
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StoredCompletionsQuery {
+    model: Option<String>,
+    metadata: Vec<(String, String)>,
+    page: ListParams,
+}
+
+impl StoredCompletionsQuery {
+    /// Only lists the completions of this model
+    #[must_use]
+    pub fn model(mut self, model: impl Into<String>) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+
+    /// Only lists the completions whose metadata has this value for `key`. Can be repeated.
+    #[must_use]
+    pub fn metadata(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.metadata.push((key.into(), value.into()));
+        self
+    }
+
+    /// Which page to list
+    #[must_use]
+    pub fn page(mut self, page: ListParams) -> Self {
+        self.page = page;
+        self
+    }
+
+    /// The filters of the query string, as pairs: metadata filters are
+    /// `metadata[key]=value`, which `serde_urlencoded` can't express as a struct
+    fn filters(&self) -> Vec<(String, String)> {
+        let model = self
+            .model
+            .iter()
+            .map(|model| ("model".into(), model.clone()));
+        let metadata = self
+            .metadata
+            .iter()
+            .map(|(key, value)| (format!("metadata[{}]", key), value.clone()));
+        model.chain(metadata).collect()
+    }
+}
+
+impl Client {
+    /// Fetches a stored completion
+    ///
+    /// # Errors
+    /// - `Error::Api` if the api returns an error, e.g. if it wasn't stored
+    pub async fn stored_completion(&self, completion_id: &str) -> Result<StoredCompletion> {
+        self.get(&format!("chat/completions/{}", completion_id))
+            .await
+    }
+
+    /// Lists stored completions, filtered by model and metadata
+    ///
+    /// # Errors
+    /// - `Error::Api` if the api returns an error
+    pub async fn stored_completions(
+        &self,
+        query: &StoredCompletionsQuery,
+    ) -> Result<Page<StoredCompletion>> {
+        let url = self.url("chat/completions")?;
+        let request = self.inner.client.get(url).query(&query.page);
+        self.send(request.query(&query.filters())).await
+    }
+
+    /// Lists the messages of the request of a stored completion
+    ///
+    /// # Errors
+    /// - `Error::Api` if the api returns an error
+    pub async fn stored_completion_messages(
+        &self,
+        completion_id: &str,
+        page: &ListParams,
+    ) -> Result<Page<StoredMessage>> {
+        let endpoint = format!("chat/completions/{}/messages", completion_id);
+        self.get_with_query(&endpoint, page).await
+    }
+
+    /// Deletes a stored completion
+    ///
+    /// # Errors
+    /// - `Error::Api` if the api returns an error
+    pub async fn delete_stored_completion(&self, completion_id: &str) -> Result<Deleted> {
+        let url = self.url(&format!("chat/completions/{}", completion_id))?;
+        self.send(self.inner.client.delete(url)).await
+    }
+}
+
+#[cfg(test)]
+mod unit {
+    use mockito::Matcher;
+
+    use super::StoredCompletionsQuery;
+    use crate::{api::ListParams, unit::mocked_client};
+
+    const COMPLETION: &str = r#"{"id": "chatcmpl-1", "object": "chat.completion",
+        "created": 1738960610, "model": "gpt-4o-2024-08-06", "metadata": {"user": "alice"},
+        "choices": [{"index": 0, "message": {"role": "assistant", "content": "Hi!"},
+        "finish_reason": "stop"}]}"#;
+
+    #[tokio::test]
+    async fn lists_and_fetches_stored_completions() -> crate::Result<()> {
+        let list = mockito::mock("GET", "/chat/completions")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("model".into(), "gpt-4o".into()),
+                Matcher::UrlEncoded("metadata[user]".into(), "alice".into()),
+                Matcher::UrlEncoded("limit".into(), "1".into()),
+            ]))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(format!(
+                r#"{{"object": "list", "data": [{}], "first_id": "chatcmpl-1",
+                "last_id": "chatcmpl-1", "has_more": true}}"#,
+                COMPLETION
+            ))
+            .expect(1)
+            .create();
+        let client = mocked_client();
+        let query = StoredCompletionsQuery::default()
+            .model("gpt-4o")
+            .metadata("user", "alice")
+            .page(ListParams::builder().limit(1).build().unwrap());
+        let page = client.stored_completions(&query).await?;
+        assert!(page.has_more);
+        assert_eq!(page.last_id.as_deref(), Some("chatcmpl-1"));
+        assert_eq!(page.data[0].metadata["user"], "alice");
+        assert_eq!(page.data[0].answer.choices[0].message.content, "Hi!");
+        list.assert();
+
+        let _messages = mockito::mock("GET", "/chat/completions/chatcmpl-1/messages")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"{"object": "list", "data": [{"id": "chatcmpl-1-0", "role": "developer",
+                "content": "Be brief."}], "has_more": false}"#,
+            )
+            .create();
+        let messages = client
+            .stored_completion_messages("chatcmpl-1", &ListParams::default())
+            .await?;
+        assert_eq!(messages.data[0].role, "developer");
+
+        let _deleted = mockito::mock("DELETE", "/chat/completions/chatcmpl-1")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"{"object": "chat.completion.deleted", "id": "chatcmpl-1", "deleted": true}"#,
+            )
+            .create();
+        assert!(client.delete_stored_completion("chatcmpl-1").await?.deleted);
+        Ok(())
+    }
+}
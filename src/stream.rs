@@ -0,0 +1,350 @@
+//! Helpers to consume streamed answers, and the server-sent events they arrive as.
+use std::collections::VecDeque;
+
+use futures::{Stream, StreamExt};
+use serde::de::DeserializeOwned;
+use tokio::sync::mpsc::Sender;
+
+use crate::{api::ErrorWrapper, pool, response, retry, Client, Result};
+
+/// Forwards the text of each delta of `deltas`, e.g. the `ChatDelta`s of
+/// `Client::chat_stream`, into `sender`, and returns the whole answer once the stream
+/// ends. Deltas without text, such as the first one carrying the role, aren't forwarded.
+///
+/// This is the shape web frameworks expect to relay an answer as it is generated, e.g.
+/// over server-sent events or a websocket: the handler streams out of the receiver, while
+/// this future runs in a task and returns the answer to store once complete.
+///
+/// If the receiver is dropped, e.g. because the user disconnected, forwarding stops, and
+/// the answer received so far is returned.
+///
+/// # Errors
+/// - The first error of the stream. Deltas before it were already forwarded.
+///
+/// # Example
+/// ```no_run
+/// # use openai_api::{api::{ChatArgs, ChatRole}, stream::forward_deltas, Client};
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), openai_api::Error> {
+/// let client = Client::new("sk-...")?;
+/// let args = ChatArgs::from(vec![(ChatRole::User, "Hello!".to_string())]);
+/// let deltas = client.chat_stream(args).await?;
+/// let (tx, mut rx) = tokio::sync::mpsc::channel(16);
+/// let answer = tokio::spawn(async move { forward_deltas(deltas, &tx).await });
+/// while let Some(delta) = rx.recv().await {
+///     print!("{}", delta);
+/// }
+/// let answer = answer.await.unwrap()?;
+/// # Ok(())
+/// # }
+/// ```
+pub async fn forward_deltas<S, T>(deltas: S, sender: &Sender<String>) -> Result<String>
+where
+    S: Stream<Item = Result<T>>,
+    T: std::fmt::Display,
+{
+    let mut deltas = std::pin::pin!(deltas);
+    let mut answer = String::new();
+    while let Some(delta) = deltas.next().await {
+        let delta = delta?.to_string();
+        if delta.is_empty() {
+            continue;
+        }
+        answer.push_str(&delta);
+        if sender.send(delta).await.is_err() {
+            break;
+        }
+    }
+    Ok(answer)
+}
+
+/// Incremental parser of server-sent events, keeping their `data` only: the API sends
+/// nothing else of use
+#[derive(Default)]
+pub(crate) struct SseParser {
+    /// Bytes of the line being received, in a pooled buffer
+    line: pool::Buffer,
+    /// Data of the event being received
+    data: Option<String>,
+}
+
+impl SseParser {
+    /// Reads `chunk`, returning the data of the events it completes
+    pub(crate) fn feed(&mut self, chunk: &[u8]) -> Vec<String> {
+        let mut events = Vec::new();
+        for &byte in chunk {
+            if byte != b'\n' {
+                self.line.push(byte);
+                continue;
+            }
+            if self.line.last() == Some(&b'\r') {
+                self.line.pop();
+            }
+            if self.line.is_empty() {
+                events.extend(self.data.take());
+            } else if let Some(value) = self.line.strip_prefix(b"data:") {
+                let value = value.strip_prefix(b" ").unwrap_or(value);
+                let value = String::from_utf8_lossy(value);
+                match &mut self.data {
+                    Some(data) => {
+                        data.push('\n');
+                        data.push_str(&value);
+                    }
+                    None => self.data = Some(value.into_owned()),
+                }
+            }
+            self.line.clear();
+        }
+        events
+    }
+
+    /// The data of the last event, if the stream ended without the blank line closing it
+    pub(crate) fn finish(&mut self) -> Option<String> {
+        self.feed(b"\n\n").pop()
+    }
+}
+
+/// The body of a streamed response
+enum Body {
+    /// Arriving over the network
+    #[cfg(not(target_arch = "wasm32"))]
+    Live(reqwest::Response),
+    /// Already received: replayed from a cassette, or read whole by a custom transport or
+    /// the browser
+    Buffered(Option<bytes::Bytes>),
+}
+
+impl Body {
+    async fn chunk(&mut self) -> Result<Option<bytes::Bytes>> {
+        match self {
+            #[cfg(not(target_arch = "wasm32"))]
+            Body::Live(response) => Ok(response.chunk().await?),
+            Body::Buffered(body) => Ok(body.take()),
+        }
+    }
+}
+
+/// Parses the events of `body` as `T`s, until the `[DONE]` event or the end of the body
+fn events<T: DeserializeOwned>(body: Body, limit: usize) -> impl Stream<Item = Result<T>> {
+    struct State {
+        body: Body,
+        parser: SseParser,
+        pending: VecDeque<String>,
+        size: usize,
+        limit: usize,
+        ended: bool,
+    }
+
+    let state = State {
+        body,
+        parser: SseParser::default(),
+        pending: VecDeque::new(),
+        size: 0,
+        limit,
+        ended: false,
+    };
+    futures::stream::try_unfold(state, |mut state| async move {
+        loop {
+            if let Some(data) = state.pending.pop_front() {
+                if data == "[DONE]" {
+                    return Ok(None);
+                }
+                return Ok(Some((event(&data)?, state)));
+            }
+            if state.ended {
+                return Ok(None);
+            }
+            match state.body.chunk().await? {
+                Some(chunk) => {
+                    state.size += chunk.len();
+                    response::check_size(state.size as u64, state.limit)?;
+                    state.pending.extend(state.parser.feed(&chunk));
+                }
+                None => {
+                    state.ended = true;
+                    state.pending.extend(state.parser.finish());
+                }
+            }
+        }
+    })
+}
+
+/// Parses the data of an event, or the error the API sent instead mid-stream
+fn event<T: DeserializeOwned>(data: &str) -> Result<T> {
+    serde_json::from_str(data).map_err(|err| match serde_json::from_str::<ErrorWrapper>(data) {
+        Ok(wrapper) => wrapper.error.into(),
+        Err(_) => err.into(),
+    })
+}
+
+impl Client {
+    /// Private helper posting a request whose response streams back as server-sent
+    /// events, parsed as `T`s. Only opening the stream is retried: events already
+    /// received can't be taken back.
+    pub(crate) async fn post_stream<T>(
+        &self,
+        endpoint: &str,
+        body: &serde_json::Value,
+    ) -> Result<impl Stream<Item = Result<T>>>
+    where
+        T: DeserializeOwned,
+    {
+        let url = self.url(endpoint)?;
+        self.send_stream(self.inner.client.post(url).json(body))
+            .await
+    }
+
+    /// Private helper sending a request whose response streams back as server-sent
+    /// events, for those needing more than `post_stream`, e.g. extra headers
+    pub(crate) async fn send_stream<T>(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> Result<impl Stream<Item = Result<T>>>
+    where
+        T: DeserializeOwned,
+    {
+        let request = self.prepare(request).await?;
+        let body = retry::with_retries(&self.inner.retry, || {
+            self.open_stream(retry::resend(&request))
+        })
+        .await?;
+        let limit = self.inner.max_response_size.unwrap_or(usize::MAX);
+        Ok(events(body, limit))
+    }
+
+    /// Private helper making a single attempt at opening a stream
+    async fn open_stream(&self, request: reqwest::Request) -> Result<Body> {
+        #[cfg(not(target_arch = "wasm32"))]
+        if !self.buffered() {
+            return self.open_live_stream(request).await;
+        }
+        let response = self.execute(request).await?;
+        self.remember(response.meta());
+        if response.status != reqwest::StatusCode::OK {
+            return Err(Self::api_error(&response));
+        }
+        Ok(Body::Buffered(Some(response.body)))
+    }
+
+    /// Private helper opening a stream read as it arrives over the network
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn open_live_stream(&self, request: reqwest::Request) -> Result<Body> {
+        let response = self.inner.client.execute(request).await?;
+        if response.status() != reqwest::StatusCode::OK {
+            let response =
+                response::RawResponse::read(response, self.inner.max_response_size).await?;
+            self.remember(response.meta());
+            return Err(Self::api_error(&response));
+        }
+        self.remember(response::ResponseMeta {
+            status: response.status().as_u16(),
+            headers: response.headers().clone(),
+        });
+        Ok(Body::Live(response))
+    }
+}
+
+#[cfg(test)]
+mod unit {
+    use super::{forward_deltas, SseParser};
+    use crate::{
+        api::{ChatArgs, ChatRole},
+        unit::mocked_client,
+        Error, Result,
+    };
+
+    fn deltas(parts: &[&str]) -> Vec<Result<String>> {
+        parts.iter().map(|p| Ok(p.to_string())).collect()
+    }
+
+    #[tokio::test]
+    async fn forwards_and_accumulates() -> Result<()> {
+        let (tx, rx) = tokio::sync::mpsc::channel(1);
+        let items = futures::stream::iter(deltas(&["a", "b", "c"]));
+        // Owns the sender, so that the channel closes once the stream is forwarded
+        let forward = async move { forward_deltas(items, &tx).await };
+        let received = collect(rx);
+        let (answer, received) = futures::join!(forward, received);
+        assert_eq!(answer?, "abc");
+        assert_eq!(received, ["a", "b", "c"]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn forwards_chat_streams() -> Result<()> {
+        let chunk = |delta: serde_json::Value| {
+            format!(
+                "data: {}\n\n",
+                serde_json::json!({"id": "chatcmpl-1", "choices": [{"index": 0, "delta": delta}]})
+            )
+        };
+        let body = [
+            chunk(serde_json::json!({"role": "assistant"})),
+            chunk(serde_json::json!({"content": "Hel"})),
+            chunk(serde_json::json!({"content": "lo!"})),
+            "data: [DONE]\n\n".to_string(),
+        ]
+        .concat();
+        let mock = mockito::mock("POST", "/chat/completions")
+            .match_body(mockito::Matcher::PartialJson(serde_json::json!({
+                "stream": true,
+                "messages": [{"content": "Forward this"}],
+            })))
+            .with_status(200)
+            .with_header("content-type", "text/event-stream")
+            .with_body(body)
+            .expect(1)
+            .create();
+        let args = ChatArgs::from(vec![(ChatRole::User, "Forward this".to_string())]);
+        let deltas = mocked_client().chat_stream(args).await?;
+        let (tx, rx) = tokio::sync::mpsc::channel(8);
+        let answer = forward_deltas(deltas, &tx).await?;
+        drop(tx);
+        assert_eq!(answer, "Hello!");
+        assert_eq!(collect(rx).await, ["Hel", "lo!"]);
+        mock.assert();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn stops_on_error_or_closed_receiver() {
+        let (tx, rx) = tokio::sync::mpsc::channel(8);
+        let mut items = deltas(&["a"]);
+        items.push(Err(Error::BadArguments("broken stream".into())));
+        items.extend(deltas(&["b"]));
+        let result = forward_deltas(futures::stream::iter(items), &tx).await;
+        assert!(matches!(result, Err(Error::BadArguments(_))));
+        drop(tx);
+        assert_eq!(collect(rx).await, ["a"]);
+
+        let (tx, rx) = tokio::sync::mpsc::channel(8);
+        drop(rx);
+        let answer = forward_deltas(futures::stream::iter(deltas(&["a", "b"])), &tx).await;
+        assert_eq!(answer.unwrap(), "a");
+    }
+
+    #[test]
+    fn parses_server_sent_events() {
+        let mut parser = SseParser::default();
+        assert!(parser.feed(b": keep-alive\n\ndata: {\"a\"").is_empty());
+        assert_eq!(
+            parser.feed(b": 1}\r\n\r\ndata: [DONE]\n\n"),
+            ["{\"a\": 1}", "[DONE]"]
+        );
+        assert_eq!(
+            parser.feed(b"event: x\ndata: one\ndata:two\n\n"),
+            ["one\ntwo"]
+        );
+        parser.feed(b"data: last");
+        assert_eq!(parser.finish().as_deref(), Some("last"));
+        assert_eq!(parser.finish(), None);
+    }
+
+    async fn collect(mut rx: tokio::sync::mpsc::Receiver<String>) -> Vec<String> {
+        let mut received = Vec::new();
+        while let Some(delta) = rx.recv().await {
+            received.push(delta);
+        }
+        received
+    }
+}
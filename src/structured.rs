@@ -0,0 +1,232 @@
+//! Structured outputs with the `schemars` feature: answers deserialized into Rust types,
+//! with the JSON schema the model follows derived from the type.
+//!
+//! # Example
+//! ```no_run
+//! # use openai_api::api::{ChatArgs, ChatFormat, ChatRole};
+//! #[derive(serde::Deserialize, schemars::JsonSchema)]
+//! struct Weather {
+//!     /// Temperature in degrees Celsius
+//!     celsius: f64,
+//!     summary: String,
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), openai_api::Error> {
+//! let client = openai_api::Client::new("sk-...")?;
+//! let args = ChatArgs::builder()
+//!     .model("gpt-4o-mini")
+//!     .messages(vec![ChatFormat::new(ChatRole::User, "What's the weather in Paris?".into())]);
+//! let weather: Weather = client.chat_structured(args).await?;
+//! # Ok(())
+//! # }
+//! ```
+use schemars::{gen::SchemaSettings, JsonSchema};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::{
+    api::{self, ChatArgs},
+    Client, Error, Result,
+};
+
+/// The strict schema of `T`, for `ResponseFormat::JsonSchema`.
+///
+/// Strict schemas must list every property as required and allow no other: optional
+/// fields are required but nullable, as `Option<T>` already allows `null`.
+///
+/// # Example
+/// ```
+/// # use openai_api::structured::schema_for;
+/// #[derive(schemars::JsonSchema)]
+/// struct Answer {
+///     text: String,
+///     source: Option<String>,
+/// }
+///
+/// let schema = schema_for::<Answer>();
+/// assert_eq!(schema.name, "Answer");
+/// assert_eq!(schema.schema["required"], serde_json::json!(["source", "text"]));
+/// assert_eq!(schema.schema["additionalProperties"], false);
+/// ```
+#[must_use]
+pub fn schema_for<T: JsonSchema>() -> api::JsonSchema {
+    let root = SchemaSettings::draft2019_09()
+        .with(|settings| {
+            settings.meta_schema = None;
+        })
+        .into_generator()
+        .into_root_schema_for::<T>();
+    let mut schema = serde_json::to_value(root).expect("schemas serialize");
+    strict(&mut schema);
+    let description = schema
+        .as_object_mut()
+        .and_then(|root| root.remove("description"))
+        .and_then(|description| description.as_str().map(String::from));
+    let name: String = T::schema_name()
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '_' | '-' => c,
+            _ => '_',
+        })
+        .take(64)
+        .collect();
+    api::JsonSchema {
+        description,
+        ..api::JsonSchema::new(name, schema)
+    }
+}
+
+/// Makes every object of `schema` strict: all its properties required, and no other
+/// allowed. Number formats, e.g. `uint32`, are dropped, since strict schemas only accept
+/// string formats.
+fn strict(schema: &mut Value) {
+    match schema {
+        Value::Object(object) => {
+            if let Some(Value::Object(properties)) = object.get("properties") {
+                let required = properties.keys().cloned().map(Value::from).collect();
+                object.insert("required".into(), Value::Array(required));
+                object.insert("additionalProperties".into(), false.into());
+            }
+            let numeric = |kind: &Value| matches!(kind.as_str(), Some("integer" | "number"));
+            let is_number = match object.get("type") {
+                Some(Value::Array(kinds)) => kinds.iter().any(numeric),
+                Some(kind) => numeric(kind),
+                None => false,
+            };
+            if is_number {
+                object.remove("format");
+            }
+            object.values_mut().for_each(strict);
+        }
+        Value::Array(items) => items.iter_mut().for_each(strict),
+        _ => {}
+    }
+}
+
+impl Client {
+    /// Sends a chat request whose answer follows the schema of `T`, and deserializes it.
+    /// The `response_format` of `args` is replaced by the strict schema of `T`, see
+    /// `schema_for`.
+    ///
+    /// # Errors
+    ///  - The errors of `chat`
+    ///  - `Error::InvalidOutput` if the answer isn't a `T`, e.g. when the model refused, or
+    ///    ran out of tokens
+    pub async fn chat_structured<T, A>(&self, args: A) -> Result<T>
+    where
+        T: DeserializeOwned + JsonSchema,
+        A: TryInto<ChatArgs>,
+        Error: From<<A as TryInto<ChatArgs>>::Error>,
+    {
+        let mut args: ChatArgs = args.try_into()?;
+        args.response_format = Some(schema_for::<T>().into());
+        let answer = self.chat::<ChatArgs>(args).await?;
+        let content = answer
+            .choices
+            .into_iter()
+            .next()
+            .map(|choice| choice.message.content.text().into_owned())
+            .unwrap_or_default();
+        serde_json::from_str(&content).map_err(|source| Error::InvalidOutput { content, source })
+    }
+}
+
+#[cfg(test)]
+mod unit {
+    use mockito::Matcher;
+    use serde::Deserialize;
+    use serde_json::json;
+
+    use super::schema_for;
+    use crate::{
+        api::{ChatArgs, ChatFormat, ChatRole},
+        unit::mocked_client,
+        Error,
+    };
+
+    /// A city
+    #[derive(Deserialize, schemars::JsonSchema, Debug, PartialEq)]
+    struct City {
+        name: String,
+        population: u32,
+        landmarks: Vec<Landmark>,
+    }
+
+    #[derive(Deserialize, schemars::JsonSchema, Debug, PartialEq)]
+    struct Landmark {
+        name: String,
+        built: Option<i32>,
+    }
+
+    #[test]
+    fn derives_strict_schemas() {
+        let schema = schema_for::<City>();
+        assert_eq!(schema.name, "City");
+        assert_eq!(schema.description.as_deref(), Some("A city"));
+        assert_eq!(schema.strict, Some(true));
+        let root = &schema.schema;
+        assert_eq!(root["additionalProperties"], false);
+        assert!(root["properties"]["population"].get("format").is_none());
+        let landmark = &root["definitions"]["Landmark"];
+        assert_eq!(landmark["required"], json!(["built", "name"]));
+        assert_eq!(landmark["additionalProperties"], false);
+        assert_eq!(
+            landmark["properties"]["built"]["type"],
+            json!(["integer", "null"])
+        );
+    }
+
+    #[tokio::test]
+    async fn chats_structured() -> crate::Result<()> {
+        let answer = |content: &str| {
+            json!({"choices": [{"index": 0, "finish_reason": "stop", "message": {
+                "role": "assistant", "content": content
+            }}]})
+            .to_string()
+        };
+        let ask = |question: &str| {
+            ChatArgs::builder()
+                .model("gpt-4o-mini")
+                .messages(vec![ChatFormat::new(ChatRole::User, question.into())])
+        };
+        let valid = mockito::mock("POST", "/chat/completions")
+            .match_body(Matcher::PartialJson(json!({
+                "messages": [{"content": "Describe a city."}],
+                "response_format": {"type": "json_schema", "json_schema": {"name": "City", "strict": true}},
+            })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(answer(
+                r#"{"name": "Paris", "population": 2102650, "landmarks": [{"name": "Louvre", "built": null}]}"#,
+            ))
+            .expect(1)
+            .create();
+        let city: City = mocked_client()
+            .chat_structured(ask("Describe a city."))
+            .await?;
+        assert_eq!(city.landmarks[0].name, "Louvre");
+        valid.assert();
+
+        let invalid = mockito::mock("POST", "/chat/completions")
+            .match_body(Matcher::PartialJson(json!({
+                "messages": [{"content": "Describe a landmark."}],
+            })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(answer(r#"{"name": "Louvre"}"#))
+            .expect(1)
+            .create();
+        match mocked_client()
+            .chat_structured::<City, _>(ask("Describe a landmark."))
+            .await
+        {
+            Err(Error::InvalidOutput { content, .. }) => {
+                assert_eq!(content, r#"{"name": "Louvre"}"#)
+            }
+            other => panic!("expected InvalidOutput, got {:?}", other),
+        }
+        invalid.assert();
+        Ok(())
+    }
+}
@@ -0,0 +1,449 @@
+//! Helpers to test code using the client against a local mock server.
+//!
+//! A [`TestServer`] wraps a [`wiremock::MockServer`] with realistic fixtures for the chat,
+//! completion and embedding endpoints, including server-sent event streams, and creates
+//! clients pointed at it. The underlying server is available to mount any other mock.
+//!
+//! A [`MockOpenAI`] is a `TestServer` already answering every chat, completion, embeddings
+//! and models request, for tests that only need the API to answer something.
+//!
+//! # Example
+//! ```
+//! # use openai_api::{api::{ChatArgs, ChatRole}, test_support::TestServer};
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), openai_api::Error> {
+//! let server = TestServer::start().await;
+//! server.mock_chat("Hello!").await;
+//! let args = ChatArgs::from(vec![(ChatRole::User, "Hi".to_string())]);
+//! assert_eq!(server.client().chat(args).await?.to_string(), "role: Assistant, content: Hello!");
+//! # Ok(())
+//! # }
+//! ```
+use serde_json::Value;
+use wiremock::{
+    matchers::{method, path},
+    Mock, MockServer, Request, ResponseTemplate,
+};
+
+use crate::Client;
+
+/// A local mock of the API. See the module documentation.
+pub struct TestServer {
+    server: MockServer,
+}
+
+impl TestServer {
+    /// Starts a server on a random local port
+    pub async fn start() -> Self {
+        Self {
+            server: MockServer::start().await,
+        }
+    }
+
+    /// The underlying wiremock server, to mount custom mocks or inspect requests
+    #[must_use]
+    pub fn server(&self) -> &MockServer {
+        &self.server
+    }
+
+    /// Base URL of the server, ending with a slash
+    #[must_use]
+    pub fn base_url(&self) -> String {
+        format!("{}/", self.server.uri())
+    }
+
+    /// A client sending its requests to this server
+    #[must_use]
+    pub fn client(&self) -> Client {
+        Client::new("sk-test")
+            .expect("valid token")
+            .with_base_url(&self.base_url())
+            .expect("valid URL")
+    }
+
+    /// Answers every chat request with an assistant message of `content`
+    pub async fn mock_chat(&self, content: &str) {
+        self.mock_json("POST", "/chat/completions", fixtures::chat_answer(content))
+            .await;
+    }
+
+    /// Answers every completion request with `text`
+    pub async fn mock_completion(&self, text: &str) {
+        self.mock_json("POST", "/completions", fixtures::completion(text))
+            .await;
+    }
+
+    /// Answers every embeddings request with `vectors`, one per input
+    pub async fn mock_embeddings(&self, vectors: &[Vec<f32>]) {
+        self.mock_json("POST", "/embeddings", fixtures::embeddings(vectors))
+            .await;
+    }
+
+    /// Answers every chat request with a stream of server-sent events, one per content
+    /// delta, as sent when `stream` is true
+    pub async fn mock_chat_stream(&self, deltas: &[&str]) {
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("content-type", "text/event-stream")
+                    .set_body_string(fixtures::chat_stream(deltas)),
+            )
+            .mount(&self.server)
+            .await;
+    }
+
+    /// Answers every `method` request to `path` with an API error
+    pub async fn mock_error(&self, method_: &str, path_: &str, status: u16, message: &str) {
+        Mock::given(method(method_))
+            .and(path(path_))
+            .respond_with(ResponseTemplate::new(status).set_body_json(fixtures::error(message)))
+            .mount(&self.server)
+            .await;
+    }
+
+    /// Answers every `method` request to `path` with a JSON `body`
+    pub async fn mock_json(&self, method_: &str, path_: &str, body: Value) {
+        Mock::given(method(method_))
+            .and(path(path_))
+            .respond_with(ResponseTemplate::new(200).set_body_json(body))
+            .mount(&self.server)
+            .await;
+    }
+}
+
+/// A mock of the API answering every chat, completion, embeddings and models request with
+/// canned responses, and a client pointed at it.
+///
+/// Streamed chats are answered with a stream of the same reply. Embeddings come one per
+/// input, the same for the same text. Mocks mounted with [`TestServer`]'s methods take
+/// precedence over the canned responses.
+///
+/// # Example
+/// ```
+/// # use openai_api::{api::{ChatArgs, ChatRole}, test_support::MockOpenAI};
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), openai_api::Error> {
+/// let mock = MockOpenAI::start().await;
+/// let args = ChatArgs::from(vec![(ChatRole::User, "Hi".to_string())]);
+/// let answer = mock.client().chat(args.clone()).await?;
+/// assert_eq!(answer.choices[0].message.content, MockOpenAI::CHAT_REPLY);
+///
+/// mock.server().mock_chat("Something else").await;
+/// assert_eq!(mock.client().chat(args).await?.choices[0].message.content, "Something else");
+/// # Ok(())
+/// # }
+/// ```
+pub struct MockOpenAI {
+    server: TestServer,
+}
+
+impl MockOpenAI {
+    /// The reply to every chat request
+    pub const CHAT_REPLY: &'static str = "Hello from the mock!";
+    /// The text of every completion
+    pub const COMPLETION: &'static str = " and that is all.";
+    /// The size of the embedding vectors
+    pub const EMBEDDING_DIMENSIONS: usize = 8;
+    /// The models listed
+    pub const MODELS: &'static [&'static str] = &["gpt-4o-mini", "text-embedding-3-small"];
+
+    /// Starts the server on a random local port, with the canned responses
+    pub async fn start() -> Self {
+        let server = TestServer::start().await;
+        for (method_, path_, respond) in [
+            (
+                "POST",
+                "/chat/completions",
+                chat as fn(&Request) -> ResponseTemplate,
+            ),
+            ("POST", "/completions", |_: &Request| {
+                ResponseTemplate::new(200)
+                    .set_body_json(fixtures::completion(MockOpenAI::COMPLETION))
+            }),
+            ("POST", "/embeddings", embeddings),
+            ("GET", "/models", |_: &Request| {
+                ResponseTemplate::new(200).set_body_json(fixtures::models(MockOpenAI::MODELS))
+            }),
+        ] {
+            // wiremock prefers the lowest priority, 5 by default
+            Mock::given(method(method_))
+                .and(path(path_))
+                .respond_with(respond)
+                .with_priority(u8::MAX)
+                .mount(server.server())
+                .await;
+        }
+        Self { server }
+    }
+
+    /// A client sending its requests to the mock
+    #[must_use]
+    pub fn client(&self) -> Client {
+        self.server.client()
+    }
+
+    /// The underlying server, to mount other responses
+    #[must_use]
+    pub fn server(&self) -> &TestServer {
+        &self.server
+    }
+}
+
+/// Answers a chat request, streamed or not, with `MockOpenAI::CHAT_REPLY`
+fn chat(request: &Request) -> ResponseTemplate {
+    let streamed = request
+        .body_json::<Value>()
+        .is_ok_and(|body| body["stream"] == true);
+    if streamed {
+        ResponseTemplate::new(200)
+            .insert_header("content-type", "text/event-stream")
+            .set_body_string(fixtures::chat_stream(&[MockOpenAI::CHAT_REPLY]))
+    } else {
+        ResponseTemplate::new(200).set_body_json(fixtures::chat_answer(MockOpenAI::CHAT_REPLY))
+    }
+}
+
+/// Answers an embeddings request with a vector per input, derived from its text
+fn embeddings(request: &Request) -> ResponseTemplate {
+    let body = request.body_json::<Value>().unwrap_or_default();
+    let inputs: Vec<String> = match &body["input"] {
+        Value::Array(inputs) => inputs.iter().map(Value::to_string).collect(),
+        input => vec![input.to_string()],
+    };
+    let vectors: Vec<Vec<f32>> = inputs.iter().map(|input| embedding(input)).collect();
+    ResponseTemplate::new(200).set_body_json(fixtures::embeddings(&vectors))
+}
+
+/// A unit vector of `MockOpenAI::EMBEDDING_DIMENSIONS` summing the bytes of `text`
+fn embedding(text: &str) -> Vec<f32> {
+    let mut vector = [1.0; MockOpenAI::EMBEDDING_DIMENSIONS];
+    for (i, byte) in text.bytes().enumerate() {
+        vector[i % MockOpenAI::EMBEDDING_DIMENSIONS] += f32::from(byte);
+    }
+    let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+    vector.iter().map(|x| x / norm).collect()
+}
+
+/// Response bodies shaped like the API's
+pub mod fixtures {
+    use serde_json::{json, Value};
+
+    /// A chat completion answering `content`
+    #[must_use]
+    pub fn chat_answer(content: &str) -> Value {
+        json!({
+            "id": "chatcmpl-test",
+            "object": "chat.completion",
+            "created": 1_677_652_288,
+            "model": "gpt-3.5-turbo-0613",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": content},
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 9, "completion_tokens": 12, "total_tokens": 21}
+        })
+    }
+
+    /// A text completion of `text`
+    #[must_use]
+    pub fn completion(text: &str) -> Value {
+        json!({
+            "id": "cmpl-test",
+            "object": "text_completion",
+            "created": 1_589_478_378,
+            "model": "gpt-3.5-turbo-instruct",
+            "choices": [{
+                "text": text,
+                "index": 0,
+                "logprobs": null,
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 5, "completion_tokens": 7, "total_tokens": 12}
+        })
+    }
+
+    /// Embeddings of as many inputs as there are `vectors`
+    #[must_use]
+    pub fn embeddings(vectors: &[Vec<f32>]) -> Value {
+        let data: Vec<_> = vectors
+            .iter()
+            .enumerate()
+            .map(|(index, embedding)| {
+                json!({"object": "embedding", "index": index, "embedding": embedding})
+            })
+            .collect();
+        json!({
+            "object": "list",
+            "model": "text-embedding-ada-002",
+            "data": data,
+            "usage": {"prompt_tokens": 8, "total_tokens": 8}
+        })
+    }
+
+    /// The server-sent events of a streamed chat completion of `deltas`, ending with
+    /// `data: [DONE]`
+    #[must_use]
+    pub fn chat_stream(deltas: &[&str]) -> String {
+        let chunk = |delta: Value, finish_reason: Value| {
+            json!({
+                "id": "chatcmpl-test",
+                "object": "chat.completion.chunk",
+                "created": 1_677_652_288,
+                "model": "gpt-3.5-turbo-0613",
+                "choices": [{"index": 0, "delta": delta, "finish_reason": finish_reason}]
+            })
+        };
+        let mut events = vec![chunk(
+            json!({"role": "assistant", "content": ""}),
+            Value::Null,
+        )];
+        events.extend(
+            deltas
+                .iter()
+                .map(|content| chunk(json!({ "content": content }), Value::Null)),
+        );
+        events.push(chunk(json!({}), json!("stop")));
+
+        let mut body = String::new();
+        for event in events {
+            body.push_str(&format!("data: {}\n\n", event));
+        }
+        body.push_str("data: [DONE]\n\n");
+        body
+    }
+
+    /// The list of the models `ids`
+    #[must_use]
+    pub fn models(ids: &[&str]) -> Value {
+        let data: Vec<_> = ids
+            .iter()
+            .map(|id| {
+                json!({"id": id, "object": "model", "created": 1_686_935_002, "owned_by": "openai"})
+            })
+            .collect();
+        json!({"object": "list", "data": data})
+    }
+
+    /// An API error with `message`
+    #[must_use]
+    pub fn error(message: &str) -> Value {
+        json!({
+            "error": {
+                "message": message,
+                "type": "invalid_request_error",
+                "param": null,
+                "code": "invalid_request"
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod unit {
+    use super::{fixtures, MockOpenAI, TestServer};
+    use crate::{
+        api::{ChatArgs, ChatRole, FinishReason},
+        Error,
+    };
+
+    #[tokio::test]
+    async fn serves_fixtures() -> crate::Result<()> {
+        let server = TestServer::start().await;
+        server.mock_chat("Hello!").await;
+        server.mock_completion("there").await;
+        server
+            .mock_embeddings(&[vec![1.0, 0.0], vec![0.0, 1.0]])
+            .await;
+        let client = server.client();
+
+        let args = ChatArgs::from(vec![(ChatRole::User, "Hi".to_string())]);
+        assert_eq!(
+            client.chat(args).await?.choices[0].message.content,
+            "Hello!"
+        );
+        assert_eq!(client.complete_prompt("Hi").await?.to_string(), "there");
+        let embeddings = client
+            .embeddings(vec!["a".to_string(), "b".to_string()])
+            .await?;
+        assert_eq!(embeddings.data[1].embedding, [0.0, 1.0]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn serves_errors() {
+        let server = TestServer::start().await;
+        server.mock_error("GET", "/models", 401, "Bad key").await;
+        match server.client().models().await {
+            Err(Error::Api(err)) => assert_eq!(err.message, "Bad key"),
+            other => panic!("expected an API error, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn streams_chat() -> crate::Result<()> {
+        use futures::TryStreamExt;
+
+        let server = TestServer::start().await;
+        server.mock_chat_stream(&["Hel", "lo!"]).await;
+        let deltas: Vec<_> = server
+            .client()
+            .chat_stream(ChatArgs::from(vec![(ChatRole::User, "Hi".to_string())]))
+            .await?
+            .try_collect()
+            .await?;
+        assert_eq!(deltas[0].choices[0].delta.role, Some(ChatRole::Assistant));
+        let answer: String = deltas.iter().map(|d| d.to_string()).collect();
+        assert_eq!(answer, "Hello!");
+        let last = &deltas.last().unwrap().choices[0];
+        assert_eq!(last.finish_reason, Some(FinishReason::Stop));
+        Ok(())
+    }
+
+    #[test]
+    fn chat_stream_events() {
+        let body = fixtures::chat_stream(&["Hel", "lo"]);
+        let events: Vec<_> = body.split("\n\n").filter(|e| !e.is_empty()).collect();
+        assert_eq!(events.len(), 5);
+        assert!(events[1].contains(r#""delta":{"content":"Hel"}"#));
+        assert_eq!(events[4], "data: [DONE]");
+    }
+
+    #[tokio::test]
+    async fn mock_openai_answers_everything() -> crate::Result<()> {
+        use futures::TryStreamExt;
+
+        let mock = MockOpenAI::start().await;
+        let client = mock.client();
+        let hi = || ChatArgs::from(vec![(ChatRole::User, "Hi".to_string())]);
+        assert_eq!(
+            client.chat(hi()).await?.choices[0].message.content,
+            MockOpenAI::CHAT_REPLY
+        );
+        let deltas: Vec<_> = client.chat_stream(hi()).await?.try_collect().await?;
+        let answer: String = deltas.iter().map(|d| d.to_string()).collect();
+        assert_eq!(answer, MockOpenAI::CHAT_REPLY);
+        assert_eq!(
+            client.complete_prompt("Hi").await?.to_string(),
+            MockOpenAI::COMPLETION
+        );
+        let ids: Vec<_> = client.models().await?.into_iter().map(|m| m.id).collect();
+        assert_eq!(ids, MockOpenAI::MODELS);
+
+        let inputs = ["a", "b", "a"].map(String::from).to_vec();
+        let embeddings = client.embeddings(inputs).await?;
+        assert_eq!(embeddings.data.len(), 3);
+        let [a, b, a_again] = [0, 1, 2].map(|i| &embeddings.data[i].embedding);
+        assert_eq!(a.len(), MockOpenAI::EMBEDDING_DIMENSIONS);
+        assert_eq!(a, a_again);
+        assert_ne!(a, b);
+
+        mock.server().mock_chat("Overridden").await;
+        assert_eq!(
+            client.chat(hi()).await?.choices[0].message.content,
+            "Overridden"
+        );
+        Ok(())
+    }
+}
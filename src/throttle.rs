@@ -0,0 +1,210 @@
+//! A client-side rate limiter keeping requests within per-minute budgets.
+//!
+//! A [`Throttle`] set with [`Client::with_throttle`] or [`ClientBuilder::throttle`] delays
+//! each request until it fits in the requests and tokens budgets of the last minute.
+//! Waiting requests are let through in the order they arrived. The budget is shared by the
+//! clones of the client, and by any other client given a clone of the same throttle.
+//!
+//! The tokens of a request are estimated before sending it, from the size of the text in
+//! its body and the completion tokens it asks for. Retries of a request don't count again.
+//!
+//! [`Client::with_throttle`]: crate::Client::with_throttle
+//! [`ClientBuilder::throttle`]: crate::ClientBuilder::throttle
+use std::{collections::VecDeque, sync::Arc, time::Duration};
+
+use tokio::sync::Mutex;
+
+use crate::{runtime::Instant, Client};
+
+const WINDOW: Duration = Duration::from_secs(60);
+
+/// Budgets of requests and tokens per minute, see the module documentation
+///
+/// # Example
+/// ```
+/// # use openai_api::{throttle::Throttle, Client};
+/// # fn main() -> Result<(), openai_api::Error> {
+/// let client = Client::builder("sk-...")
+///     .throttle(Throttle::new(Some(500), Some(90_000)))
+///     .build()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Throttle {
+    budget: Arc<Mutex<Budget>>,
+}
+
+impl Throttle {
+    /// A throttle allowing at most `requests_per_minute` requests and `tokens_per_minute`
+    /// tokens in any one minute, unlimited when `None`
+    #[must_use]
+    pub fn new(requests_per_minute: Option<u32>, tokens_per_minute: Option<u64>) -> Self {
+        Self {
+            budget: Arc::new(Mutex::new(Budget::new(
+                requests_per_minute,
+                tokens_per_minute,
+            ))),
+        }
+    }
+
+    /// Waits until a request using `tokens` fits in the budget, and counts it
+    pub(crate) async fn acquire(&self, tokens: u64) {
+        // Holding the lock while waiting lets the waiting requests through in order.
+        let mut budget = self.budget.lock().await;
+        while let Some(wait) = budget.wait_time(tokens, Instant::now()) {
+            crate::runtime::sleep(wait).await;
+        }
+        budget.record(tokens, Instant::now());
+    }
+}
+
+/// Estimates the tokens of a request from its JSON body: the text it sends, and the
+/// completion tokens it asks for. Bodies that aren't JSON, such as uploads, count as no
+/// tokens. Requests are estimated before the `gzip` feature compresses their body.
+pub(crate) fn estimate(request: &reqwest::Request) -> u64 {
+    let body = request.body().and_then(reqwest::Body::as_bytes);
+    let body: serde_json::Value = match body.map(serde_json::from_slice) {
+        Some(Ok(body)) => body,
+        _ => return 0,
+    };
+    let completion = ["max_tokens", "max_completion_tokens"]
+        .iter()
+        .find_map(|field| body[field].as_u64())
+        .unwrap_or(0);
+    let choices = body["n"].as_u64().unwrap_or(1);
+    text_tokens(&body) + completion * choices
+}
+
+fn text_tokens(value: &serde_json::Value) -> u64 {
+    match value {
+        serde_json::Value::String(text) => crate::estimate_tokens(text) as u64,
+        serde_json::Value::Array(items) => items.iter().map(text_tokens).sum(),
+        serde_json::Value::Object(fields) => fields.values().map(text_tokens).sum(),
+        _ => 0,
+    }
+}
+
+impl Client {
+    /// Private helper waiting until `request` fits in the throttle's budget, if any
+    pub(crate) async fn throttle(&self, request: &reqwest::Request) {
+        if let Some(throttle) = &self.inner.throttle {
+            throttle.acquire(estimate(request)).await;
+        }
+    }
+}
+
+/// The requests and tokens spent over the last minute
+#[derive(Debug, Default)]
+pub(crate) struct Budget {
+    requests_per_minute: Option<u32>,
+    tokens_per_minute: Option<u64>,
+    sent: VecDeque<(Instant, u64)>,
+}
+
+impl Budget {
+    pub(crate) fn new(requests_per_minute: Option<u32>, tokens_per_minute: Option<u64>) -> Self {
+        Self {
+            requests_per_minute,
+            tokens_per_minute,
+            sent: VecDeque::new(),
+        }
+    }
+
+    /// How long to wait until a request using `tokens` fits in the budget, `None` if it
+    /// fits now
+    pub(crate) fn wait_time(&mut self, tokens: u64, now: Instant) -> Option<Duration> {
+        while matches!(self.sent.front(), Some((at, _)) if now.duration_since(*at) >= WINDOW) {
+            self.sent.pop_front();
+        }
+        let mut wait = None;
+        if let Some(rpm) = self.requests_per_minute {
+            let rpm = rpm.max(1) as usize;
+            if self.sent.len() >= rpm {
+                wait = Some(self.sent[self.sent.len() - rpm].0 + WINDOW - now);
+            }
+        }
+        if let Some(tpm) = self.tokens_per_minute {
+            // A request bigger than the whole budget goes through once nothing else is in
+            // the window, rather than never.
+            let mut used: u64 = self.sent.iter().map(|(_, t)| t).sum();
+            for (at, t) in &self.sent {
+                if used + tokens <= tpm {
+                    break;
+                }
+                used -= t;
+                let until = *at + WINDOW - now;
+                wait = Some(wait.map_or(until, |w: Duration| w.max(until)));
+            }
+        }
+        wait
+    }
+
+    pub(crate) fn record(&mut self, tokens: u64, now: Instant) {
+        self.sent.push_back((now, tokens));
+    }
+}
+
+#[cfg(test)]
+mod unit {
+    use std::time::Duration;
+
+    use super::{estimate, Budget, Throttle};
+    use crate::runtime::Instant;
+
+    #[test]
+    fn budget_limits_requests_and_tokens() {
+        let start = Instant::now();
+        let mut budget = Budget {
+            requests_per_minute: Some(2),
+            tokens_per_minute: Some(100),
+            ..Budget::default()
+        };
+        assert_eq!(budget.wait_time(60, start), None);
+        budget.record(60, start);
+        let later = start + Duration::from_secs(10);
+        assert_eq!(budget.wait_time(60, later), Some(Duration::from_secs(50)));
+        assert_eq!(budget.wait_time(40, later), None);
+        budget.record(40, later);
+        assert_eq!(budget.wait_time(1, later), Some(Duration::from_secs(50)));
+        assert_eq!(budget.wait_time(1, start + Duration::from_secs(60)), None);
+
+        let mut budget = Budget {
+            tokens_per_minute: Some(100),
+            ..Budget::default()
+        };
+        assert_eq!(budget.wait_time(1000, start), None);
+    }
+
+    #[test]
+    fn estimates_request_tokens() -> crate::Result<()> {
+        let request = |body: serde_json::Value| {
+            reqwest::Client::new()
+                .post("https://example.com/chat/completions")
+                .json(&body)
+                .build()
+        };
+        let chat = request(serde_json::json!({
+            "model": "gpt",
+            "messages": [{"role": "user", "content": "a".repeat(30)}],
+            "max_tokens": 10,
+            "n": 2
+        }))?;
+        // "gpt", "user" and the content, then 10 tokens for each of the 2 choices
+        assert_eq!(estimate(&chat), 1 + 2 + 10 + 20);
+        let get = reqwest::Client::new()
+            .get("https://example.com/models")
+            .build()?;
+        assert_eq!(estimate(&get), 0);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn delays_requests_over_budget() {
+        let throttle = Throttle::new(Some(1), None);
+        throttle.acquire(0).await;
+        let clone = throttle.clone();
+        let second = tokio::time::timeout(Duration::from_millis(50), clone.acquire(0)).await;
+        assert!(second.is_err());
+    }
+}
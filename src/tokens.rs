@@ -0,0 +1,113 @@
+//! Exact token counts with OpenAI's BPE tokenizers, with the `tiktoken` feature.
+//!
+//! Counting before sending tells whether a prompt fits in a model's context, or what it
+//! will cost, without calling the API. The tokenizers are loaded on first use, once per
+//! process.
+//!
+//! # Example
+//! ```
+//! # use openai_api::{api::{ChatFormat, ChatRole}, tokens};
+//! assert_eq!(tokens::count_tokens("gpt-4", "hello world"), 2);
+//! let messages = [ChatFormat::new(ChatRole::User, "hello world".into())];
+//! assert_eq!(tokens::chat_token_estimate("gpt-4", &messages), 9);
+//! ```
+use tiktoken_rs::tokenizer::{get_tokenizer, Tokenizer};
+
+use crate::api::{ChatFormat, ChatRole};
+
+/// Tokens the API adds around every message
+const TOKENS_PER_MESSAGE: usize = 3;
+/// Tokens priming the answer of the assistant
+const TOKENS_PER_REPLY: usize = 3;
+
+/// The number of tokens of `text` for `model`. Models the tokenizer doesn't know, such as
+/// those of OpenAI-compatible servers, are counted with `cl100k_base`, the tokenizer of
+/// gpt-4 and gpt-3.5-turbo.
+#[must_use]
+pub fn count_tokens(model: &str, text: &str) -> usize {
+    let bpe = match get_tokenizer(model).unwrap_or(Tokenizer::Cl100kBase) {
+        Tokenizer::O200kBase => tiktoken_rs::o200k_base_singleton(),
+        Tokenizer::Cl100kBase => tiktoken_rs::cl100k_base_singleton(),
+        Tokenizer::P50kBase => tiktoken_rs::p50k_base_singleton(),
+        Tokenizer::P50kEdit => tiktoken_rs::p50k_edit_singleton(),
+        Tokenizer::R50kBase | Tokenizer::Gpt2 => tiktoken_rs::r50k_base_singleton(),
+    };
+    let bpe = bpe.lock();
+    bpe.encode_ordinary(text).len()
+}
+
+/// The number of prompt tokens of a chat request sending `messages` to `model`, as the API
+/// bills them: the messages, the tokens formatting them, and those priming the answer.
+/// Functions and tools offered to the model aren't included.
+#[must_use]
+pub fn chat_token_estimate(model: &str, messages: &[ChatFormat]) -> usize {
+    let messages: usize = messages.iter().map(|m| message_tokens(model, m)).sum();
+    messages + TOKENS_PER_REPLY
+}
+
+/// The tokens of one message in a chat request, with those formatting it
+pub(crate) fn message_tokens(model: &str, message: &ChatFormat) -> usize {
+    let count = |text: &str| count_tokens(model, text);
+    let mut tokens = TOKENS_PER_MESSAGE
+        + count(role(&message.role))
+        + count(&message.content.text())
+        + message.content.image_tokens();
+    if let Some(name) = &message.name {
+        tokens += 1 + count(name);
+    }
+    let calls = message.function_call.iter().chain(
+        message
+            .tool_calls
+            .iter()
+            .flatten()
+            .map(|call| &call.function),
+    );
+    for call in calls {
+        tokens += count(&call.name) + count(&call.arguments);
+    }
+    tokens
+}
+
+fn role(role: &ChatRole) -> &'static str {
+    match role {
+        ChatRole::System => "system",
+        ChatRole::User => "user",
+        ChatRole::Assistant => "assistant",
+        ChatRole::Function => "function",
+        ChatRole::Tool => "tool",
+    }
+}
+
+#[cfg(test)]
+mod unit {
+    use super::{chat_token_estimate, count_tokens};
+    use crate::api::{ChatFormat, ChatRole, FunctionCall};
+
+    #[test]
+    fn counts_with_the_model_tokenizer() {
+        // r50k_base has no tokens for runs of spaces, cl100k_base does
+        let text = "    indented";
+        assert_ne!(count_tokens("davinci", text), count_tokens("gpt-4", text));
+        assert_eq!(
+            count_tokens("llama3.1:8b", text),
+            count_tokens("gpt-4", text)
+        );
+        assert_eq!(count_tokens("gpt-4", ""), 0);
+    }
+
+    #[test]
+    fn estimates_chats() {
+        let hello = ChatFormat::new(ChatRole::User, "hello world".into());
+        let one = chat_token_estimate("gpt-4", std::slice::from_ref(&hello));
+        assert_eq!(one, 3 + 1 + 2 + 3);
+
+        let mut call = ChatFormat::new(ChatRole::Assistant, String::new());
+        call.function_call = Some(FunctionCall {
+            name: "get_time".into(),
+            arguments: "{}".into(),
+        });
+        let result = ChatFormat::function_result("get_time", "12:00");
+        let all = chat_token_estimate("gpt-4", &[hello, call, result]);
+        assert!(all > one + 2 * 4);
+    }
+}
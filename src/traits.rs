@@ -0,0 +1,127 @@
+//! The client's API as a trait, so that code using it can be tested against a fake.
+use async_trait::async_trait;
+#[cfg(not(target_arch = "wasm32"))]
+use futures::stream::BoxStream;
+#[cfg(target_arch = "wasm32")]
+use futures::stream::LocalBoxStream as BoxStream;
+use futures::StreamExt;
+
+use crate::{
+    api::{ChatAnswer, ChatArgs, ChatDelta, Completion, CompletionArgs, ModelInfo},
+    embeddings::{Embeddings, EmbeddingsArgs},
+    Client, Result,
+};
+
+/// A stream of chat deltas, as returned by [`OpenAiApi::chat_stream`]
+pub type ChatDeltaStream = BoxStream<'static, Result<ChatDelta>>;
+
+/// The requests a `Client` can make.
+///
+/// The trait is object safe: application code can hold an `Arc<dyn OpenAiApi>`, use a
+/// `Client` in production, and substitute a fake in its unit tests. Methods take built
+/// arguments; the inherent `Client` methods of the same names also accept builders.
+///
+/// It covers models, completions, chats, streamed chats and embeddings. The helpers
+/// built on top of those, e.g. `Client::chat_many` or `Client::embed_all`, and the other
+/// endpoints, stay methods of `Client`.
+///
+/// On `wasm32`, the futures of the methods aren't `Send`, as those of the browser's
+/// `fetch` aren't.
+///
+/// # Example
+/// ```
+/// # use openai_api::{api::ChatArgs, OpenAiApi};
+/// async fn greet(api: &dyn OpenAiApi) -> Result<String, openai_api::Error> {
+///     let args = ChatArgs::builder().build()?;
+///     Ok(api.chat(args).await?.to_string())
+/// }
+/// ```
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+pub trait OpenAiApi: Send + Sync {
+    /// Lists the currently available models. See [`Client::models`].
+    async fn models(&self) -> Result<Vec<ModelInfo>>;
+
+    /// Retrieves a model. See [`Client::model`].
+    async fn model(&self, model: &str) -> Result<ModelInfo>;
+
+    /// Gets the predicted completion of a prompt. See [`Client::complete_prompt`].
+    async fn complete_prompt(&self, args: CompletionArgs) -> Result<Completion>;
+
+    /// Answers a chat conversation. See [`Client::chat`].
+    async fn chat(&self, args: ChatArgs) -> Result<ChatAnswer>;
+
+    /// Answers a chat conversation as it is generated. See [`Client::chat_stream`].
+    async fn chat_stream(&self, args: ChatArgs) -> Result<ChatDeltaStream>;
+
+    /// Creates embedding vectors of the input texts. See [`Client::embeddings`].
+    async fn embeddings(&self, args: EmbeddingsArgs) -> Result<Embeddings>;
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl OpenAiApi for Client {
+    async fn models(&self) -> Result<Vec<ModelInfo>> {
+        Client::models(self).await
+    }
+
+    async fn model(&self, model: &str) -> Result<ModelInfo> {
+        Client::model(self, model).await
+    }
+
+    async fn complete_prompt(&self, args: CompletionArgs) -> Result<Completion> {
+        Client::complete_prompt(self, args).await
+    }
+
+    async fn chat(&self, args: ChatArgs) -> Result<ChatAnswer> {
+        Client::chat(self, args).await
+    }
+
+    async fn chat_stream(&self, args: ChatArgs) -> Result<ChatDeltaStream> {
+        #[cfg(not(target_arch = "wasm32"))]
+        return Ok(Client::chat_stream(self, args).await?.boxed());
+        #[cfg(target_arch = "wasm32")]
+        return Ok(Client::chat_stream(self, args).await?.boxed_local());
+    }
+
+    async fn embeddings(&self, args: EmbeddingsArgs) -> Result<Embeddings> {
+        Client::embeddings(self, args).await
+    }
+}
+
+#[cfg(test)]
+mod unit {
+    use std::sync::Arc;
+
+    use super::OpenAiApi;
+    use crate::{
+        api::{ChatArgs, ChatRole},
+        unit::mocked_client,
+    };
+
+    #[tokio::test]
+    async fn client_behind_trait_object() -> crate::Result<()> {
+        let mock = mockito::mock("GET", "/models/davinci-trait")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"{
+                "id": "davinci-trait",
+                "object": "model",
+                "owned_by": "openai",
+                "permission": []
+            }"#,
+            )
+            .create();
+        let api: Arc<dyn OpenAiApi> = Arc::new(mocked_client().with_dry_run(true));
+        assert_eq!(api.model("davinci-trait").await?.id, "davinci-trait");
+        mock.assert();
+
+        let args = ChatArgs::from(vec![(ChatRole::User, "Hello!".to_string())]);
+        assert!(matches!(
+            api.chat(args).await,
+            Err(crate::Error::DryRun { .. })
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,682 @@
+//! Settings of the HTTP connections: proxies and TLS certificates.
+//!
+//! On `wasm32`, the browser makes the connections, so only the user agent can be set.
+//!
+//! Like most HTTP clients, the client goes through the proxies set by the `HTTPS_PROXY`,
+//! `HTTP_PROXY` and `ALL_PROXY` environment variables (or their lowercase forms),
+//! except for the hosts listed by `NO_PROXY`. [`Client::without_env_proxy`] ignores them.
+//!
+//! [`Client::with_proxy`] sets a proxy explicitly. With the `socks` feature, it can be a
+//! SOCKS5 proxy: `socks5://` resolves host names locally, `socks5h://` through the proxy.
+//!
+//! Behind a proxy intercepting TLS, as in some corporate networks, the proxy's certificate
+//! authority is trusted with [`Client::with_root_certificates`].
+//!
+//! With the `hickory-dns` feature, [`Client::with_hickory_dns`] resolves host names with
+//! hickory-dns instead of the system resolver, caching the answers as [`DnsCache`] says.
+//!
+//! [`Client::with_timeout`] and [`Client::with_connect_timeout`] bound how long requests
+//! and connections may take; there is no limit by default.
+//!
+//! [`Client::warmup`] opens a connection ahead of the first request, sparing it the DNS
+//! lookup and the TCP and TLS handshakes.
+//!
+//! The requests go through reqwest, unless [`Client::with_http_transport`] hands them to
+//! another HTTP stack implementing [`HttpTransport`]: hyper, a browser's `fetch`, or a fake
+//! answering tests. The settings above only apply to reqwest.
+use std::sync::Arc;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Duration;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use reqwest::{
+    header::{HeaderMap, HeaderValue, USER_AGENT},
+    Method, StatusCode, Url,
+};
+#[cfg(not(target_arch = "wasm32"))]
+use reqwest::{Certificate, Proxy};
+
+use crate::{response::RawResponse, Client, Error, Result};
+
+/// An HTTP stack sending the requests of a client, see [`Client::with_http_transport`].
+///
+/// The headers include the authentication ones. Responses are read whole, streamed ones
+/// included, and timeouts are left to the transport.
+///
+/// # Example
+/// ```
+/// # use openai_api::{transport::{HttpResponse, HttpTransport}, Client};
+/// # use reqwest::{header::HeaderMap, Method, Url};
+/// /// Answers every request with the empty list of models
+/// #[derive(Debug)]
+/// struct NoModels;
+///
+/// #[async_trait::async_trait]
+/// impl HttpTransport for NoModels {
+///     async fn request(
+///         &self,
+///         _: Method,
+///         _: Url,
+///         _: HeaderMap,
+///         _: Option<bytes::Bytes>,
+///     ) -> Result<HttpResponse, openai_api::Error> {
+///         Ok(HttpResponse::new(200, r#"{"object": "list", "data": []}"#))
+///     }
+/// }
+///
+/// # async fn example() -> Result<(), openai_api::Error> {
+/// let client = Client::new("sk-...")?.with_http_transport(NoModels);
+/// assert!(client.models().await?.is_empty());
+/// # Ok(())
+/// # }
+/// ```
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+pub trait HttpTransport: std::fmt::Debug + Send + Sync {
+    /// Sends a request and reads its response. Failing to get a response is an error,
+    /// whereas error statuses are responses.
+    async fn request(
+        &self,
+        method: Method,
+        url: Url,
+        headers: HeaderMap,
+        body: Option<Bytes>,
+    ) -> Result<HttpResponse>;
+}
+
+/// A response read by an `HttpTransport`
+#[derive(Debug, Clone, Default)]
+pub struct HttpResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Bytes,
+}
+
+impl HttpResponse {
+    /// A response with `status` and `body`, and no headers
+    ///
+    /// # Panics
+    /// If `status` isn't between 100 and 999
+    #[must_use]
+    pub fn new(status: u16, body: impl Into<Bytes>) -> Self {
+        Self {
+            status: StatusCode::from_u16(status).expect("valid status code"),
+            headers: HeaderMap::new(),
+            body: body.into(),
+        }
+    }
+}
+
+/// A transport shared with other clients, or kept to inspect it
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl<T: HttpTransport + ?Sized> HttpTransport for Arc<T> {
+    async fn request(
+        &self,
+        method: Method,
+        url: Url,
+        headers: HeaderMap,
+        body: Option<Bytes>,
+    ) -> Result<HttpResponse> {
+        (**self).request(method, url, headers, body).await
+    }
+}
+
+/// The default transport
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl HttpTransport for reqwest::Client {
+    async fn request(
+        &self,
+        method: Method,
+        url: Url,
+        headers: HeaderMap,
+        body: Option<Bytes>,
+    ) -> Result<HttpResponse> {
+        let mut request = self.request(method, url).headers(headers);
+        if let Some(body) = body {
+            request = request.body(body);
+        }
+        let response = request.send().await?;
+        Ok(HttpResponse {
+            status: response.status(),
+            headers: response.headers().clone(),
+            body: response.bytes().await?,
+        })
+    }
+}
+
+/// How the HTTP client is built. Kept to rebuild it when a setting changes.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Transport {
+    /// Headers authenticating every request
+    pub auth: HeaderMap,
+    #[cfg(not(target_arch = "wasm32"))]
+    pub no_env_proxy: bool,
+    #[cfg(not(target_arch = "wasm32"))]
+    pub proxy: Option<Proxy>,
+    /// Certificates trusted besides the built-in roots
+    #[cfg(not(target_arch = "wasm32"))]
+    pub root_certificates: Vec<Certificate>,
+    #[cfg(not(target_arch = "wasm32"))]
+    pub accept_invalid_certs: bool,
+    /// Limit of a whole request, from connecting to reading the body
+    #[cfg(not(target_arch = "wasm32"))]
+    pub timeout: Option<Duration>,
+    #[cfg(not(target_arch = "wasm32"))]
+    pub connect_timeout: Option<Duration>,
+    pub user_agent: Option<HeaderValue>,
+    #[cfg(feature = "hickory-dns")]
+    pub dns: Option<DnsCache>,
+}
+
+impl Transport {
+    pub(crate) fn build(&self) -> Result<reqwest::Client> {
+        let mut builder = reqwest::Client::builder().default_headers(self.auth.clone());
+        if let Some(user_agent) = &self.user_agent {
+            builder = builder.user_agent(user_agent.clone());
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            builder = self.native(builder);
+        }
+        Ok(builder.build()?)
+    }
+
+    /// Applies the settings of connections made by reqwest rather than the browser
+    #[cfg(not(target_arch = "wasm32"))]
+    fn native(&self, mut builder: reqwest::ClientBuilder) -> reqwest::ClientBuilder {
+        if self.no_env_proxy {
+            builder = builder.no_proxy();
+        }
+        if let Some(proxy) = &self.proxy {
+            builder = builder.proxy(proxy.clone());
+        }
+        for certificate in &self.root_certificates {
+            builder = builder.add_root_certificate(certificate.clone());
+        }
+        if self.accept_invalid_certs {
+            builder = builder.danger_accept_invalid_certs(true);
+        }
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(timeout) = self.connect_timeout {
+            builder = builder.connect_timeout(timeout);
+        }
+        #[cfg(feature = "hickory-dns")]
+        if let Some(cache) = &self.dns {
+            builder = builder.dns_resolver(std::sync::Arc::new(hickory::Resolver::new(cache)));
+        }
+        builder
+    }
+}
+
+impl Client {
+    /// Connects directly to the API, ignoring the proxies set in the environment
+    ///
+    /// # Errors
+    /// - `Error::AsyncProtocol` if the HTTP client can't be created
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn without_env_proxy(self) -> Result<Self> {
+        self.with_transport(|transport| transport.no_env_proxy = true)
+    }
+
+    /// Sends all requests through the proxy at `url`, e.g. `http://proxy:3128`, or
+    /// `socks5h://localhost:1080` with the `socks` feature. Credentials can be given in the
+    /// URL. The proxies set in the environment are still used for the hosts this one
+    /// doesn't serve, unless `without_env_proxy` is called as well.
+    ///
+    /// # Errors
+    /// - `Error::InvalidConfig` if the URL isn't valid, or its scheme isn't supported
+    /// - `Error::AsyncProtocol` if the HTTP client can't be created
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn with_proxy(self, url: &str) -> Result<Self> {
+        let proxy = Proxy::all(url)
+            .map_err(|e| Error::InvalidConfig(format!("invalid proxy {:?}: {}", url, e)))?;
+        self.with_transport(|transport| transport.proxy = Some(proxy))
+    }
+
+    /// Trusts the certificates of a PEM bundle as roots, in addition to the built-in ones
+    ///
+    /// # Errors
+    /// - `Error::InvalidConfig` if `pem` holds no valid certificate
+    /// - `Error::AsyncProtocol` if the HTTP client can't be created
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn with_root_certificates(self, pem: &[u8]) -> Result<Self> {
+        let certificates = Certificate::from_pem_bundle(pem)
+            .map_err(|e| Error::InvalidConfig(format!("invalid certificates: {}", e)))?;
+        if certificates.is_empty() {
+            return Err(Error::InvalidConfig("no certificate in PEM".into()));
+        }
+        self.with_transport(|transport| transport.root_certificates.extend(certificates))
+    }
+
+    /// Accepts any TLS certificate, including expired, self-signed or for another host.
+    /// Anyone between the client and the API can then read the API key and requests, so
+    /// prefer `with_root_certificates`.
+    ///
+    /// # Errors
+    /// - `Error::AsyncProtocol` if the HTTP client can't be created
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn danger_accept_invalid_certs(self) -> Result<Self> {
+        self.with_transport(|transport| transport.accept_invalid_certs = true)
+    }
+
+    /// Fails requests taking longer than `timeout` as a whole, from connecting to reading
+    /// the response, with `Error::Timeout`. Streamed responses
+    /// count as a whole too, so give them a generous timeout.
+    ///
+    /// # Errors
+    /// - `Error::AsyncProtocol` if the HTTP client can't be created
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn with_timeout(self, timeout: Duration) -> Result<Self> {
+        self.with_transport(|transport| transport.timeout = Some(timeout))
+    }
+
+    /// Fails requests that can't connect to the server within `timeout`
+    ///
+    /// # Errors
+    /// - `Error::AsyncProtocol` if the HTTP client can't be created
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn with_connect_timeout(self, timeout: Duration) -> Result<Self> {
+        self.with_transport(|transport| transport.connect_timeout = Some(timeout))
+    }
+
+    /// Sends `user_agent` as the `User-Agent` of every request, e.g. to identify an
+    /// application to a gateway
+    ///
+    /// # Errors
+    /// - `Error::InvalidConfig` if `user_agent` isn't a valid header value
+    /// - `Error::AsyncProtocol` if the HTTP client can't be created
+    pub fn with_user_agent(self, user_agent: &str) -> Result<Self> {
+        let user_agent = HeaderValue::from_str(user_agent).map_err(|e| {
+            Error::InvalidConfig(format!("invalid user agent {:?}: {}", user_agent, e))
+        })?;
+        self.with_transport(|transport| transport.user_agent = Some(user_agent))
+    }
+
+    /// Resolves host names with hickory-dns, reading the name servers from the system
+    /// configuration (`/etc/resolv.conf` on Unix), and caching answers as `cache` says. Use
+    /// it where the system resolver adds latency or jitter, as in some container
+    /// environments.
+    ///
+    /// # Errors
+    /// - `Error::AsyncProtocol` if the HTTP client can't be created
+    #[cfg(feature = "hickory-dns")]
+    pub fn with_hickory_dns(self, cache: DnsCache) -> Result<Self> {
+        self.with_transport(|transport| transport.dns = Some(cache))
+    }
+
+    /// Resolves the API's host name and opens a connection to it, TLS handshake included,
+    /// with a `HEAD` request to the base URL. The connection is then kept in the pool that
+    /// the client and its clones share, ready for the first request. Whatever status the
+    /// server answers with, the connection is open, so only failing to connect is an error.
+    ///
+    /// Servers close idle connections after a while, so call it shortly before the first
+    /// request is expected, e.g. at startup.
+    ///
+    /// # Errors
+    /// - `Error::AsyncProtocol` if the API can't be reached
+    pub async fn warmup(&self) -> Result<()> {
+        if self.buffered() {
+            return Ok(());
+        }
+        self.inner
+            .client
+            .head(self.inner.base_url.clone())
+            .send()
+            .await?;
+        Ok(())
+    }
+
+    /// Sends the requests through `transport` rather than reqwest, see `HttpTransport`.
+    /// The proxy, certificates, timeouts and DNS settings of the client no longer apply.
+    #[must_use]
+    pub fn with_http_transport(mut self, transport: impl HttpTransport + 'static) -> Self {
+        self.inner_mut().http = Some(Arc::new(transport));
+        self
+    }
+
+    /// Private helper sending `request` through the custom transport
+    pub(crate) async fn execute_custom(
+        &self,
+        transport: &dyn HttpTransport,
+        request: reqwest::Request,
+    ) -> Result<RawResponse> {
+        // reqwest adds these itself, from the client's settings
+        let mut headers = self.inner.transport.auth.clone();
+        if let Some(user_agent) = &self.inner.transport.user_agent {
+            headers.insert(USER_AGENT, user_agent.clone());
+        }
+        let body = request
+            .body()
+            .and_then(reqwest::Body::as_bytes)
+            .map(Bytes::copy_from_slice);
+        let (method, url) = (request.method().clone(), request.url().clone());
+        for name in request.headers().keys() {
+            headers.remove(name);
+        }
+        for (name, value) in request.headers() {
+            headers.append(name, value.clone());
+        }
+        let response = transport.request(method, url, headers, body).await?;
+        if let Some(limit) = self.inner.max_response_size {
+            crate::response::check_size(response.body.len() as u64, limit)?;
+        }
+        Ok(RawResponse {
+            status: response.status,
+            headers: response.headers,
+            body: response.body,
+        })
+    }
+
+    /// Changes the transport settings and rebuilds the HTTP client accordingly
+    fn with_transport(mut self, change: impl FnOnce(&mut Transport)) -> Result<Self> {
+        let inner = self.inner_mut();
+        change(&mut inner.transport);
+        inner.client = inner.transport.build()?;
+        Ok(self)
+    }
+}
+
+/// How the hickory-dns resolver caches answers, see [`Client::with_hickory_dns`]
+#[cfg(feature = "hickory-dns")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DnsCache {
+    /// Most answers kept
+    pub size: usize,
+    /// Shortest time an answer is kept, even if its TTL is shorter
+    pub min_ttl: Option<std::time::Duration>,
+    /// Longest time an answer is kept, even if its TTL is longer
+    pub max_ttl: Option<std::time::Duration>,
+}
+
+#[cfg(feature = "hickory-dns")]
+impl Default for DnsCache {
+    /// hickory-dns's defaults: 32 answers, kept as long as their TTL says
+    fn default() -> Self {
+        Self {
+            size: 32,
+            min_ttl: None,
+            max_ttl: None,
+        }
+    }
+}
+
+#[cfg(feature = "hickory-dns")]
+mod hickory {
+    use std::{
+        io,
+        net::SocketAddr,
+        sync::{Arc, OnceLock},
+    };
+
+    use hickory_resolver::{system_conf, TokioAsyncResolver};
+    use hyper::client::connect::dns::Name;
+    use reqwest::dns::{Addrs, Resolve, Resolving};
+
+    use super::DnsCache;
+
+    /// A reqwest resolver backed by hickory-dns
+    pub(super) struct Resolver {
+        cache: DnsCache,
+        /// Created on first use, in the runtime making the requests
+        resolver: Arc<OnceLock<TokioAsyncResolver>>,
+    }
+
+    impl Resolver {
+        pub(super) fn new(cache: &DnsCache) -> Self {
+            Self {
+                cache: cache.clone(),
+                resolver: Arc::default(),
+            }
+        }
+
+        fn resolver(&self) -> io::Result<TokioAsyncResolver> {
+            if let Some(resolver) = self.resolver.get() {
+                return Ok(resolver.clone());
+            }
+            let (config, mut options) = system_conf::read_system_conf().map_err(|e| {
+                io::Error::other(format!("can't read the DNS configuration: {}", e))
+            })?;
+            options.cache_size = self.cache.size;
+            options.positive_min_ttl = self.cache.min_ttl;
+            options.positive_max_ttl = self.cache.max_ttl;
+            Ok(self
+                .resolver
+                .get_or_init(|| TokioAsyncResolver::tokio(config, options))
+                .clone())
+        }
+    }
+
+    impl Resolve for Resolver {
+        fn resolve(&self, name: Name) -> Resolving {
+            let resolver = self.resolver();
+            Box::pin(async move {
+                let lookup = resolver?.lookup_ip(name.as_str()).await?;
+                let addrs: Addrs = Box::new(
+                    lookup
+                        .into_iter()
+                        .map(|ip| SocketAddr::new(ip, 0))
+                        .collect::<Vec<_>>()
+                        .into_iter(),
+                );
+                Ok(addrs)
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod unit {
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+    };
+
+    use super::{HttpResponse, HttpTransport};
+    use crate::{Client, Error};
+
+    const MODELS: &str = r#"{"object": "list", "data": []}"#;
+
+    /// Reads a request and answers it with the empty list of models, returning the request
+    async fn answer_models(stream: &mut TcpStream) -> String {
+        let mut request = vec![0; 4096];
+        let read = stream.read(&mut request).await.unwrap();
+        let response = format!(
+            "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n{}",
+            MODELS.len(),
+            MODELS
+        );
+        stream.write_all(response.as_bytes()).await.unwrap();
+        String::from_utf8_lossy(&request[..read]).into_owned()
+    }
+
+    #[tokio::test]
+    async fn http_proxy() -> crate::Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let proxy = format!("http://{}", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            answer_models(&mut stream).await
+        });
+        let client = Client::new("bogus")?
+            .with_base_url("http://api.invalid/v1/")?
+            .with_proxy(&proxy)?;
+        assert!(client.models().await?.is_empty());
+        assert!(server
+            .await
+            .unwrap()
+            .starts_with("GET http://api.invalid/v1/models HTTP/1.1"));
+
+        assert!(matches!(
+            Client::new("bogus")?.with_proxy("not a url"),
+            Err(Error::InvalidConfig(_))
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn warmup() -> crate::Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}/v1/", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            // A single connection serves both the warmup and the request
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = vec![0; 4096];
+            let read = stream.read(&mut request).await.unwrap();
+            stream
+                .write_all(b"HTTP/1.1 404 Not Found\r\ncontent-length: 0\r\n\r\n")
+                .await
+                .unwrap();
+            let warmup = String::from_utf8_lossy(&request[..read]).into_owned();
+            (warmup, answer_models(&mut stream).await)
+        });
+        let client = Client::new("bogus")?.with_base_url(&base_url)?;
+        client.warmup().await?;
+        assert!(client.models().await?.is_empty());
+        let (warmup, request) = server.await.unwrap();
+        assert!(warmup.starts_with("HEAD /v1/ HTTP/1.1"));
+        assert!(request.starts_with("GET /v1/models HTTP/1.1"));
+
+        let unreachable = Client::new("bogus")?.with_base_url("http://127.0.0.1:1/v1/")?;
+        assert!(matches!(
+            unreachable.warmup().await,
+            Err(Error::AsyncProtocol(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn root_certificates() -> crate::Result<()> {
+        for pem in [
+            "",
+            "-----BEGIN CERTIFICATE-----\nbogus\n-----END CERTIFICATE-----\n",
+        ] {
+            assert!(matches!(
+                Client::new("bogus")?.with_root_certificates(pem.as_bytes()),
+                Err(Error::InvalidConfig(_))
+            ));
+        }
+        let client = Client::new("bogus")?.danger_accept_invalid_certs()?;
+        assert!(client.inner.transport.accept_invalid_certs);
+        assert!(client.inner.transport.root_certificates.is_empty());
+        Ok(())
+    }
+
+    /// Answers every request with `body`, keeping the requests
+    #[derive(Debug)]
+    struct Fake {
+        body: &'static str,
+        requests: std::sync::Mutex<Vec<(reqwest::Method, String, reqwest::header::HeaderMap)>>,
+    }
+
+    #[async_trait::async_trait]
+    impl HttpTransport for Fake {
+        async fn request(
+            &self,
+            method: reqwest::Method,
+            url: reqwest::Url,
+            headers: reqwest::header::HeaderMap,
+            _: Option<bytes::Bytes>,
+        ) -> crate::Result<HttpResponse> {
+            self.requests
+                .lock()
+                .unwrap()
+                .push((method, url.to_string(), headers));
+            Ok(HttpResponse::new(200, self.body))
+        }
+    }
+
+    #[tokio::test]
+    async fn custom_transport() -> crate::Result<()> {
+        let fake = std::sync::Arc::new(Fake {
+            body: "data: {\"id\": \"chatcmpl-1\", \"created\": 1, \"choices\": [{\"index\": 0, \
+                   \"delta\": {\"content\": \"Hi!\"}, \"finish_reason\": null}]}\n\ndata: [DONE]\n\n",
+            requests: Default::default(),
+        });
+        let client = Client::new("sk-test")?
+            .with_user_agent("my-app/1.0")?
+            .with_http_transport(fake.clone());
+        let mut stream = Box::pin(
+            client
+                .chat_stream(vec![(crate::api::ChatRole::User, "Hello".to_string())])
+                .await?,
+        );
+        let delta = futures::StreamExt::next(&mut stream).await.unwrap()?;
+        assert_eq!(delta.choices[0].delta.content.as_deref(), Some("Hi!"));
+        assert!(futures::StreamExt::next(&mut stream).await.is_none());
+
+        let (method, url, headers) = fake.requests.lock().unwrap().remove(0);
+        assert_eq!(method, reqwest::Method::POST);
+        assert_eq!(url, "https://api.openai.com/v1/chat/completions");
+        assert_eq!(headers["authorization"], "Bearer sk-test");
+        assert_eq!(headers["user-agent"], "my-app/1.0");
+        assert_eq!(headers["content-type"], "application/json");
+
+        let limited = client.with_max_response_size(10);
+        assert!(matches!(
+            limited.models().await,
+            Err(Error::ResponseTooLarge { .. })
+        ));
+        Ok(())
+    }
+
+    #[cfg(feature = "hickory-dns")]
+    #[tokio::test]
+    async fn hickory_dns() -> crate::Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            answer_models(&mut stream).await
+        });
+        // localhost is answered from the hosts file, without a name server
+        let client = Client::new("bogus")?
+            .with_base_url(&format!("http://localhost:{}/v1/", port))?
+            .with_hickory_dns(super::DnsCache::default())?;
+        assert!(client.inner.transport.dns.is_some());
+        assert!(client.models().await?.is_empty());
+        assert!(server.await.unwrap().starts_with("GET /v1/models HTTP/1.1"));
+        Ok(())
+    }
+
+    #[cfg(feature = "socks")]
+    #[tokio::test]
+    async fn socks5h_proxy() -> crate::Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let proxy = format!("socks5h://{}", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            // Greeting, accepted without authentication
+            let mut greeting = [0; 3];
+            stream.read_exact(&mut greeting).await.unwrap();
+            stream.write_all(&[5, 0]).await.unwrap();
+            // CONNECT to a host name, which the proxy resolves
+            let mut connect = [0; 5];
+            stream.read_exact(&mut connect).await.unwrap();
+            assert_eq!(connect[..4], [5, 1, 0, 3]);
+            let mut host = vec![0; usize::from(connect[4]) + 2];
+            stream.read_exact(&mut host).await.unwrap();
+            stream
+                .write_all(&[5, 0, 0, 1, 127, 0, 0, 1, 0, 80])
+                .await
+                .unwrap();
+            let request = answer_models(&mut stream).await;
+            (
+                String::from_utf8_lossy(&host[..host.len() - 2]).into_owned(),
+                request,
+            )
+        });
+        let client = Client::new("bogus")?
+            .with_base_url("http://api.invalid/v1/")?
+            .with_proxy(&proxy)?;
+        assert!(client.models().await?.is_empty());
+        let (host, request) = server.await.unwrap();
+        assert_eq!(host, "api.invalid");
+        assert!(request.starts_with("GET /v1/models HTTP/1.1"));
+        Ok(())
+    }
+}
@@ -0,0 +1,336 @@
+//! Record and replay of API interactions, to test code built on the client without a
+//! network or an API key.
+//!
+//! A [`Cassette`] in record mode forwards requests to the API and appends each interaction
+//! to a JSON file. In replay mode, it answers requests from that file instead. Headers are
+//! never recorded, and API keys found in bodies are redacted, so cassettes are safe to
+//! commit.
+//!
+//! # Example
+//! ```no_run
+//! # use openai_api::{vcr::Cassette, Client};
+//! # fn main() -> Result<(), openai_api::Error> {
+//! // Records on the first run, and replays on the following ones
+//! let cassette = Cassette::auto("tests/cassettes/chat.json")?;
+//! let client = Client::new("sk-...")?.with_cassette(cassette);
+//! # Ok(())
+//! # }
+//! ```
+use std::{
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{response::RawResponse, Error, Result};
+
+/// Whether a cassette records or replays interactions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// Send requests to the API, and record them
+    Record,
+    /// Answer requests from the recording, and never reach the API
+    Replay,
+}
+
+/// A file of recorded API interactions. See the module documentation.
+#[derive(Debug)]
+pub struct Cassette {
+    path: PathBuf,
+    mode: Mode,
+    state: Mutex<State>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    interactions: Vec<Interaction>,
+    /// Which interactions were already replayed
+    used: Vec<bool>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct Interaction {
+    request: RecordedRequest,
+    response: RecordedResponse,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct RecordedRequest {
+    method: String,
+    /// Path and query, relative to the client's base URL
+    path: String,
+    #[serde(default)]
+    body: Value,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct RecordedResponse {
+    status: u16,
+    #[serde(default)]
+    body: Value,
+}
+
+impl Cassette {
+    /// Creates a cassette recording to `path`, replacing any previous recording there
+    #[must_use]
+    pub fn record(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            mode: Mode::Record,
+            state: Mutex::default(),
+        }
+    }
+
+    /// Loads the recording at `path` to replay it
+    ///
+    /// # Errors
+    /// - `Error::Cassette` if the file can't be read
+    /// - `Error::Json` if it isn't a valid recording
+    pub fn replay(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let json = std::fs::read_to_string(&path)
+            .map_err(|e| Error::Cassette(format!("can't read {}: {}", path.display(), e)))?;
+        let interactions: Vec<Interaction> = serde_json::from_str(&json)?;
+        Ok(Self {
+            path,
+            mode: Mode::Replay,
+            state: Mutex::new(State {
+                used: vec![false; interactions.len()],
+                interactions,
+            }),
+        })
+    }
+
+    /// Replays the recording at `path` if there is one, or records it otherwise
+    ///
+    /// # Errors
+    /// - Same as [`Cassette::replay`] when the file exists
+    pub fn auto(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        if path.exists() {
+            Self::replay(path)
+        } else {
+            Ok(Self::record(path))
+        }
+    }
+
+    /// Whether the cassette records or replays
+    #[must_use]
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    /// Path of the recording
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Number of interactions recorded, or loaded to replay
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.lock().interactions.len()
+    }
+
+    /// Whether the cassette holds no interactions
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub(crate) async fn execute(
+        &self,
+        client: &reqwest::Client,
+        base_url: &str,
+        request: reqwest::Request,
+        limit: Option<usize>,
+    ) -> Result<RawResponse> {
+        let recorded = RecordedRequest {
+            method: request.method().to_string(),
+            path: relative_path(base_url, request.url()),
+            body: to_value(request.body().and_then(reqwest::Body::as_bytes)),
+        };
+        match self.mode {
+            Mode::Replay => self.replay_request(&recorded),
+            Mode::Record => {
+                let response = RawResponse::read(client.execute(request).await?, limit).await?;
+                self.record_interaction(Interaction {
+                    request: recorded,
+                    response: RecordedResponse {
+                        status: response.status.as_u16(),
+                        body: to_value(Some(&response.body[..])),
+                    },
+                })?;
+                Ok(response)
+            }
+        }
+    }
+
+    /// Answers `request` with the first unused interaction recorded for it
+    fn replay_request(&self, request: &RecordedRequest) -> Result<RawResponse> {
+        let mut state = self.lock();
+        let State { interactions, used } = &mut *state;
+        let index = interactions
+            .iter()
+            .zip(used.iter())
+            .position(|(interaction, used)| !used && &interaction.request == request)
+            .ok_or_else(|| {
+                Error::Cassette(format!(
+                    "no recording of {} {} in {}",
+                    request.method,
+                    request.path,
+                    self.path.display()
+                ))
+            })?;
+        used[index] = true;
+        let response = &interactions[index].response;
+        let status = reqwest::StatusCode::from_u16(response.status)
+            .map_err(|e| Error::Cassette(e.to_string()))?;
+        let body = match &response.body {
+            Value::String(text) => text.clone().into_bytes(),
+            body => serde_json::to_vec(body)?,
+        }
+        .into();
+        Ok(RawResponse {
+            status,
+            headers: reqwest::header::HeaderMap::new(),
+            body,
+        })
+    }
+
+    /// Appends an interaction, and saves the whole recording so that it survives a test
+    /// that panics halfway
+    fn record_interaction(&self, interaction: Interaction) -> Result<()> {
+        let mut state = self.lock();
+        state.interactions.push(interaction);
+        state.used.push(true);
+        let json = serde_json::to_string_pretty(&state.interactions)?;
+        if let Some(dir) = self.path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| Error::Cassette(format!("can't create {}: {}", dir.display(), e)))?;
+        }
+        std::fs::write(&self.path, json)
+            .map_err(|e| Error::Cassette(format!("can't write {}: {}", self.path.display(), e)))
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// The part of `url` following `base_url`, e.g. `"models?limit=2"`
+fn relative_path(base_url: &str, url: &reqwest::Url) -> String {
+    let url = url.as_str();
+    url.strip_prefix(base_url).unwrap_or(url).to_string()
+}
+
+/// A body as JSON, or as a string when it isn't JSON, with API keys redacted
+fn to_value(body: Option<&[u8]>) -> Value {
+    let body = match body {
+        Some(body) if !body.is_empty() => body,
+        _ => return Value::Null,
+    };
+    let mut value = serde_json::from_slice(body)
+        .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(body).into_owned()));
+    redact(&mut value);
+    value
+}
+
+/// Replaces anything that looks like an API key with `"sk-REDACTED"`
+fn redact(value: &mut Value) {
+    match value {
+        Value::String(text) if text.contains("sk-") => *text = redact_text(text),
+        Value::Array(values) => values.iter_mut().for_each(redact),
+        Value::Object(map) => map.values_mut().for_each(redact),
+        _ => {}
+    }
+}
+
+fn redact_text(text: &str) -> String {
+    let is_key_char = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_';
+    let mut redacted = String::with_capacity(text.len());
+    let mut copied = 0;
+    for (start, _) in text.match_indices("sk-") {
+        // Only keys starting a word, not e.g. the "sk-" of "task-"
+        if start < copied || text[..start].ends_with(is_key_char) {
+            continue;
+        }
+        let rest = &text[start + 3..];
+        redacted.push_str(&text[copied..start]);
+        redacted.push_str("sk-REDACTED");
+        copied = start + 3 + rest.find(|c| !is_key_char(c)).unwrap_or(rest.len());
+    }
+    redacted.push_str(&text[copied..]);
+    redacted
+}
+
+#[cfg(test)]
+mod unit {
+    use serde_json::json;
+
+    use super::{redact, Cassette, Mode};
+    use crate::{api::ChatArgs, unit::mocked_client, Error};
+
+    #[test]
+    fn redacts_api_keys() {
+        let mut value = json!({
+            "user": "sk-abc123DEF",
+            "messages": [{"content": "my key is sk-proj-XYZ_9, keep it."}],
+            "task": "task-sk-1",
+        });
+        redact(&mut value);
+        assert_eq!(
+            value,
+            json!({
+                "user": "sk-REDACTED",
+                "messages": [{"content": "my key is sk-REDACTED, keep it."}],
+                "task": "task-sk-1",
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn records_then_replays() -> crate::Result<()> {
+        let path = std::env::temp_dir().join(format!("openai-api-vcr-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let mock = mockito::mock("POST", "/chat/completions")
+            .match_body(mockito::Matcher::PartialJsonString(
+                r#"{"model": "vcr-test"}"#.into(),
+            ))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"{
+                "id": "chatcmpl-vcr",
+                "created": 1677652288,
+                "choices": [{
+                    "index": 0,
+                    "message": {"role": "assistant", "content": "Recorded!"},
+                    "finish_reason": "stop"
+                }]
+            }"#,
+            )
+            .expect(1)
+            .create();
+        let args = || ChatArgs::builder().model("vcr-test").build().unwrap();
+
+        let client = mocked_client().with_cassette(Cassette::record(&path));
+        assert_eq!(client.chat(args()).await?.id, "chatcmpl-vcr");
+        mock.assert();
+
+        let cassette = Cassette::auto(&path)?;
+        assert_eq!(cassette.mode(), Mode::Replay);
+        assert_eq!(cassette.len(), 1);
+        let client = mocked_client().with_cassette(cassette);
+        let answer = client.chat(args()).await?;
+        assert_eq!(answer.choices[0].message.content, "Recorded!");
+        // Each interaction is replayed once
+        assert!(matches!(client.chat(args()).await, Err(Error::Cassette(_))));
+        mock.assert();
+
+        std::fs::remove_file(&path).ok();
+        Ok(())
+    }
+}
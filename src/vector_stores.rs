@@ -0,0 +1,552 @@
+//! Vector stores, which index files for the `file_search` tool of assistants.
+//!
+//! Files are chunked and embedded in the background after they are added: until then
+//! `file_search` silently finds nothing in them. `Client::wait_for_vector_store_file` and
+//! `Client::wait_for_vector_store_file_batch` poll until ingestion is over.
+//!
+//! # Example
+//! ```no_run
+//! # use openai_api::{poll::PollConfig, vector_stores::VectorStoreArgs};
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), openai_api::Error> {
+//! let client = openai_api::Client::new("sk-...")?;
+//! let store = client
+//!     .create_vector_store(VectorStoreArgs::builder().name("Manuals"))
+//!     .await?;
+//! let files = vec!["file-abc".to_string(), "file-def".to_string()];
+//! let batch = client
+//!     .create_vector_store_file_batch(&store.id, files)
+//!     .await?;
+//! client
+//!     .wait_for_vector_store_file_batch(batch, &PollConfig::default(), |_| {})
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::{Deleted, ListParams, Page},
+    poll::PollConfig,
+    Client, Error, Result,
+};
+
+/// Where a vector store is in its lifecycle
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum VectorStoreStatus {
+    /// Some of its files are still being ingested
+    InProgress,
+    /// All its files are ingested, and it can be used
+    Completed,
+    /// It expired, see `VectorStore::expires_after`, and can't be used anymore
+    Expired,
+    /// A status this version of the library doesn't know about
+    #[serde(other)]
+    Other,
+}
+
+/// When a vector store expires, deleting it
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ExpiresAfter {
+    /// What the expiry counts from, only `"last_active_at"` for now
+    pub anchor: String,
+    /// Days from the anchor to the expiry, from 1 to 365
+    pub days: u32,
+}
+
+impl ExpiresAfter {
+    /// Expires the store after `days` without use
+    #[must_use]
+    pub fn inactive_days(days: u32) -> Self {
+        Self {
+            anchor: "last_active_at".into(),
+            days,
+        }
+    }
+}
+
+/// Arguments of a vector store's creation
+#[derive(Serialize, Deserialize, Debug, Builder, Clone, Default)]
+#[builder(pattern = "immutable")]
+pub struct VectorStoreArgs {
+    #[builder(setter(into, strip_option), default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    /// Ids of files uploaded with the `assistants` purpose, to add to the store
+    #[builder(setter(into), default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    file_ids: Vec<String>,
+    #[builder(setter(strip_option), default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    expires_after: Option<ExpiresAfter>,
+    /// Up to 16 key-value pairs, for the caller's own use
+    #[builder(setter(into), default)]
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    metadata: HashMap<String, String>,
+}
+
+impl VectorStoreArgs {
+    /// Build a `VectorStoreArgs` from the defaults
+    #[must_use]
+    pub fn builder() -> VectorStoreArgsBuilder {
+        VectorStoreArgsBuilder::default()
+    }
+}
+
+impl TryFrom<VectorStoreArgsBuilder> for VectorStoreArgs {
+    type Error = VectorStoreArgsBuilderError;
+
+    fn try_from(builder: VectorStoreArgsBuilder) -> std::result::Result<Self, Self::Error> {
+        builder.build()
+    }
+}
+
+impl From<VectorStoreArgsBuilderError> for Error {
+    fn from(err: VectorStoreArgsBuilderError) -> Self {
+        Error::BadArguments(err.to_string())
+    }
+}
+
+/// A vector store
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct VectorStore {
+    pub id: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    pub status: VectorStoreStatus,
+    /// Size of the store, in bytes
+    #[serde(default, deserialize_with = "crate::api::lenient")]
+    pub usage_bytes: u64,
+    #[serde(default)]
+    pub file_counts: FileCounts,
+    /// Unix timestamp, in seconds
+    #[serde(default, deserialize_with = "crate::api::lenient")]
+    pub created_at: u64,
+    #[serde(default)]
+    pub expires_after: Option<ExpiresAfter>,
+    /// When the store expires, as a Unix timestamp in seconds, if it does
+    #[serde(default)]
+    pub expires_at: Option<u64>,
+    #[serde(default, deserialize_with = "crate::api::lenient")]
+    pub metadata: HashMap<String, String>,
+}
+
+/// Where the ingestion of a file, or batch of files, is
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum IngestionStatus {
+    InProgress,
+    Completed,
+    Cancelled,
+    Failed,
+    /// A status this version of the library doesn't know about
+    #[serde(other)]
+    Other,
+}
+
+impl IngestionStatus {
+    /// Whether ingestion is over, successfully or not
+    #[must_use]
+    pub fn is_terminal(self) -> bool {
+        !matches!(self, IngestionStatus::InProgress | IngestionStatus::Other)
+    }
+}
+
+/// A file of a vector store
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct VectorStoreFile {
+    /// The id of the file, as uploaded
+    pub id: String,
+    pub vector_store_id: String,
+    pub status: IngestionStatus,
+    /// Size of the file in the vector store, in bytes
+    #[serde(default, deserialize_with = "crate::api::lenient")]
+    pub usage_bytes: u64,
+    /// Unix timestamp, in seconds
+    #[serde(default, deserialize_with = "crate::api::lenient")]
+    pub created_at: u64,
+    /// Why ingestion failed, if it did
+    #[serde(default)]
+    pub last_error: Option<IngestionError>,
+}
+
+/// The error the ingestion of a file failed with
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct IngestionError {
+    /// E.g. `"unsupported_file"`
+    #[serde(default, deserialize_with = "crate::api::lenient")]
+    pub code: String,
+    #[serde(default, deserialize_with = "crate::api::lenient")]
+    pub message: String,
+}
+
+/// Files added to a vector store together
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct VectorStoreFileBatch {
+    pub id: String,
+    pub vector_store_id: String,
+    pub status: IngestionStatus,
+    /// Unix timestamp, in seconds
+    #[serde(default, deserialize_with = "crate::api::lenient")]
+    pub created_at: u64,
+    #[serde(default)]
+    pub file_counts: FileCounts,
+}
+
+/// How many files of a batch, or store, are in each status
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(default)]
+pub struct FileCounts {
+    pub in_progress: u64,
+    pub completed: u64,
+    pub failed: u64,
+    pub cancelled: u64,
+    pub total: u64,
+}
+
+impl FileCounts {
+    /// The fraction of the files whose ingestion is over, between 0 and 1
+    #[must_use]
+    pub fn progress(&self) -> f64 {
+        if self.total == 0 {
+            1.0
+        } else {
+            (self.total - self.in_progress.min(self.total)) as f64 / self.total as f64
+        }
+    }
+}
+
+impl Client {
+    /// Creates a vector store, ingesting its files in the background
+    ///
+    /// # Errors
+    ///  - `Error::BadArguments` if the arguments can't be built
+    ///  - `Error::DryRun` in dry-run mode
+    ///  - `Error::Api` if the api returns an error
+    pub async fn create_vector_store<A>(&self, args: A) -> Result<VectorStore>
+    where
+        A: TryInto<VectorStoreArgs>,
+        Error: From<<A as TryInto<VectorStoreArgs>>::Error>,
+    {
+        let args: VectorStoreArgs = args.try_into()?;
+        self.dry_run("vector_stores", &args, || Ok(()))?;
+        self.assistants_post("vector_stores", &args).await
+    }
+
+    /// Fetches the current state of a vector store
+    ///
+    /// # Errors
+    /// - `Error::Api` if the api returns an error
+    pub async fn retrieve_vector_store(&self, vector_store_id: &str) -> Result<VectorStore> {
+        self.assistants_get(&format!("vector_stores/{}", vector_store_id))
+            .await
+    }
+
+    /// Lists the vector stores of the organization
+    ///
+    /// # Errors
+    /// - `Error::Api` if the api returns an error
+    pub async fn list_vector_stores(&self, page: &ListParams) -> Result<Page<VectorStore>> {
+        self.assistants_get_with_query("vector_stores", page).await
+    }
+
+    /// Deletes a vector store. Its files are only removed from it, not deleted.
+    ///
+    /// # Errors
+    /// - `Error::Api` if the api returns an error
+    pub async fn delete_vector_store(&self, vector_store_id: &str) -> Result<Deleted> {
+        self.assistants_delete(&format!("vector_stores/{}", vector_store_id))
+            .await
+    }
+
+    /// Adds an uploaded file to a vector store, ingesting it in the background
+    ///
+    /// # Errors
+    /// - `Error::DryRun` in dry-run mode
+    /// - `Error::Api` if the api returns an error
+    pub async fn create_vector_store_file(
+        &self,
+        vector_store_id: &str,
+        file_id: &str,
+    ) -> Result<VectorStoreFile> {
+        let endpoint = format!("vector_stores/{}/files", vector_store_id);
+        let body = serde_json::json!({ "file_id": file_id });
+        self.dry_run(&endpoint, &body, || Ok(()))?;
+        self.assistants_post(&endpoint, &body).await
+    }
+
+    /// Lists the files of a vector store
+    ///
+    /// # Errors
+    /// - `Error::Api` if the api returns an error
+    pub async fn list_vector_store_files(
+        &self,
+        vector_store_id: &str,
+        page: &ListParams,
+    ) -> Result<Page<VectorStoreFile>> {
+        let endpoint = format!("vector_stores/{}/files", vector_store_id);
+        self.assistants_get_with_query(&endpoint, page).await
+    }
+
+    /// Removes a file from a vector store. The file itself isn't deleted.
+    ///
+    /// # Errors
+    /// - `Error::Api` if the api returns an error
+    pub async fn delete_vector_store_file(
+        &self,
+        vector_store_id: &str,
+        file_id: &str,
+    ) -> Result<Deleted> {
+        self.assistants_delete(&format!(
+            "vector_stores/{}/files/{}",
+            vector_store_id, file_id
+        ))
+        .await
+    }
+
+    /// Adds uploaded files to a vector store together, ingesting them in the background
+    ///
+    /// # Errors
+    /// - `Error::BadArguments` if there are no files
+    /// - `Error::DryRun` in dry-run mode
+    /// - `Error::Api` if the api returns an error
+    pub async fn create_vector_store_file_batch(
+        &self,
+        vector_store_id: &str,
+        file_ids: Vec<String>,
+    ) -> Result<VectorStoreFileBatch> {
+        if file_ids.is_empty() {
+            return Err(Error::BadArguments(
+                "a file batch needs at least one file".into(),
+            ));
+        }
+        let endpoint = format!("vector_stores/{}/file_batches", vector_store_id);
+        let body = serde_json::json!({ "file_ids": file_ids });
+        self.dry_run(&endpoint, &body, || Ok(()))?;
+        self.assistants_post(&endpoint, &body).await
+    }
+
+    /// Cancels the ingestion of the files of a batch that are still in progress
+    ///
+    /// # Errors
+    /// - `Error::Api` if the api returns an error
+    pub async fn cancel_vector_store_file_batch(
+        &self,
+        vector_store_id: &str,
+        batch_id: &str,
+    ) -> Result<VectorStoreFileBatch> {
+        let endpoint = format!(
+            "vector_stores/{}/file_batches/{}/cancel",
+            vector_store_id, batch_id
+        );
+        self.assistants_post(&endpoint, &serde_json::json!({}))
+            .await
+    }
+
+    /// Fetches the current state of a file of a vector store
+    ///
+    /// # Errors
+    /// - `Error::Api` if the api returns an error
+    pub async fn retrieve_vector_store_file(
+        &self,
+        vector_store_id: &str,
+        file_id: &str,
+    ) -> Result<VectorStoreFile> {
+        self.assistants_get(&format!(
+            "vector_stores/{}/files/{}",
+            vector_store_id, file_id
+        ))
+        .await
+    }
+
+    /// Fetches the current state of a batch of files of a vector store
+    ///
+    /// # Errors
+    /// - `Error::Api` if the api returns an error
+    pub async fn retrieve_vector_store_file_batch(
+        &self,
+        vector_store_id: &str,
+        batch_id: &str,
+    ) -> Result<VectorStoreFileBatch> {
+        self.assistants_get(&format!(
+            "vector_stores/{}/file_batches/{}",
+            vector_store_id, batch_id
+        ))
+        .await
+    }
+
+    /// Polls `file` until its ingestion is over, calling `progress` with each state seen
+    /// before. Check the status of the result: failed files are returned, not errors.
+    ///
+    /// # Errors
+    /// - `Error::PollTimeout` if ingestion is still going when `config` runs out of time
+    /// - `Error::Api` if the api returns an error
+    pub async fn wait_for_vector_store_file(
+        &self,
+        file: VectorStoreFile,
+        config: &PollConfig,
+        progress: impl FnMut(&VectorStoreFile),
+    ) -> Result<VectorStoreFile> {
+        let (store_id, file_id) = (file.vector_store_id.clone(), file.id.clone());
+        crate::poll::until(
+            config,
+            file,
+            || self.retrieve_vector_store_file(&store_id, &file_id),
+            |file| file.status.is_terminal(),
+            progress,
+        )
+        .await
+    }
+
+    /// Polls `batch` until the ingestion of all its files is over, calling `progress` with
+    /// each state seen before, e.g. to report `batch.file_counts.progress()`
+    ///
+    /// # Errors
+    /// - `Error::PollTimeout` if ingestion is still going when `config` runs out of time
+    /// - `Error::Api` if the api returns an error
+    pub async fn wait_for_vector_store_file_batch(
+        &self,
+        batch: VectorStoreFileBatch,
+        config: &PollConfig,
+        progress: impl FnMut(&VectorStoreFileBatch),
+    ) -> Result<VectorStoreFileBatch> {
+        let (store_id, batch_id) = (batch.vector_store_id.clone(), batch.id.clone());
+        crate::poll::until(
+            config,
+            batch,
+            || self.retrieve_vector_store_file_batch(&store_id, &batch_id),
+            |batch| batch.status.is_terminal(),
+            progress,
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod unit {
+    use std::time::Duration;
+
+    use mockito::Matcher;
+    use serde_json::json;
+
+    use super::{
+        ExpiresAfter, IngestionStatus, VectorStoreArgs, VectorStoreFileBatch, VectorStoreStatus,
+    };
+    use crate::{api::ListParams, poll::PollConfig, unit::mocked_client};
+
+    #[tokio::test]
+    async fn manages_stores_and_files() -> crate::Result<()> {
+        let create = mockito::mock("POST", "/vector_stores")
+            .match_header("openai-beta", "assistants=v2")
+            .match_body(Matcher::Json(json!({
+                "name": "Manuals",
+                "file_ids": ["file-1"],
+                "expires_after": {"anchor": "last_active_at", "days": 7},
+            })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"{"id": "vs_2", "object": "vector_store", "created_at": 1699061776,
+                "name": "Manuals", "usage_bytes": 0, "status": "in_progress", "file_counts":
+                {"in_progress": 1, "completed": 0, "failed": 0, "cancelled": 0, "total": 1},
+                "expires_after": {"anchor": "last_active_at", "days": 7}, "expires_at": null,
+                "metadata": {}}"#,
+            )
+            .expect(1)
+            .create();
+        let add = mockito::mock("POST", "/vector_stores/vs_2/files")
+            .match_body(Matcher::Json(json!({"file_id": "file-2"})))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"{"id": "file-2", "object": "vector_store.file", "created_at": 1699061776,
+                "vector_store_id": "vs_2", "status": "in_progress", "last_error": null}"#,
+            )
+            .expect(1)
+            .create();
+        let list = mockito::mock("GET", "/vector_stores/vs_2/files")
+            .match_query(Matcher::UrlEncoded("limit".into(), "2".into()))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"{"object": "list", "data": [{"id": "file-2", "vector_store_id": "vs_2",
+                "status": "completed", "usage_bytes": 1024}], "first_id": "file-2",
+                "last_id": "file-2", "has_more": false}"#,
+            )
+            .expect(1)
+            .create();
+        let delete = mockito::mock("DELETE", "/vector_stores/vs_2")
+            .match_header("openai-beta", "assistants=v2")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"id": "vs_2", "object": "vector_store.deleted", "deleted": true}"#)
+            .expect(1)
+            .create();
+
+        let client = mocked_client();
+        let args = VectorStoreArgs::builder()
+            .name("Manuals")
+            .file_ids(vec!["file-1".into()])
+            .expires_after(ExpiresAfter::inactive_days(7));
+        let store = client.create_vector_store(args).await?;
+        assert_eq!(store.status, VectorStoreStatus::InProgress);
+        assert_eq!(store.file_counts.progress(), 0.0);
+
+        let file = client.create_vector_store_file(&store.id, "file-2").await?;
+        assert_eq!(file.status, IngestionStatus::InProgress);
+        let page = ListParams::builder().limit(2).build().unwrap();
+        let files = client.list_vector_store_files(&store.id, &page).await?;
+        assert_eq!(files.data[0].usage_bytes, 1024);
+        assert!(client.delete_vector_store(&store.id).await?.deleted);
+        assert!(client
+            .create_vector_store_file_batch(&store.id, vec![])
+            .await
+            .is_err());
+        for mock in [create, add, list, delete] {
+            mock.assert();
+        }
+        Ok(())
+    }
+
+    fn batch(status: &str, in_progress: u64, completed: u64) -> String {
+        format!(
+            r#"{{"id": "vsfb_1", "object": "vector_store.file_batch", "created_at": 1699061776,
+            "vector_store_id": "vs_1", "status": "{}", "file_counts": {{"in_progress": {},
+            "completed": {}, "failed": 0, "cancelled": 0, "total": 4}}}}"#,
+            status, in_progress, completed
+        )
+    }
+
+    #[tokio::test]
+    async fn waits_for_ingestion() -> crate::Result<()> {
+        let started: VectorStoreFileBatch = serde_json::from_str(&batch("in_progress", 4, 0))?;
+        let _halfway = mockito::mock("GET", "/vector_stores/vs_1/file_batches/vsfb_1")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(batch("in_progress", 2, 2))
+            .expect(1)
+            .create();
+        let _done = mockito::mock("GET", "/vector_stores/vs_1/file_batches/vsfb_1")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(batch("completed", 0, 4))
+            .create();
+        let config = PollConfig {
+            initial_interval: Duration::from_millis(1),
+            ..PollConfig::default()
+        };
+        let mut seen = vec![];
+        let done = mocked_client()
+            .wait_for_vector_store_file_batch(started, &config, |batch| {
+                seen.push(batch.file_counts.progress())
+            })
+            .await?;
+        assert_eq!(done.status, IngestionStatus::Completed);
+        assert_eq!(done.file_counts.completed, 4);
+        assert_eq!(seen, vec![0.0, 0.5]);
+        Ok(())
+    }
+}